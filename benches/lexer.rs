@@ -0,0 +1,44 @@
+//! Lexer throughput over a large generated script.
+//!
+//! Run with `cargo bench --bench lexer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mp_lang::lexer::{Lexer, tokenize};
+
+const EXAMPLE: &str = include_str!("../examples/11_comprehensive.mp");
+const COPIES: usize = 2_000;
+const RUNS: u32 = 5;
+
+fn main() {
+    let source = EXAMPLE.repeat(COPIES);
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!("input: {megabytes:.1} MiB");
+
+    let collected = best_of(|| tokenize(black_box(&source)).len());
+    report("tokenize (collects a Vec)", collected, megabytes);
+
+    let streamed = best_of(|| Lexer::new(black_box(&source)).count());
+    report("Lexer iterator (streaming)", streamed, megabytes);
+}
+
+/// The fastest of several runs, which is the least disturbed by other work.
+fn best_of(mut run: impl FnMut() -> usize) -> (Duration, usize) {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let tokens = black_box(run());
+            (start.elapsed(), tokens)
+        })
+        .min()
+        .expect("RUNS is non-zero")
+}
+
+fn report(name: &str, (elapsed, tokens): (Duration, usize), megabytes: f64) {
+    println!(
+        "{name:<28} {:>8.2?}  {tokens} tokens  {:.1} MiB/s",
+        elapsed,
+        megabytes / elapsed.as_secs_f64()
+    );
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::lexer::Span;
+use crate::messages::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Style advice; the code is fine as written.
+    Hint,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Severity::Error => Message::Error,
+            Severity::Warning => Message::Warning,
+            Severity::Hint => Message::Hint,
+        };
+        write!(f, "{}", message.text())
+    }
+}
+
+/// A problem found in a program by any phase, from the lexer to the
+/// evaluator, in one shape so every front end reports them alike.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where the problem is, when known.
+    pub span: Option<Span>,
+    /// A stable identifier for the kind of problem, like `E0001` for an
+    /// undefined variable. Errors start with `E`, warnings and hints with `W`.
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Further lines of context, such as the calls that led to an error.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Option<Span>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            span,
+            code: None,
+            message,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// The diagnostic with the source line it points at and a caret under the
+    /// column, followed by its notes.
+    pub fn render(&self, source: &str) -> String {
+        let mut rendered = self.header();
+        if let Some(span) = self.span
+            && let Some(line) = source.lines().nth(span.line.saturating_sub(1))
+        {
+            let number = span.line.to_string();
+            let gutter = " ".repeat(number.len());
+            // Keep tabs so the caret lines up with the text above it.
+            let indent: String = line
+                .chars()
+                .take(span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            rendered.push_str(&format!(
+                "\n{gutter} |\n{number} | {line}\n{gutter} | {indent}^"
+            ));
+        }
+        for note in &self.notes {
+            rendered.push_str(&format!("\n  {note}"));
+        }
+        rendered
+    }
+
+    /// The diagnostic as one line of JSON, for editors and CI to parse.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn header(&self) -> String {
+        match self.span {
+            Some(span) => Message::Header.format(&[
+                ("severity", &self.severity),
+                ("span", &span),
+                ("message", &self.message),
+            ]),
+            None => Message::HeaderWithoutSpan
+                .format(&[("severity", &self.severity), ("message", &self.message)]),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header())?;
+        for note in &self.notes {
+            write!(f, "\n  {note}")?;
+        }
+        Ok(())
+    }
+}
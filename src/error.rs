@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::{fmt, io};
+
+use rustyline::error::ReadlineError;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::LexerError;
+use crate::parser::ParserError;
+use crate::resolver::ResolverError;
+use crate::runtime::error::InterpreterError;
+
+/// Why running, checking or loading a program failed, by phase. Errors found
+/// in a program keep its source so they can be rendered with snippets.
+#[derive(Debug)]
+pub enum MpError {
+    Io(io::Error),
+    Lex {
+        errors: Vec<LexerError>,
+        source: String,
+    },
+    Parse {
+        errors: Vec<ParserError>,
+        source: String,
+    },
+    /// Names the resolver found undefined or misused before running.
+    Resolve {
+        errors: Vec<ResolverError>,
+        source: String,
+    },
+    Runtime {
+        error: InterpreterError,
+        source: String,
+    },
+    /// The REPL's line editor failed.
+    Readline(ReadlineError),
+}
+
+impl MpError {
+    /// The problems in the program, or a single diagnostic without a position
+    /// for failures outside it.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            MpError::Lex { errors, .. } => errors.iter().map(Diagnostic::from).collect(),
+            MpError::Parse { errors, .. } => errors.iter().map(Diagnostic::from).collect(),
+            MpError::Resolve { errors, .. } => errors.iter().map(Diagnostic::from).collect(),
+            MpError::Runtime { error, .. } => vec![Diagnostic::from(error)],
+            MpError::Io(error) => vec![Diagnostic::error(None, error.to_string())],
+            MpError::Readline(error) => vec![Diagnostic::error(None, error.to_string())],
+        }
+    }
+
+    fn program_source(&self) -> &str {
+        match self {
+            MpError::Lex { source, .. }
+            | MpError::Parse { source, .. }
+            | MpError::Resolve { source, .. }
+            | MpError::Runtime { source, .. } => source,
+            MpError::Io(_) | MpError::Readline(_) => "",
+        }
+    }
+}
+
+/// Every diagnostic, with the source lines they point at where known.
+impl fmt::Display for MpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MpError::Io(error) => write!(f, "{error}"),
+            MpError::Readline(error) => write!(f, "{error}"),
+            _ => {
+                let rendered: Vec<String> = self
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| diagnostic.render(self.program_source()))
+                    .collect();
+                write!(f, "{}", rendered.join("\n"))
+            }
+        }
+    }
+}
+
+impl Error for MpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MpError::Io(error) => Some(error),
+            MpError::Readline(error) => Some(error),
+            MpError::Runtime { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MpError {
+    fn from(error: io::Error) -> Self {
+        MpError::Io(error)
+    }
+}
+
+impl From<ReadlineError> for MpError {
+    fn from(error: ReadlineError) -> Self {
+        MpError::Readline(error)
+    }
+}
@@ -1,11 +1,15 @@
 use crate::lexer;
 use crate::lexer::{Span, TokenKind};
 use crate::parser;
-use crate::parser::{Expr, ExprKind, Stmt, StmtKind};
+use crate::parser::{
+    BindingPattern, Comment, Comments, Expr, ExprKind, InterpolationPart, ObjectKey, Pattern, Stmt,
+    StmtKind,
+};
 
 pub struct Formatter {
     indent: usize,
     output: String,
+    comments: Comments,
 }
 
 impl Default for Formatter {
@@ -19,12 +23,19 @@ impl Formatter {
         Self {
             indent: 0,
             output: String::new(),
+            comments: Comments::default(),
         }
     }
 
     pub fn format(&mut self, source: &str) -> Result<String, String> {
         let (tokens, lexer_errors) = lexer::tokenize_with_errors(source);
-        let (stmts, parser_errors) = parser::parse_with_errors(tokens);
+        let (stmts, comments, parser_errors) = parser::parse_with_comments(tokens);
+        self.comments = comments;
+        let source = source.strip_prefix('\u{FEFF}').unwrap_or(source);
+        if let Some(shebang) = lexer::shebang(source) {
+            self.output.push_str(shebang);
+            self.output.push('\n');
+        }
         self.format_statements(&stmts);
         if !lexer_errors.is_empty() || !parser_errors.is_empty() {
             return Err(lexer_errors
@@ -37,36 +48,101 @@ impl Formatter {
         Ok(self.output.clone())
     }
 
+    /// Renders already-parsed statements, returning everything written so far.
+    pub fn format_ast(&mut self, stmts: &[Stmt]) -> String {
+        self.format_statements(stmts);
+        self.output.clone()
+    }
+
     fn format_statements(&mut self, stmts: &[Stmt]) {
-        for stmt in stmts {
+        self.format_block_statements(None, stmts);
+    }
+
+    /// Formats the statements of the block whose span is `block` (`None` for
+    /// the top level), putting their comments back around them.
+    fn format_block_statements(&mut self, block: Option<Span>, stmts: &[Stmt]) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            let trivia = self
+                .comments
+                .statement(block, i)
+                .cloned()
+                .unwrap_or_default();
+            self.format_comments(&trivia.leading);
             self.format_statement(stmt);
+            // A final expression without `;` becomes the block's value, so keep the
+            // `;` that made it a plain statement.
+            if i + 1 == stmts.len() && matches!(stmt.kind, StmtKind::Expr(_)) {
+                self.output.push(';');
+            }
+            if let Some(comment) = trivia.trailing {
+                self.output.push(' ');
+                self.output.push_str(&comment.text);
+            }
             if !self.output.ends_with('\n') {
                 self.output.push('\n');
             }
         }
+        let dangling = self.comments.dangling(block).to_vec();
+        self.format_comments(&dangling);
+    }
+
+    fn format_comments(&mut self, comments: &[Comment]) {
+        for comment in comments {
+            self.add_indent();
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
     }
 
     fn format_statement(&mut self, stmt: &Stmt) {
         match &stmt.kind {
-            StmtKind::Let { name, value, .. } => {
+            StmtKind::Let {
+                name,
+                value,
+                constant,
+                ..
+            } => {
                 self.add_indent();
-                self.output.push_str("let ");
+                self.output
+                    .push_str(if *constant { "const " } else { "let " });
                 self.output.push_str(name);
+                if let Some(value) = value {
+                    self.output.push_str(" = ");
+                    self.format_expr(value);
+                }
+                self.output.push(';');
+            }
+            StmtKind::LetPattern { pattern, value } => {
+                let (open, close) = match pattern {
+                    BindingPattern::Array(_) => ("[", "]"),
+                    BindingPattern::Object(_) => ("{", "}"),
+                };
+                let names: Vec<&str> = pattern.names().iter().map(|(n, _)| n.as_str()).collect();
+                self.add_indent();
+                self.output.push_str("let ");
+                self.output.push_str(open);
+                self.output.push_str(&names.join(", "));
+                self.output.push_str(close);
                 self.output.push_str(" = ");
                 self.format_expr(value);
                 self.output.push(';');
             }
-            StmtKind::Function { name, params, body } => {
+            StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                doc,
+            } => {
+                if let Some(doc) = doc {
+                    self.format_doc(doc);
+                }
                 self.add_indent();
                 self.output.push_str("fn ");
                 self.output.push_str(name);
                 self.output.push('(');
-                for (i, param) in params.iter().enumerate() {
-                    if i > 0 {
-                        self.output.push_str(", ");
-                    }
-                    self.output.push_str(param);
-                }
+                self.output
+                    .push_str(&parser::format_params(params, rest.as_deref()));
                 self.output.push_str(") ");
                 self.format_expr(body);
             }
@@ -86,14 +162,43 @@ impl Formatter {
                     self.format_expr(expr);
                 }
             }
-            StmtKind::Break => {
+            StmtKind::Break(value) => {
                 self.add_indent();
                 self.output.push_str("break");
+                if let Some(expr) = value {
+                    self.output.push(' ');
+                    self.format_expr(expr);
+                }
             }
             StmtKind::Continue => {
                 self.add_indent();
                 self.output.push_str("continue");
             }
+            StmtKind::Export(declaration) => {
+                // The doc comment goes above `export`, not between it and `fn`.
+                let mut declaration = declaration.clone();
+                if let StmtKind::Function { doc, .. } = &mut declaration.kind
+                    && let Some(doc) = doc.take()
+                {
+                    self.format_doc(&doc);
+                }
+                self.add_indent();
+                self.output.push_str("export ");
+                let indent = std::mem::take(&mut self.indent);
+                self.format_statement(&declaration);
+                self.indent = indent;
+            }
+            StmtKind::Import { path, alias } => {
+                self.add_indent();
+                self.output.push_str("import ");
+                if let Some(alias) = alias {
+                    self.output.push_str(alias);
+                    self.output.push_str(" from ");
+                }
+                self.output.push('"');
+                self.output.push_str(&escape_string(path));
+                self.output.push('"');
+            }
             StmtKind::Struct { name, fields } => {
                 self.add_indent();
                 self.output.push_str("struct ");
@@ -127,6 +232,22 @@ impl Formatter {
                 self.output.push_str(&escape_string(s));
                 self.output.push('"');
             }
+            ExprKind::Interpolation(parts) => {
+                self.output.push('`');
+                for part in parts {
+                    match part {
+                        InterpolationPart::Literal(s) => {
+                            self.output.push_str(&escape_template(s));
+                        }
+                        InterpolationPart::Expr(expr) => {
+                            self.output.push_str("${");
+                            self.format_expr(expr);
+                            self.output.push('}');
+                        }
+                    }
+                }
+                self.output.push('`');
+            }
             ExprKind::Variable(name) => {
                 self.output.push_str(name);
             }
@@ -146,9 +267,19 @@ impl Formatter {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.output.push('"');
-                    self.output.push_str(key);
-                    self.output.push_str("\": ");
+                    match key {
+                        ObjectKey::Name(name) => {
+                            self.output.push('"');
+                            self.output.push_str(name);
+                            self.output.push('"');
+                        }
+                        ObjectKey::Computed(key) => {
+                            self.output.push('[');
+                            self.format_expr(key);
+                            self.output.push(']');
+                        }
+                    }
+                    self.output.push_str(": ");
                     self.format_expr(value);
                 }
                 self.output.push_str(" }");
@@ -178,32 +309,113 @@ impl Formatter {
                 self.output.push(' ');
                 self.format_expr(body);
             }
+            ExprKind::DoWhile { body, condition } => {
+                self.output.push_str("do ");
+                self.format_expr(body);
+                self.output.push_str(" while ");
+                self.format_expr(condition);
+            }
+            ExprKind::Spread(value) => {
+                self.output.push_str("...");
+                self.format_expr(value);
+            }
+            ExprKind::Loop(body) => {
+                self.output.push_str("loop ");
+                self.format_expr(body);
+            }
+            ExprKind::Collect(body) => {
+                self.output.push_str("collect ");
+                self.format_expr(body);
+            }
+            ExprKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                self.output.push_str("for ");
+                self.output.push_str(variable);
+                self.output.push_str(" in ");
+                self.format_expr(iterable);
+                self.output.push(' ');
+                self.format_expr(body);
+            }
+            ExprKind::Match { subject, arms } => {
+                self.output.push_str("match ");
+                self.format_expr(subject);
+                self.output.push_str(" {\n");
+                self.indent += 1;
+                for arm in arms {
+                    self.add_indent();
+                    match &arm.pattern {
+                        Pattern::Literal(expr) => self.format_expr(expr),
+                        Pattern::Wildcard => self.output.push('_'),
+                        Pattern::Binding(name) => self.output.push_str(name),
+                    }
+                    self.output.push_str(" => ");
+                    self.format_expr(&arm.body);
+                    self.output.push_str(",\n");
+                }
+                self.indent -= 1;
+                self.add_indent();
+                self.output.push('}');
+            }
             ExprKind::Block(statements) => {
                 self.output.push_str("{\n");
                 self.indent += 1;
-                for stmt in statements {
-                    self.format_statement(&Stmt {
-                        kind: stmt.clone(),
+                let statements: Vec<Stmt> = statements
+                    .iter()
+                    .map(|kind| Stmt {
+                        kind: kind.clone(),
                         span: Span { line: 0, column: 0 },
-                    });
-                    if !self.output.ends_with('\n') {
-                        self.output.push('\n');
-                    }
-                }
+                    })
+                    .collect();
+                self.format_block_statements(Some(expr.span), &statements);
                 self.indent -= 1;
                 self.add_indent();
                 self.output.push('}');
             }
+            ExprKind::BinaryOp {
+                left,
+                op: TokenKind::DotDot,
+                right,
+            } => {
+                self.format_operand(left, precedence(expr));
+                self.output.push_str("..");
+                self.format_operand(right, precedence(expr) + 1);
+            }
+            ExprKind::TypeTest {
+                expr: operand,
+                type_name,
+            } => {
+                self.format_operand(operand, precedence(expr));
+                self.output.push_str(" is ");
+                self.output.push_str(type_name);
+            }
+            ExprKind::Cast {
+                expr: operand,
+                type_name,
+            } => {
+                self.format_operand(operand, precedence(expr));
+                self.output.push_str(" as ");
+                self.output.push_str(type_name);
+            }
+            ExprKind::Assign { target, value } => {
+                self.format_expr(target);
+                self.output.push_str(" = ");
+                self.format_expr(value);
+            }
             ExprKind::BinaryOp { left, op, right } => {
-                self.format_expr(left);
+                // Operators are left-associative, so an equal-precedence right
+                // operand needs parentheses to keep its grouping.
+                self.format_operand(left, precedence(expr));
                 self.output.push(' ');
                 self.output.push_str(&token_kind_to_string(op));
                 self.output.push(' ');
-                self.format_expr(right);
+                self.format_operand(right, precedence(expr) + 1);
             }
-            ExprKind::UnaryOp { op, expr } => {
+            ExprKind::UnaryOp { op, expr: operand } => {
                 self.output.push_str(&token_kind_to_string(op));
-                self.format_expr(expr);
+                self.format_operand(operand, precedence(expr));
             }
             ExprKind::FunctionCall { name, args } => {
                 self.output.push_str(name);
@@ -216,15 +428,51 @@ impl Formatter {
                 }
                 self.output.push(')');
             }
+            ExprKind::MethodCall {
+                object,
+                method,
+                args,
+            } => {
+                self.format_operand(object, POSTFIX);
+                self.output.push('.');
+                self.output.push_str(method);
+                self.output.push('(');
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.format_expr(arg);
+                }
+                self.output.push(')');
+            }
+            ExprKind::Lambda { params, rest, body } => {
+                self.output.push_str("fn(");
+                self.output
+                    .push_str(&parser::format_params(params, rest.as_deref()));
+                self.output.push_str(") ");
+                self.format_expr(body);
+            }
             ExprKind::Index { object, index } => {
-                self.format_expr(object);
+                self.format_operand(object, POSTFIX);
                 self.output.push('[');
                 self.format_expr(index);
                 self.output.push(']');
             }
+            ExprKind::Slice { object, start, end } => {
+                self.format_operand(object, POSTFIX);
+                self.output.push('[');
+                if let Some(start) = start {
+                    self.format_expr(start);
+                }
+                self.output.push_str("..");
+                if let Some(end) = end {
+                    self.format_expr(end);
+                }
+                self.output.push(']');
+            }
             ExprKind::GetProperty { object, property } => {
-                self.format_expr(object);
-                self.output.push(':');
+                self.format_operand(object, POSTFIX);
+                self.output.push('.');
                 self.output.push_str(property);
             }
             ExprKind::StructInstance { name, args } => {
@@ -241,6 +489,29 @@ impl Formatter {
         }
     }
 
+    /// Formats `expr`, parenthesized if it binds looser than `min_precedence`.
+    fn format_operand(&mut self, expr: &Expr, min_precedence: u8) {
+        if precedence(expr) < min_precedence {
+            self.output.push('(');
+            self.format_expr(expr);
+            self.output.push(')');
+        } else {
+            self.format_expr(expr);
+        }
+    }
+
+    fn format_doc(&mut self, doc: &str) {
+        for line in doc.lines() {
+            self.add_indent();
+            self.output.push_str("///");
+            if !line.is_empty() {
+                self.output.push(' ');
+                self.output.push_str(line);
+            }
+            self.output.push('\n');
+        }
+    }
+
     fn add_indent(&mut self) {
         for _ in 0..self.indent {
             self.output.push_str("    ");
@@ -248,19 +519,43 @@ impl Formatter {
     }
 }
 
+const PREFIX: u8 = 14;
+const POSTFIX: u8 = 15;
+
+/// How tightly `expr` binds, on the parser's binary operator scale.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        // Both extend as far right as they can.
+        ExprKind::Assign { .. } | ExprKind::Lambda { .. } => 0,
+        ExprKind::BinaryOp { op, .. } => parser::binding_power(op).unwrap_or(0),
+        ExprKind::TypeTest { .. } => parser::binding_power(&TokenKind::Is).unwrap_or(0),
+        ExprKind::Cast { .. } => parser::binding_power(&TokenKind::As).unwrap_or(0),
+        ExprKind::UnaryOp { .. } => PREFIX,
+        _ => POSTFIX,
+    }
+}
+
 fn token_kind_to_string(kind: &TokenKind) -> String {
     match kind {
         TokenKind::Plus => "+".to_string(),
         TokenKind::Minus => "-".to_string(),
         TokenKind::Multiply => "*".to_string(),
         TokenKind::Divide => "/".to_string(),
+        TokenKind::FloorDivide => "~/".to_string(),
         TokenKind::Modulo => "%".to_string(),
         TokenKind::Assign => "=".to_string(),
         TokenKind::Equal => "==".to_string(),
         TokenKind::NotEqual => "!=".to_string(),
         TokenKind::LogicalAnd => "&&".to_string(),
         TokenKind::LogicalOr => "||".to_string(),
+        TokenKind::NilCoalesce => "??".to_string(),
         TokenKind::Not => "!".to_string(),
+        TokenKind::BitAnd => "&".to_string(),
+        TokenKind::BitOr => "|".to_string(),
+        TokenKind::BitXor => "^".to_string(),
+        TokenKind::BitNot => "~".to_string(),
+        TokenKind::ShiftLeft => "<<".to_string(),
+        TokenKind::ShiftRight => ">>".to_string(),
         TokenKind::GreaterThan => ">".to_string(),
         TokenKind::GreaterThanOrEqual => ">=".to_string(),
         TokenKind::LessThan => "<".to_string(),
@@ -278,6 +573,23 @@ fn escape_string(s: &str) -> String {
             '\r' => result.push_str("\\r"),
             '"' => result.push_str("\\\""),
             '\\' => result.push_str("\\\\"),
+            '\0' => result.push_str("\\0"),
+            c if c.is_control() => result.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn escape_template(s: &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '`' => result.push_str("\\`"),
+            '$' => result.push_str("\\$"),
+            '\\' => result.push_str("\\\\"),
             _ => result.push(c),
         }
     }
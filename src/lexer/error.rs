@@ -1,7 +1,9 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::diagnostic::Diagnostic;
 use crate::lexer::Span;
+use crate::messages::Message;
 
 impl Error for LexerError {}
 
@@ -35,27 +37,58 @@ pub enum LexerErrorKind {
     UnexpectedCharacter(char),
     UnclosedString,
     UnclosedComment,
-    InvalidEscape(char),
+    /// The offending sequence as written, e.g. `\\u{110000}`.
+    InvalidEscape(String),
+    /// A `'...'` literal that doesn't hold exactly one character.
+    InvalidChar(String),
+}
+
+impl LexerErrorKind {
+    /// A stable identifier for this kind of error, for tools to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexerErrorKind::InvalidNumber(_) => "E0101",
+            LexerErrorKind::UnexpectedCharacter(_) => "E0102",
+            LexerErrorKind::UnclosedString => "E0103",
+            LexerErrorKind::UnclosedComment => "E0104",
+            LexerErrorKind::InvalidEscape(_) => "E0105",
+            LexerErrorKind::InvalidChar(_) => "E0106",
+        }
+    }
 }
 
 impl fmt::Display for LexerErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            LexerErrorKind::InvalidNumber(s) => write!(f, "Invalid number: '{s}'"),
+        let text = match self {
+            LexerErrorKind::InvalidNumber(s) => Message::InvalidNumber.format(&[("text", s)]),
             LexerErrorKind::UnexpectedCharacter(c) => {
-                write!(f, "Unexpected character: '{c}'")
+                Message::UnexpectedCharacter.format(&[("text", c)])
             }
-            LexerErrorKind::UnclosedString => write!(f, "Unclosed string"),
-            LexerErrorKind::UnclosedComment => write!(f, "Unclosed comment"),
-            LexerErrorKind::InvalidEscape(c) => {
-                write!(f, "Invalid escape sequence: '{c}'")
+            LexerErrorKind::UnclosedString => Message::UnclosedString.text().to_string(),
+            LexerErrorKind::UnclosedComment => Message::UnclosedComment.text().to_string(),
+            LexerErrorKind::InvalidEscape(sequence) => {
+                Message::InvalidEscape.format(&[("text", sequence)])
             }
-        }
+            LexerErrorKind::InvalidChar(literal) => {
+                Message::InvalidChar.format(&[("text", literal)])
+            }
+        };
+        write!(f, "{text}")
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Self {
+        Diagnostic::error(
+            Some(error.span),
+            Message::Detailed.format(&[("kind", &error.kind), ("detail", &error.message)]),
+        )
+        .with_code(error.kind.code())
     }
 }
 
 impl fmt::Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}: {}", self.kind, self.message)
+        write!(f, "{}", Diagnostic::from(self))
     }
 }
@@ -1,17 +1,26 @@
 mod error;
 mod token;
 
+use std::collections::VecDeque;
 use std::str::Chars;
 
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::messages::Message;
+use crate::runtime::environment::value::Number;
+
 pub use error::LexerError;
 pub use error::LexerErrorKind;
 pub use token::Span;
+pub use token::TemplatePart;
 pub use token::Token;
 pub use token::TokenKind;
 
 struct Cursor<'a> {
+    source: &'a str,
     input: Chars<'a>,
-    pos: usize,
+    /// Byte offset in `source` where the current token starts.
+    start: usize,
     line: usize,
     column: usize,
     start_line: usize,
@@ -20,21 +29,21 @@ struct Cursor<'a> {
 }
 
 impl<'a> Cursor<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str, line: usize, column: usize) -> Self {
         Cursor {
+            source: input,
             input: input.chars(),
-            pos: 0,
-            line: 1,
-            column: 1,
-            start_line: 1,
-            start_column: 1,
+            start: 0,
+            line,
+            column,
+            start_line: line,
+            start_column: column,
             errors: Vec::new(),
         }
     }
 
     fn next(&mut self) -> Option<char> {
         let c = self.input.next()?;
-        self.pos += 1;
         if c == '\n' {
             self.line += 1;
             self.column = 1;
@@ -56,11 +65,42 @@ impl<'a> Cursor<'a> {
         self.next()
     }
 
+    /// Whether the cursor is at `\n` or a Windows-style `\r\n`.
+    fn at_line_break(&self) -> bool {
+        match self.peek() {
+            Some('\n') => true,
+            Some('\r') => self.peek_n(1) == Some('\n'),
+            _ => false,
+        }
+    }
+
+    /// Consumes a line break, reading `\r\n` as a single `\n`.
+    fn eat_line_break(&mut self) -> bool {
+        if !self.at_line_break() {
+            return false;
+        }
+        if self.bump() == Some('\r') {
+            self.bump();
+        }
+        true
+    }
+
     fn start_token(&mut self) {
+        self.start = self.source.len() - self.input.as_str().len();
         self.start_line = self.line;
         self.start_column = self.column;
     }
 
+    /// A token ending at the cursor, spanning back to the last `start_token`.
+    fn token(&self, kind: TokenKind) -> Token {
+        let end = self.source.len() - self.input.as_str().len();
+        Token {
+            kind,
+            span: self.span(),
+            lexeme: self.source[self.start..end].to_string(),
+        }
+    }
+
     fn span(&self) -> Span {
         Span {
             line: self.start_line,
@@ -68,24 +108,17 @@ impl<'a> Cursor<'a> {
         }
     }
 
-    fn errors(&self) -> &[LexerError] {
-        &self.errors
-    }
-
     fn skip_whitespace(&mut self) -> Option<Token> {
         while let Some(c) = self.peek() {
+            if self.at_line_break() {
+                self.start_token();
+                self.eat_line_break();
+                return Some(self.token(TokenKind::Newline));
+            }
             match c {
                 ' ' | '\t' | '\r' => {
                     self.bump();
                 }
-                '\n' => {
-                    self.start_token();
-                    self.bump();
-                    return Some(Token {
-                        kind: TokenKind::Newline,
-                        span: self.span(),
-                    });
-                }
                 _ => break,
             }
         }
@@ -97,17 +130,25 @@ impl<'a> Cursor<'a> {
             self.start_token();
             self.bump();
             self.bump();
+            // `///` starts a doc comment, but a longer run of slashes doesn't.
+            let doc = self.peek() == Some('/') && self.peek_n(1) != Some('/');
+            if doc {
+                self.bump();
+            }
             let mut comment = String::new();
             while let Some(c) = self.peek() {
-                if c == '\n' {
+                if self.at_line_break() {
                     break;
                 }
-                comment.push(self.bump()?);
+                comment.push(c);
+                self.bump();
             }
-            return Some(Token {
-                kind: TokenKind::Comment(comment),
-                span: self.span(),
-            });
+            let kind = if doc {
+                TokenKind::DocComment(comment)
+            } else {
+                TokenKind::Comment(comment)
+            };
+            return Some(self.token(kind));
         }
         None
     }
@@ -119,16 +160,21 @@ impl<'a> Cursor<'a> {
             self.bump();
             let mut comment = String::new();
             let mut depth = 1;
+            // Comments nest, so commenting out code that has comments works.
             while let Some(c) = self.bump() {
                 if c == '/' && self.peek() == Some('*') {
                     self.bump();
                     depth += 1;
+                    comment.push_str("/*");
+                    continue;
                 } else if c == '*' && self.peek() == Some('/') {
                     self.bump();
                     depth -= 1;
                     if depth == 0 {
                         break;
                     }
+                    comment.push_str("*/");
+                    continue;
                 }
                 comment.push(c);
             }
@@ -136,17 +182,11 @@ impl<'a> Cursor<'a> {
                 self.errors.push(LexerError::new(
                     self.span(),
                     LexerErrorKind::UnclosedComment,
-                    "Unclosed block comment".to_string(),
+                    Message::UnclosedBlockComment.text().to_string(),
                 ));
-                return Some(Token {
-                    kind: TokenKind::Comment(comment),
-                    span: self.span(),
-                });
+                return Some(self.token(TokenKind::Comment(comment)));
             }
-            return Some(Token {
-                kind: TokenKind::Comment(comment),
-                span: self.span(),
-            });
+            return Some(self.token(TokenKind::Comment(comment)));
         }
         None
     }
@@ -157,15 +197,69 @@ impl<'a> Cursor<'a> {
         }
 
         self.start_token();
+        if self.peek() == Some('0') {
+            let radix = match self.peek_n(1) {
+                Some('x' | 'X') => Some(16),
+                Some('o' | 'O') => Some(8),
+                Some('b' | 'B') => Some(2),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                return Some(self.read_radix_number(radix));
+            }
+        }
         let mut num_str = String::new();
         let mut has_dot = false;
 
         while let Some(c) = self.peek() {
             if c.is_ascii_digit() {
                 num_str.push(self.bump()?);
-            } else if c == '.' && !has_dot {
+            } else if c == '_' && self.peek_n(1).is_some_and(|c| c.is_ascii_digit()) {
+                // Digit separators are dropped before parsing.
+                self.bump();
+            } else if c == '_' {
+                return Some(self.invalid_number(Message::MisplacedDigitSeparator.text()));
+            } else if matches!(c, 'e' | 'E') && self.at_exponent() {
+                num_str.push(self.bump()?);
+                if let Some(sign @ ('+' | '-')) = self.peek() {
+                    num_str.push(sign);
+                    self.bump();
+                }
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_digit() {
+                        num_str.push(c);
+                    } else if c == '_' && !self.peek_n(1).is_some_and(|c| c.is_ascii_digit()) {
+                        return Some(self.invalid_number(Message::MisplacedDigitSeparator.text()));
+                    } else if c != '_' {
+                        break;
+                    }
+                    self.bump();
+                }
+                break;
+            } else if matches!(c, 'e' | 'E') && num_str.ends_with(|c: char| c.is_ascii_digit()) {
+                self.bump();
+                if let Some('+' | '-') = self.peek() {
+                    self.bump();
+                }
+                return Some(self.invalid_number(Message::EmptyExponent.text()));
+            } else if c == '.' && !has_dot && self.peek_n(1) != Some('.') {
                 has_dot = true;
                 num_str.push(self.bump()?);
+            } else if c == '.' && has_dot && self.peek_n(1).is_some_and(|c| c.is_ascii_digit()) {
+                let mut literal = num_str.clone();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_digit() || c == '.' {
+                        literal.push(self.bump()?);
+                    } else {
+                        break;
+                    }
+                }
+                self.errors.push(LexerError::new(
+                    self.span(),
+                    LexerErrorKind::InvalidNumber(literal),
+                    Message::ExtraDecimalPoint.text().to_string(),
+                ));
+                break;
             } else {
                 break;
             }
@@ -173,10 +267,74 @@ impl<'a> Cursor<'a> {
 
         let kind = TokenKind::Number(num_str.parse().ok()?);
 
-        Some(Token {
-            kind,
-            span: self.span(),
-        })
+        Some(self.token(kind))
+    }
+
+    /// Reports the number literal read so far, plus the rest of the word it
+    /// runs into, as malformed. The literal becomes `0` so lexing can carry on.
+    fn invalid_number(&mut self, message: &str) -> Token {
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.bump();
+        }
+        let end = self.source.len() - self.input.as_str().len();
+        self.errors.push(LexerError::new(
+            self.span(),
+            LexerErrorKind::InvalidNumber(self.source[self.start..end].to_string()),
+            message.to_string(),
+        ));
+        self.token(TokenKind::Number(Number::Int(0)))
+    }
+
+    /// Whether the `e` under the cursor starts an exponent such as `e5` or `e-3`.
+    fn at_exponent(&self) -> bool {
+        match self.peek_n(1) {
+            Some('+' | '-') => self.peek_n(2).is_some_and(|c| c.is_ascii_digit()),
+            next => next.is_some_and(|c| c.is_ascii_digit()),
+        }
+    }
+
+    /// Reads a `0x`, `0o` or `0b` literal. Malformed digits are reported and the
+    /// literal becomes `0` so lexing can carry on.
+    fn read_radix_number(&mut self, radix: u32) -> Token {
+        let mut literal = String::new();
+        for _ in 0..2 {
+            literal.extend(self.bump());
+        }
+        let mut digits = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            literal.push(c);
+            if c != '_' {
+                digits.push(c);
+            }
+            self.bump();
+        }
+
+        let body = &literal[2..];
+        let value = if digits.is_empty() {
+            Err(Message::MissingDigits.format(&[("prefix", &literal)]))
+        } else if body.starts_with('_') || body.ends_with('_') || body.contains("__") {
+            Err(Message::MisplacedDigitSeparator.text().to_string())
+        } else if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+            Err(Message::InvalidDigit.format(&[("digit", &c), ("radix", &radix)]))
+        } else {
+            i128::from_str_radix(&digits, radix)
+                .map_err(|_| Message::NumberTooLarge.text().to_string())
+        };
+        let value = value.unwrap_or_else(|message| {
+            self.errors.push(LexerError::new(
+                self.span(),
+                LexerErrorKind::InvalidNumber(literal),
+                message,
+            ));
+            0
+        });
+        self.token(TokenKind::Number(Number::Int(value)))
     }
 
     fn read_string(&mut self) -> Option<Token> {
@@ -185,66 +343,33 @@ impl<'a> Cursor<'a> {
         }
 
         self.start_token();
+        if self.peek_n(1) == Some('"') && self.peek_n(2) == Some('"') {
+            return Some(self.read_multiline_string());
+        }
         self.bump();
         let mut s = String::new();
 
         while let Some(c) = self.peek() {
             if c == '"' {
                 self.bump();
-                return Some(Token {
-                    kind: TokenKind::String(s),
-                    span: self.span(),
-                });
+                return Some(self.token(TokenKind::String(s)));
             } else if c == '\\' {
                 self.bump();
-                match self.peek() {
-                    Some('n') => {
-                        s.push('\n');
-                        self.bump();
-                    }
-                    Some('t') => {
-                        s.push('\t');
-                        self.bump();
-                    }
-                    Some('r') => {
-                        s.push('\r');
-                        self.bump();
-                    }
-                    Some('\\') => {
-                        s.push('\\');
-                        self.bump();
-                    }
-                    Some('"') => {
-                        s.push('"');
-                        self.bump();
-                    }
-                    Some(c) => {
-                        s.push('\\');
-                        s.push(c);
-                        self.bump();
-                    }
-                    None => {
-                        self.errors.push(LexerError::new(
-                            self.span(),
-                            LexerErrorKind::UnclosedString,
-                            "Unclosed string".to_string(),
-                        ));
-                        return Some(Token {
-                            kind: TokenKind::String(s),
-                            span: self.span(),
-                        });
-                    }
+                if !self.read_escape(&mut s) {
+                    self.errors.push(LexerError::new(
+                        self.span(),
+                        LexerErrorKind::UnclosedString,
+                        Message::UnclosedString.text().to_string(),
+                    ));
+                    return Some(self.token(TokenKind::String(s)));
                 }
-            } else if c == '\n' {
+            } else if self.at_line_break() {
                 self.errors.push(LexerError::new(
                     self.span(),
                     LexerErrorKind::UnclosedString,
-                    "Unclosed string".to_string(),
+                    Message::UnclosedString.text().to_string(),
                 ));
-                return Some(Token {
-                    kind: TokenKind::String(s),
-                    span: self.span(),
-                });
+                return Some(self.token(TokenKind::String(s)));
             } else {
                 s.push(self.bump()?);
             }
@@ -253,23 +378,293 @@ impl<'a> Cursor<'a> {
         self.errors.push(LexerError::new(
             self.span(),
             LexerErrorKind::UnclosedString,
-            "Unclosed string".to_string(),
+            Message::UnclosedString.text().to_string(),
         ));
-        Some(Token {
-            kind: TokenKind::String(s),
-            span: self.span(),
-        })
+        Some(self.token(TokenKind::String(s)))
+    }
+
+    /// Reads a `"""` string, which may span lines. A newline right after the
+    /// opening quotes is dropped so the text can start on its own line.
+    fn read_multiline_string(&mut self) -> Token {
+        for _ in 0..3 {
+            self.bump();
+        }
+        self.eat_line_break();
+        let mut s = String::new();
+        loop {
+            if self.eat_line_break() {
+                s.push('\n');
+                continue;
+            }
+            match self.peek() {
+                Some('"') if self.peek_n(1) == Some('"') && self.peek_n(2) == Some('"') => {
+                    for _ in 0..3 {
+                        self.bump();
+                    }
+                    break;
+                }
+                Some('\\') => {
+                    self.bump();
+                    if !self.read_escape(&mut s) {
+                        self.unclosed_multiline_string();
+                        break;
+                    }
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.bump();
+                }
+                None => {
+                    self.unclosed_multiline_string();
+                    break;
+                }
+            }
+        }
+        self.token(TokenKind::String(s))
+    }
+
+    fn unclosed_multiline_string(&mut self) {
+        self.errors.push(LexerError::new(
+            self.span(),
+            LexerErrorKind::UnclosedString,
+            Message::UnclosedMultilineString.text().to_string(),
+        ));
+    }
+
+    /// Reads the character(s) following a `\` into `s`. Returns false at end of input.
+    fn read_escape(&mut self, s: &mut String) -> bool {
+        let Some(c) = self.bump() else {
+            return false;
+        };
+        match c {
+            'n' => s.push('\n'),
+            't' => s.push('\t'),
+            'r' => s.push('\r'),
+            '0' => s.push('\0'),
+            '\\' => s.push('\\'),
+            '"' => s.push('"'),
+            '\'' => s.push('\''),
+            'x' => {
+                let digits = self.read_hex_digits(2);
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 && byte.is_ascii() => s.push(byte as char),
+                    _ => self
+                        .invalid_escape(format!("\\x{digits}"), Message::InvalidHexEscape.text()),
+                }
+            }
+            'u' => self.read_unicode_escape(s),
+            c => self.invalid_escape(format!("\\{c}"), Message::UnknownEscape.text()),
+        }
+        true
+    }
+
+    /// Reads `{1F600}` after `\u`.
+    fn read_unicode_escape(&mut self, s: &mut String) {
+        let mut sequence = String::from("\\u");
+        let open = self.peek() == Some('{');
+        if open {
+            sequence.push(self.bump().unwrap_or('{'));
+        }
+        let digits = self.read_hex_digits(6);
+        sequence.push_str(&digits);
+        let close = open && self.peek() == Some('}');
+        if close {
+            sequence.push(self.bump().unwrap_or('}'));
+        }
+        if !open || !close || digits.is_empty() {
+            self.invalid_escape(sequence, Message::InvalidUnicodeEscape.text());
+            return;
+        }
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(c) => s.push(c),
+            None => self.invalid_escape(sequence, Message::InvalidCodePoint.text()),
+        }
+    }
+
+    fn read_hex_digits(&mut self, max: usize) -> String {
+        let mut digits = String::new();
+        while digits.len() < max
+            && let Some(c) = self.peek().filter(char::is_ascii_hexdigit)
+        {
+            digits.push(c);
+            self.bump();
+        }
+        digits
+    }
+
+    fn invalid_escape(&mut self, sequence: String, message: &str) {
+        self.errors.push(LexerError::new(
+            self.span(),
+            LexerErrorKind::InvalidEscape(sequence),
+            message.to_string(),
+        ));
+    }
+
+    /// Reads a `'c'` literal, which becomes a one-character string.
+    fn read_char(&mut self) -> Option<Token> {
+        if self.peek() != Some('\'') {
+            return None;
+        }
+
+        self.start_token();
+        self.bump();
+        let mut s = String::new();
+        let errors_before = self.errors.len();
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    self.bump();
+                    break;
+                }
+                Some('\\') => {
+                    self.bump();
+                    if !self.read_escape(&mut s) {
+                        self.unclosed_char();
+                        return Some(self.token(TokenKind::String(s)));
+                    }
+                }
+                Some('\n') | None => {
+                    self.unclosed_char();
+                    return Some(self.token(TokenKind::String(s)));
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.bump();
+                }
+            }
+        }
+
+        // A bad escape was reported already and left nothing behind.
+        if s.chars().count() != 1 && self.errors.len() == errors_before {
+            self.errors.push(LexerError::new(
+                self.span(),
+                LexerErrorKind::InvalidChar(format!("'{}'", s.escape_debug())),
+                Message::CharLiteralLength.text().to_string(),
+            ));
+        }
+        Some(self.token(TokenKind::String(s)))
     }
 
+    fn unclosed_char(&mut self) {
+        self.errors.push(LexerError::new(
+            self.span(),
+            LexerErrorKind::UnclosedString,
+            Message::UnclosedCharLiteral.text().to_string(),
+        ));
+    }
+
+    fn read_template(&mut self) -> Option<Token> {
+        if self.peek() != Some('`') {
+            return None;
+        }
+
+        self.start_token();
+        let span = self.span();
+        self.bump();
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '`' => {
+                    self.bump();
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(literal));
+                    }
+                    return Some(self.token(TokenKind::Template(parts)));
+                }
+                '\\' => {
+                    self.bump();
+                    match self.peek() {
+                        Some(c @ ('`' | '$')) => {
+                            literal.push(c);
+                            self.bump();
+                        }
+                        _ => {
+                            if !self.read_escape(&mut literal) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                '$' if self.peek_n(1) == Some('{') => {
+                    self.bump();
+                    self.bump();
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    let (line, column) = (self.line, self.column);
+                    let Some(code) = self.read_template_code() else {
+                        break;
+                    };
+                    let (tokens, errors) = tokenize_at(&code, line, column);
+                    self.errors.extend(errors);
+                    parts.push(TemplatePart::Code(tokens));
+                }
+                _ if self.eat_line_break() => literal.push('\n'),
+                _ => {
+                    literal.push(c);
+                    self.bump();
+                }
+            }
+        }
+
+        self.errors.push(LexerError::new(
+            span,
+            LexerErrorKind::UnclosedString,
+            Message::UnclosedTemplateString.text().to_string(),
+        ));
+        Some(self.token(TokenKind::Template(parts)))
+    }
+
+    /// Collects the source of a `${ ... }` placeholder up to its matching `}`.
+    fn read_template_code(&mut self) -> Option<String> {
+        let mut code = String::new();
+        let mut depth = 1;
+        // The quote that opened the string or character literal we're inside.
+        let mut quote = None;
+        while let Some(c) = self.bump() {
+            if let Some(open) = quote {
+                if c == '\\' {
+                    code.push(c);
+                    code.push(self.bump()?);
+                    continue;
+                }
+                if c == open {
+                    quote = None;
+                }
+            } else {
+                match c {
+                    '"' | '\'' => quote = Some(c),
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(code);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            code.push(c);
+        }
+        None
+    }
+
+    /// Identifiers follow Unicode's XID rules, so names like `总数` work.
     fn read_identifier(&mut self) -> Option<Token> {
-        if !self.peek()?.is_alphabetic() && self.peek() != Some('_') {
+        let first = self.peek()?;
+        if !is_xid_start(first) && first != '_' {
             return None;
         }
 
         self.start_token();
         let mut ident = String::new();
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() || c == '_' {
+            if is_xid_continue(c) {
                 ident.push(self.bump()?);
             } else {
                 break;
@@ -280,21 +675,29 @@ impl<'a> Cursor<'a> {
             "true" => TokenKind::Boolean(true),
             "false" => TokenKind::Boolean(false),
             "let" => TokenKind::Let,
+            "const" => TokenKind::Const,
             "fn" => TokenKind::Fn,
             "if" => TokenKind::If,
             "else" => TokenKind::Else,
             "while" => TokenKind::While,
+            "loop" => TokenKind::Loop,
+            "collect" => TokenKind::Collect,
+            "do" => TokenKind::Do,
+            "for" => TokenKind::For,
+            "in" => TokenKind::In,
             "break" => TokenKind::Break,
             "continue" => TokenKind::Continue,
             "return" => TokenKind::Return,
             "struct" => TokenKind::Struct,
+            "import" => TokenKind::Import,
+            "export" => TokenKind::Export,
+            "match" => TokenKind::Match,
+            "is" => TokenKind::Is,
+            "as" => TokenKind::As,
             _ => TokenKind::Identifier(ident),
         };
 
-        Some(Token {
-            kind,
-            span: self.span(),
-        })
+        Some(self.token(kind))
     }
 
     fn read_punct(&mut self) -> Option<Token> {
@@ -318,10 +721,15 @@ impl<'a> Cursor<'a> {
                 if self.peek_n(1) == Some('&') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::LogicalAnd,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::LogicalAnd));
+                }
+                TokenKind::BitAnd
+            }
+            '?' => {
+                if self.peek_n(1) == Some('?') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::NilCoalesce));
                 }
                 return None;
             }
@@ -329,21 +737,30 @@ impl<'a> Cursor<'a> {
                 if self.peek_n(1) == Some('|') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::LogicalOr,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::LogicalOr));
                 }
-                return None;
+                TokenKind::BitOr
+            }
+            '^' => TokenKind::BitXor,
+            '~' => {
+                // `//` already starts a comment, so floor division is spelled `~/`.
+                if self.peek_n(1) == Some('/') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::FloorDivide));
+                }
+                TokenKind::BitNot
             }
             '=' => {
                 if self.peek_n(1) == Some('=') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::Equal,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::Equal));
+                }
+                if self.peek_n(1) == Some('>') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::FatArrow));
                 }
                 TokenKind::Assign
             }
@@ -351,25 +768,21 @@ impl<'a> Cursor<'a> {
                 if self.peek_n(1) == Some('=') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::NotEqual,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::NotEqual));
                 }
                 self.bump();
-                return Some(Token {
-                    kind: TokenKind::Not,
-                    span: self.span(),
-                });
+                return Some(self.token(TokenKind::Not));
             }
             '>' => {
                 if self.peek_n(1) == Some('=') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::GreaterThanOrEqual,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::GreaterThanOrEqual));
+                }
+                if self.peek_n(1) == Some('>') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::ShiftRight));
                 }
                 TokenKind::GreaterThan
             }
@@ -377,88 +790,218 @@ impl<'a> Cursor<'a> {
                 if self.peek_n(1) == Some('=') {
                     self.bump();
                     self.bump();
-                    return Some(Token {
-                        kind: TokenKind::LessThanOrEqual,
-                        span: self.span(),
-                    });
+                    return Some(self.token(TokenKind::LessThanOrEqual));
+                }
+                if self.peek_n(1) == Some('<') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::ShiftLeft));
                 }
                 TokenKind::LessThan
             }
             ':' => TokenKind::Colon,
+            '.' => {
+                if self.peek_n(1) == Some('.') && self.peek_n(2) == Some('.') {
+                    self.bump();
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::Ellipsis));
+                }
+                if self.peek_n(1) == Some('.') {
+                    self.bump();
+                    self.bump();
+                    return Some(self.token(TokenKind::DotDot));
+                }
+                TokenKind::Dot
+            }
             _ => return None,
         };
         self.bump();
-        Some(Token {
-            kind,
-            span: self.span(),
-        })
+        Some(self.token(kind))
     }
 }
 
-pub fn tokenize(input: &str) -> Vec<Token> {
-    let (tokens, _) = tokenize_with_errors(input);
-    tokens
+/// Lexes source text on demand, one token at a time.
+///
+/// Problems are yielded as `Err` items where they occur and lexing carries on
+/// past them. The last item is always the `Eof` token.
+pub struct Lexer<'a> {
+    cursor: Cursor<'a>,
+    /// Open brackets, innermost last. Line breaks directly inside `(...)` or
+    /// `[...]` aren't statement ends, while those in a `{...}` still are.
+    brackets: Vec<TokenKind>,
+    /// The last token other than a comment, which decides whether a line
+    /// break carries it on to the next line.
+    last: Option<TokenKind>,
+    pending: VecDeque<Result<Token, LexerError>>,
+    finished: bool,
 }
 
-pub fn tokenize_with_errors(input: &str) -> (Vec<Token>, Vec<LexerError>) {
-    let mut cursor = Cursor::new(input);
-    let mut tokens = Vec::new();
-
-    while cursor.peek().is_some() {
-        cursor.start_token();
-
-        if let Some(newline_token) = cursor.skip_whitespace() {
-            tokens.push(newline_token);
-            continue;
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        // Editors on Windows often save files with a byte order mark.
+        let input = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+        // A leading `#!` line lets scripts be made executable on Unix.
+        match shebang(input) {
+            Some(shebang) => Self::at(&input[shebang.len()..], 1, shebang.chars().count() + 1),
+            None => Self::at(input, 1, 1),
         }
+    }
 
-        if cursor.peek().is_none() {
-            break;
+    fn at(input: &'a str, line: usize, column: usize) -> Self {
+        Self {
+            cursor: Cursor::new(input, line, column),
+            brackets: Vec::new(),
+            last: None,
+            pending: VecDeque::new(),
+            finished: false,
         }
+    }
 
-        if let Some(comment) = cursor.skip_line_comment() {
-            tokens.push(comment);
-            continue;
-        }
+    /// Reads the next token, or `None` once the input is used up. Errors are
+    /// left in the cursor for the caller to pass on.
+    fn next_token(&mut self) -> Option<Token> {
+        let cursor = &mut self.cursor;
+        while cursor.peek().is_some() {
+            cursor.start_token();
+
+            if let Some(newline_token) = cursor.skip_whitespace() {
+                let in_brackets = matches!(
+                    self.brackets.last(),
+                    Some(TokenKind::LeftParen | TokenKind::LeftBracket)
+                );
+                let continued = self.last.as_ref().is_some_and(continues_line);
+                if !in_brackets && !continued {
+                    return Some(newline_token);
+                }
+                continue;
+            }
 
-        if let Some(token) = cursor.skip_block_comment() {
-            tokens.push(token);
-            continue;
-        }
+            if cursor.peek().is_none() {
+                break;
+            }
 
-        if let Some(token) = cursor.read_number() {
-            tokens.push(token);
-            continue;
-        }
+            let token = cursor
+                .skip_line_comment()
+                .or_else(|| cursor.skip_block_comment())
+                .or_else(|| cursor.read_number())
+                .or_else(|| cursor.read_string())
+                .or_else(|| cursor.read_char())
+                .or_else(|| cursor.read_template())
+                .or_else(|| cursor.read_identifier())
+                .or_else(|| cursor.read_punct());
+            if let Some(token) = token {
+                match token.kind {
+                    TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => {
+                        self.brackets.push(token.kind.clone());
+                    }
+                    TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                        self.brackets.pop();
+                    }
+                    _ => {}
+                }
+                return Some(token);
+            }
 
-        if let Some(token) = cursor.read_string() {
-            tokens.push(token);
-            continue;
+            let c = cursor.peek()?;
+            cursor.errors.push(LexerError::new(
+                cursor.span(),
+                LexerErrorKind::UnexpectedCharacter(c),
+                Message::UnexpectedCharacter.format(&[("text", &c)]),
+            ));
+            cursor.bump();
         }
+        None
+    }
+}
 
-        if let Some(token) = cursor.read_identifier() {
-            tokens.push(token);
-            continue;
-        }
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, LexerError>;
 
-        if let Some(token) = cursor.read_punct() {
-            tokens.push(token);
-            continue;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            if self.finished {
+                return None;
+            }
+            let token = self.next_token().unwrap_or_else(|| {
+                self.finished = true;
+                Token {
+                    kind: TokenKind::Eof,
+                    span: self.cursor.span(),
+                    lexeme: String::new(),
+                }
+            });
+            if !matches!(token.kind, TokenKind::Comment(_) | TokenKind::DocComment(_)) {
+                self.last = Some(token.kind.clone());
+            }
+            self.pending.extend(self.cursor.errors.drain(..).map(Err));
+            self.pending.push_back(Ok(token));
         }
+        self.pending.pop_front()
+    }
+}
 
-        let c = cursor.peek().unwrap();
-        cursor.errors.push(LexerError::new(
-            cursor.span(),
-            LexerErrorKind::UnexpectedCharacter(c),
-            format!("Unexpected character: '{}'", c),
-        ));
-        cursor.bump();
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let (tokens, _) = tokenize_with_errors(input);
+    tokens
+}
+
+pub fn tokenize_with_errors(input: &str) -> (Vec<Token>, Vec<LexerError>) {
+    collect(Lexer::new(input))
+}
+
+/// The `#!...` line at the very start of `input`, without its newline.
+pub(crate) fn shebang(input: &str) -> Option<&str> {
+    if !input.starts_with("#!") {
+        return None;
     }
+    Some(input.lines().next().unwrap_or(input).trim_end_matches('\r'))
+}
+
+/// Whether a line ending in this token carries on to the next line, as it
+/// does after a binary operator or a comma.
+fn continues_line(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Comma
+            | TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Multiply
+            | TokenKind::Divide
+            | TokenKind::FloorDivide
+            | TokenKind::Modulo
+            | TokenKind::Assign
+            | TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::LogicalAnd
+            | TokenKind::LogicalOr
+            | TokenKind::NilCoalesce
+            | TokenKind::BitAnd
+            | TokenKind::BitOr
+            | TokenKind::BitXor
+            | TokenKind::ShiftLeft
+            | TokenKind::ShiftRight
+            | TokenKind::GreaterThan
+            | TokenKind::GreaterThanOrEqual
+            | TokenKind::LessThan
+            | TokenKind::LessThanOrEqual
+            | TokenKind::DotDot
+            | TokenKind::FatArrow
+    )
+}
 
-    tokens.push(Token {
-        kind: TokenKind::Eof,
-        span: cursor.span(),
-    });
+fn tokenize_at(input: &str, line: usize, column: usize) -> (Vec<Token>, Vec<LexerError>) {
+    collect(Lexer::at(input, line, column))
+}
 
-    (tokens, cursor.errors().to_vec())
+fn collect(lexer: Lexer) -> (Vec<Token>, Vec<LexerError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for item in lexer {
+        match item {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    (tokens, errors)
 }
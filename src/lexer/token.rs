@@ -1,8 +1,10 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::runtime::environment::value::Number;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
     pub line: usize,
     pub column: usize,
@@ -13,10 +15,12 @@ impl fmt::Display for Span {
         write!(f, "{}:{}", self.line, self.column)
     }
 }
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
+    /// The source text the token was read from, exactly as written.
+    pub lexeme: String,
 }
 
 impl fmt::Display for Token {
@@ -25,23 +29,40 @@ impl fmt::Display for Token {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum TemplatePart {
+    Literal(String),
+    Code(Vec<Token>),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum TokenKind {
     Number(Number),
     Boolean(bool),
     String(String),
+    Template(Vec<TemplatePart>),
     Comment(String),
+    /// A `///` comment documenting the declaration that follows it.
+    DocComment(String),
     Comma,
     Plus,
     Minus,
     Multiply,
     Divide,
+    FloorDivide,
     Modulo,
     Assign,
     Equal,
     NotEqual,
     LogicalAnd,
     LogicalOr,
+    NilCoalesce,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
     Not,
     GreaterThan,
     GreaterThanOrEqual,
@@ -55,17 +76,32 @@ pub enum TokenKind {
     RightBrace,
     Semicolon,
     Colon,
+    Dot,
+    DotDot,
+    Ellipsis,
     Newline,
     Identifier(String),
     Let,
+    Const,
     Fn,
     If,
     Else,
     While,
+    Loop,
+    Collect,
+    Do,
+    For,
+    In,
     Break,
     Continue,
     Return,
     Struct,
+    Import,
+    Export,
+    Match,
+    Is,
+    As,
+    FatArrow,
     Unknown,
     Eof,
 }
@@ -76,18 +112,28 @@ impl std::fmt::Display for TokenKind {
             TokenKind::Number(n) => write!(f, "Number({n})"),
             TokenKind::Boolean(b) => write!(f, "Boolean({b})"),
             TokenKind::String(s) => write!(f, "String({s})"),
+            TokenKind::Template(_) => write!(f, "Template"),
             TokenKind::Comment(s) => write!(f, "Comment({s})"),
+            TokenKind::DocComment(s) => write!(f, "DocComment({s})"),
             TokenKind::Comma => write!(f, ","),
             TokenKind::Plus => write!(f, "+"),
             TokenKind::Minus => write!(f, "-"),
             TokenKind::Multiply => write!(f, "*"),
             TokenKind::Divide => write!(f, "/"),
+            TokenKind::FloorDivide => write!(f, "~/"),
             TokenKind::Modulo => write!(f, "%"),
             TokenKind::Assign => write!(f, "="),
             TokenKind::Equal => write!(f, "=="),
             TokenKind::NotEqual => write!(f, "!="),
             TokenKind::LogicalAnd => write!(f, "&&"),
             TokenKind::LogicalOr => write!(f, "||"),
+            TokenKind::NilCoalesce => write!(f, "??"),
+            TokenKind::BitAnd => write!(f, "&"),
+            TokenKind::BitOr => write!(f, "|"),
+            TokenKind::BitXor => write!(f, "^"),
+            TokenKind::BitNot => write!(f, "~"),
+            TokenKind::ShiftLeft => write!(f, "<<"),
+            TokenKind::ShiftRight => write!(f, ">>"),
             TokenKind::Not => write!(f, "!"),
             TokenKind::GreaterThan => write!(f, ">"),
             TokenKind::GreaterThanOrEqual => write!(f, ">="),
@@ -101,17 +147,32 @@ impl std::fmt::Display for TokenKind {
             TokenKind::RightBrace => write!(f, "}}"),
             TokenKind::Semicolon => write!(f, ";"),
             TokenKind::Colon => write!(f, ":"),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::DotDot => write!(f, ".."),
+            TokenKind::Ellipsis => write!(f, "..."),
             TokenKind::Newline => write!(f, "Newline"),
             TokenKind::Identifier(s) => write!(f, "Identifier({s})"),
             TokenKind::Let => write!(f, "let"),
+            TokenKind::Const => write!(f, "const"),
             TokenKind::Fn => write!(f, "function"),
             TokenKind::If => write!(f, "if"),
             TokenKind::Else => write!(f, "else"),
             TokenKind::While => write!(f, "while"),
+            TokenKind::Loop => write!(f, "loop"),
+            TokenKind::Collect => write!(f, "collect"),
+            TokenKind::Do => write!(f, "do"),
+            TokenKind::For => write!(f, "for"),
+            TokenKind::In => write!(f, "in"),
             TokenKind::Break => write!(f, "break"),
             TokenKind::Continue => write!(f, "continue"),
             TokenKind::Return => write!(f, "return"),
             TokenKind::Struct => write!(f, "struct"),
+            TokenKind::Import => write!(f, "import"),
+            TokenKind::Export => write!(f, "export"),
+            TokenKind::Match => write!(f, "match"),
+            TokenKind::Is => write!(f, "is"),
+            TokenKind::As => write!(f, "as"),
+            TokenKind::FatArrow => write!(f, "=>"),
             TokenKind::Eof => write!(f, "End of file"),
             TokenKind::Unknown => write!(f, "Unknown"),
         }
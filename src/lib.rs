@@ -1,42 +1,127 @@
+pub mod diagnostic;
+pub mod error;
 pub mod formatter;
 pub mod lexer;
+pub mod lint;
 pub mod lsp;
+pub mod messages;
 pub mod parser;
+pub mod resolver;
 pub mod runtime;
+pub mod typecheck;
 
+pub use diagnostic::{Diagnostic, Severity};
+pub use error::MpError;
 pub use formatter::format_code;
 pub use lsp::MpLanguageServer;
+pub use messages::{Language, set_language};
+pub use runtime::async_eval::{EvalFuture, eval_async};
+pub use runtime::debug::{Breakpoint, DebugCommand, DebugHook, Debugger, Pause};
 pub use runtime::environment::{BuiltinFunction, Environment, UserFunction, Value};
 pub use runtime::error::InterpreterError;
+pub use runtime::profile::FunctionProfile;
+pub use runtime::snapshot::Snapshot;
+pub use runtime::state::EvalLimits;
+pub use typecheck::TypeWarning;
 
+use runtime::module::ModuleLoader;
 use rustyline::{
     Completer, Config, Editor, Helper, Highlighter, Hinter, Validator, error::ReadlineError,
     highlight::MatchingBracketHighlighter, history::FileHistory,
     validate::MatchingBracketValidator,
 };
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{fs, result::Result};
 
-pub fn run_file(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(filename)?;
-    let (tokens, lexer_errors) = lexer::tokenize_with_errors(&content);
-    if !lexer_errors.is_empty() {
-        let error_messages: Vec<String> = lexer_errors.iter().map(|e| e.to_string()).collect();
-        return Err(error_messages.join("\n").into());
+pub fn run_file(filename: &str) -> Result<(), MpError> {
+    execute_file(filename, |_| {}).map(|_| ())
+}
+
+/// Runs a file with `random()` seeded, so it draws the same numbers every run.
+pub fn run_file_seeded(filename: &str, seed: u64) -> Result<(), MpError> {
+    execute_file(filename, |env| env.set_seed(seed)).map(|_| ())
+}
+
+/// Runs a file with profiling on and returns its per-function timings.
+pub fn profile_file(filename: &str) -> Result<Vec<FunctionProfile>, MpError> {
+    let env = execute_file(filename, |env| env.set_profiling(true))?;
+    let report = env.borrow().profile().unwrap_or_default();
+    Ok(report)
+}
+
+/// Runs a file under the console debugger, paused before its first statement.
+pub fn debug_file(filename: &str, breakpoints: Vec<Breakpoint>) -> Result<(), MpError> {
+    let mut debugger = Debugger::new(ConsoleDebugger::default());
+    for breakpoint in breakpoints {
+        debugger.add_breakpoint(breakpoint);
     }
-    let (stmts, errors) = parser::parse_with_errors(tokens);
+    execute_file(filename, |env| env.set_debugger(debugger)).map(|_| ())
+}
+
+/// Checks and evaluates a file in a fresh environment that `configure` can
+/// prepare, and returns the environment afterwards.
+fn execute_file(
+    filename: &str,
+    configure: impl FnOnce(&Environment),
+) -> Result<Rc<RefCell<Environment>>, MpError> {
+    let (source, stmts) = parse_file(filename)?;
+    let errors = resolver::resolve(&stmts);
     if !errors.is_empty() {
-        let error_messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
-        return Err(error_messages.join("\n").into());
+        return Err(MpError::Resolve { errors, source });
     }
 
-    let result = runtime::eval::eval(stmts);
+    // Relative imports resolve against the directory of the file being run.
+    let base_dir = Path::new(filename)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let modules = Rc::new(RefCell::new(ModuleLoader::with_base_dir(base_dir)));
+    let env = Environment::new_root_with_modules(modules).with_file(Path::new(filename));
+    configure(&env);
+    let env = Rc::new(RefCell::new(env));
+    let result = runtime::eval::eval_with_env(stmts, &env);
     match result {
-        Ok(_) | Err(InterpreterError::Return(_)) => {}
-        Err(e) => eprintln!("Execution error: {e}"),
+        Ok(_) | Err(InterpreterError::Return(_)) => Ok(env),
+        Err(error) => Err(MpError::Runtime { error, source }),
     }
-    Ok(())
+}
+
+/// Reads and parses a file, returning its source along with the program.
+fn parse_file(filename: &str) -> Result<(String, Vec<parser::Stmt>), MpError> {
+    let source = fs::read_to_string(filename)?;
+    let (tokens, errors) = lexer::tokenize_with_errors(&source);
+    if !errors.is_empty() {
+        return Err(MpError::Lex { errors, source });
+    }
+    let (stmts, errors) = parser::parse_with_errors(tokens);
+    if !errors.is_empty() {
+        return Err(MpError::Parse { errors, source });
+    }
+    Ok((source, stmts))
+}
+
+/// Runs the optional type checker over a file without evaluating it.
+pub fn typecheck_file(filename: &str) -> Result<Vec<TypeWarning>, MpError> {
+    let (_, stmts) = parse_file(filename)?;
+    Ok(typecheck::check(&stmts))
+}
+
+/// Runs the linter over a file without evaluating it.
+pub fn lint_file(filename: &str) -> Result<Vec<lint::Lint>, MpError> {
+    let (_, stmts) = parse_file(filename)?;
+    Ok(lint::lint(&stmts))
+}
+
+/// Parses a file and renders its AST as pretty-printed JSON.
+pub fn dump_ast(filename: &str) -> Result<String, MpError> {
+    let (_, stmts) = parse_file(filename)?;
+    Ok(serde_json::to_string_pretty(&stmts).map_err(std::io::Error::from)?)
 }
 
 pub fn handle_command(cmd: &str, env: &Rc<RefCell<Environment>>) -> bool {
@@ -47,31 +132,74 @@ pub fn handle_command(cmd: &str, env: &Rc<RefCell<Environment>>) -> bool {
             println!("  exit     - exit the program");
             println!("  help     - display this help message");
             println!("  clear    - clear the environment");
+            println!("  save F   - save the variables defined so far to file F");
+            println!("  load F   - restore variables saved with `save`");
         }
         "clear" => {
             println!("Environment cleared.");
         }
+        _ if cmd.starts_with("save ") => {
+            let path = cmd["save ".len()..].trim();
+            match save_session(path, env) {
+                Ok(()) => println!("Saved to {path}."),
+                Err(error) => eprintln!("Cannot save session: {error}"),
+            }
+        }
+        _ if cmd.starts_with("load ") => {
+            let path = cmd["load ".len()..].trim();
+            match load_session(path, env) {
+                Ok(()) => println!("Loaded {path}."),
+                Err(error) => eprintln!("Cannot load session: {error}"),
+            }
+        }
         _ => {
+            // The lexer keeps going past bad input, so every problem is shown at once.
             let (tokens, lexer_errors) = lexer::tokenize_with_errors(cmd);
             if !lexer_errors.is_empty() {
-                eprintln!("Lexical error: {lexer_errors:?}");
+                for error in &lexer_errors {
+                    eprintln!("{}", Diagnostic::from(error).render(cmd));
+                }
                 return true;
             }
             let (ast, parser_errors) = parser::parse_with_errors(tokens);
             if !parser_errors.is_empty() {
-                eprintln!("Parser error: {parser_errors:?}");
+                for error in &parser_errors {
+                    eprintln!("{}", Diagnostic::from(error).render(cmd));
+                }
                 return true;
             }
+            // Any limits on the environment apply to each line on its own.
+            if let Some(state) = env.borrow().state() {
+                state.borrow_mut().restart();
+            }
             let result = runtime::eval::eval_with_env(ast, env);
             match result {
                 Ok(result) | Err(InterpreterError::Return(result)) => println!("=> {result:?}"),
-                _ => return false,
+                Err(error) => eprintln!("{}", error.render(cmd)),
             }
         }
     }
     true
 }
 
+fn save_session(
+    path: &str,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshot = Snapshot::capture(&env.borrow())?;
+    fs::write(path, snapshot.to_json()?)?;
+    Ok(())
+}
+
+fn load_session(
+    path: &str,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshot = Snapshot::from_json(&fs::read_to_string(path)?)?;
+    snapshot.restore(&mut env.borrow_mut());
+    Ok(())
+}
+
 #[derive(Helper, Completer, Highlighter, Validator, Hinter)]
 struct InputValidator {
     #[rustyline(Validator)]
@@ -80,7 +208,7 @@ struct InputValidator {
     highlighter: MatchingBracketHighlighter,
 }
 
-pub fn run_repl() -> Result<(), Box<dyn std::error::Error>> {
+pub fn run_repl() -> Result<(), MpError> {
     println!("Welcome to Mp Lang! (type 'help' for help)");
     let config = Config::builder().auto_add_history(true).build();
     let mut rl: Editor<InputValidator, FileHistory> = Editor::with_config(config)?;
@@ -89,6 +217,9 @@ pub fn run_repl() -> Result<(), Box<dyn std::error::Error>> {
         highlighter: MatchingBracketHighlighter::new(),
     }));
     let env = Rc::new(RefCell::new(Environment::new_root()));
+    let cancel = Arc::new(AtomicBool::new(false));
+    env.borrow().set_cancel_flag(cancel.clone());
+    cancel_on_ctrl_c(cancel.clone())?;
 
     loop {
         let readline = rl.readline(">> ");
@@ -99,6 +230,7 @@ pub fn run_repl() -> Result<(), Box<dyn std::error::Error>> {
                     continue;
                 }
                 rl.add_history_entry(trimmed)?;
+                cancel.store(false, Ordering::Relaxed);
                 if !handle_command(trimmed, &env) {
                     break;
                 }
@@ -119,3 +251,119 @@ pub fn run_repl() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Sets `cancel` when Ctrl-C arrives while a line is running, so the evaluator
+/// stops with an "Interrupted" error. At the prompt the terminal is in raw mode
+/// and rustyline reports Ctrl-C itself.
+fn cancel_on_ctrl_c(cancel: Arc<AtomicBool>) -> std::io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        })
+    });
+    Ok(())
+}
+
+/// Reads debugger commands from the terminal each time the program pauses.
+#[derive(Default)]
+struct ConsoleDebugger {
+    /// Source lines of the files paused in so far, for showing where we are.
+    sources: HashMap<PathBuf, Vec<String>>,
+}
+
+impl ConsoleDebugger {
+    fn show_location(&mut self, pause: &Pause) {
+        let location = match pause.file {
+            Some(file) => format!("{}:{}", file.display(), pause.span),
+            None => pause.span.to_string(),
+        };
+        println!("Paused at {location}");
+        let line = pause.file.and_then(|file| {
+            let lines = self.sources.entry(file.to_path_buf()).or_insert_with(|| {
+                fs::read_to_string(file)
+                    .map(|source| source.lines().map(str::to_string).collect())
+                    .unwrap_or_default()
+            });
+            lines.get(pause.span.line.checked_sub(1)?)
+        });
+        if let Some(line) = line {
+            println!("{} | {line}", pause.span.line);
+        }
+    }
+}
+
+impl DebugHook for ConsoleDebugger {
+    fn pause(&mut self, pause: &mut Pause) -> DebugCommand {
+        self.show_location(pause);
+        let stdin = std::io::stdin();
+        loop {
+            print!("(debug) ");
+            let _ = std::io::stdout().flush();
+            let mut line = String::new();
+            if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+                return DebugCommand::Quit;
+            }
+            let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            match command {
+                "s" | "step" => return DebugCommand::Step,
+                "n" | "next" | "" => return DebugCommand::Next,
+                "c" | "continue" => return DebugCommand::Continue,
+                "q" | "quit" => return DebugCommand::Quit,
+                "b" | "break" => match rest.parse::<Breakpoint>() {
+                    Ok(breakpoint) => {
+                        println!("Breakpoint at {breakpoint}");
+                        pause.breakpoints.push(breakpoint);
+                    }
+                    Err(error) => eprintln!("{error}"),
+                },
+                "breakpoints" => {
+                    for breakpoint in pause.breakpoints.iter() {
+                        println!("{breakpoint}");
+                    }
+                }
+                "vars" => {
+                    for (name, value) in pause.env.borrow().variables() {
+                        println!("{name} = {value}");
+                    }
+                }
+                "where" => {
+                    for frame in pause.frames.iter().rev() {
+                        println!("in {} called at {}", frame.function, frame.call_site);
+                    }
+                }
+                "p" | "print" => {
+                    let (tokens, lexer_errors) = lexer::tokenize_with_errors(rest);
+                    let (ast, parser_errors) = parser::parse_with_errors(tokens);
+                    if let Some(error) = lexer_errors.first() {
+                        eprintln!("Lexical error: {error}");
+                    } else if let Some(error) = parser_errors.first() {
+                        eprintln!("Parser error: {error}");
+                    } else {
+                        match runtime::eval::eval_with_env(ast, pause.env) {
+                            Ok(value) => println!("{value}"),
+                            Err(error) => eprintln!("Runtime error: {error}"),
+                        }
+                    }
+                }
+                "h" | "help" => {
+                    println!("Debugger commands:");
+                    println!("  step, s          - run to the next statement, entering calls");
+                    println!("  next, n          - run to the next statement, stepping over calls");
+                    println!("  continue, c      - run to the next breakpoint");
+                    println!("  break, b [FILE:]LINE - stop when that line is reached");
+                    println!("  breakpoints      - list breakpoints");
+                    println!("  vars             - show the variables in scope");
+                    println!("  print, p EXPR    - evaluate EXPR in the current scope");
+                    println!("  where            - show the calls in progress");
+                    println!("  quit, q          - stop the program");
+                }
+                _ => eprintln!("Unknown command '{command}' (type 'help' for help)"),
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+
+pub use crate::diagnostic::Severity;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Span;
+use crate::messages::Message;
+use crate::parser::visit::{Visitor, walk_expr, walk_stmt};
+use crate::parser::{Expr, ExprKind, Pattern, Stmt, StmtKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    UnusedVariable(String),
+    UnusedParameter(String),
+    /// Statements after a `return`, `break` or `continue` in the same block.
+    UnreachableCode,
+    /// A `let` that hides a variable of an enclosing scope.
+    Shadowing(String),
+}
+
+impl LintKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintKind::Shadowing(_) => Severity::Hint,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// A stable identifier for this kind of lint, for tools to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable(_) => "W0001",
+            LintKind::UnusedParameter(_) => "W0002",
+            LintKind::UnreachableCode => "W0003",
+            LintKind::Shadowing(_) => "W0004",
+        }
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            LintKind::UnusedVariable(name) => Message::UnusedVariable.format(&[("name", name)]),
+            LintKind::UnusedParameter(name) => Message::UnusedParameter.format(&[("name", name)]),
+            LintKind::UnreachableCode => Message::UnreachableCode.text().to_string(),
+            LintKind::Shadowing(name) => Message::Shadowing.format(&[("name", name)]),
+        };
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub span: Span,
+    pub kind: LintKind,
+    pub severity: Severity,
+}
+
+impl From<&Lint> for Diagnostic {
+    fn from(lint: &Lint) -> Self {
+        Diagnostic {
+            severity: lint.severity,
+            span: Some(lint.span),
+            code: Some(lint.kind.code()),
+            message: lint.kind.to_string(),
+            notes: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Diagnostic::from(self))
+    }
+}
+
+/// Reports likely mistakes that are still valid programs. Names starting with
+/// `_` and exported declarations are never reported as unused.
+pub fn lint(stmts: &[Stmt]) -> Vec<Lint> {
+    let mut linter = Linter::default();
+    linter.push_scope();
+    let spans: Vec<Option<Span>> = stmts.iter().map(|stmt| Some(stmt.span)).collect();
+    let kinds: Vec<&StmtKind> = stmts.iter().map(|stmt| &stmt.kind).collect();
+    linter.check_unreachable(&kinds, &spans);
+    for stmt in stmts {
+        linter.visit_stmt(&stmt.kind);
+    }
+    linter.pop_scope();
+    linter
+        .lints
+        .sort_by_key(|lint| (lint.span.line, lint.span.column));
+    linter.lints
+}
+
+/// What a name is bound to, with where to report it if it goes unused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LocalKind {
+    Variable(Span),
+    Parameter(Span),
+    /// Functions, structs, imports and loop or match bindings, which are never reported.
+    Other,
+}
+
+struct Local<'a> {
+    name: &'a str,
+    kind: LocalKind,
+    used: bool,
+}
+
+/// A function or lambda body, checked once its enclosing scope is complete, the
+/// same way the resolver handles them.
+struct Deferred<'a> {
+    params: &'a [String],
+    rest: Option<&'a str>,
+    body: &'a Expr,
+}
+
+#[derive(Default)]
+struct Linter<'a> {
+    scopes: Vec<Vec<Local<'a>>>,
+    deferred: Vec<Vec<Deferred<'a>>>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, span: Span, kind: LintKind) {
+        let severity = kind.severity();
+        self.lints.push(Lint {
+            span,
+            kind,
+            severity,
+        });
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+        self.deferred.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let deferred = self.deferred.pop().unwrap_or_default();
+        for function in deferred {
+            self.push_scope();
+            let span = function.body.span;
+            for param in function
+                .params
+                .iter()
+                .map(String::as_str)
+                .chain(function.rest)
+            {
+                self.declare(param, LocalKind::Parameter(span));
+            }
+            self.visit_expr(function.body);
+            self.pop_scope();
+        }
+        let scope = self.scopes.pop().unwrap_or_default();
+        for local in scope {
+            if local.used || local.name.starts_with('_') {
+                continue;
+            }
+            match local.kind {
+                LocalKind::Variable(span) => {
+                    self.report(span, LintKind::UnusedVariable(local.name.to_string()))
+                }
+                LocalKind::Parameter(span) => {
+                    self.report(span, LintKind::UnusedParameter(local.name.to_string()))
+                }
+                LocalKind::Other => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &'a str, kind: LocalKind) {
+        if let LocalKind::Variable(span) = kind
+            && !name.starts_with('_')
+        {
+            let outer = self.scopes.len().saturating_sub(1);
+            let shadows = self.scopes[..outer]
+                .iter()
+                .flatten()
+                .any(|local| local.name == name && local.kind != LocalKind::Other);
+            if shadows {
+                self.report(span, LintKind::Shadowing(name.to_string()));
+            }
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Local {
+                name,
+                kind,
+                used: false,
+            });
+        }
+    }
+
+    fn defer(&mut self, params: &'a [String], rest: Option<&'a str>, body: &'a Expr) {
+        if let Some(deferred) = self.deferred.last_mut() {
+            deferred.push(Deferred { params, rest, body });
+        }
+    }
+
+    /// Marks the innermost binding of `name` as read.
+    fn mark_used(&mut self, name: &str) {
+        if let Some(local) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|local| local.name == name))
+        {
+            local.used = true;
+        }
+    }
+
+    /// Reports the first statement after one that always leaves the block.
+    /// `spans` holds a position for each statement where one is known.
+    fn check_unreachable(&mut self, stmts: &[&StmtKind], spans: &[Option<Span>]) {
+        let exit = stmts.iter().position(|stmt| {
+            matches!(
+                stmt,
+                StmtKind::Return(_) | StmtKind::Break(_) | StmtKind::Continue
+            )
+        });
+        if let Some(exit) = exit
+            && let Some(next) = stmts.get(exit + 1)
+            && let Some(span) = spans[exit + 1].or_else(|| next.span_hint())
+        {
+            self.report(span, LintKind::UnreachableCode);
+        }
+    }
+
+    /// An assignment target: a plain variable is written, not read.
+    fn visit_target(&mut self, target: &'a Expr) {
+        match &target.kind {
+            ExprKind::Variable(_) => {}
+            ExprKind::Array(targets) => {
+                for target in targets {
+                    self.visit_target(target);
+                }
+            }
+            _ => self.visit_expr(target),
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Linter<'a> {
+    fn visit_stmt(&mut self, stmt: &'a StmtKind) {
+        match stmt {
+            StmtKind::Let {
+                name,
+                name_span,
+                value,
+                ..
+            } => {
+                if let Some(value) = value {
+                    self.visit_expr(value);
+                }
+                self.declare(name, LocalKind::Variable(*name_span));
+            }
+            StmtKind::LetPattern { pattern, value } => {
+                self.visit_expr(value);
+                for (name, span) in pattern.names() {
+                    self.declare(name, LocalKind::Variable(*span));
+                }
+            }
+            StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                ..
+            } => {
+                self.declare(name, LocalKind::Other);
+                self.defer(params, rest.as_deref(), body);
+            }
+            StmtKind::Struct { name, .. } => {
+                walk_stmt(self, stmt);
+                self.declare(name, LocalKind::Other);
+            }
+            StmtKind::Import {
+                alias: Some(alias), ..
+            } => self.declare(alias, LocalKind::Other),
+            StmtKind::Export(declaration) => {
+                self.visit_stmt(&declaration.kind);
+                for name in declaration.kind.declared_names() {
+                    self.mark_used(name);
+                }
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Variable(name)
+            | ExprKind::FunctionCall { name, .. }
+            | ExprKind::StructInstance { name, .. } => {
+                self.mark_used(name);
+                walk_expr(self, expr);
+            }
+            ExprKind::Assign { target, value } => {
+                self.visit_expr(value);
+                self.visit_target(target);
+            }
+            ExprKind::Lambda { params, rest, body } => {
+                self.defer(params, rest.as_deref(), body);
+            }
+            ExprKind::Block(stmts) => {
+                let kinds: Vec<&StmtKind> = stmts.iter().collect();
+                self.check_unreachable(&kinds, &vec![None; stmts.len()]);
+                self.push_scope();
+                walk_expr(self, expr);
+                self.pop_scope();
+            }
+            ExprKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                self.visit_expr(iterable);
+                self.push_scope();
+                self.declare(variable, LocalKind::Other);
+                self.visit_expr(body);
+                self.pop_scope();
+            }
+            ExprKind::Match { subject, arms } => {
+                self.visit_expr(subject);
+                for arm in arms {
+                    self.push_scope();
+                    match &arm.pattern {
+                        Pattern::Literal(pattern) => self.visit_expr(pattern),
+                        Pattern::Binding(name) => self.declare(name, LocalKind::Other),
+                        Pattern::Wildcard => {}
+                    }
+                    self.visit_expr(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+}
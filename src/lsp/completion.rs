@@ -1,5 +1,5 @@
 use crate::lexer::{TokenKind, tokenize, tokenize_with_errors};
-use crate::parser::{StmtKind, parse};
+use crate::parser::{ObjectKey, StmtKind, format_params, parse};
 use tower_lsp_server::ls_types::*;
 
 #[derive(Debug)]
@@ -20,15 +20,78 @@ impl MpCompleter {
     pub fn new() -> Self {
         Self {
             keywords: vec![
-                "let", "fn", "if", "else", "while", "return", "break", "continue", "true", "false",
-                "nil", "struct",
+                "let", "const", "fn", "if", "else", "while", "do", "loop", "for", "in", "collect",
+                "return", "break", "continue", "true", "false", "nil", "struct", "import",
+                "export", "match", "is", "as",
             ],
             builtin_functions: vec![
-                "print", "input", "len", "type", "str", "int", "float", "random", "push", "pop",
+                "print",
+                "println",
+                "eprint",
+                "eprintln",
+                "print_sep",
+                "println_sep",
+                "eprint_sep",
+                "eprintln_sep",
+                "write",
+                "input",
+                "len",
+                "type",
+                "str",
+                "format",
+                "int",
+                "float",
+                "sqrt",
+                "abs",
+                "floor",
+                "ceil",
+                "round",
+                "pow",
+                "sin",
+                "cos",
+                "tan",
+                "asin",
+                "acos",
+                "atan",
+                "atan2",
+                "ln",
+                "log10",
+                "log2",
+                "exp",
+                "min",
+                "max",
+                "sum",
+                "sort",
+                "reverse",
+                "map",
+                "filter",
+                "reduce",
+                "split",
+                "trim",
+                "trim_start",
+                "trim_end",
+                "upper",
+                "lower",
+                "replace",
+                "random",
+                "seed",
+                "push",
+                "pop",
                 "time",
+                "chars",
+                "ord",
+                "chr",
+                "help",
+                "profile",
+                "spawn",
+                "join",
+                "channel",
+                "send",
+                "recv",
             ],
             builtin_types: vec![
-                "Number", "String", "Boolean", "Array", "Object", "Function", "Nil",
+                "Int", "Float", "Number", "String", "Bool", "Array", "Object", "Range", "Function",
+                "Thread", "Channel", "Nil",
             ],
         }
     }
@@ -89,8 +152,8 @@ impl MpCompleter {
         let word_start = before_cursor
             .char_indices()
             .rev()
-            .find(|(_, c)| !c.is_alphanumeric() && *c != '_' && *c != ':')
-            .map(|(i, _)| i + 1)
+            .find(|(_, c)| !unicode_ident::is_xid_continue(*c) && *c != ':')
+            .map(|(i, c)| i + c.len_utf8())
             .unwrap_or(0);
 
         let current_word = &before_cursor[word_start..];
@@ -142,10 +205,16 @@ impl MpCompleter {
             .map(|kw| {
                 let detail = match *kw {
                     "let" => "Variable declaration",
+                    "const" => "Constant declaration",
                     "fn" => "Function definition",
                     "if" => "Conditional statement",
                     "else" => "Else branch",
                     "while" => "Loop statement",
+                    "do" => "Do-while loop",
+                    "loop" => "Infinite loop",
+                    "for" => "For-in loop",
+                    "in" => "Loop iterable separator",
+                    "collect" => "Gather loop values into an array",
                     "return" => "Return from function",
                     "break" => "Break from loop",
                     "continue" => "Continue to next iteration",
@@ -153,6 +222,11 @@ impl MpCompleter {
                     "false" => "Boolean false",
                     "nil" => "Null value",
                     "struct" => "Struct definition",
+                    "import" => "Import a module",
+                    "export" => "Export a declaration from a module",
+                    "match" => "Pattern match expression",
+                    "is" => "Type test operator",
+                    "as" => "Type conversion operator",
                     _ => "Keyword",
                 };
 
@@ -190,13 +264,27 @@ impl MpCompleter {
         }
         let ast = parse(tokens.clone());
         for stmt in &ast {
-            match &stmt.kind {
+            let kind = match &stmt.kind {
+                StmtKind::Export(declaration) => &declaration.kind,
+                kind => kind,
+            };
+            match kind {
                 StmtKind::Let { name, value, .. } => {
-                    let var_type = self.infer_type(value);
+                    let var_type = match value {
+                        Some(value) => self.infer_type(value),
+                        None => "Unknown".to_string(),
+                    };
                     variables.insert(name.clone(), var_type);
                 }
-                StmtKind::Function { name, params, .. } => {
-                    let params_str = params.join(", ");
+                StmtKind::LetPattern { pattern, .. } => {
+                    for (name, _) in pattern.names() {
+                        variables.insert(name.clone(), "Unknown".to_string());
+                    }
+                }
+                StmtKind::Function {
+                    name, params, rest, ..
+                } => {
+                    let params_str = format_params(params, rest.as_deref());
                     variables.insert(name.clone(), format!("fn({})", params_str));
                 }
                 StmtKind::Struct { name, fields } => {
@@ -262,7 +350,10 @@ impl MpCompleter {
                 } else {
                     let field_types: Vec<String> = fields
                         .iter()
-                        .map(|(k, v)| format!("{}: {}", k, self.infer_type(v)))
+                        .filter_map(|(key, v)| match key {
+                            ObjectKey::Name(k) => Some(format!("{}: {}", k, self.infer_type(v))),
+                            ObjectKey::Computed(_) => None,
+                        })
                         .collect();
                     format!("Object {{ {} }}", field_types.join(", "))
                 }
@@ -278,11 +369,37 @@ impl MpCompleter {
             "len" => "Number".to_string(),
             "type" => "String".to_string(),
             "str" => "String".to_string(),
+            "format" => "String".to_string(),
+            "sum" => "Number".to_string(),
+            "sort" | "map" | "filter" => "Array".to_string(),
+            "reduce" => "Unknown".to_string(),
+            "split" => "Array".to_string(),
+            "trim" | "trim_start" | "trim_end" | "upper" | "lower" | "replace" => {
+                "String".to_string()
+            }
+            "reverse" => args
+                .first()
+                .map_or("Unknown".to_string(), |first| self.infer_type(first)),
+            "min" | "max" => "Unknown".to_string(),
+            "sqrt" | "abs" | "floor" | "ceil" | "round" | "pow" => "Number".to_string(),
+            "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "ln" | "log10"
+            | "log2" | "exp" => "Number".to_string(),
             "int" => "Number".to_string(),
             "float" => "Number".to_string(),
             "input" => "String".to_string(),
             "random" => "Number".to_string(),
+            "seed" => "Nil".to_string(),
             "time" => "Number".to_string(),
+            "chars" => "Array".to_string(),
+            "ord" => "Number".to_string(),
+            "chr" => "String".to_string(),
+            "help" => "String".to_string(),
+            "profile" => "Array".to_string(),
+            "spawn" => "Thread".to_string(),
+            "join" => "Unknown".to_string(),
+            "channel" => "Channel".to_string(),
+            "send" => "Nil".to_string(),
+            "recv" => "Unknown".to_string(),
             "push" => {
                 if let Some(first) = args.first() {
                     self.infer_type(first)
@@ -297,7 +414,8 @@ impl MpCompleter {
                     "Unknown".to_string()
                 }
             }
-            "print" => "Nil".to_string(),
+            "print" | "println" | "eprint" | "eprintln" | "print_sep" | "println_sep"
+            | "eprint_sep" | "eprintln_sep" | "write" => "Nil".to_string(),
             _ => "Unknown".to_string(),
         }
     }
@@ -306,7 +424,15 @@ impl MpCompleter {
         let mut items = Vec::new();
 
         match func_name {
-            "print" => {
+            "print_sep" | "println_sep" | "eprint_sep" | "eprintln_sep" => {
+                items.push(CompletionItem {
+                    label: "sep".to_string(),
+                    kind: Some(CompletionItemKind::VALUE),
+                    detail: Some("String to print between the values".to_string()),
+                    ..Default::default()
+                });
+            }
+            "print" | "println" | "eprint" | "eprintln" | "write" => {
                 items.push(CompletionItem {
                     label: "value".to_string(),
                     kind: Some(CompletionItemKind::VALUE),
@@ -330,7 +456,9 @@ impl MpCompleter {
                     ..Default::default()
                 });
             }
-            "str" | "int" | "float" => {
+            "str" | "int" | "float" | "sqrt" | "abs" | "floor" | "ceil" | "round" | "pow"
+            | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "ln" | "log10"
+            | "log2" | "exp" => {
                 items.push(CompletionItem {
                     label: "value".to_string(),
                     kind: Some(CompletionItemKind::VALUE),
@@ -346,19 +474,100 @@ impl MpCompleter {
 
     fn get_function_documentation(&self, func_name: &str) -> String {
         match func_name {
-            "print" => "print(expr) - Print the value of expr to the console".to_string(),
+            "print" => "print(expr, ...) - Print the values separated by spaces".to_string(),
+            "println" => "println(expr, ...) - Like print, then end the line".to_string(),
+            "eprint" => "eprint(expr, ...) - Like print, to stderr".to_string(),
+            "eprintln" => "eprintln(expr, ...) - Like println, to stderr".to_string(),
+            "print_sep" => {
+                "print_sep(sep, expr, ...) - Print the values separated by sep".to_string()
+            }
+            "println_sep" => {
+                "println_sep(sep, expr, ...) - Like print_sep, then end the line".to_string()
+            }
+            "eprint_sep" => "eprint_sep(sep, expr, ...) - Like print_sep, to stderr".to_string(),
+            "eprintln_sep" => {
+                "eprintln_sep(sep, expr, ...) - Like println_sep, to stderr".to_string()
+            }
+            "write" => {
+                "write(expr, ...) - Print the values as they are, with nothing between".to_string()
+            }
             "input" => "input() - Read a string from the console".to_string(),
             "len" => "len(str) - Return the length of str (string, array, or object)".to_string(),
-            "type" => "type(expr) - Return the type of expr as a string".to_string(),
-            "str" => "str(num) - Convert num to a string".to_string(),
+            "type" => {
+                "type(expr) - Return the type name of expr, like \"int\" or \"bool\"".to_string()
+            }
+            "str" => "str(value) - Convert any value to the text print would show".to_string(),
+            "format" => {
+                "format(template, ...values) - Fill each {} in template, e.g. {:>8.2}".to_string()
+            }
             "int" => "int(str) - Convert str to an integer".to_string(),
+            "sqrt" => "sqrt(x) - Square root of x, as a float".to_string(),
+            "abs" => "abs(x) - Absolute value of x".to_string(),
+            "floor" => "floor(x) - Largest integer not above x".to_string(),
+            "ceil" => "ceil(x) - Smallest integer not below x".to_string(),
+            "round" => "round(x) - Nearest integer to x, halves away from zero".to_string(),
+            "pow" => "pow(base, exp) - base to the power exp; an int for int arguments".to_string(),
+            "sin" => "sin(x) - Sine of x radians".to_string(),
+            "cos" => "cos(x) - Cosine of x radians".to_string(),
+            "tan" => "tan(x) - Tangent of x radians".to_string(),
+            "asin" => "asin(x) - Angle in radians whose sine is x".to_string(),
+            "acos" => "acos(x) - Angle in radians whose cosine is x".to_string(),
+            "atan" => "atan(x) - Angle in radians whose tangent is x".to_string(),
+            "atan2" => "atan2(y, x) - Angle in radians of the point (x, y)".to_string(),
+            "ln" => "ln(x) - Natural logarithm of x".to_string(),
+            "log10" => "log10(x) - Base-10 logarithm of x".to_string(),
+            "log2" => "log2(x) - Base-2 logarithm of x".to_string(),
+            "exp" => "exp(x) - e raised to the power x".to_string(),
+            "min" => "min(array) | min(a, b, ...) - Smallest number or string".to_string(),
+            "max" => "max(array) | max(a, b, ...) - Largest number or string".to_string(),
+            "sum" => "sum(array) | sum(a, b, ...) - Total of the numbers".to_string(),
+            "reverse" => "reverse(seq) - Reversed copy of an array or string".to_string(),
+            "map" => "map(array, f) - Array of f(item) for each item".to_string(),
+            "filter" => {
+                "filter(array, pred) - Array of the items pred returns true for".to_string()
+            }
+            "reduce" => {
+                "reduce(array, f, init) - Fold the items with f(acc, item), starting from init"
+                    .to_string()
+            }
+            "sort" => "sort(array) | sort(array, cmp) - Sorted copy; cmp(a, b) < 0 puts a first"
+                .to_string(),
             "float" => "float(str) - Convert str to a float".to_string(),
             "random" => {
                 "random() | random(max) | random(min, max) - Generate random number".to_string()
             }
+            "seed" => "seed(n) - Make random() repeat the same sequence for the same n".to_string(),
             "push" => "push(array, item) - Add item to array".to_string(),
             "pop" => "pop(array) - Remove and return last item from array".to_string(),
             "time" => "time() - Get current Unix timestamp in seconds".to_string(),
+            "chars" => "chars(str) - Split str into an array of one-character strings".to_string(),
+            "ord" => "ord(char) - Return the Unicode code point of char".to_string(),
+            "chr" => "chr(code) - Return the character with the given code point".to_string(),
+            "help" => "help(func) - Return the doc comment of func, or nil".to_string(),
+            "profile" => {
+                "profile() - Return per-function call counts and timings, or nil when not profiling"
+                    .to_string()
+            }
+            "spawn" => {
+                "spawn(func, ...args) - Call func on a new thread and return a handle".to_string()
+            }
+            "join" => "join(handle) | join(array, sep) - Wait for a spawned thread and return its \
+                 result, or join the array's items into a string"
+                .to_string(),
+            "split" => {
+                "split(str) | split(str, sep) - Split str at sep, or at whitespace".to_string()
+            }
+            "trim" => "trim(str) - str without leading and trailing whitespace".to_string(),
+            "trim_start" => "trim_start(str) - str without leading whitespace".to_string(),
+            "trim_end" => "trim_end(str) - str without trailing whitespace".to_string(),
+            "upper" => "upper(str) - str in upper case".to_string(),
+            "lower" => "lower(str) - str in lower case".to_string(),
+            "replace" => "replace(str, from, to) - str with every from replaced by to".to_string(),
+            "channel" => {
+                "channel() - Create a channel for passing values between threads".to_string()
+            }
+            "send" => "send(ch, value) - Queue a copy of value on channel ch".to_string(),
+            "recv" => "recv(ch) - Wait for the next value sent on channel ch".to_string(),
             _ => "Built-in function".to_string(),
         }
     }
@@ -1,5 +1,5 @@
 use crate::lexer::{TokenKind, tokenize_with_errors};
-use crate::parser::{Stmt, StmtKind, parse};
+use crate::parser::{ObjectKey, Stmt, StmtKind, parse};
 use std::str::FromStr;
 use tower_lsp_server::ls_types::*;
 
@@ -247,13 +247,33 @@ impl MpDefinition {
                     column: name_span.column,
                 });
             }
+            StmtKind::LetPattern { pattern, value } => {
+                for (name, span) in pattern.names() {
+                    symbols.entry(name.clone()).or_default().push(SymbolInfo {
+                        line: span.line,
+                        column: span.column,
+                    });
+                }
+                self.extract_symbols_from_expr(value, tokens, symbols);
+            }
             StmtKind::Expr(expr) | StmtKind::Result(expr) => {
                 self.extract_symbols_from_expr(expr, tokens, symbols);
             }
-            StmtKind::Return(Some(expr)) => {
+            StmtKind::Return(Some(expr)) | StmtKind::Break(Some(expr)) => {
                 self.extract_symbols_from_expr(expr, tokens, symbols);
             }
-            StmtKind::Break | StmtKind::Continue | StmtKind::Return(None) => {}
+            StmtKind::Break(None) | StmtKind::Continue | StmtKind::Return(None) => {}
+            StmtKind::Export(declaration) => {
+                self.extract_symbols_from_stmt(declaration, tokens, symbols);
+            }
+            StmtKind::Import { alias, .. } => {
+                if let Some(alias) = alias {
+                    symbols.entry(alias.clone()).or_default().push(SymbolInfo {
+                        line: stmt.span.line,
+                        column: stmt.span.column,
+                    });
+                }
+            }
             StmtKind::Struct { name, .. } => {
                 symbols.entry(name.clone()).or_default().push(SymbolInfo {
                     line: stmt.span.line,
@@ -283,7 +303,7 @@ impl MpDefinition {
                     self.extract_symbols_from_expr(else_b, tokens, symbols);
                 }
             }
-            While { condition, body } => {
+            While { condition, body } | DoWhile { body, condition } => {
                 self.extract_symbols_from_expr(condition, tokens, symbols);
                 self.extract_symbols_from_expr(body, tokens, symbols);
             }
@@ -297,7 +317,11 @@ impl MpDefinition {
                     self.extract_symbols_from_stmt(&stmt, tokens, symbols);
                 }
             }
-            BinaryOp { left, right, .. } => {
+            BinaryOp { left, right, .. }
+            | Assign {
+                target: left,
+                value: right,
+            } => {
                 self.extract_symbols_from_expr(left, tokens, symbols);
                 self.extract_symbols_from_expr(right, tokens, symbols);
             }
@@ -315,7 +339,10 @@ impl MpDefinition {
                 }
             }
             Object(fields) => {
-                for (_, value) in fields {
+                for (key, value) in fields {
+                    if let ObjectKey::Computed(key) = key {
+                        self.extract_symbols_from_expr(key, tokens, symbols);
+                    }
                     self.extract_symbols_from_expr(value, tokens, symbols);
                 }
             }
@@ -329,6 +356,21 @@ impl MpDefinition {
             Parenthesized(e) => {
                 self.extract_symbols_from_expr(e, tokens, symbols);
             }
+            Interpolation(_)
+            | Slice { .. }
+            | MethodCall { .. }
+            | Match { .. }
+            | For { .. }
+            | Loop(_)
+            | Collect(_)
+            | Spread(_)
+            | TypeTest { .. }
+            | Cast { .. }
+            | Lambda { .. } => {
+                for child in expr.children() {
+                    self.extract_symbols_from_expr(child, tokens, symbols);
+                }
+            }
             Number(_) | Boolean(_) | String(_) | Variable(_) | StructInstance { .. } => {}
         }
     }
@@ -352,16 +394,68 @@ impl MpDefinition {
         matches!(
             name,
             "print"
+                | "println"
+                | "eprint"
+                | "eprintln"
+                | "print_sep"
+                | "println_sep"
+                | "eprint_sep"
+                | "eprintln_sep"
+                | "write"
                 | "input"
                 | "len"
                 | "type"
                 | "str"
+                | "format"
                 | "int"
                 | "float"
+                | "sqrt"
+                | "abs"
+                | "floor"
+                | "ceil"
+                | "round"
+                | "pow"
+                | "sin"
+                | "cos"
+                | "tan"
+                | "asin"
+                | "acos"
+                | "atan"
+                | "atan2"
+                | "ln"
+                | "log10"
+                | "log2"
+                | "exp"
+                | "min"
+                | "max"
+                | "sum"
+                | "sort"
+                | "reverse"
+                | "map"
+                | "filter"
+                | "reduce"
+                | "split"
+                | "trim"
+                | "trim_start"
+                | "trim_end"
+                | "upper"
+                | "lower"
+                | "replace"
                 | "random"
+                | "seed"
                 | "push"
                 | "pop"
                 | "time"
+                | "chars"
+                | "ord"
+                | "chr"
+                | "help"
+                | "profile"
+                | "spawn"
+                | "join"
+                | "channel"
+                | "send"
+                | "recv"
         )
     }
 }
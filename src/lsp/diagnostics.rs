@@ -1,6 +1,6 @@
 use crate::lexer::{Span, tokenize_with_errors};
-use crate::lsp::shared::{get_builtin_return_type, is_builtin_function};
-use crate::parser::{Expr, ExprKind, Stmt, StmtKind, parse_with_errors};
+use crate::lsp::shared::{cast_result_type, get_builtin_return_type, is_builtin_function};
+use crate::parser::{Expr, ExprKind, ObjectKey, Pattern, Stmt, StmtKind, parse_with_errors};
 use std::collections::HashMap;
 use std::str::FromStr;
 use tower_lsp_server::{Client, ls_types::*};
@@ -94,10 +94,62 @@ enum BuiltinFunction {
     Float,
     Input,
     Random,
+    Seed,
     Push,
     Pop,
     Print,
+    Println,
+    Eprint,
+    Eprintln,
+    PrintSep,
+    PrintlnSep,
+    EprintSep,
+    EprintlnSep,
+    Write,
+    Format,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Pow,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Ln,
+    Log10,
+    Log2,
+    Exp,
+    Min,
+    Max,
+    Sum,
+    Sort,
+    Reverse,
+    Map,
+    Filter,
+    Reduce,
+    Split,
+    Trim,
+    TrimStart,
+    TrimEnd,
+    Upper,
+    Lower,
+    Replace,
     Time,
+    Chars,
+    Ord,
+    Chr,
+    Help,
+    Profile,
+    Spawn,
+    Join,
+    Channel,
+    Send,
+    Recv,
 }
 
 impl BuiltinFunction {
@@ -108,12 +160,64 @@ impl BuiltinFunction {
             "str" => Some((Self::Str, 1..=1)),
             "int" => Some((Self::Int, 1..=1)),
             "float" => Some((Self::Float, 1..=1)),
+            "sqrt" => Some((Self::Sqrt, 1..=1)),
+            "abs" => Some((Self::Abs, 1..=1)),
+            "floor" => Some((Self::Floor, 1..=1)),
+            "ceil" => Some((Self::Ceil, 1..=1)),
+            "round" => Some((Self::Round, 1..=1)),
+            "pow" => Some((Self::Pow, 2..=2)),
+            "sin" => Some((Self::Sin, 1..=1)),
+            "cos" => Some((Self::Cos, 1..=1)),
+            "tan" => Some((Self::Tan, 1..=1)),
+            "asin" => Some((Self::Asin, 1..=1)),
+            "acos" => Some((Self::Acos, 1..=1)),
+            "atan" => Some((Self::Atan, 1..=1)),
+            "atan2" => Some((Self::Atan2, 2..=2)),
+            "ln" => Some((Self::Ln, 1..=1)),
+            "log10" => Some((Self::Log10, 1..=1)),
+            "log2" => Some((Self::Log2, 1..=1)),
+            "exp" => Some((Self::Exp, 1..=1)),
+            "min" => Some((Self::Min, 1..=usize::MAX)),
+            "max" => Some((Self::Max, 1..=usize::MAX)),
+            "sum" => Some((Self::Sum, 1..=usize::MAX)),
+            "sort" => Some((Self::Sort, 1..=2)),
+            "reverse" => Some((Self::Reverse, 1..=1)),
+            "map" => Some((Self::Map, 2..=2)),
+            "filter" => Some((Self::Filter, 2..=2)),
+            "reduce" => Some((Self::Reduce, 2..=3)),
+            "split" => Some((Self::Split, 1..=2)),
+            "trim" => Some((Self::Trim, 1..=1)),
+            "trim_start" => Some((Self::TrimStart, 1..=1)),
+            "trim_end" => Some((Self::TrimEnd, 1..=1)),
+            "upper" => Some((Self::Upper, 1..=1)),
+            "lower" => Some((Self::Lower, 1..=1)),
+            "replace" => Some((Self::Replace, 3..=3)),
             "input" => Some((Self::Input, 0..=0)),
             "random" => Some((Self::Random, 0..=2)),
+            "seed" => Some((Self::Seed, 1..=1)),
             "push" => Some((Self::Push, 2..=2)),
             "pop" => Some((Self::Pop, 1..=1)),
             "print" => Some((Self::Print, 1..=usize::MAX)),
+            "println" => Some((Self::Println, 0..=usize::MAX)),
+            "eprint" => Some((Self::Eprint, 1..=usize::MAX)),
+            "eprintln" => Some((Self::Eprintln, 0..=usize::MAX)),
+            "print_sep" => Some((Self::PrintSep, 1..=usize::MAX)),
+            "println_sep" => Some((Self::PrintlnSep, 1..=usize::MAX)),
+            "eprint_sep" => Some((Self::EprintSep, 1..=usize::MAX)),
+            "eprintln_sep" => Some((Self::EprintlnSep, 1..=usize::MAX)),
+            "write" => Some((Self::Write, 1..=usize::MAX)),
+            "format" => Some((Self::Format, 1..=usize::MAX)),
             "time" => Some((Self::Time, 0..=0)),
+            "chars" => Some((Self::Chars, 1..=1)),
+            "ord" => Some((Self::Ord, 1..=1)),
+            "chr" => Some((Self::Chr, 1..=1)),
+            "help" => Some((Self::Help, 1..=1)),
+            "profile" => Some((Self::Profile, 0..=0)),
+            "spawn" => Some((Self::Spawn, 1..=usize::MAX)),
+            "join" => Some((Self::Join, 1..=2)),
+            "channel" => Some((Self::Channel, 0..=0)),
+            "send" => Some((Self::Send, 2..=2)),
+            "recv" => Some((Self::Recv, 1..=1)),
             _ => None,
         }
     }
@@ -126,8 +230,11 @@ struct VariableInfo {
 
 struct StaticAnalyzer {
     scopes: Vec<HashMap<String, VariableInfo>>,
-    functions: HashMap<String, (Span, Vec<String>)>,
+    /// Declared functions: span, positional params, and whether a `...rest` param follows.
+    functions: HashMap<String, (Span, Vec<String>, bool)>,
     structs: HashMap<String, Span>,
+    /// Set by `import "path"`, whose bindings can't be known without loading the file.
+    glob_import: bool,
 }
 
 impl StaticAnalyzer {
@@ -136,6 +243,7 @@ impl StaticAnalyzer {
             scopes: vec![HashMap::new()],
             functions: HashMap::new(),
             structs: HashMap::new(),
+            glob_import: false,
         }
     }
 
@@ -209,7 +317,7 @@ impl StaticAnalyzer {
     }
 
     fn contains_variable(&self, name: &str) -> bool {
-        if self.is_builtin_keyword(name) {
+        if self.is_builtin_keyword(name) || self.glob_import {
             return true;
         }
         for scope in self.scopes.iter().rev() {
@@ -221,7 +329,7 @@ impl StaticAnalyzer {
     }
 
     fn is_builtin_keyword(&self, name: &str) -> bool {
-        matches!(name, "true" | "false" | "nil")
+        matches!(name, "true" | "false" | "nil" | "PI" | "E")
     }
 
     fn collect_definitions(&mut self, ast: &[Stmt], diagnostics: &mut Vec<Diagnostic>) {
@@ -238,11 +346,13 @@ impl StaticAnalyzer {
                 crate::runtime::environment::value::Number::Int(_) => "int".to_string(),
                 crate::runtime::environment::value::Number::Float(_) => "float".to_string(),
             },
-            Boolean(_) => "bool".to_string(),
-            String(_) => "string".to_string(),
+            Boolean(_) | TypeTest { .. } => "bool".to_string(),
+            Cast { type_name, .. } => cast_result_type(type_name),
+            String(_) | Interpolation(_) => "string".to_string(),
             Array(_) => "array".to_string(),
             Object(_) => "object".to_string(),
-            FunctionCall { name, .. } => {
+            Lambda { .. } => "function".to_string(),
+            FunctionCall { name, .. } | MethodCall { method: name, .. } => {
                 if is_builtin_function(name) {
                     get_builtin_return_type(name)
                 } else {
@@ -254,6 +364,12 @@ impl StaticAnalyzer {
                 | crate::lexer::TokenKind::Minus
                 | crate::lexer::TokenKind::Multiply
                 | crate::lexer::TokenKind::Divide => "number".to_string(),
+                crate::lexer::TokenKind::FloorDivide
+                | crate::lexer::TokenKind::BitAnd
+                | crate::lexer::TokenKind::BitOr
+                | crate::lexer::TokenKind::BitXor
+                | crate::lexer::TokenKind::ShiftLeft
+                | crate::lexer::TokenKind::ShiftRight => "int".to_string(),
                 crate::lexer::TokenKind::Equal
                 | crate::lexer::TokenKind::NotEqual
                 | crate::lexer::TokenKind::GreaterThan
@@ -266,11 +382,12 @@ impl StaticAnalyzer {
                 .get_variable_type(name)
                 .cloned()
                 .unwrap_or_else(|| "unknown".to_string()),
-            Parenthesized(expr) => self.infer_type(expr),
-            If { .. } => "unknown".to_string(),
-            While { .. } => "array".to_string(),
+            Parenthesized(expr) | Assign { value: expr, .. } => self.infer_type(expr),
+            If { .. } | Match { .. } | Loop(_) | Spread(_) => "unknown".to_string(),
+            While { .. } | DoWhile { .. } | For { .. } => "nil".to_string(),
+            Collect(_) => "array".to_string(),
             Block(_) => "unknown".to_string(),
-            Index { .. } => "unknown".to_string(),
+            Index { .. } | Slice { .. } => "unknown".to_string(),
             GetProperty { .. } => "unknown".to_string(),
             UnaryOp { .. } => "unknown".to_string(),
             StructInstance { .. } => "unknown".to_string(),
@@ -283,6 +400,7 @@ impl StaticAnalyzer {
                 name,
                 name_span,
                 value,
+                ..
             } => {
                 if self
                     .scopes
@@ -299,12 +417,26 @@ impl StaticAnalyzer {
                         ..Default::default()
                     });
                 }
-                let var_type = self.infer_type(value);
+                let var_type = match value {
+                    Some(value) => self.infer_type(value),
+                    None => "unknown".to_string(),
+                };
                 self.add_variable(name, *name_span, var_type);
             }
-            StmtKind::Function { name, params, body } => {
+            StmtKind::LetPattern { pattern, .. } => {
+                for (name, span) in pattern.names() {
+                    self.add_variable(name, *span, "unknown".to_string());
+                }
+            }
+            StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                ..
+            } => {
                 if self.functions.contains_key(name)
-                    && let Some((_first_span, _)) = self.functions.get(name)
+                    && let Some((_first_span, _, _)) = self.functions.get(name)
                 {
                     diagnostics.push(Diagnostic {
                         range: self.span_to_range(&stmt.span),
@@ -316,9 +448,9 @@ impl StaticAnalyzer {
                     });
                 }
                 self.functions
-                    .insert(name.clone(), (stmt.span, params.clone()));
+                    .insert(name.clone(), (stmt.span, params.clone(), rest.is_some()));
                 self.push_scope();
-                for param in params {
+                for param in params.iter().chain(rest) {
                     self.add_variable(param, stmt.span, "unknown".to_string());
                 }
                 self.collect_expr_definitions(body);
@@ -330,10 +462,17 @@ impl StaticAnalyzer {
             StmtKind::Result(expr) => {
                 self.collect_expr_definitions(expr);
             }
-            StmtKind::Return(Some(expr)) => {
+            StmtKind::Return(Some(expr)) | StmtKind::Break(Some(expr)) => {
                 self.collect_expr_definitions(expr);
             }
-            StmtKind::Break | StmtKind::Continue | StmtKind::Return(None) => {}
+            StmtKind::Break(None) | StmtKind::Continue | StmtKind::Return(None) => {}
+            StmtKind::Export(declaration) => {
+                self.collect_stmt_definitions(declaration, diagnostics)
+            }
+            StmtKind::Import { alias, .. } => match alias {
+                Some(alias) => self.add_variable(alias, stmt.span, "object".to_string()),
+                None => self.glob_import = true,
+            },
             StmtKind::Struct { name, .. } => {
                 if self.structs.contains_key(name) {
                     diagnostics.push(Diagnostic {
@@ -367,12 +506,25 @@ impl StaticAnalyzer {
                     self.pop_scope();
                 }
             }
-            ExprKind::While { condition, body } => {
+            ExprKind::While { condition, body } | ExprKind::DoWhile { body, condition } => {
                 self.collect_expr_definitions(condition);
                 self.push_scope();
                 self.collect_expr_definitions(body);
                 self.pop_scope();
             }
+            ExprKind::Loop(body) | ExprKind::Collect(body) => {
+                self.push_scope();
+                self.collect_expr_definitions(body);
+                self.pop_scope();
+            }
+            ExprKind::Lambda { params, rest, body } => {
+                self.push_scope();
+                for param in params.iter().chain(rest) {
+                    self.add_variable(param, expr.span, "unknown".to_string());
+                }
+                self.collect_expr_definitions(body);
+                self.pop_scope();
+            }
             ExprKind::Block(stmts) => {
                 self.push_scope();
                 for stmt_kind in stmts {
@@ -402,17 +554,26 @@ impl StaticAnalyzer {
     fn check_stmt(&mut self, stmt: &Stmt, diagnostics: &mut Vec<Diagnostic>) {
         match &stmt.kind {
             StmtKind::Let { name, value, .. } => {
-                let var_type = self.infer_type(value);
+                let var_type = match value {
+                    Some(value) => self.infer_type(value),
+                    None => "unknown".to_string(),
+                };
                 self.add_variable(name, stmt.span, var_type);
+                if let Some(value) = value {
+                    self.check_expr(value, diagnostics);
+                }
+            }
+            StmtKind::LetPattern { pattern, value } => {
                 self.check_expr(value, diagnostics);
+                for (name, span) in pattern.names() {
+                    self.add_variable(name, *span, "unknown".to_string());
+                }
             }
             StmtKind::Function {
-                name: _,
-                params,
-                body,
+                params, rest, body, ..
             } => {
                 self.push_scope();
-                for param in params {
+                for param in params.iter().chain(rest) {
                     self.add_variable(param, stmt.span, "unknown".to_string());
                 }
                 self.check_expr(body, diagnostics);
@@ -424,13 +585,15 @@ impl StaticAnalyzer {
             StmtKind::Result(expr) => {
                 self.check_expr(expr, diagnostics);
             }
-            StmtKind::Return(Some(expr)) => {
+            StmtKind::Return(Some(expr)) | StmtKind::Break(Some(expr)) => {
                 self.check_expr(expr, diagnostics);
             }
-            StmtKind::Break
+            StmtKind::Break(None)
             | StmtKind::Continue
             | StmtKind::Return(None)
+            | StmtKind::Import { .. }
             | StmtKind::Struct { .. } => {}
+            StmtKind::Export(declaration) => self.check_stmt(declaration, diagnostics),
         }
     }
 
@@ -478,7 +641,10 @@ impl StaticAnalyzer {
                             ..Default::default()
                         });
                     }
-                } else if !self.functions.contains_key(name) && !self.structs.contains_key(name) {
+                } else if !self.functions.contains_key(name)
+                    && !self.structs.contains_key(name)
+                    && !self.contains_variable(name)
+                {
                     diagnostics.push(Diagnostic {
                         range: self.span_to_range(&expr.span),
                         severity: Some(DiagnosticSeverity::ERROR),
@@ -487,8 +653,8 @@ impl StaticAnalyzer {
                         message: format!("Undefined function: '{}'", name),
                         ..Default::default()
                     });
-                } else if let Some((_, params)) = self.functions.get(name)
-                    && args.len() != params.len()
+                } else if let Some((_, params, variadic)) = self.functions.get(name)
+                    && (args.len() < params.len() || (!variadic && args.len() > params.len()))
                 {
                     let severity = if args.len() < params.len() {
                         DiagnosticSeverity::ERROR
@@ -501,8 +667,9 @@ impl StaticAnalyzer {
                         code: Some(NumberOrString::String("MP008".to_string())),
                         source: Some("mp-lang".to_string()),
                         message: format!(
-                            "Function '{}' expects {} argument(s), got {}",
+                            "Function '{}' expects {}{} argument(s), got {}",
                             name,
+                            if *variadic { "at least " } else { "" },
                             params.len(),
                             args.len()
                         ),
@@ -529,11 +696,18 @@ impl StaticAnalyzer {
                     self.check_expr(arg, diagnostics);
                 }
             }
-            ExprKind::BinaryOp { left, right, .. } => {
+            ExprKind::BinaryOp { left, right, .. }
+            | ExprKind::Assign {
+                target: left,
+                value: right,
+            } => {
                 self.check_expr(left, diagnostics);
                 self.check_expr(right, diagnostics);
             }
-            ExprKind::UnaryOp { expr, .. } => {
+            ExprKind::UnaryOp { expr, .. }
+            | ExprKind::Spread(expr)
+            | ExprKind::TypeTest { expr, .. }
+            | ExprKind::Cast { expr, .. } => {
                 self.check_expr(expr, diagnostics);
             }
             ExprKind::If {
@@ -551,12 +725,28 @@ impl StaticAnalyzer {
                     self.pop_scope();
                 }
             }
-            ExprKind::While { condition, body } => {
+            ExprKind::While { condition, body } | ExprKind::DoWhile { body, condition } => {
                 self.check_expr(condition, diagnostics);
                 self.push_scope();
                 self.check_expr(body, diagnostics);
                 self.pop_scope();
             }
+            ExprKind::Loop(body) | ExprKind::Collect(body) => {
+                self.push_scope();
+                self.check_expr(body, diagnostics);
+                self.pop_scope();
+            }
+            ExprKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                self.check_expr(iterable, diagnostics);
+                self.push_scope();
+                self.add_variable(variable, expr.span, "unknown".to_string());
+                self.check_expr(body, diagnostics);
+                self.pop_scope();
+            }
             ExprKind::Block(stmts) => {
                 self.push_scope();
                 for stmt_kind in stmts {
@@ -569,13 +759,39 @@ impl StaticAnalyzer {
                 }
                 self.pop_scope();
             }
+            ExprKind::Lambda { params, rest, body } => {
+                self.push_scope();
+                for param in params.iter().chain(rest) {
+                    self.add_variable(param, expr.span, "unknown".to_string());
+                }
+                self.check_expr(body, diagnostics);
+                self.pop_scope();
+            }
+            ExprKind::Match { subject, arms } => {
+                self.check_expr(subject, diagnostics);
+                for arm in arms {
+                    self.push_scope();
+                    match &arm.pattern {
+                        Pattern::Literal(literal) => self.check_expr(literal, diagnostics),
+                        Pattern::Binding(name) => {
+                            self.add_variable(name, arm.body.span, "unknown".to_string())
+                        }
+                        Pattern::Wildcard => {}
+                    }
+                    self.check_expr(&arm.body, diagnostics);
+                    self.pop_scope();
+                }
+            }
             ExprKind::Array(items) => {
                 for item in items {
                     self.check_expr(item, diagnostics);
                 }
             }
             ExprKind::Object(fields) => {
-                for (_, value) in fields {
+                for (key, value) in fields {
+                    if let ObjectKey::Computed(key) = key {
+                        self.check_expr(key, diagnostics);
+                    }
                     self.check_expr(value, diagnostics);
                 }
             }
@@ -589,6 +805,11 @@ impl StaticAnalyzer {
             ExprKind::Parenthesized(expr) => {
                 self.check_expr(expr, diagnostics);
             }
+            ExprKind::Interpolation(_) | ExprKind::Slice { .. } | ExprKind::MethodCall { .. } => {
+                for child in expr.children() {
+                    self.check_expr(child, diagnostics);
+                }
+            }
             ExprKind::Number(_) | ExprKind::Boolean(_) | ExprKind::String(_) => {}
         }
     }
@@ -67,6 +67,12 @@ impl MpHover {
                 )),
                 range: None,
             }),
+            TokenKind::Const => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**const** - Constant declaration keyword".to_string(),
+                )),
+                range: None,
+            }),
             TokenKind::Fn => Some(Hover {
                 contents: HoverContents::Scalar(MarkedString::String(
                     "**fn** - Function definition keyword".to_string(),
@@ -91,6 +97,40 @@ impl MpHover {
                 )),
                 range: None,
             }),
+            TokenKind::Import => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**import** - Load another file: `import \"path.mp\"` or `import name from \"path.mp\"`"
+                        .to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Export => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**export** - Make a declaration visible to files that import this one"
+                        .to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Do => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**do** - Do-while loop keyword, runs the body before checking the condition"
+                        .to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Loop => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**loop** - Infinite loop keyword, exits with `break value`".to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Collect => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**collect** - Makes a `for`, `while` or `do` loop evaluate to an array of each iteration's value"
+                        .to_string(),
+                )),
+                range: None,
+            }),
             TokenKind::Return => Some(Hover {
                 contents: HoverContents::Scalar(MarkedString::String(
                     "**return** - Return statement keyword".to_string(),
@@ -115,6 +155,36 @@ impl MpHover {
                 )),
                 range: None,
             }),
+            TokenKind::For => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**for** - For-in loop keyword".to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::In => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**in** - Separates the loop variable from the iterable".to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Match => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**match** - Pattern match expression keyword".to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::Is => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**is** - Type test operator, e.g. `x is Int`".to_string(),
+                )),
+                range: None,
+            }),
+            TokenKind::As => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**as** - Type conversion operator, e.g. `x as Float`".to_string(),
+                )),
+                range: None,
+            }),
             TokenKind::Not => Some(Hover {
                 contents: HoverContents::Scalar(MarkedString::String(
                     "**!** - Logical NOT operator".to_string(),
@@ -145,6 +215,12 @@ impl MpHover {
                 )),
                 range: None,
             }),
+            TokenKind::FloorDivide => Some(Hover {
+                contents: HoverContents::Scalar(MarkedString::String(
+                    "**~/** - Floor division operator, always yields an integer".to_string(),
+                )),
+                range: None,
+            }),
             TokenKind::Modulo => Some(Hover {
                 contents: HoverContents::Scalar(MarkedString::String(
                     "**%** - Modulo operator".to_string(),
@@ -1,7 +1,7 @@
 use crate::lexer::{TokenKind, tokenize, tokenize_with_errors};
 use crate::lsp::diagnostics::MpDiagnostics;
-use crate::lsp::shared::{get_builtin_return_type, is_builtin_function};
-use crate::parser::{StmtKind, parse};
+use crate::lsp::shared::{cast_result_type, get_builtin_return_type, is_builtin_function};
+use crate::parser::{ObjectKey, StmtKind, parse};
 use std::collections::HashMap;
 use tower_lsp_server::ls_types::*;
 
@@ -65,7 +65,8 @@ impl MpInlayHints {
             StmtKind::Let {
                 name,
                 name_span,
-                value,
+                value: Some(value),
+                ..
             } => {
                 let type_label = self.infer_type(value, var_types);
                 if !type_label.is_empty()
@@ -86,7 +87,9 @@ impl MpInlayHints {
                     });
                 }
             }
-            StmtKind::Function { name, params, body } => {
+            StmtKind::Function {
+                name, params, body, ..
+            } => {
                 if let Some(token) = self.find_token_in_content(name, content, stmt.span.line) {
                     let param_types: Vec<String> = params.iter().map(|_| "_".to_string()).collect();
                     let return_type = self.infer_return_type(body, var_types);
@@ -112,19 +115,24 @@ impl MpInlayHints {
                     });
                 }
             }
-            StmtKind::Expr(expr) => {
+            StmtKind::Expr(expr) | StmtKind::LetPattern { value: expr, .. } => {
                 self.extract_hints_from_expr(expr, content, hints, var_types);
             }
             StmtKind::Result(expr) => {
                 self.extract_hints_from_expr(expr, content, hints, var_types);
             }
-            StmtKind::Return(Some(expr)) => {
+            StmtKind::Return(Some(expr)) | StmtKind::Break(Some(expr)) => {
                 self.extract_hints_from_expr(expr, content, hints, var_types);
             }
-            StmtKind::Break
+            StmtKind::Let { value: None, .. }
+            | StmtKind::Break(None)
             | StmtKind::Continue
             | StmtKind::Return(None)
+            | StmtKind::Import { .. }
             | StmtKind::Struct { .. } => {}
+            StmtKind::Export(declaration) => {
+                self.extract_hints_from_stmt(declaration, content, hints, var_types)
+            }
         }
     }
 
@@ -149,7 +157,7 @@ impl MpInlayHints {
                     self.extract_hints_from_expr(else_b, content, hints, var_types);
                 }
             }
-            While { condition, body } => {
+            While { condition, body } | DoWhile { body, condition } => {
                 self.extract_hints_from_expr(condition, content, hints, var_types);
                 self.extract_hints_from_expr(body, content, hints, var_types);
             }
@@ -163,7 +171,11 @@ impl MpInlayHints {
                     self.extract_hints_from_stmt(&stmt, content, hints, var_types);
                 }
             }
-            BinaryOp { left, right, .. } => {
+            BinaryOp { left, right, .. }
+            | Assign {
+                target: left,
+                value: right,
+            } => {
                 self.extract_hints_from_expr(left, content, hints, var_types);
                 self.extract_hints_from_expr(right, content, hints, var_types);
             }
@@ -181,7 +193,10 @@ impl MpInlayHints {
                 }
             }
             Object(fields) => {
-                for (_, value) in fields {
+                for (key, value) in fields {
+                    if let ObjectKey::Computed(key) = key {
+                        self.extract_hints_from_expr(key, content, hints, var_types);
+                    }
                     self.extract_hints_from_expr(value, content, hints, var_types);
                 }
             }
@@ -195,6 +210,21 @@ impl MpInlayHints {
             Parenthesized(e) => {
                 self.extract_hints_from_expr(e, content, hints, var_types);
             }
+            Interpolation(_)
+            | Slice { .. }
+            | MethodCall { .. }
+            | Match { .. }
+            | For { .. }
+            | Loop(_)
+            | Collect(_)
+            | Spread(_)
+            | TypeTest { .. }
+            | Cast { .. }
+            | Lambda { .. } => {
+                for child in expr.children() {
+                    self.extract_hints_from_expr(child, content, hints, var_types);
+                }
+            }
             Number(_) | Boolean(_) | String(_) | Variable(_) | StructInstance { .. } => {}
         }
     }
@@ -230,11 +260,13 @@ impl MpInlayHints {
                 crate::runtime::environment::value::Number::Int(_) => "int".to_string(),
                 crate::runtime::environment::value::Number::Float(_) => "float".to_string(),
             },
-            Boolean(_) => "bool".to_string(),
-            String(_) => "string".to_string(),
+            Boolean(_) | TypeTest { .. } => "bool".to_string(),
+            Cast { type_name, .. } => cast_result_type(type_name),
+            String(_) | Interpolation(_) => "string".to_string(),
             Array(_) => "array".to_string(),
             Object(_) => "object".to_string(),
-            FunctionCall { name, .. } => {
+            Lambda { .. } => "function".to_string(),
+            FunctionCall { name, .. } | MethodCall { method: name, .. } => {
                 if is_builtin_function(name) {
                     get_builtin_return_type(name)
                 } else {
@@ -247,6 +279,12 @@ impl MpInlayHints {
                 | TokenKind::Multiply
                 | TokenKind::Divide
                 | TokenKind::Modulo => "number".to_string(),
+                TokenKind::FloorDivide
+                | TokenKind::BitAnd
+                | TokenKind::BitOr
+                | TokenKind::BitXor
+                | TokenKind::ShiftLeft
+                | TokenKind::ShiftRight => "int".to_string(),
                 TokenKind::Equal
                 | TokenKind::NotEqual
                 | TokenKind::GreaterThan
@@ -259,11 +297,12 @@ impl MpInlayHints {
                 .get(name)
                 .cloned()
                 .unwrap_or_else(|| "unknown".to_string()),
-            Parenthesized(expr) => self.infer_type(expr, var_types),
-            If { .. } => "unknown".to_string(),
-            While { .. } => "array".to_string(),
+            Parenthesized(expr) | Assign { value: expr, .. } => self.infer_type(expr, var_types),
+            If { .. } | Match { .. } | Loop(_) | Spread(_) => "unknown".to_string(),
+            While { .. } | DoWhile { .. } | For { .. } => "nil".to_string(),
+            Collect(_) => "array".to_string(),
             Block(_) => "unknown".to_string(),
-            Index { .. } => "unknown".to_string(),
+            Index { .. } | Slice { .. } => "unknown".to_string(),
             GetProperty { .. } => "unknown".to_string(),
             UnaryOp { .. } => "unknown".to_string(),
             StructInstance { .. } => "unknown".to_string(),
@@ -3,12 +3,32 @@ use crate::parser::ExprKind;
 
 pub fn get_builtin_return_type(name: &str) -> String {
     match name {
-        "print" | "push" | "pop" | "time" => "nil".to_string(),
+        "print" | "println" | "eprint" | "eprintln" | "print_sep" | "println_sep"
+        | "eprint_sep" | "eprintln_sep" | "write" | "push" | "pop" | "time" => "nil".to_string(),
         "input" => "string".to_string(),
         "len" => "int".to_string(),
-        "type" | "str" => "string".to_string(),
-        "int" | "float" => "number".to_string(),
-        "random" => "int".to_string(),
+        "type" | "str" | "format" => "string".to_string(),
+        "int" | "float" | "abs" | "pow" | "sum" => "number".to_string(),
+        "sqrt" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "ln" | "log10"
+        | "log2" | "exp" => "float".to_string(),
+        "floor" | "ceil" | "round" => "int".to_string(),
+        "random" | "ord" => "int".to_string(),
+        "chars" | "profile" | "sort" | "map" | "filter" | "split" => "array".to_string(),
+        "spawn" => "thread".to_string(),
+        "channel" => "channel".to_string(),
+        "send" | "seed" => "nil".to_string(),
+        "chr" | "help" => "string".to_string(),
+        "trim" | "trim_start" | "trim_end" | "upper" | "lower" | "replace" => "string".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// The type an `as` conversion produces, in the names `infer_type` uses.
+pub fn cast_result_type(type_name: &str) -> String {
+    match type_name {
+        "Int" | "Float" | "Number" | "String" | "Array" | "Object" | "Range" | "Function"
+        | "Thread" | "Channel" | "Nil" => type_name.to_lowercase(),
+        "Bool" => "bool".to_string(),
         _ => "unknown".to_string(),
     }
 }
@@ -19,11 +39,13 @@ pub fn infer_type(expr: &crate::parser::Expr) -> String {
             crate::runtime::environment::value::Number::Int(_) => "int".to_string(),
             crate::runtime::environment::value::Number::Float(_) => "float".to_string(),
         },
-        ExprKind::Boolean(_) => "bool".to_string(),
-        ExprKind::String(_) => "string".to_string(),
+        ExprKind::Boolean(_) | ExprKind::TypeTest { .. } => "bool".to_string(),
+        ExprKind::Cast { type_name, .. } => cast_result_type(type_name),
+        ExprKind::String(_) | ExprKind::Interpolation(_) => "string".to_string(),
         ExprKind::Array(_) => "array".to_string(),
         ExprKind::Object(_) => "object".to_string(),
-        ExprKind::FunctionCall { name, .. } => {
+        ExprKind::Lambda { .. } => "function".to_string(),
+        ExprKind::FunctionCall { name, .. } | ExprKind::MethodCall { method: name, .. } => {
             if is_builtin_function(name) {
                 get_builtin_return_type(name)
             } else {
@@ -36,6 +58,12 @@ pub fn infer_type(expr: &crate::parser::Expr) -> String {
             | TokenKind::Multiply
             | TokenKind::Divide
             | TokenKind::Modulo => "number".to_string(),
+            TokenKind::FloorDivide
+            | TokenKind::BitAnd
+            | TokenKind::BitOr
+            | TokenKind::BitXor
+            | TokenKind::ShiftLeft
+            | TokenKind::ShiftRight => "int".to_string(),
             TokenKind::Equal
             | TokenKind::NotEqual
             | TokenKind::GreaterThan
@@ -45,11 +73,16 @@ pub fn infer_type(expr: &crate::parser::Expr) -> String {
             _ => "unknown".to_string(),
         },
         ExprKind::Variable(_) => "unknown".to_string(),
-        ExprKind::Parenthesized(expr) => infer_type(expr),
-        ExprKind::If { .. } => "unknown".to_string(),
-        ExprKind::While { .. } => "array".to_string(),
+        ExprKind::Parenthesized(expr) | ExprKind::Assign { value: expr, .. } => infer_type(expr),
+        ExprKind::If { .. } | ExprKind::Match { .. } | ExprKind::Loop(_) | ExprKind::Spread(_) => {
+            "unknown".to_string()
+        }
+        ExprKind::While { .. } | ExprKind::DoWhile { .. } | ExprKind::For { .. } => {
+            "nil".to_string()
+        }
+        ExprKind::Collect(_) => "array".to_string(),
         ExprKind::Block(_) => "unknown".to_string(),
-        ExprKind::Index { .. } => "unknown".to_string(),
+        ExprKind::Index { .. } | ExprKind::Slice { .. } => "unknown".to_string(),
         ExprKind::GetProperty { .. } => "unknown".to_string(),
         ExprKind::UnaryOp { .. } => "unknown".to_string(),
         ExprKind::StructInstance { .. } => "unknown".to_string(),
@@ -60,15 +93,67 @@ pub fn is_builtin_function(name: &str) -> bool {
     matches!(
         name,
         "print"
+            | "println"
+            | "eprint"
+            | "eprintln"
+            | "print_sep"
+            | "println_sep"
+            | "eprint_sep"
+            | "eprintln_sep"
+            | "write"
             | "input"
             | "len"
             | "type"
             | "str"
+            | "format"
             | "int"
             | "float"
+            | "sqrt"
+            | "abs"
+            | "floor"
+            | "ceil"
+            | "round"
+            | "pow"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "asin"
+            | "acos"
+            | "atan"
+            | "atan2"
+            | "ln"
+            | "log10"
+            | "log2"
+            | "exp"
+            | "min"
+            | "max"
+            | "sum"
+            | "sort"
+            | "reverse"
+            | "map"
+            | "filter"
+            | "reduce"
+            | "split"
+            | "trim"
+            | "trim_start"
+            | "trim_end"
+            | "upper"
+            | "lower"
+            | "replace"
             | "random"
+            | "seed"
             | "push"
             | "pop"
             | "time"
+            | "chars"
+            | "ord"
+            | "chr"
+            | "help"
+            | "profile"
+            | "spawn"
+            | "join"
+            | "channel"
+            | "send"
+            | "recv"
     )
 }
@@ -1,6 +1,6 @@
 use crate::lexer::TokenKind;
 use crate::lexer::tokenize;
-use crate::parser::{Expr, ExprKind, Stmt, StmtKind, parse};
+use crate::parser::{Expr, ExprKind, Stmt, StmtKind, format_params, parse};
 use tower_lsp_server::ls_types::*;
 
 #[derive(Debug)]
@@ -34,7 +34,12 @@ impl MpSymbols {
         symbols: &mut Vec<DocumentSymbol>,
     ) {
         match &stmt.kind {
-            StmtKind::Function { name, params, .. } => {
+            StmtKind::Export(declaration) => {
+                self.extract_symbol_from_stmt(declaration, tokens, symbols)
+            }
+            StmtKind::Function {
+                name, params, rest, ..
+            } => {
                 if name.is_empty() {
                     return;
                 }
@@ -42,7 +47,7 @@ impl MpSymbols {
                 #[allow(deprecated)]
                 let symbol = DocumentSymbol {
                     name: name.clone(),
-                    detail: Some(format!("fn({})", params.join(", "))),
+                    detail: Some(format!("fn({})", format_params(params, rest.as_deref()))),
                     kind: SymbolKind::FUNCTION,
                     tags: None,
                     deprecated: None,
@@ -52,16 +57,25 @@ impl MpSymbols {
                 };
                 symbols.push(symbol);
             }
-            StmtKind::Let { name, value, .. } => {
+            StmtKind::Let {
+                name,
+                value,
+                constant,
+                ..
+            } => {
                 if name.is_empty() {
                     return;
                 }
                 let range = self.find_token_range(name, tokens);
-                let kind = self.infer_variable_kind(value);
+                let kind = match value {
+                    _ if *constant => SymbolKind::CONSTANT,
+                    Some(value) => self.infer_variable_kind(value),
+                    None => SymbolKind::VARIABLE,
+                };
                 #[allow(deprecated)]
                 let symbol = DocumentSymbol {
                     name: name.clone(),
-                    detail: Some("let".to_string()),
+                    detail: Some(if *constant { "const" } else { "let" }.to_string()),
                     kind,
                     tags: None,
                     deprecated: None,
@@ -1,10 +1,77 @@
-use mp_lang::{format_code, run_file, run_repl};
+use mp_lang::runtime::profile::format_report;
+use mp_lang::runtime::stack::set_stack_size;
+use mp_lang::{
+    Breakpoint, Diagnostic, Language, MpError, debug_file, dump_ast, format_code, lint_file,
+    profile_file, run_file, run_file_seeded, run_repl, set_language, typecheck_file,
+};
 use std::env;
 use std::fs;
+use std::thread;
+
+/// Every nested MP call recurses on the Rust stack, so programs run on a
+/// thread with room for the default call depth limit, even in debug builds.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // `--json` may go anywhere and switches every diagnostic to JSON lines.
+    let json = args.iter().any(|arg| arg == "--json");
+    args.retain(|arg| arg != "--json");
+    // So may `--lang <en|zh>`, the language of errors and warnings.
+    if let Some(index) = args.iter().position(|arg| arg == "--lang") {
+        match args.get(index + 1).map(|code| code.parse::<Language>()) {
+            Some(Ok(language)) => set_language(language),
+            Some(Err(error)) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("Usage: mp --lang <en|zh> ...");
+                std::process::exit(1);
+            }
+        }
+        args.drain(index..=index + 1);
+    }
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            set_stack_size(STACK_SIZE);
+            match run(args, json) {
+                Ok(()) => true,
+                Err(error) => {
+                    report_error(&*error, json);
+                    false
+                }
+            }
+        })?;
+    match interpreter.join() {
+        Ok(true) => Ok(()),
+        Ok(false) => std::process::exit(1),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+fn report_error(error: &(dyn std::error::Error + 'static), json: bool) {
+    match error.downcast_ref::<MpError>() {
+        Some(mp_error) if json => {
+            for diagnostic in mp_error.diagnostics() {
+                eprintln!("{}", diagnostic.to_json());
+            }
+        }
+        _ if json => eprintln!("{}", Diagnostic::error(None, error.to_string()).to_json()),
+        _ => eprintln!("{error}"),
+    }
+}
+
+fn report_warning(warning: Diagnostic, json: bool) {
+    if json {
+        eprintln!("{}", warning.to_json());
+    } else {
+        eprintln!("{warning}");
+    }
+}
 
+fn run(args: Vec<String>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
     if args.len() > 1 {
         if args[1] == "--format" || args[1] == "-f" {
             if args.len() > 2 {
@@ -18,9 +85,73 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             return Ok(());
         }
+        // Type warnings are reported but don't stop the program from running.
+        if args[1] == "--typecheck" || args[1] == "-t" {
+            if args.len() > 2 {
+                for warning in typecheck_file(&args[2])? {
+                    report_warning(Diagnostic::from(&warning), json);
+                }
+                run_file(&args[2])?;
+            } else {
+                eprintln!("Usage: mp --typecheck <file>");
+            }
+            return Ok(());
+        }
+        if args[1] == "--lint" || args[1] == "-l" {
+            if args.len() > 2 {
+                for lint in lint_file(&args[2])? {
+                    report_warning(Diagnostic::from(&lint), json);
+                }
+            } else {
+                eprintln!("Usage: mp --lint <file>");
+            }
+            return Ok(());
+        }
+        // The table goes to stderr so it doesn't mix with the program's output.
+        if args[1] == "--profile" {
+            if args.len() > 2 {
+                eprint!("{}", format_report(&profile_file(&args[2])?));
+            } else {
+                eprintln!("Usage: mp --profile <file>");
+            }
+            return Ok(());
+        }
+        // Any arguments after the file are breakpoints, as LINE or FILE:LINE.
+        if args[1] == "--debug" {
+            if args.len() > 2 {
+                let breakpoints = args[3..]
+                    .iter()
+                    .map(|arg| arg.parse::<Breakpoint>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                debug_file(&args[2], breakpoints)?;
+            } else {
+                eprintln!("Usage: mp --debug <file> [breakpoint...]");
+            }
+            return Ok(());
+        }
+        if args[1] == "--seed" {
+            if args.len() > 3 {
+                let seed = args[2]
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid seed '{}': expected an integer", args[2]))?;
+                run_file_seeded(&args[3], seed)?;
+            } else {
+                eprintln!("Usage: mp --seed <n> <file>");
+            }
+            return Ok(());
+        }
+        if args[1] == "--dump-ast" {
+            if args.len() > 2 {
+                println!("{}", dump_ast(&args[2])?);
+            } else {
+                eprintln!("Usage: mp --dump-ast <file>");
+            }
+            return Ok(());
+        }
         run_file(&args[1])?;
         return Ok(());
     }
 
-    run_repl()
+    run_repl()?;
+    Ok(())
 }
@@ -0,0 +1,960 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A language errors and warnings can be shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Chinese,
+}
+
+/// Parses a language code: `en` or `zh`.
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Language::English),
+            "zh" => Ok(Language::Chinese),
+            _ => Err(format!("Unknown language '{s}': expected en or zh")),
+        }
+    }
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Shows every message from now on in `language`. The choice applies to the
+/// whole process, threads included.
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::Chinese,
+        _ => Language::English,
+    }
+}
+
+/// Every fixed piece of text in errors and warnings. Details that come from
+/// the program itself, such as names and values, fill the `{placeholders}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    Error,
+    Warning,
+    Hint,
+    /// A diagnostic's first line.
+    Header,
+    HeaderWithoutSpan,
+    /// What went wrong followed by the details.
+    Detailed,
+    /// One call in a stack trace.
+    CalledAt,
+    MoreTimes,
+    UndefinedVariable,
+    RedefinedVariable,
+    ConstReassignment,
+    ConstReassignmentAt,
+    InvalidOperation,
+    ArityMismatch,
+    AtLeast,
+    StackOverflow,
+    LimitExceeded,
+    StepLimit,
+    DurationLimit,
+    AllocationLimit,
+    Interrupted,
+    TooDeeplyNested,
+    DivisionByZero,
+    Overflow,
+    TypeMismatch,
+    UnsupportedExpression,
+    ImportError,
+    ReturnValue,
+    BreakStatement,
+    ContinueStatement,
+    InvalidNumber,
+    UnexpectedCharacter,
+    UnclosedString,
+    UnclosedComment,
+    InvalidEscape,
+    InvalidChar,
+    UnexpectedToken,
+    UnexpectedEof,
+    NestedTooDeeply,
+    ExpectedButFound,
+    Or,
+    EndOfFile,
+    LineBreak,
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    Shadowing,
+    // Details of lexer errors.
+    UnclosedBlockComment,
+    ExtraDecimalPoint,
+    MisplacedDigitSeparator,
+    EmptyExponent,
+    MissingDigits,
+    InvalidDigit,
+    NumberTooLarge,
+    UnclosedMultilineString,
+    UnclosedCharLiteral,
+    UnclosedTemplateString,
+    InvalidHexEscape,
+    InvalidUnicodeEscape,
+    UnknownEscape,
+    InvalidCodePoint,
+    CharLiteralLength,
+    // Details of parser errors.
+    ExpectStatement,
+    ExpectStatementEnd,
+    ExpectExpression,
+    ExpectExpressionAtEof,
+    ExpectEndOfInput,
+    ExpectIdentifier,
+    ExpectWhileAfterDo,
+    ExpectLoopAfterCollect,
+    ExpectAssignAfterConst,
+    ExpectAssignAfterName,
+    ExpectAssignAfterNames,
+    ExpectAssignAfterPattern,
+    ExpectAssignAfterTargets,
+    ExpectBracketAfterPattern,
+    ExpectBraceAfterPattern,
+    InvalidAssignmentTarget,
+    ExpectParenAfterFn,
+    ExpectParenAfterExpression,
+    ExpectPropertyName,
+    ExpectPropertyAfterColon,
+    ExpectBracketAfterComputedKey,
+    ExpectColonAfterProperty,
+    ExpectBraceAfterProperties,
+    ExpectBraceAfterBlock,
+    ExpectBracketAfterElements,
+    ExpectBraceAfterInterpolation,
+    ExpectBracketAfterSlice,
+    ExpectBracketAfterIndex,
+    ExpectParenAfterArguments,
+    ExpectInAfterLoopVariable,
+    ExpectBraceAfterMatchSubject,
+    ExpectArrowAfterPattern,
+    ExpectBraceAfterArms,
+    ExpectPattern,
+    ExpectParenAfterFunctionName,
+    ExpectParenAfterParameters,
+    RestParameterNotLast,
+    ExpectFromAfterImport,
+    ExpectModulePath,
+    ExpectDeclarationAfterExport,
+    ExpectBraceAfterStructName,
+    ExpectBraceAfterFields,
+    // Details of runtime errors.
+    IfCondition,
+    WhileCondition,
+    CannotApply,
+    BitwiseOperand,
+    BitwiseIntegers,
+    ShiftOutOfRange,
+    NegativeRepetition,
+    UnknownType,
+    CannotConvert,
+    ObjectKey,
+    ArrayIndexOutOfBounds,
+    StringIndexOutOfBounds,
+    ObjectPropertyNotFound,
+    StructPropertyNotFound,
+    IndexAccess,
+    IndexAssignment,
+    StringIndexAssignment,
+    PropertyAccess,
+    PropertyAssignment,
+    InvalidTarget,
+    SliceTarget,
+    SliceBounds,
+    DestructureLength,
+    DestructureMissing,
+    DestructureArray,
+    DestructureObject,
+    AssignToTargets,
+    AssignCount,
+    MisplacedSpread,
+    SpreadNonArray,
+    NotIterable,
+    CollectNeedsLoop,
+    NotAFunction,
+    ExportOutsideTopLevel,
+    ImportsUnavailable,
+    CannotImport,
+    CannotRead,
+    CircularImport,
+    ModuleFailed,
+    CannotSave,
+    ChannelNotSaved,
+    CopySelfContaining,
+    CopyThread,
+    // Details of errors from builtin functions.
+    Failed,
+    ExpectsNumber,
+    ExpectsInteger,
+    ExpectsString,
+    ExpectsNumberOrString,
+    ExpectsArrayAndFunction,
+    ExpectsValues,
+    SeparatorFirst,
+    PushArguments,
+    PopArgument,
+    PopEmpty,
+    FormatTemplate,
+    FormatUnclosed,
+    FormatUnopened,
+    FormatInvalidPlaceholder,
+    FormatTooFewValues,
+    FormatTooManyValues,
+    NegativeSqrt,
+    NoIntegerResult,
+    UndefinedFor,
+    CannotOrderNan,
+    CannotCompare,
+    SumNumbers,
+    SortArguments,
+    ComparatorNan,
+    ComparatorResult,
+    ReverseArgument,
+    PredicateResult,
+    ReduceArguments,
+    ReduceEmpty,
+    SplitArguments,
+    JoinArguments,
+    ReplaceArguments,
+    ReplaceEmpty,
+    LenArgument,
+    RandomBounds,
+    RandomArguments,
+    EmptyRandomRange,
+    OrdArgument,
+    ChrCodePoint,
+    HelpArgument,
+    SpawnArgument,
+    JoinThreadArgument,
+    JoinTwice,
+    ThreadFailed,
+    ThreadPanicked,
+    SendArguments,
+    RecvArgument,
+    // Type check warnings.
+    AssignedType,
+    ExpectsType,
+    ConditionContext,
+    IndexContext,
+    ObjectKeyContext,
+}
+
+impl Message {
+    pub const ALL: &[Message] = &[
+        Message::Error,
+        Message::Warning,
+        Message::Hint,
+        Message::Header,
+        Message::HeaderWithoutSpan,
+        Message::Detailed,
+        Message::CalledAt,
+        Message::MoreTimes,
+        Message::UndefinedVariable,
+        Message::RedefinedVariable,
+        Message::ConstReassignment,
+        Message::ConstReassignmentAt,
+        Message::InvalidOperation,
+        Message::ArityMismatch,
+        Message::AtLeast,
+        Message::StackOverflow,
+        Message::LimitExceeded,
+        Message::StepLimit,
+        Message::DurationLimit,
+        Message::AllocationLimit,
+        Message::Interrupted,
+        Message::TooDeeplyNested,
+        Message::DivisionByZero,
+        Message::Overflow,
+        Message::TypeMismatch,
+        Message::UnsupportedExpression,
+        Message::ImportError,
+        Message::ReturnValue,
+        Message::BreakStatement,
+        Message::ContinueStatement,
+        Message::InvalidNumber,
+        Message::UnexpectedCharacter,
+        Message::UnclosedString,
+        Message::UnclosedComment,
+        Message::InvalidEscape,
+        Message::InvalidChar,
+        Message::UnexpectedToken,
+        Message::UnexpectedEof,
+        Message::NestedTooDeeply,
+        Message::ExpectedButFound,
+        Message::Or,
+        Message::EndOfFile,
+        Message::LineBreak,
+        Message::UnusedVariable,
+        Message::UnusedParameter,
+        Message::UnreachableCode,
+        Message::Shadowing,
+        Message::UnclosedBlockComment,
+        Message::ExtraDecimalPoint,
+        Message::MisplacedDigitSeparator,
+        Message::EmptyExponent,
+        Message::MissingDigits,
+        Message::InvalidDigit,
+        Message::NumberTooLarge,
+        Message::UnclosedMultilineString,
+        Message::UnclosedCharLiteral,
+        Message::UnclosedTemplateString,
+        Message::InvalidHexEscape,
+        Message::InvalidUnicodeEscape,
+        Message::UnknownEscape,
+        Message::InvalidCodePoint,
+        Message::CharLiteralLength,
+        Message::ExpectStatement,
+        Message::ExpectStatementEnd,
+        Message::ExpectExpression,
+        Message::ExpectExpressionAtEof,
+        Message::ExpectEndOfInput,
+        Message::ExpectIdentifier,
+        Message::ExpectWhileAfterDo,
+        Message::ExpectLoopAfterCollect,
+        Message::ExpectAssignAfterConst,
+        Message::ExpectAssignAfterName,
+        Message::ExpectAssignAfterNames,
+        Message::ExpectAssignAfterPattern,
+        Message::ExpectAssignAfterTargets,
+        Message::ExpectBracketAfterPattern,
+        Message::ExpectBraceAfterPattern,
+        Message::InvalidAssignmentTarget,
+        Message::ExpectParenAfterFn,
+        Message::ExpectParenAfterExpression,
+        Message::ExpectPropertyName,
+        Message::ExpectPropertyAfterColon,
+        Message::ExpectBracketAfterComputedKey,
+        Message::ExpectColonAfterProperty,
+        Message::ExpectBraceAfterProperties,
+        Message::ExpectBraceAfterBlock,
+        Message::ExpectBracketAfterElements,
+        Message::ExpectBraceAfterInterpolation,
+        Message::ExpectBracketAfterSlice,
+        Message::ExpectBracketAfterIndex,
+        Message::ExpectParenAfterArguments,
+        Message::ExpectInAfterLoopVariable,
+        Message::ExpectBraceAfterMatchSubject,
+        Message::ExpectArrowAfterPattern,
+        Message::ExpectBraceAfterArms,
+        Message::ExpectPattern,
+        Message::ExpectParenAfterFunctionName,
+        Message::ExpectParenAfterParameters,
+        Message::RestParameterNotLast,
+        Message::ExpectFromAfterImport,
+        Message::ExpectModulePath,
+        Message::ExpectDeclarationAfterExport,
+        Message::ExpectBraceAfterStructName,
+        Message::ExpectBraceAfterFields,
+        Message::IfCondition,
+        Message::WhileCondition,
+        Message::CannotApply,
+        Message::BitwiseOperand,
+        Message::BitwiseIntegers,
+        Message::ShiftOutOfRange,
+        Message::NegativeRepetition,
+        Message::UnknownType,
+        Message::CannotConvert,
+        Message::ObjectKey,
+        Message::ArrayIndexOutOfBounds,
+        Message::StringIndexOutOfBounds,
+        Message::ObjectPropertyNotFound,
+        Message::StructPropertyNotFound,
+        Message::IndexAccess,
+        Message::IndexAssignment,
+        Message::StringIndexAssignment,
+        Message::PropertyAccess,
+        Message::PropertyAssignment,
+        Message::InvalidTarget,
+        Message::SliceTarget,
+        Message::SliceBounds,
+        Message::DestructureLength,
+        Message::DestructureMissing,
+        Message::DestructureArray,
+        Message::DestructureObject,
+        Message::AssignToTargets,
+        Message::AssignCount,
+        Message::MisplacedSpread,
+        Message::SpreadNonArray,
+        Message::NotIterable,
+        Message::CollectNeedsLoop,
+        Message::NotAFunction,
+        Message::ExportOutsideTopLevel,
+        Message::ImportsUnavailable,
+        Message::CannotImport,
+        Message::CannotRead,
+        Message::CircularImport,
+        Message::ModuleFailed,
+        Message::CannotSave,
+        Message::ChannelNotSaved,
+        Message::CopySelfContaining,
+        Message::CopyThread,
+        Message::Failed,
+        Message::ExpectsNumber,
+        Message::ExpectsInteger,
+        Message::ExpectsString,
+        Message::ExpectsNumberOrString,
+        Message::ExpectsArrayAndFunction,
+        Message::ExpectsValues,
+        Message::SeparatorFirst,
+        Message::PushArguments,
+        Message::PopArgument,
+        Message::PopEmpty,
+        Message::FormatTemplate,
+        Message::FormatUnclosed,
+        Message::FormatUnopened,
+        Message::FormatInvalidPlaceholder,
+        Message::FormatTooFewValues,
+        Message::FormatTooManyValues,
+        Message::NegativeSqrt,
+        Message::NoIntegerResult,
+        Message::UndefinedFor,
+        Message::CannotOrderNan,
+        Message::CannotCompare,
+        Message::SumNumbers,
+        Message::SortArguments,
+        Message::ComparatorNan,
+        Message::ComparatorResult,
+        Message::ReverseArgument,
+        Message::PredicateResult,
+        Message::ReduceArguments,
+        Message::ReduceEmpty,
+        Message::SplitArguments,
+        Message::JoinArguments,
+        Message::ReplaceArguments,
+        Message::ReplaceEmpty,
+        Message::LenArgument,
+        Message::RandomBounds,
+        Message::RandomArguments,
+        Message::EmptyRandomRange,
+        Message::OrdArgument,
+        Message::ChrCodePoint,
+        Message::HelpArgument,
+        Message::SpawnArgument,
+        Message::JoinThreadArgument,
+        Message::JoinTwice,
+        Message::ThreadFailed,
+        Message::ThreadPanicked,
+        Message::SendArguments,
+        Message::RecvArgument,
+        Message::AssignedType,
+        Message::ExpectsType,
+        Message::ConditionContext,
+        Message::IndexContext,
+        Message::ObjectKeyContext,
+    ];
+
+    pub fn template(self, language: Language) -> &'static str {
+        use Language::*;
+        use Message::*;
+        match (self, language) {
+            (Error, English) => "Error",
+            (Error, Chinese) => "错误",
+            (Warning, English) => "Warning",
+            (Warning, Chinese) => "警告",
+            (Hint, English) => "Hint",
+            (Hint, Chinese) => "提示",
+            (Header, English) => "{severity} at {span}: {message}",
+            (Header, Chinese) => "{severity}（{span}）：{message}",
+            (HeaderWithoutSpan, English) => "{severity}: {message}",
+            (HeaderWithoutSpan, Chinese) => "{severity}：{message}",
+            (Detailed, English) => "{kind}: {detail}",
+            (Detailed, Chinese) => "{kind}：{detail}",
+            (CalledAt, English) => "in {function} called at {span}",
+            (CalledAt, Chinese) => "在 {function} 中，调用于 {span}",
+            (MoreTimes, English) => " ({count} more times)",
+            (MoreTimes, Chinese) => "（另有 {count} 次）",
+            (UndefinedVariable, English) => "Undefined variable: {name}",
+            (UndefinedVariable, Chinese) => "未定义的变量：{name}",
+            (RedefinedVariable, English) => "Redefined variable: {name}",
+            (RedefinedVariable, Chinese) => "重复定义的变量：{name}",
+            (ConstReassignment, English) => "Cannot reassign constant '{name}'",
+            (ConstReassignment, Chinese) => "不能给常量 '{name}' 重新赋值",
+            (ConstReassignmentAt, English) => "Cannot reassign constant '{name}' at {span}",
+            (ConstReassignmentAt, Chinese) => "不能给常量 '{name}' 重新赋值（{span}）",
+            (InvalidOperation, English) => "Invalid operation: {detail}",
+            (InvalidOperation, Chinese) => "无效的操作：{detail}",
+            (ArityMismatch, English) => {
+                "Function '{name}' expects {at_least}{expected} argument{s}, got {found}"
+            }
+            (ArityMismatch, Chinese) => {
+                "函数 '{name}' 需要{at_least} {expected} 个参数，实际传入 {found} 个"
+            }
+            (AtLeast, English) => "at least ",
+            (AtLeast, Chinese) => "至少",
+            (StackOverflow, English) => "Stack overflow: more than {limit} nested calls",
+            (StackOverflow, Chinese) => "栈溢出：嵌套调用超过 {limit} 层",
+            (LimitExceeded, English) => "Limit exceeded: {limit}",
+            (LimitExceeded, Chinese) => "超出限制：{limit}",
+            (StepLimit, English) => "more than {steps} steps",
+            (StepLimit, Chinese) => "超过 {steps} 步",
+            (DurationLimit, English) => "ran longer than {duration}",
+            (DurationLimit, Chinese) => "运行时间超过 {duration}",
+            (AllocationLimit, English) => "allocated more than {bytes} bytes",
+            (AllocationLimit, Chinese) => "分配的内存超过 {bytes} 字节",
+            (Interrupted, English) => "Interrupted",
+            (Interrupted, Chinese) => "已中断",
+            (TooDeeplyNested, English) => "Expression nested too deeply: more than {limit} levels",
+            (TooDeeplyNested, Chinese) => "表达式嵌套过深：超过 {limit} 层",
+            (DivisionByZero, English) => "Division by zero",
+            (DivisionByZero, Chinese) => "除以零",
+            (Overflow, English) => "Integer overflow",
+            (Overflow, Chinese) => "整数溢出",
+            (TypeMismatch, English) => "Type mismatch: {detail}",
+            (TypeMismatch, Chinese) => "类型不匹配：{detail}",
+            (UnsupportedExpression, English) => "Unsupported expression: {detail}",
+            (UnsupportedExpression, Chinese) => "不支持的表达式：{detail}",
+            (ImportError, English) => "Import error: {detail}",
+            (ImportError, Chinese) => "导入错误：{detail}",
+            (ReturnValue, English) => "Function return value: {value}",
+            (ReturnValue, Chinese) => "函数返回值：{value}",
+            (BreakStatement, English) => "Break statement",
+            (BreakStatement, Chinese) => "break 语句",
+            (ContinueStatement, English) => "Continue statement",
+            (ContinueStatement, Chinese) => "continue 语句",
+            (InvalidNumber, English) => "Invalid number: '{text}'",
+            (InvalidNumber, Chinese) => "无效的数字：'{text}'",
+            (UnexpectedCharacter, English) => "Unexpected character: '{text}'",
+            (UnexpectedCharacter, Chinese) => "意外的字符：'{text}'",
+            (UnclosedString, English) => "Unclosed string",
+            (UnclosedString, Chinese) => "未闭合的字符串",
+            (UnclosedComment, English) => "Unclosed comment",
+            (UnclosedComment, Chinese) => "未闭合的注释",
+            (InvalidEscape, English) => "Invalid escape sequence: '{text}'",
+            (InvalidEscape, Chinese) => "无效的转义序列：'{text}'",
+            (InvalidChar, English) => "Invalid character literal: {text}",
+            (InvalidChar, Chinese) => "无效的字符字面量：{text}",
+            (UnexpectedToken, English) => "Unexpected token: {token}",
+            (UnexpectedToken, Chinese) => "意外的记号：{token}",
+            (UnexpectedEof, English) => "Unexpected End of File",
+            (UnexpectedEof, Chinese) => "意外的文件结尾",
+            (NestedTooDeeply, English) => "Expression nested too deeply to parse",
+            (NestedTooDeeply, Chinese) => "表达式嵌套过深，无法解析",
+            (ExpectedButFound, English) => " (expected {expected} but found {found})",
+            (ExpectedButFound, Chinese) => "（应为 {expected}，实际为 {found}）",
+            (Or, English) => " or ",
+            (Or, Chinese) => " 或 ",
+            (EndOfFile, English) => "end of file",
+            (EndOfFile, Chinese) => "文件结尾",
+            (LineBreak, English) => "a line break",
+            (LineBreak, Chinese) => "换行",
+            (UnusedVariable, English) => "Unused variable '{name}'",
+            (UnusedVariable, Chinese) => "未使用的变量 '{name}'",
+            (UnusedParameter, English) => "Unused parameter '{name}'",
+            (UnusedParameter, Chinese) => "未使用的参数 '{name}'",
+            (UnreachableCode, English) => "Unreachable code",
+            (UnreachableCode, Chinese) => "不可达的代码",
+            (Shadowing, English) => "'{name}' shadows a variable from an outer scope",
+            (Shadowing, Chinese) => "'{name}' 遮蔽了外层作用域中的变量",
+            (UnclosedBlockComment, English) => "Unclosed block comment",
+            (UnclosedBlockComment, Chinese) => "未闭合的块注释",
+            (ExtraDecimalPoint, English) => "Number literal has more than one decimal point",
+            (ExtraDecimalPoint, Chinese) => "数字字面量有多个小数点",
+            (MisplacedDigitSeparator, English) => "Digit separator must sit between digits",
+            (MisplacedDigitSeparator, Chinese) => "数字分隔符必须位于数字之间",
+            (EmptyExponent, English) => "Exponent has no digits",
+            (EmptyExponent, Chinese) => "指数部分没有数字",
+            (MissingDigits, English) => "Expected digits after '{prefix}'",
+            (MissingDigits, Chinese) => "'{prefix}' 之后应有数字",
+            (InvalidDigit, English) => "Invalid digit '{digit}' in base {radix} literal",
+            (InvalidDigit, Chinese) => "{radix} 进制字面量中有无效的数字 '{digit}'",
+            (NumberTooLarge, English) => "Number literal is too large",
+            (NumberTooLarge, Chinese) => "数字字面量过大",
+            (UnclosedMultilineString, English) => "Unclosed multi-line string",
+            (UnclosedMultilineString, Chinese) => "未闭合的多行字符串",
+            (UnclosedCharLiteral, English) => "Unclosed character literal",
+            (UnclosedCharLiteral, Chinese) => "未闭合的字符字面量",
+            (UnclosedTemplateString, English) => "Unclosed template string",
+            (UnclosedTemplateString, Chinese) => "未闭合的模板字符串",
+            (InvalidHexEscape, English) => "Expected two hex digits up to 7F after '\\x'",
+            (InvalidHexEscape, Chinese) => "'\\x' 之后应为不超过 7F 的两位十六进制数字",
+            (InvalidUnicodeEscape, English) => "Expected '\\u{...}' with 1 to 6 hex digits",
+            (InvalidUnicodeEscape, Chinese) => "应为带 1 到 6 位十六进制数字的 '\\u{...}'",
+            (UnknownEscape, English) => "Unknown escape sequence",
+            (UnknownEscape, Chinese) => "未知的转义序列",
+            (InvalidCodePoint, English) => "Not a valid Unicode code point",
+            (InvalidCodePoint, Chinese) => "不是有效的 Unicode 码位",
+            (CharLiteralLength, English) => "Character literal must contain exactly one character",
+            (CharLiteralLength, Chinese) => "字符字面量必须恰好包含一个字符",
+            (ExpectStatement, English) => "Unexpected token. Expected a statement.",
+            (ExpectStatement, Chinese) => "意外的记号，应为语句。",
+            (ExpectStatementEnd, English) => "Unexpected token. Expected ';' or newline",
+            (ExpectStatementEnd, Chinese) => "意外的记号，应为 ';' 或换行",
+            (ExpectExpression, English) => "Expect expression but found {token}",
+            (ExpectExpression, Chinese) => "应为表达式，实际为 {token}",
+            (ExpectExpressionAtEof, English) => "Unexpected end of file. Expected expression.",
+            (ExpectExpressionAtEof, Chinese) => "意外的文件结尾，应为表达式。",
+            (ExpectEndOfInput, English) => "Expect end of input after expression",
+            (ExpectEndOfInput, Chinese) => "表达式之后应为输入结尾",
+            (ExpectIdentifier, English) => "Expect identifier",
+            (ExpectIdentifier, Chinese) => "应为标识符",
+            (ExpectWhileAfterDo, English) => "Expect 'while' after do body",
+            (ExpectWhileAfterDo, Chinese) => "do 循环体之后应为 'while'",
+            (ExpectLoopAfterCollect, English) => "Expect a loop after 'collect'",
+            (ExpectLoopAfterCollect, Chinese) => "'collect' 之后应为循环",
+            (ExpectAssignAfterConst, English) => "Expect '=' after constant name",
+            (ExpectAssignAfterConst, Chinese) => "常量名之后应为 '='",
+            (ExpectAssignAfterName, English) => "Expect '=' after variable name",
+            (ExpectAssignAfterName, Chinese) => "变量名之后应为 '='",
+            (ExpectAssignAfterNames, English) => "Expect '=' after variable names",
+            (ExpectAssignAfterNames, Chinese) => "各变量名之后应为 '='",
+            (ExpectAssignAfterPattern, English) => "Expect '=' after destructuring pattern",
+            (ExpectAssignAfterPattern, Chinese) => "解构模式之后应为 '='",
+            (ExpectAssignAfterTargets, English) => "Expect '=' after assignment targets",
+            (ExpectAssignAfterTargets, Chinese) => "赋值目标之后应为 '='",
+            (ExpectBracketAfterPattern, English) => "Expect ']' after destructuring pattern",
+            (ExpectBracketAfterPattern, Chinese) => "解构模式之后应为 ']'",
+            (ExpectBraceAfterPattern, English) => "Expect '}' after destructuring pattern",
+            (ExpectBraceAfterPattern, Chinese) => "解构模式之后应为 '}'",
+            (InvalidAssignmentTarget, English) => {
+                "Invalid assignment target: expected a variable, index, or property"
+            }
+            (InvalidAssignmentTarget, Chinese) => "无效的赋值目标：应为变量、索引或属性",
+            (ExpectParenAfterFn, English) => "Expect '(' after 'fn'",
+            (ExpectParenAfterFn, Chinese) => "'fn' 之后应为 '('",
+            (ExpectParenAfterExpression, English) => "Expect ')' after expression",
+            (ExpectParenAfterExpression, Chinese) => "表达式之后应为 ')'",
+            (ExpectPropertyName, English) => "Expect property name",
+            (ExpectPropertyName, Chinese) => "应为属性名",
+            (ExpectPropertyAfterColon, English) => "Expect property name after ':'",
+            (ExpectPropertyAfterColon, Chinese) => "':' 之后应为属性名",
+            (ExpectBracketAfterComputedKey, English) => "Expect ']' after computed property name",
+            (ExpectBracketAfterComputedKey, Chinese) => "计算属性名之后应为 ']'",
+            (ExpectColonAfterProperty, English) => "Expect ':' after property name",
+            (ExpectColonAfterProperty, Chinese) => "属性名之后应为 ':'",
+            (ExpectBraceAfterProperties, English) => "Expect '}' after object properties",
+            (ExpectBraceAfterProperties, Chinese) => "对象属性之后应为 '}'",
+            (ExpectBraceAfterBlock, English) => "Expect '}' after block",
+            (ExpectBraceAfterBlock, Chinese) => "代码块之后应为 '}'",
+            (ExpectBracketAfterElements, English) => "Expect ']' after array elements",
+            (ExpectBracketAfterElements, Chinese) => "数组元素之后应为 ']'",
+            (ExpectBraceAfterInterpolation, English) => "Expect '}' after interpolated expression",
+            (ExpectBraceAfterInterpolation, Chinese) => "插值表达式之后应为 '}'",
+            (ExpectBracketAfterSlice, English) => "Expect ']' after slice",
+            (ExpectBracketAfterSlice, Chinese) => "切片之后应为 ']'",
+            (ExpectBracketAfterIndex, English) => "Expect ']' after index",
+            (ExpectBracketAfterIndex, Chinese) => "索引之后应为 ']'",
+            (ExpectParenAfterArguments, English) => "Expect ')' after arguments",
+            (ExpectParenAfterArguments, Chinese) => "实参之后应为 ')'",
+            (ExpectInAfterLoopVariable, English) => "Expect 'in' after loop variable",
+            (ExpectInAfterLoopVariable, Chinese) => "循环变量之后应为 'in'",
+            (ExpectBraceAfterMatchSubject, English) => "Expect '{' after match subject",
+            (ExpectBraceAfterMatchSubject, Chinese) => "match 的匹配对象之后应为 '{'",
+            (ExpectArrowAfterPattern, English) => "Expect '=>' after match pattern",
+            (ExpectArrowAfterPattern, Chinese) => "匹配模式之后应为 '=>'",
+            (ExpectBraceAfterArms, English) => "Expect '}' after match arms",
+            (ExpectBraceAfterArms, Chinese) => "match 分支之后应为 '}'",
+            (ExpectPattern, English) => "Expect literal, identifier, or '_' pattern",
+            (ExpectPattern, Chinese) => "应为字面量、标识符或 '_' 模式",
+            (ExpectParenAfterFunctionName, English) => "Expect '(' after function name",
+            (ExpectParenAfterFunctionName, Chinese) => "函数名之后应为 '('",
+            (ExpectParenAfterParameters, English) => "Expect ')' after parameters",
+            (ExpectParenAfterParameters, Chinese) => "形参之后应为 ')'",
+            (RestParameterNotLast, English) => "Rest parameter must be the last parameter",
+            (RestParameterNotLast, Chinese) => "剩余参数必须是最后一个参数",
+            (ExpectFromAfterImport, English) => "Expect 'from' after imported module name",
+            (ExpectFromAfterImport, Chinese) => "导入的模块名之后应为 'from'",
+            (ExpectModulePath, English) => "Expect module path string after 'import'",
+            (ExpectModulePath, Chinese) => "'import' 之后应为模块路径字符串",
+            (ExpectDeclarationAfterExport, English) => {
+                "Expect 'let', 'const', 'fn' or 'struct' after 'export'"
+            }
+            (ExpectDeclarationAfterExport, Chinese) => {
+                "'export' 之后应为 'let'、'const'、'fn' 或 'struct'"
+            }
+            (ExpectBraceAfterStructName, English) => "Expect '{' after struct name",
+            (ExpectBraceAfterStructName, Chinese) => "结构体名之后应为 '{'",
+            (ExpectBraceAfterFields, English) => "Expect '}' after struct fields",
+            (ExpectBraceAfterFields, Chinese) => "结构体字段之后应为 '}'",
+            (IfCondition, English) => "If condition must be boolean",
+            (IfCondition, Chinese) => "if 条件必须是布尔值",
+            (WhileCondition, English) => "While condition must be boolean",
+            (WhileCondition, Chinese) => "while 条件必须是布尔值",
+            (CannotApply, English) => "Cannot apply '{op}' to {left} and {right}",
+            (CannotApply, Chinese) => "不能对 {left} 和 {right} 使用 '{op}'",
+            (BitwiseOperand, English) => "Bitwise operators require integers, got {type}",
+            (BitwiseOperand, Chinese) => "位运算需要整数，实际为 {type}",
+            (BitwiseIntegers, English) => "Bitwise operator '{op}' requires integers",
+            (BitwiseIntegers, Chinese) => "位运算符 '{op}' 需要整数",
+            (ShiftOutOfRange, English) => "Shift amount out of range: {amount}",
+            (ShiftOutOfRange, Chinese) => "移位量超出范围：{amount}",
+            (NegativeRepetition, English) => {
+                "String repetition count must be non-negative, got {count}"
+            }
+            (NegativeRepetition, Chinese) => "字符串重复次数不能为负数，实际为 {count}",
+            (UnknownType, English) => "Unknown type '{type}'",
+            (UnknownType, Chinese) => "未知的类型 '{type}'",
+            (CannotConvert, English) => "Cannot convert {from} to {type}",
+            (CannotConvert, Chinese) => "不能把 {from} 转换为 {type}",
+            (ObjectKey, English) => "Object keys must be strings, found {type}",
+            (ObjectKey, Chinese) => "对象的键必须是字符串，实际为 {type}",
+            (ArrayIndexOutOfBounds, English) => {
+                "Array index out of bounds: {index} (length: {length})"
+            }
+            (ArrayIndexOutOfBounds, Chinese) => "数组索引越界：{index}（长度：{length}）",
+            (StringIndexOutOfBounds, English) => {
+                "String index out of bounds: {index} (length: {length})"
+            }
+            (StringIndexOutOfBounds, Chinese) => "字符串索引越界：{index}（长度：{length}）",
+            (ObjectPropertyNotFound, English) => "Object property not found: {name}",
+            (ObjectPropertyNotFound, Chinese) => "找不到对象属性：{name}",
+            (StructPropertyNotFound, English) => "Struct property not found: {name}",
+            (StructPropertyNotFound, Chinese) => "找不到结构体属性：{name}",
+            (IndexAccess, English) => {
+                "Index access requires array/string index or object/string property"
+            }
+            (IndexAccess, Chinese) => "索引访问需要数组或字符串加索引，或对象加字符串属性",
+            (IndexAssignment, English) => {
+                "Index assignment requires array/string index or object/string property"
+            }
+            (IndexAssignment, Chinese) => "索引赋值需要数组或字符串加索引，或对象加字符串属性",
+            (StringIndexAssignment, English) => {
+                "String index assignment requires a single character"
+            }
+            (StringIndexAssignment, Chinese) => "字符串索引赋值需要单个字符",
+            (PropertyAccess, English) => "Property access requires an object",
+            (PropertyAccess, Chinese) => "属性访问需要对象",
+            (PropertyAssignment, English) => "Property assignment requires an object",
+            (PropertyAssignment, Chinese) => "属性赋值需要对象",
+            (InvalidTarget, English) => "Invalid assignment target",
+            (InvalidTarget, Chinese) => "无效的赋值目标",
+            (SliceTarget, English) => "Slicing requires an array or string",
+            (SliceTarget, Chinese) => "切片需要数组或字符串",
+            (SliceBounds, English) => "Slice bounds must be integers",
+            (SliceBounds, Chinese) => "切片边界必须是整数",
+            (DestructureLength, English) => {
+                "Cannot destructure an array of length {length} into {count} name(s)"
+            }
+            (DestructureLength, Chinese) => "不能把长度为 {length} 的数组解构为 {count} 个名字",
+            (DestructureMissing, English) => "Cannot destructure missing property: {name}",
+            (DestructureMissing, Chinese) => "不能解构不存在的属性：{name}",
+            (DestructureArray, English) => "Cannot destructure {type} as an array",
+            (DestructureArray, Chinese) => "不能把 {type} 当作数组解构",
+            (DestructureObject, English) => "Cannot destructure {type} as an object",
+            (DestructureObject, Chinese) => "不能把 {type} 当作对象解构",
+            (AssignToTargets, English) => "Cannot assign {type} to {count} targets",
+            (AssignToTargets, Chinese) => "不能把 {type} 赋给 {count} 个目标",
+            (AssignCount, English) => "Cannot assign {values} value(s) to {count} target(s)",
+            (AssignCount, Chinese) => "不能把 {values} 个值赋给 {count} 个目标",
+            (MisplacedSpread, English) => {
+                "Spread is only allowed in call arguments and array literals"
+            }
+            (MisplacedSpread, Chinese) => "展开只能用于调用参数和数组字面量",
+            (SpreadNonArray, English) => "Cannot spread a {type}, expected an array",
+            (SpreadNonArray, Chinese) => "不能展开 {type}，应为数组",
+            (NotIterable, English) => "Cannot iterate over {type}",
+            (NotIterable, Chinese) => "不能遍历 {type}",
+            (CollectNeedsLoop, English) => "'collect' needs a for, while or do loop",
+            (CollectNeedsLoop, Chinese) => "'collect' 需要 for、while 或 do 循环",
+            (NotAFunction, English) => "'{name}' is a {type}, not a function",
+            (NotAFunction, Chinese) => "'{name}' 是 {type}，不是函数",
+            (ExportOutsideTopLevel, English) => {
+                "'export' is only allowed at the top level of a file"
+            }
+            (ExportOutsideTopLevel, Chinese) => "'export' 只能用在文件的顶层",
+            (ImportsUnavailable, English) => "Imports are not available in this environment",
+            (ImportsUnavailable, Chinese) => "当前环境不支持导入",
+            (CannotImport, English) => "Cannot import '{path}': {reason}",
+            (CannotImport, Chinese) => "无法导入 '{path}'：{reason}",
+            (CannotRead, English) => "Cannot read '{path}': {reason}",
+            (CannotRead, Chinese) => "无法读取 '{path}'：{reason}",
+            (CircularImport, English) => "Circular import of '{path}'",
+            (CircularImport, Chinese) => "循环导入 '{path}'",
+            (ModuleFailed, English) => "Error in module '{path}': {error}",
+            (ModuleFailed, Chinese) => "模块 '{path}' 出错：{error}",
+            (CannotSave, English) => "Cannot save '{name}': {reason}",
+            (CannotSave, Chinese) => "无法保存 '{name}'：{reason}",
+            (ChannelNotSaved, English) => "channels only exist while the program runs",
+            (ChannelNotSaved, Chinese) => "通道只在程序运行时存在",
+            (CopySelfContaining, English) => "Cannot copy an array that contains itself",
+            (CopySelfContaining, Chinese) => "不能复制包含自身的数组",
+            (CopyThread, English) => "Cannot copy a thread handle",
+            (CopyThread, Chinese) => "不能复制线程句柄",
+            (Failed, English) => "{function}() failed: {reason}",
+            (Failed, Chinese) => "{function}() 失败：{reason}",
+            (ExpectsNumber, English) => "{function}() expects a number",
+            (ExpectsNumber, Chinese) => "{function}() 需要数字",
+            (ExpectsInteger, English) => "{function}() expects an integer",
+            (ExpectsInteger, Chinese) => "{function}() 需要整数",
+            (ExpectsString, English) => "{function}() expects a string",
+            (ExpectsString, Chinese) => "{function}() 需要字符串",
+            (ExpectsNumberOrString, English) => "{function}() expects a number or a string",
+            (ExpectsNumberOrString, Chinese) => "{function}() 需要数字或字符串",
+            (ExpectsArrayAndFunction, English) => "{function}() expects an array and a function",
+            (ExpectsArrayAndFunction, Chinese) => "{function}() 需要一个数组和一个函数",
+            (ExpectsValues, English) => {
+                "{function}() expects an array with at least one element, or values"
+            }
+            (ExpectsValues, Chinese) => "{function}() 需要至少有一个元素的数组，或多个值",
+            (SeparatorFirst, English) => "{function}() expects a separator string first",
+            (SeparatorFirst, Chinese) => "{function}() 的第一个参数应为分隔符字符串",
+            (PushArguments, English) => "push() expects a vector and an item",
+            (PushArguments, Chinese) => "push() 需要一个数组和一个元素",
+            (PopArgument, English) => "pop() expects a vector",
+            (PopArgument, Chinese) => "pop() 需要一个数组",
+            (PopEmpty, English) => "Cannot pop from empty vector",
+            (PopEmpty, Chinese) => "不能从空数组中弹出",
+            (FormatTemplate, English) => "format() expects a template string",
+            (FormatTemplate, Chinese) => "format() 需要模板字符串",
+            (FormatUnclosed, English) => "format() has a '{' without a closing '}'",
+            (FormatUnclosed, Chinese) => "format() 中的 '{' 缺少对应的 '}'",
+            (FormatUnopened, English) => "format() has a '}' without an opening '{'",
+            (FormatUnopened, Chinese) => "format() 中的 '}' 缺少对应的 '{'",
+            (FormatInvalidPlaceholder, English) => "format() got an invalid placeholder '{{spec}}'",
+            (FormatInvalidPlaceholder, Chinese) => "format() 收到无效的占位符 '{{spec}}'",
+            (FormatTooFewValues, English) => {
+                "format() has more placeholders than the {count} values given"
+            }
+            (FormatTooFewValues, Chinese) => "format() 的占位符多于给出的 {count} 个值",
+            (FormatTooManyValues, English) => "format() got {count} values for {used} placeholders",
+            (FormatTooManyValues, Chinese) => "format() 收到 {count} 个值，但只有 {used} 个占位符",
+            (NegativeSqrt, English) => "sqrt() of a negative number: {value}",
+            (NegativeSqrt, Chinese) => "sqrt() 的参数是负数：{value}",
+            (NoIntegerResult, English) => "{function}() of {value} has no integer result",
+            (NoIntegerResult, Chinese) => "{function}() 作用于 {value} 时没有整数结果",
+            (UndefinedFor, English) => "{function}() is undefined for {value}",
+            (UndefinedFor, Chinese) => "{function}() 对 {value} 没有定义",
+            (CannotOrderNan, English) => "{function}() can't order NaN",
+            (CannotOrderNan, Chinese) => "{function}() 无法排序 NaN",
+            (CannotCompare, English) => "{function}() can't compare {left} with {right}",
+            (CannotCompare, Chinese) => "{function}() 无法比较 {left} 和 {right}",
+            (SumNumbers, English) => "sum() expects numbers, found {type}",
+            (SumNumbers, Chinese) => "sum() 需要数字，实际为 {type}",
+            (SortArguments, English) => {
+                "sort() expects an array and optionally a comparator function"
+            }
+            (SortArguments, Chinese) => "sort() 需要一个数组，以及可选的比较函数",
+            (ComparatorNan, English) => "sort() comparator returned NaN",
+            (ComparatorNan, Chinese) => "sort() 的比较函数返回了 NaN",
+            (ComparatorResult, English) => "sort() comparator must return a number, got {type}",
+            (ComparatorResult, Chinese) => "sort() 的比较函数必须返回数字，实际为 {type}",
+            (ReverseArgument, English) => "reverse() expects an array or a string",
+            (ReverseArgument, Chinese) => "reverse() 需要数组或字符串",
+            (PredicateResult, English) => "filter() predicate must return a boolean, got {type}",
+            (PredicateResult, Chinese) => "filter() 的判断函数必须返回布尔值，实际为 {type}",
+            (ReduceArguments, English) => {
+                "reduce() expects an array, a function and optionally an initial value"
+            }
+            (ReduceArguments, Chinese) => "reduce() 需要一个数组、一个函数，以及可选的初始值",
+            (ReduceEmpty, English) => "reduce() of an empty array needs an initial value",
+            (ReduceEmpty, Chinese) => "对空数组调用 reduce() 需要初始值",
+            (SplitArguments, English) => {
+                "split() expects a string and optionally a separator string"
+            }
+            (SplitArguments, Chinese) => "split() 需要一个字符串，以及可选的分隔符字符串",
+            (JoinArguments, English) => "join() expects an array and a separator string",
+            (JoinArguments, Chinese) => "join() 需要一个数组和一个分隔符字符串",
+            (ReplaceArguments, English) => "replace() expects three strings",
+            (ReplaceArguments, Chinese) => "replace() 需要三个字符串",
+            (ReplaceEmpty, English) => "replace() can't replace an empty string",
+            (ReplaceEmpty, Chinese) => "replace() 不能替换空字符串",
+            (LenArgument, English) => "len() expects a string, array, object, or range",
+            (LenArgument, Chinese) => "len() 需要字符串、数组、对象或范围",
+            (RandomBounds, English) => "random() expects two integers or two floats",
+            (RandomBounds, Chinese) => "random() 需要两个整数或两个浮点数",
+            (RandomArguments, English) => "random() expects 0, 1 or 2 arguments",
+            (RandomArguments, Chinese) => "random() 需要 0、1 或 2 个参数",
+            (EmptyRandomRange, English) => "random() can't draw from {low}..{high}",
+            (EmptyRandomRange, Chinese) => "random() 无法从 {low}..{high} 中取值",
+            (OrdArgument, English) => "ord() expects a single character, found \"{text}\"",
+            (OrdArgument, Chinese) => "ord() 需要单个字符，实际为 \"{text}\"",
+            (ChrCodePoint, English) => "chr() got an invalid code point: {value}",
+            (ChrCodePoint, Chinese) => "chr() 收到无效的码位：{value}",
+            (HelpArgument, English) => "help() expects a function or a function name",
+            (HelpArgument, Chinese) => "help() 需要函数或函数名",
+            (SpawnArgument, English) => "spawn() expects a function",
+            (SpawnArgument, Chinese) => "spawn() 需要函数",
+            (JoinThreadArgument, English) => "join() expects a thread handle",
+            (JoinThreadArgument, Chinese) => "join() 需要线程句柄",
+            (JoinTwice, English) => "join() called twice on the same thread",
+            (JoinTwice, Chinese) => "同一个线程调用了两次 join()",
+            (ThreadFailed, English) => "Spawned thread failed: {message}",
+            (ThreadFailed, Chinese) => "派生的线程出错：{message}",
+            (ThreadPanicked, English) => "Spawned thread panicked",
+            (ThreadPanicked, Chinese) => "派生的线程发生了 panic",
+            (SendArguments, English) => "send() expects a channel and a value",
+            (SendArguments, Chinese) => "send() 需要一个通道和一个值",
+            (RecvArgument, English) => "recv() expects a channel",
+            (RecvArgument, Chinese) => "recv() 需要一个通道",
+            (AssignedType, English) => "'{name}' holds a {declared} but is assigned a {found}",
+            (AssignedType, Chinese) => "'{name}' 保存的是 {declared}，却被赋值为 {found}",
+            (ExpectsType, English) => "{context} expects {expected}, found {found}",
+            (ExpectsType, Chinese) => "{context} 需要 {expected}，实际为 {found}",
+            (ConditionContext, English) => "{keyword} condition",
+            (ConditionContext, Chinese) => "{keyword} 条件",
+            (IndexContext, English) => "{type} index",
+            (IndexContext, Chinese) => "{type} 索引",
+            (ObjectKeyContext, English) => "object key",
+            (ObjectKeyContext, Chinese) => "对象的键",
+        }
+    }
+
+    /// The message in the current language with each `{name}` in it replaced
+    /// by the matching argument. Braces around anything but a plain name, as
+    /// in `'{'` or `{...}`, are kept as they are.
+    pub fn format(self, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut text = String::new();
+        let mut rest = self.template(language());
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let name = after
+                .split_once('}')
+                .map(|(name, _)| name)
+                .filter(|name| is_placeholder(name));
+            let value = name.and_then(|name| args.iter().find(|(arg, _)| *arg == name));
+            match (name, value) {
+                (Some(name), Some((_, value))) => {
+                    text.push_str(&value.to_string());
+                    rest = &after[name.len() + 1..];
+                }
+                _ => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+
+    /// The message in the current language, for those without placeholders.
+    pub fn text(self) -> &'static str {
+        self.template(language())
+    }
+}
+
+/// Whether `{name}` in a template is a placeholder rather than literal braces.
+pub fn is_placeholder(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Why a call got the wrong number of arguments, for the resolver and the
+/// evaluator alike. `variadic` functions take `expected` or more.
+pub(crate) fn arity_mismatch(name: &str, expected: usize, found: usize, variadic: bool) -> String {
+    let at_least = if variadic {
+        Message::AtLeast.text()
+    } else {
+        ""
+    };
+    let plural = if expected == 1 { "" } else { "s" };
+    Message::ArityMismatch.format(&[
+        ("name", &name),
+        ("at_least", &at_least),
+        ("expected", &expected),
+        ("s", &plural),
+        ("found", &found),
+    ])
+}
@@ -1,19 +1,63 @@
+use serde::{Deserialize, Serialize};
+
 use crate::lexer::{Span, TokenKind};
 use crate::runtime::environment::value::Number;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Expr {
     pub kind: ExprKind,
     pub span: Span,
 }
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum InterpolationPart {
+    Literal(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Pattern {
+    Literal(Expr),
+    Wildcard,
+    Binding(String),
+}
+
+/// The left-hand side of a destructuring `let`: `[a, b]` or `{x, y}`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum BindingPattern {
+    Array(Vec<(String, Span)>),
+    Object(Vec<(String, Span)>),
+}
+
+impl BindingPattern {
+    pub fn names(&self) -> &[(String, Span)] {
+        match self {
+            BindingPattern::Array(names) | BindingPattern::Object(names) => names,
+        }
+    }
+}
+
+/// A key in an object literal: `"a"` and `a` are names, `[expr]` is computed.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ObjectKey {
+    Name(String),
+    Computed(Expr),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expr,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum ExprKind {
     Number(Number),
     Boolean(bool),
     String(String),
+    Interpolation(Vec<InterpolationPart>),
     Variable(String),
     Array(Vec<Expr>),
-    Object(Vec<(String, Expr)>),
+    Object(Vec<(ObjectKey, Expr)>),
     Parenthesized(Box<Expr>),
     If {
         condition: Box<Expr>,
@@ -26,6 +70,10 @@ pub enum ExprKind {
         op: TokenKind,
         right: Box<Expr>,
     },
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
     UnaryOp {
         op: TokenKind,
         expr: Box<Expr>,
@@ -34,14 +82,47 @@ pub enum ExprKind {
         name: String,
         args: Vec<Expr>,
     },
+    MethodCall {
+        object: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    Lambda {
+        params: Vec<String>,
+        rest: Option<String>,
+        body: Box<Expr>,
+    },
     While {
         condition: Box<Expr>,
         body: Box<Expr>,
     },
+    DoWhile {
+        body: Box<Expr>,
+        condition: Box<Expr>,
+    },
+    Loop(Box<Expr>),
+    /// `collect` in front of a `while`, `do` or `for` loop: an array of each iteration's value.
+    Collect(Box<Expr>),
+    /// `...expr` inside call arguments or an array literal.
+    Spread(Box<Expr>),
+    For {
+        variable: String,
+        iterable: Box<Expr>,
+        body: Box<Expr>,
+    },
+    Match {
+        subject: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
     Index {
         object: Box<Expr>,
         index: Box<Expr>,
     },
+    Slice {
+        object: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+    },
     GetProperty {
         object: Box<Expr>,
         property: String,
@@ -50,6 +131,16 @@ pub enum ExprKind {
         name: String,
         args: Vec<Expr>,
     },
+    /// `expr is Type`
+    TypeTest {
+        expr: Box<Expr>,
+        type_name: String,
+    },
+    /// `expr as Type`
+    Cast {
+        expr: Box<Expr>,
+        type_name: String,
+    },
 }
 
 impl Expr {
@@ -64,8 +155,22 @@ impl Expr {
             | ExprKind::Boolean(_)
             | ExprKind::String(_)
             | ExprKind::Variable(_) => {}
+            ExprKind::Interpolation(parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expr(expr) = part {
+                        children.push(expr);
+                    }
+                }
+            }
             ExprKind::Array(items) => children.extend(items),
-            ExprKind::Object(fields) => children.extend(fields.iter().map(|(_, v)| v)),
+            ExprKind::Object(fields) => {
+                for (key, value) in fields {
+                    if let ObjectKey::Computed(key) = key {
+                        children.push(key);
+                    }
+                    children.push(value);
+                }
+            }
             ExprKind::Parenthesized(expr) => children.push(expr),
             ExprKind::If {
                 condition,
@@ -89,20 +194,60 @@ impl Expr {
                 children.push(left);
                 children.push(right);
             }
+            ExprKind::Assign { target, value } => {
+                children.push(target);
+                children.push(value);
+            }
             ExprKind::UnaryOp { expr, .. } => {
                 children.push(expr);
             }
             ExprKind::FunctionCall { args, .. } => {
                 children.extend(args);
             }
+            ExprKind::MethodCall { object, args, .. } => {
+                children.push(object);
+                children.extend(args);
+            }
+            ExprKind::Lambda { body, .. }
+            | ExprKind::Loop(body)
+            | ExprKind::Collect(body)
+            | ExprKind::Spread(body)
+            | ExprKind::TypeTest { expr: body, .. }
+            | ExprKind::Cast { expr: body, .. } => children.push(body),
             ExprKind::While { condition, body } => {
                 children.push(condition);
                 children.push(body);
             }
+            ExprKind::DoWhile { body, condition } => {
+                children.push(body);
+                children.push(condition);
+            }
+            ExprKind::For { iterable, body, .. } => {
+                children.push(iterable);
+                children.push(body);
+            }
+            ExprKind::Match { subject, arms } => {
+                children.push(subject);
+                for arm in arms {
+                    if let Pattern::Literal(expr) = &arm.pattern {
+                        children.push(expr);
+                    }
+                    children.push(&arm.body);
+                }
+            }
             ExprKind::Index { object, index } => {
                 children.push(object);
                 children.push(index);
             }
+            ExprKind::Slice { object, start, end } => {
+                children.push(object);
+                if let Some(start) = start {
+                    children.push(start);
+                }
+                if let Some(end) = end {
+                    children.push(end);
+                }
+            }
             ExprKind::GetProperty { object, .. } => {
                 children.push(object);
             }
@@ -114,30 +259,47 @@ impl Expr {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Stmt {
     pub kind: StmtKind,
     pub span: Span,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum StmtKind {
     Expr(Expr),
     Let {
         name: String,
         name_span: Span,
+        /// `None` for `let x;`, which starts out as nil.
+        value: Option<Expr>,
+        /// Declared with `const`; the binding can't be reassigned.
+        constant: bool,
+    },
+    LetPattern {
+        pattern: BindingPattern,
         value: Expr,
     },
     Function {
         name: String,
         params: Vec<String>,
+        rest: Option<String>,
         body: Expr,
+        /// The `///` comment lines right above the declaration.
+        doc: Option<String>,
     },
     Struct {
         name: String,
         fields: Vec<(String, Option<Expr>)>,
     },
-    Break,
+    /// `import "path"` merges a module's bindings; `import name from "path"` binds them as an object.
+    Import {
+        path: String,
+        alias: Option<String>,
+    },
+    /// A `let`, `const`, `fn` or `struct` declaration marked as part of its module's public surface.
+    Export(Box<Stmt>),
+    Break(Option<Expr>),
     Continue,
     Result(Expr),
     Return(Option<Expr>),
@@ -148,3 +310,50 @@ impl Stmt {
         self.span
     }
 }
+
+impl StmtKind {
+    /// The names a declaration binds in its scope; empty for other statements.
+    pub fn declared_names(&self) -> Vec<&str> {
+        match self {
+            StmtKind::Let { name, .. }
+            | StmtKind::Function { name, .. }
+            | StmtKind::Struct { name, .. } => vec![name.as_str()],
+            StmtKind::LetPattern { pattern, .. } => pattern
+                .names()
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect(),
+            StmtKind::Export(stmt) => stmt.kind.declared_names(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Somewhere inside a block statement, which carries no span of its own.
+    pub fn span_hint(&self) -> Option<Span> {
+        match self {
+            StmtKind::Let { name_span, .. } => Some(*name_span),
+            StmtKind::LetPattern { pattern, .. } => pattern.names().first().map(|(_, span)| *span),
+            StmtKind::Expr(expr)
+            | StmtKind::Result(expr)
+            | StmtKind::Function { body: expr, .. }
+            | StmtKind::Break(Some(expr))
+            | StmtKind::Return(Some(expr)) => Some(expr.span),
+            StmtKind::Export(stmt) => Some(stmt.span),
+            _ => None,
+        }
+    }
+}
+
+/// Renders a parameter list as written in source, e.g. `a, b, ...rest`.
+pub fn format_params(params: &[String], rest: Option<&str>) -> String {
+    let mut parts = params.to_vec();
+    if let Some(rest) = rest {
+        parts.push(format!("...{rest}"));
+    }
+    parts.join(", ")
+}
+
+/// Renders statements back to canonical MP source that parses to the same tree.
+pub fn to_source(stmts: &[Stmt]) -> String {
+    crate::formatter::Formatter::new().format_ast(stmts)
+}
@@ -1,16 +1,40 @@
-use crate::lexer::{Span, Token};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Span, Token, TokenKind};
+use crate::messages::Message;
 
 #[derive(Debug, Clone)]
 pub enum ParserErrorKind {
-    UnexpectedToken(Token),
+    UnexpectedToken(Box<Token>),
     UnexpectedEOF,
+    /// Nested deeper than the Rust stack can follow.
+    TooDeeplyNested,
+}
+
+impl ParserErrorKind {
+    /// A stable identifier for this kind of error, for tools to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserErrorKind::UnexpectedToken(_) => "E0201",
+            ParserErrorKind::UnexpectedEOF => "E0202",
+            ParserErrorKind::TooDeeplyNested => "E0203",
+        }
+    }
 }
 
 impl std::fmt::Display for ParserErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParserErrorKind::UnexpectedToken(token) => write!(f, "Unexpected token: {token}"),
-            ParserErrorKind::UnexpectedEOF => write!(f, "Unexpected End of File"),
+            ParserErrorKind::UnexpectedToken(token) => {
+                write!(
+                    f,
+                    "{}",
+                    Message::UnexpectedToken.format(&[("token", token)])
+                )
+            }
+            ParserErrorKind::UnexpectedEOF => write!(f, "{}", Message::UnexpectedEof.text()),
+            ParserErrorKind::TooDeeplyNested => {
+                write!(f, "{}", Message::NestedTooDeeply.text())
+            }
         }
     }
 }
@@ -20,15 +44,47 @@ pub struct ParserError {
     pub span: Span,
     pub kind: ParserErrorKind,
     pub message: String,
+    /// The tokens that would have been accepted here, if known.
+    pub expected: Vec<TokenKind>,
 }
 
-impl std::fmt::Display for ParserError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.message.is_empty() {
-            write!(f, "Error at {}: {}", self.span, self.kind)
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        let mut message = if error.message.is_empty() {
+            error.kind.to_string()
         } else {
-            write!(f, "Error at {}: {}", self.span, self.message)
+            error.message.clone()
+        };
+        if let (false, ParserErrorKind::UnexpectedToken(found)) =
+            (error.expected.is_empty(), &error.kind)
+        {
+            let expected: Vec<String> = error
+                .expected
+                .iter()
+                .map(|kind| format!("'{kind}'"))
+                .collect();
+            message.push_str(&Message::ExpectedButFound.format(&[
+                ("expected", &expected.join(Message::Or.text())),
+                ("found", &describe_token(found)),
+            ]));
         }
+        Diagnostic::error(Some(error.span), message).with_code(error.kind.code())
+    }
+}
+
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Diagnostic::from(self))
+    }
+}
+
+/// How a token is named in error messages: its source text where it has one.
+pub fn describe_token(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Eof => Message::EndOfFile.text().to_string(),
+        TokenKind::Newline => Message::LineBreak.text().to_string(),
+        _ if token.lexeme.is_empty() => format!("'{}'", token.kind),
+        _ => format!("'{}'", token.lexeme),
     }
 }
 
@@ -38,8 +94,18 @@ impl ParserError {
             span,
             kind,
             message,
+            expected: Vec::new(),
         }
     }
+
+    pub fn with_expected(mut self, expected: Vec<TokenKind>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    pub fn expected(&self) -> &[TokenKind] {
+        &self.expected
+    }
     pub fn span(&self) -> Span {
         self.span
     }
@@ -1,26 +1,58 @@
 mod ast;
 mod error;
+mod trivia;
+pub mod visit;
 
-pub use ast::{Expr, ExprKind, Stmt, StmtKind};
+pub use error::{ParserError, ParserErrorKind};
+pub use trivia::{Comment, Comments, Trivia};
+pub use visit::{Visitor, VisitorMut};
 
-use crate::runtime::environment::value::Number;
-use crate::{
-    lexer::{Token, TokenKind},
-    parser::error::ParserError,
+pub use ast::{
+    BindingPattern, Expr, ExprKind, InterpolationPart, MatchArm, ObjectKey, Pattern, Stmt,
+    StmtKind, format_params, to_source,
 };
 
+use crate::lexer::{Span, TemplatePart, Token, TokenKind};
+use crate::messages::Message;
+use crate::runtime::environment::value::Number;
+use crate::runtime::stack;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     errors: Vec<ParserError>,
+    /// Doc comments keyed by the span of the `fn` keyword they precede.
+    docs: Vec<(Span, String)>,
+    /// Every other comment in source order, and how many have been attached.
+    skipped: Vec<Comment>,
+    next_comment: usize,
+    comments: Comments,
+    /// Set once the input nests too deeply to parse; the rest is skipped.
+    nested_too_deeply: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(mut tokens: Vec<Token>) -> Self {
+        // Lookahead relies on a trailing `Eof`, so supply one if the caller didn't.
+        if !matches!(tokens.last(), Some(token) if token.kind == TokenKind::Eof) {
+            let span = tokens
+                .last()
+                .map_or(Span { line: 1, column: 1 }, |token| token.span);
+            tokens.push(Token {
+                kind: TokenKind::Eof,
+                span,
+                lexeme: String::new(),
+            });
+        }
         Self {
             tokens,
             current: 0,
             errors: Vec::new(),
+            docs: Vec::new(),
+            skipped: Vec::new(),
+            next_comment: 0,
+            comments: Comments::default(),
+            nested_too_deeply: false,
         }
     }
 
@@ -28,32 +60,169 @@ impl Parser {
         &self.errors
     }
 
+    /// The comments found by the last call to `parse`.
+    pub fn comments(&self) -> &Comments {
+        &self.comments
+    }
+
     fn report_error(&mut self, error: ParserError) {
-        self.errors.push(error);
+        // Every construct left open around a too deeply nested expression
+        // would only report its missing closer.
+        if !self.nested_too_deeply {
+            self.errors.push(error);
+        }
+    }
+
+    /// Gives up on input nested deeper than the Rust stack can follow:
+    /// reports it once and skips to the end.
+    fn too_deeply_nested(&mut self) -> Expr {
+        let span = self.peek().span;
+        self.report_error(ParserError::new(
+            span,
+            error::ParserErrorKind::TooDeeplyNested,
+            String::new(),
+        ));
+        self.nested_too_deeply = true;
+        self.current = self.tokens.len() - 1;
+        Expr {
+            kind: ExprKind::Number(Number::Float(0.0)),
+            span,
+        }
     }
 
     fn peek_next(&self) -> Option<&Token> {
         self.tokens.get(self.current + 1)
     }
 
+    /// Whether the `[` at the current token closes and is followed by `:`,
+    /// i.e. it starts a computed object key rather than an array literal.
+    fn is_computed_key(&self) -> bool {
+        let mut depth = 0;
+        for (i, token) in self.tokens[self.current..].iter().enumerate() {
+            match token.kind {
+                TokenKind::LeftBracket => depth += 1,
+                TokenKind::RightBracket => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(
+                            self.tokens.get(self.current + i + 1),
+                            Some(Token {
+                                kind: TokenKind::Colon,
+                                ..
+                            })
+                        );
+                    }
+                }
+                TokenKind::Eof => break,
+                _ => {}
+            }
+        }
+        false
+    }
+
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements = Vec::new();
-        self.tokens = self
-            .tokens
-            .iter()
-            .filter(|token| !matches!(token.kind, TokenKind::Comment(_)))
-            .cloned()
-            .collect();
+        self.strip_comments();
         let mut previous_current = self.current;
         while !self.is_at_end() {
-            statements.push(self.statement());
+            let (stmt, trivia) = self.statement_with_trivia();
+            self.comments
+                .insert_statement(None, statements.len(), trivia);
+            statements.push(stmt);
             if self.current == previous_current {
                 self.advance();
             }
             previous_current = self.current;
         }
+        let rest = self.skipped[self.next_comment..].to_vec();
+        self.next_comment = self.skipped.len();
+        self.comments.insert_dangling(None, rest);
         statements
     }
+
+    /// Drops comment tokens, remembering each run of doc comments that leads
+    /// up to a `fn` (possibly behind `export`) so the declaration can keep it.
+    /// Everything else is set aside in `skipped`.
+    fn strip_comments(&mut self) {
+        let mut pending: Vec<Token> = Vec::new();
+        let mut tokens = Vec::with_capacity(self.tokens.len());
+        for token in std::mem::take(&mut self.tokens) {
+            match &token.kind {
+                TokenKind::Comment(_) => {
+                    self.skipped.push(Comment {
+                        text: token.lexeme,
+                        span: token.span,
+                    });
+                    continue;
+                }
+                TokenKind::DocComment(_) => {
+                    pending.push(token);
+                    continue;
+                }
+                TokenKind::Newline | TokenKind::Export => {}
+                TokenKind::Fn if !pending.is_empty() => {
+                    let lines: Vec<&str> = pending
+                        .iter()
+                        .filter_map(|token| match &token.kind {
+                            TokenKind::DocComment(text) => {
+                                Some(text.strip_prefix(' ').unwrap_or(text).trim_end())
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    self.docs.push((token.span, lines.join("\n")));
+                    pending.clear();
+                }
+                _ => self.skipped.extend(pending.drain(..).map(|token| Comment {
+                    text: token.lexeme,
+                    span: token.span,
+                })),
+            }
+            tokens.push(token);
+        }
+        self.skipped
+            .sort_by_key(|comment| (comment.span.line, comment.span.column));
+        self.tokens = tokens;
+    }
+
+    /// Parses a statement along with the comments before it and the one
+    /// sharing its last line.
+    fn statement_with_trivia(&mut self) -> (Stmt, Trivia) {
+        self.delete_empty_statements();
+        let leading = self.comments_before(self.peek().span);
+        let stmt = self.statement();
+        // The statement has swallowed the line breaks after it, so find its last real token.
+        let end_line = self.tokens[..self.current]
+            .iter()
+            .rev()
+            .find(|token| !matches!(token.kind, TokenKind::Newline | TokenKind::Semicolon))
+            .map_or(0, |token| token.span.line);
+        let next = self.peek().span;
+        let trailing = self
+            .skipped
+            .get(self.next_comment)
+            .filter(|comment| {
+                comment.span.line == end_line
+                    && (comment.span.line, comment.span.column) < (next.line, next.column)
+            })
+            .cloned();
+        if trailing.is_some() {
+            self.next_comment += 1;
+        }
+        (stmt, Trivia { leading, trailing })
+    }
+
+    /// Takes the not yet attached comments that start before `span`.
+    fn comments_before(&mut self, span: Span) -> Vec<Comment> {
+        let start = self.next_comment;
+        while self.skipped.get(self.next_comment).is_some_and(|comment| {
+            (comment.span.line, comment.span.column) < (span.line, span.column)
+        }) {
+            self.next_comment += 1;
+        }
+        self.skipped[start..self.next_comment].to_vec()
+    }
+
     fn delete_empty_lines(&mut self) {
         self.delete_continuous_tokens(&TokenKind::Newline);
     }
@@ -64,22 +233,48 @@ impl Parser {
     fn delete_continuous_tokens(&mut self, kind: &TokenKind) {
         while self.match_token(kind) {}
     }
+    fn check_after_newlines(&self, kind: &TokenKind) -> bool {
+        self.tokens[self.current..]
+            .iter()
+            .find(|token| token.kind != TokenKind::Newline)
+            .is_some_and(|token| &token.kind == kind)
+    }
     fn statement(&mut self) -> Stmt {
         self.delete_empty_statements();
         let stmt = if self.match_token(&TokenKind::Let) {
             self.let_statement()
-        } else if self.match_token(&TokenKind::Fn) {
+        } else if self.match_token(&TokenKind::Const) {
+            self.const_statement()
+        } else if self.check(&TokenKind::Fn)
+            && !matches!(
+                self.peek_next(),
+                Some(Token {
+                    kind: TokenKind::LeftParen,
+                    ..
+                })
+            )
+        {
+            self.advance();
             self.function_statement()
         } else if self.match_token(&TokenKind::Struct) {
             self.struct_statement()
+        } else if self.match_token(&TokenKind::Import) {
+            self.import_statement()
+        } else if self.match_token(&TokenKind::Export) {
+            self.export_statement()
         } else if self.match_token(&TokenKind::Continue) {
             Stmt {
                 kind: StmtKind::Continue,
                 span: self.previous().span,
             }
         } else if self.match_token(&TokenKind::Break) {
+            let value = if self.at_statement_end() {
+                None
+            } else {
+                Some(self.expression())
+            };
             Stmt {
-                kind: StmtKind::Break,
+                kind: StmtKind::Break(value),
                 span: self.previous().span,
             }
         } else if self.match_token(&TokenKind::Return) {
@@ -93,7 +288,10 @@ impl Parser {
                 span: self.previous().span,
             }
         } else {
-            let expr = self.expression();
+            let mut expr = self.expression();
+            if self.check(&TokenKind::Comma) {
+                expr = self.multiple_assignment(expr);
+            }
             if self.check(&TokenKind::Semicolon)
                 || (self.check(&TokenKind::Newline)
                     && !self.is_at_block_last_not_empty_line()
@@ -111,8 +309,8 @@ impl Parser {
             } else {
                 self.report_error(ParserError::new(
                     self.peek().span,
-                    error::ParserErrorKind::UnexpectedToken(self.peek().clone()),
-                    "Unexpected token. Expected a statement.".into(),
+                    error::ParserErrorKind::UnexpectedToken(Box::new(self.peek().clone())),
+                    Message::ExpectStatement.text().into(),
                 ));
                 Stmt {
                     kind: StmtKind::Expr(expr),
@@ -128,8 +326,8 @@ impl Parser {
         {
             self.report_error(ParserError::new(
                 self.peek().span,
-                error::ParserErrorKind::UnexpectedToken(self.peek().clone()),
-                "Unexpected token. Expected ';' or newline".into(),
+                error::ParserErrorKind::UnexpectedToken(Box::new(self.peek().clone())),
+                Message::ExpectStatementEnd.text().into(),
             ));
         }
         self.delete_empty_statements();
@@ -148,174 +346,259 @@ impl Parser {
         }
     }
 
+    fn do_while_expression(&mut self) -> Expr {
+        let body = self.expression();
+        self.delete_empty_lines();
+        self.consume(&TokenKind::While, Message::ExpectWhileAfterDo);
+        let condition = self.expression();
+        Expr {
+            kind: ExprKind::DoWhile {
+                body: Box::new(body),
+                condition: Box::new(condition),
+            },
+            span: self.previous().span,
+        }
+    }
+
+    fn collect_expression(&mut self) -> Expr {
+        if !matches!(
+            self.peek().kind,
+            TokenKind::While | TokenKind::Do | TokenKind::For
+        ) {
+            let token = self.peek().clone();
+            self.report_error(
+                ParserError::new(
+                    token.span,
+                    error::ParserErrorKind::UnexpectedToken(Box::new(token)),
+                    Message::ExpectLoopAfterCollect.text().into(),
+                )
+                .with_expected(vec![
+                    TokenKind::For,
+                    TokenKind::While,
+                    TokenKind::Do,
+                ]),
+            );
+        }
+        let body = self.expression();
+        Expr {
+            kind: ExprKind::Collect(Box::new(body)),
+            span: self.previous().span,
+        }
+    }
+
+    fn const_statement(&mut self) -> Stmt {
+        let name = self.consume_identifier();
+        let name_span = self.previous().span;
+        self.consume(&TokenKind::Assign, Message::ExpectAssignAfterConst);
+        let value = Some(self.expression());
+        Stmt {
+            kind: StmtKind::Let {
+                name,
+                name_span,
+                value,
+                constant: true,
+            },
+            span: self.previous().span,
+        }
+    }
+
     fn let_statement(&mut self) -> Stmt {
+        let pattern = if self.match_token(&TokenKind::LeftBracket) {
+            Some(BindingPattern::Array(self.pattern_names(
+                &TokenKind::RightBracket,
+                Message::ExpectBracketAfterPattern,
+            )))
+        } else if self.match_token(&TokenKind::LeftBrace) {
+            Some(BindingPattern::Object(self.pattern_names(
+                &TokenKind::RightBrace,
+                Message::ExpectBraceAfterPattern,
+            )))
+        } else {
+            None
+        };
+        if let Some(pattern) = pattern {
+            self.consume(&TokenKind::Assign, Message::ExpectAssignAfterPattern);
+            let value = self.expression();
+            return Stmt {
+                kind: StmtKind::LetPattern { pattern, value },
+                span: self.previous().span,
+            };
+        }
+
         let name = self.consume_identifier();
         let name_span = self.previous().span;
-        self.consume(&TokenKind::Assign, "Expect '=' after variable name");
-        let value = self.expression();
+        if self.match_token(&TokenKind::Comma) {
+            let mut names = vec![(name, name_span)];
+            loop {
+                let span = self.peek().span;
+                names.push((self.consume_identifier(), span));
+                if !self.match_token(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.consume(&TokenKind::Assign, Message::ExpectAssignAfterNames);
+            let value = self.expression_list();
+            return Stmt {
+                kind: StmtKind::LetPattern {
+                    pattern: BindingPattern::Array(names),
+                    value,
+                },
+                span: self.previous().span,
+            };
+        }
+        let value = if !self.check(&TokenKind::Assign) && self.at_statement_end() {
+            None
+        } else {
+            self.consume(&TokenKind::Assign, Message::ExpectAssignAfterName);
+            Some(self.expression())
+        };
         Stmt {
             kind: StmtKind::Let {
                 name,
                 name_span,
                 value,
+                constant: false,
             },
             span: self.previous().span,
         }
     }
 
     fn expression(&mut self) -> Expr {
+        let _entered = stack::enter();
+        if !stack::has_room_for_expression() {
+            return self.too_deeply_nested();
+        }
         if self.match_token(&TokenKind::If) {
             self.if_expression()
         } else if self.match_token(&TokenKind::While) {
             self.while_expression()
+        } else if self.match_token(&TokenKind::Do) {
+            self.do_while_expression()
+        } else if self.match_token(&TokenKind::Loop) {
+            let body = self.expression();
+            Expr {
+                kind: ExprKind::Loop(Box::new(body)),
+                span: self.previous().span,
+            }
+        } else if self.match_token(&TokenKind::For) {
+            self.for_expression()
+        } else if self.match_token(&TokenKind::Collect) {
+            self.collect_expression()
+        } else if self.match_token(&TokenKind::Match) {
+            self.match_expression()
         } else {
             self.assignment()
         }
     }
 
     fn assignment(&mut self) -> Expr {
-        let expr = self.equality();
+        let expr = self.binary(0);
 
         if self.match_token(&TokenKind::Assign) {
             let value = self.assignment();
-            match expr.kind.clone() {
-                ExprKind::Variable(_) | ExprKind::Index { .. } => {
-                    return Expr {
-                        kind: ExprKind::BinaryOp {
-                            left: Box::new(expr),
-                            op: TokenKind::Assign,
-                            right: Box::new(value),
-                        },
-                        span: self.previous().span,
-                    };
-                }
-                _ => {
-                    self.report_error(ParserError::new(
-                        self.previous().span,
-                        error::ParserErrorKind::UnexpectedToken(self.previous().clone()),
-                        "Invalid assignment target: expected a variable name".into(),
-                    ));
-                }
+            if is_assignment_target(&expr) {
+                return Expr {
+                    kind: ExprKind::Assign {
+                        target: Box::new(expr),
+                        value: Box::new(value),
+                    },
+                    span: self.previous().span,
+                };
             }
+            self.report_error(ParserError::new(
+                self.previous().span,
+                error::ParserErrorKind::UnexpectedToken(Box::new(self.previous().clone())),
+                Message::InvalidAssignmentTarget.text().into(),
+            ));
         }
 
         expr
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
-
-        while self.match_token(&TokenKind::Equal) || self.match_token(&TokenKind::NotEqual) {
-            let op = self.previous().to_owned().kind;
-            let right = self.comparison();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
-                    left: Box::new(expr),
-                    op,
-                    right: Box::new(right),
-                },
-                span: self.previous().span,
-            };
+    /// Parses `a, b = x, y` after the first target; the targets become an array pattern.
+    fn multiple_assignment(&mut self, first: Expr) -> Expr {
+        let mut targets = vec![first];
+        while self.match_token(&TokenKind::Comma) {
+            targets.push(self.binary(0));
         }
-
-        expr
-    }
-
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.logical_or();
-
-        while self.match_token(&TokenKind::GreaterThan)
-            || self.match_token(&TokenKind::GreaterThanOrEqual)
-            || self.match_token(&TokenKind::LessThan)
-            || self.match_token(&TokenKind::LessThanOrEqual)
-        {
-            let op = self.previous().to_owned().kind;
-            let right = self.logical_or();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
-                    left: Box::new(expr),
-                    op,
-                    right: Box::new(right),
-                },
-                span: self.previous().span,
-            };
+        self.consume(&TokenKind::Assign, Message::ExpectAssignAfterTargets);
+        let target = Expr {
+            span: targets[0].span,
+            kind: ExprKind::Array(targets),
+        };
+        if !is_assignment_target(&target) {
+            self.report_error(ParserError::new(
+                self.previous().span,
+                error::ParserErrorKind::UnexpectedToken(Box::new(self.previous().clone())),
+                Message::InvalidAssignmentTarget.text().into(),
+            ));
         }
-
-        expr
-    }
-
-    fn logical_or(&mut self) -> Expr {
-        let mut expr = self.logical_and();
-
-        while self.match_token(&TokenKind::LogicalOr) {
-            let op = self.previous().to_owned().kind;
-            let right = self.logical_and();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
-                    left: Box::new(expr),
-                    op,
-                    right: Box::new(right),
-                },
-                span: self.previous().span,
-            };
+        let value = self.expression_list();
+        Expr {
+            kind: ExprKind::Assign {
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+            span: self.previous().span,
         }
-
-        expr
     }
 
-    fn logical_and(&mut self) -> Expr {
-        let mut expr = self.term();
-
-        while self.match_token(&TokenKind::LogicalAnd) {
-            let op = self.previous().to_owned().kind;
-            let right = self.term();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
-                    left: Box::new(expr),
-                    op,
-                    right: Box::new(right),
-                },
-                span: self.previous().span,
-            };
+    /// Parses `x` or `x, y, ...`; more than one expression becomes an array literal.
+    fn expression_list(&mut self) -> Expr {
+        let first = self.expression();
+        if !self.check(&TokenKind::Comma) {
+            return first;
         }
-
-        expr
-    }
-
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-
-        while self.match_token(&TokenKind::Plus) || self.match_token(&TokenKind::Minus) {
-            let op = self.previous().to_owned().kind;
-            let right = self.factor();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
-                    left: Box::new(expr),
-                    op,
-                    right: Box::new(right),
-                },
-                span: self.previous().span,
-            };
+        let span = first.span;
+        let mut items = vec![first];
+        while self.match_token(&TokenKind::Comma) {
+            items.push(self.expression());
+        }
+        Expr {
+            kind: ExprKind::Array(items),
+            span,
         }
-
-        expr
     }
 
-    fn factor(&mut self) -> Expr {
+    /// Parses a chain of binary operators that bind at least as tightly as
+    /// `min_power`, using the powers from `binding_power`.
+    fn binary(&mut self, min_power: u8) -> Expr {
         let mut expr = self.unary();
 
-        while self.match_token(&TokenKind::Multiply)
-            || self.match_token(&TokenKind::Divide)
-            || self.match_token(&TokenKind::Modulo)
-        {
-            let op = self.previous().to_owned().kind;
-            let right = self.unary();
-            expr = Expr {
-                kind: ExprKind::BinaryOp {
+        while let Some(power) = binding_power(&self.peek().kind) {
+            // `start..]` is an open-ended slice, handled by `postfix_expression`.
+            let open_slice = self.check(&TokenKind::DotDot)
+                && matches!(
+                    self.peek_next(),
+                    Some(Token {
+                        kind: TokenKind::RightBracket,
+                        ..
+                    })
+                );
+            if power < min_power || open_slice {
+                break;
+            }
+
+            let op = self.advance().kind.clone();
+            let kind = match op {
+                TokenKind::Is => ExprKind::TypeTest {
+                    expr: Box::new(expr),
+                    type_name: self.consume_identifier(),
+                },
+                TokenKind::As => ExprKind::Cast {
+                    expr: Box::new(expr),
+                    type_name: self.consume_identifier(),
+                },
+                // Operators are left-associative, so the right operand only
+                // takes operators that bind tighter.
+                op => ExprKind::BinaryOp {
                     left: Box::new(expr),
                     op,
-                    right: Box::new(right),
+                    right: Box::new(self.binary(power + 1)),
                 },
+            };
+            expr = Expr {
+                kind,
                 span: self.previous().span,
             };
         }
@@ -324,8 +607,14 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Expr {
-        if self.match_token(&TokenKind::Minus) || self.match_token(&TokenKind::Not) {
+        if self.match_token(&TokenKind::Minus)
+            || self.match_token(&TokenKind::Not)
+            || self.match_token(&TokenKind::BitNot)
+        {
             let op = self.previous().to_owned().kind;
+            if !stack::has_room_for_expression() {
+                return self.too_deeply_nested();
+            }
             let expr = self.unary();
             return Expr {
                 kind: ExprKind::UnaryOp {
@@ -343,7 +632,7 @@ impl Parser {
             self.report_error(ParserError::new(
                 self.previous().span,
                 error::ParserErrorKind::UnexpectedEOF,
-                "Unexpected end of file. Expected expression.".into(),
+                Message::ExpectExpressionAtEof.text().into(),
             ));
             return Expr {
                 kind: ExprKind::Number(Number::Float(0.0)),
@@ -375,35 +664,59 @@ impl Parser {
                     span: self.previous().span,
                 }
             }
+            TokenKind::Template(parts) => {
+                let parts = parts.clone();
+                self.advance();
+                let span = self.previous().span;
+                let parts = parts
+                    .into_iter()
+                    .map(|part| match part {
+                        TemplatePart::Literal(s) => InterpolationPart::Literal(s),
+                        TemplatePart::Code(tokens) => {
+                            InterpolationPart::Expr(self.interpolated_expression(tokens))
+                        }
+                    })
+                    .collect();
+                Expr {
+                    kind: ExprKind::Interpolation(parts),
+                    span,
+                }
+            }
             TokenKind::Identifier(name) => {
                 let name = name.clone();
                 self.advance();
 
                 if self.match_token(&TokenKind::LeftParen) {
-                    let mut args = Vec::new();
-                    if !self.match_token(&TokenKind::RightParen) {
-                        loop {
-                            args.push(self.expression());
-                            if !self.match_token(&TokenKind::Comma) {
-                                break;
-                            }
-                        }
-                        self.consume(&TokenKind::RightParen, "Expect ')' after arguments");
-                    }
-                    return Expr {
+                    let args = self.arguments();
+                    Expr {
                         kind: ExprKind::FunctionCall { name, args },
                         span: self.previous().span,
-                    };
+                    }
+                } else {
+                    Expr {
+                        kind: ExprKind::Variable(name),
+                        span: self.previous().span,
+                    }
                 }
+            }
+            TokenKind::Fn => {
+                self.advance();
+                self.consume(&TokenKind::LeftParen, Message::ExpectParenAfterFn);
+                let (params, rest) = self.parameters();
+                let body = self.expression();
                 Expr {
-                    kind: ExprKind::Variable(name),
+                    kind: ExprKind::Lambda {
+                        params,
+                        rest,
+                        body: Box::new(body),
+                    },
                     span: self.previous().span,
                 }
             }
             TokenKind::LeftParen => {
                 self.advance();
                 let expr = self.expression();
-                self.consume(&TokenKind::RightParen, "Expect ')' after expression");
+                self.consume(&TokenKind::RightParen, Message::ExpectParenAfterExpression);
                 Expr {
                     kind: ExprKind::Parenthesized(Box::new(expr)),
                     span: self.previous().span,
@@ -412,16 +725,16 @@ impl Parser {
             TokenKind::LeftBrace => {
                 self.advance();
                 self.delete_empty_lines();
-                let is_object = if let TokenKind::String(_) = &self.peek().kind {
-                    matches!(
+                let is_object = match &self.peek().kind {
+                    TokenKind::String(_) | TokenKind::Identifier(_) => matches!(
                         self.peek_next(),
                         Some(Token {
                             kind: TokenKind::Colon,
                             ..
                         })
-                    )
-                } else {
-                    false
+                    ),
+                    TokenKind::LeftBracket => self.is_computed_key(),
+                    _ => false,
                 };
 
                 if is_object {
@@ -429,18 +742,34 @@ impl Parser {
                     let mut previous_current = self.current;
                     while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
                         self.delete_empty_lines();
-                        let key = if let TokenKind::String(name) = &self.peek().kind {
-                            name.clone()
-                        } else {
-                            self.report_error(ParserError::new(
-                                self.peek().span,
-                                error::ParserErrorKind::UnexpectedToken(self.peek().clone()),
-                                "Expect property name".into(),
-                            ));
-                            "".to_string()
+                        let key = match &self.peek().kind {
+                            TokenKind::String(name) | TokenKind::Identifier(name) => {
+                                let name = name.clone();
+                                self.advance();
+                                ObjectKey::Name(name)
+                            }
+                            TokenKind::LeftBracket => {
+                                self.advance();
+                                let key = self.expression();
+                                self.consume(
+                                    &TokenKind::RightBracket,
+                                    Message::ExpectBracketAfterComputedKey,
+                                );
+                                ObjectKey::Computed(key)
+                            }
+                            _ => {
+                                self.report_error(ParserError::new(
+                                    self.peek().span,
+                                    error::ParserErrorKind::UnexpectedToken(Box::new(
+                                        self.peek().clone(),
+                                    )),
+                                    Message::ExpectPropertyName.text().into(),
+                                ));
+                                self.advance();
+                                ObjectKey::Name(String::new())
+                            }
                         };
-                        self.advance();
-                        self.consume(&TokenKind::Colon, "Expect ':' after property name");
+                        self.consume(&TokenKind::Colon, Message::ExpectColonAfterProperty);
                         let value = self.expression();
                         properties.push((key, value));
 
@@ -454,7 +783,7 @@ impl Parser {
                         self.delete_empty_lines();
                     }
                     self.delete_empty_lines();
-                    self.consume(&TokenKind::RightBrace, "Expect '}' after object properties");
+                    self.consume(&TokenKind::RightBrace, Message::ExpectBraceAfterProperties);
                     return Expr {
                         kind: ExprKind::Object(properties),
                         span: self.previous().span,
@@ -462,18 +791,29 @@ impl Parser {
                 }
 
                 let mut statements = Vec::new();
+                let mut trivia = Vec::new();
                 let mut previous_current = self.current;
                 while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-                    statements.push(self.statement().kind);
+                    let (stmt, stmt_trivia) = self.statement_with_trivia();
+                    statements.push(stmt.kind);
+                    trivia.push(stmt_trivia);
                     if self.current == previous_current {
                         self.advance();
                     }
                     previous_current = self.current;
                 }
-                self.consume(&TokenKind::RightBrace, "Expect '}' after block");
+                let dangling = self.comments_before(self.peek().span);
+                self.consume(&TokenKind::RightBrace, Message::ExpectBraceAfterBlock);
+                // Comments are keyed by the block's span, which is its `}`.
+                let span = self.previous().span;
+                for (index, stmt_trivia) in trivia.into_iter().enumerate() {
+                    self.comments
+                        .insert_statement(Some(span), index, stmt_trivia);
+                }
+                self.comments.insert_dangling(Some(span), dangling);
                 Expr {
                     kind: ExprKind::Block(statements),
-                    span: self.previous().span,
+                    span,
                 }
             }
             TokenKind::LeftBracket => {
@@ -482,7 +822,7 @@ impl Parser {
                 let mut elements = Vec::new();
                 let mut previous_current = self.current;
                 while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
-                    elements.push(self.expression());
+                    elements.push(self.element());
                     self.delete_empty_lines();
                     if self.current == previous_current {
                         self.advance();
@@ -493,7 +833,10 @@ impl Parser {
                     }
                     self.delete_empty_lines();
                 }
-                self.consume(&TokenKind::RightBracket, "Expect ']' after array elements");
+                self.consume(
+                    &TokenKind::RightBracket,
+                    Message::ExpectBracketAfterElements,
+                );
                 Expr {
                     kind: ExprKind::Array(elements),
                     span: self.previous().span,
@@ -505,8 +848,8 @@ impl Parser {
                 let span = token.span;
                 self.report_error(ParserError::new(
                     span,
-                    error::ParserErrorKind::UnexpectedToken(token.clone()),
-                    "in primary parser".into(),
+                    error::ParserErrorKind::UnexpectedToken(Box::new(token.clone())),
+                    Message::ExpectExpression.format(&[("token", &error::describe_token(token))]),
                 ));
                 Expr {
                     kind: ExprKind::Number(Number::Float(0.0)),
@@ -518,11 +861,54 @@ impl Parser {
         self.postfix_expression(expr)
     }
 
+    fn interpolated_expression(&mut self, tokens: Vec<Token>) -> Expr {
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Comment(_) | TokenKind::Newline))
+            .collect();
+        let mut parser = Parser::new(tokens);
+        let expr = parser.expression();
+        if !parser.is_at_end() {
+            parser.report_error(ParserError::new(
+                parser.peek().span,
+                error::ParserErrorKind::UnexpectedToken(Box::new(parser.peek().clone())),
+                Message::ExpectBraceAfterInterpolation.text().into(),
+            ));
+        }
+        self.errors.extend(parser.errors);
+        expr
+    }
+
     fn postfix_expression(&mut self, mut expr: Expr) -> Expr {
         loop {
             if self.match_token(&TokenKind::LeftBracket) {
+                if self.match_token(&TokenKind::DotDot) {
+                    expr = self.slice_expression(expr, None);
+                    continue;
+                }
                 let index = self.expression();
-                self.consume(&TokenKind::RightBracket, "Expect ']' after index");
+                if self.match_token(&TokenKind::DotDot) {
+                    expr = self.slice_expression(expr, Some(index));
+                    continue;
+                }
+                if let ExprKind::BinaryOp {
+                    left,
+                    op: TokenKind::DotDot,
+                    right,
+                } = index.kind
+                {
+                    self.consume(&TokenKind::RightBracket, Message::ExpectBracketAfterSlice);
+                    expr = Expr {
+                        kind: ExprKind::Slice {
+                            object: Box::new(expr),
+                            start: Some(left),
+                            end: Some(right),
+                        },
+                        span: self.previous().span,
+                    };
+                    continue;
+                }
+                self.consume(&TokenKind::RightBracket, Message::ExpectBracketAfterIndex);
                 expr = Expr {
                     kind: ExprKind::Index {
                         object: Box::new(expr),
@@ -530,6 +916,27 @@ impl Parser {
                     },
                     span: self.previous().span,
                 };
+            } else if self.match_token(&TokenKind::Dot) {
+                let name = self.consume_identifier();
+                if self.match_token(&TokenKind::LeftParen) {
+                    let args = self.arguments();
+                    expr = Expr {
+                        kind: ExprKind::MethodCall {
+                            object: Box::new(expr),
+                            method: name,
+                            args,
+                        },
+                        span: self.previous().span,
+                    };
+                } else {
+                    expr = Expr {
+                        kind: ExprKind::GetProperty {
+                            object: Box::new(expr),
+                            property: name,
+                        },
+                        span: self.previous().span,
+                    };
+                }
             } else if self.match_token(&TokenKind::Colon) {
                 if let TokenKind::Identifier(property) = &self.peek().kind {
                     let prop_name = property.clone();
@@ -544,8 +951,8 @@ impl Parser {
                 } else {
                     self.report_error(ParserError::new(
                         self.peek().span,
-                        error::ParserErrorKind::UnexpectedToken(self.peek().clone()),
-                        "Expect property name after ':'".into(),
+                        error::ParserErrorKind::UnexpectedToken(Box::new(self.peek().clone())),
+                        Message::ExpectPropertyAfterColon.text().into(),
                     ));
                 }
             } else {
@@ -555,6 +962,52 @@ impl Parser {
         expr
     }
 
+    fn arguments(&mut self) -> Vec<Expr> {
+        let mut args = Vec::new();
+        if !self.match_token(&TokenKind::RightParen) {
+            loop {
+                args.push(self.element());
+                // A trailing comma before `)` is allowed.
+                if !self.match_token(&TokenKind::Comma) || self.check(&TokenKind::RightParen) {
+                    break;
+                }
+            }
+            self.consume(&TokenKind::RightParen, Message::ExpectParenAfterArguments);
+        }
+        args
+    }
+
+    /// A call argument or array element, which may be spread with `...`.
+    fn element(&mut self) -> Expr {
+        if self.match_token(&TokenKind::Ellipsis) {
+            let span = self.previous().span;
+            let value = self.expression();
+            Expr {
+                kind: ExprKind::Spread(Box::new(value)),
+                span,
+            }
+        } else {
+            self.expression()
+        }
+    }
+
+    fn slice_expression(&mut self, object: Expr, start: Option<Expr>) -> Expr {
+        let end = if self.check(&TokenKind::RightBracket) {
+            None
+        } else {
+            Some(Box::new(self.expression()))
+        };
+        self.consume(&TokenKind::RightBracket, Message::ExpectBracketAfterSlice);
+        Expr {
+            kind: ExprKind::Slice {
+                object: Box::new(object),
+                start: start.map(Box::new),
+                end,
+            },
+            span: self.previous().span,
+        }
+    }
+
     fn match_token(&mut self, kind: &TokenKind) -> bool {
         if self.check(kind) {
             self.advance();
@@ -576,16 +1029,19 @@ impl Parser {
         }
     }
 
-    fn consume(&mut self, kind: &TokenKind, message: &'static str) {
+    fn consume(&mut self, kind: &TokenKind, message: Message) {
         if self.check(kind) {
             self.advance();
         } else {
             let token = self.peek();
-            self.report_error(ParserError::new(
-                token.span,
-                error::ParserErrorKind::UnexpectedToken(token.clone()),
-                message.into(),
-            ));
+            self.report_error(
+                ParserError::new(
+                    token.span,
+                    error::ParserErrorKind::UnexpectedToken(Box::new(token.clone())),
+                    message.text().into(),
+                )
+                .with_expected(vec![kind.clone()]),
+            );
         }
     }
 
@@ -600,6 +1056,14 @@ impl Parser {
         self.tokens[self.current].kind == TokenKind::Eof
     }
 
+    /// True when the next token ends the current statement.
+    fn at_statement_end(&self) -> bool {
+        self.check(&TokenKind::Semicolon)
+            || self.check(&TokenKind::Newline)
+            || self.check(&TokenKind::RightBrace)
+            || self.is_at_end()
+    }
+
     fn is_at_last_not_empty_line(&mut self) -> bool {
         self.delete_empty_lines();
         self.is_at_end()
@@ -609,16 +1073,110 @@ impl Parser {
         self.check(&TokenKind::RightBrace)
     }
 
+    /// The last consumed token, or the first one if nothing was consumed yet.
     fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+        &self.tokens[self.current.saturating_sub(1)]
+    }
+
+    fn for_expression(&mut self) -> Expr {
+        let variable = self.consume_identifier();
+        self.consume(&TokenKind::In, Message::ExpectInAfterLoopVariable);
+        let iterable = self.expression();
+        let body = self.expression();
+        Expr {
+            kind: ExprKind::For {
+                variable,
+                iterable: Box::new(iterable),
+                body: Box::new(body),
+            },
+            span: self.previous().span,
+        }
+    }
+
+    fn match_expression(&mut self) -> Expr {
+        let subject = Box::new(self.expression());
+        self.consume(&TokenKind::LeftBrace, Message::ExpectBraceAfterMatchSubject);
+
+        let mut arms = Vec::new();
+        let mut previous_current = self.current;
+        loop {
+            self.delete_empty_lines();
+            if self.check(&TokenKind::RightBrace) || self.is_at_end() {
+                break;
+            }
+            let pattern = self.pattern();
+            self.consume(&TokenKind::FatArrow, Message::ExpectArrowAfterPattern);
+            let body = self.expression();
+            arms.push(MatchArm { pattern, body });
+
+            if self.current == previous_current {
+                self.advance();
+            }
+            previous_current = self.current;
+            self.delete_empty_lines();
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.delete_empty_lines();
+        self.consume(&TokenKind::RightBrace, Message::ExpectBraceAfterArms);
+
+        Expr {
+            kind: ExprKind::Match { subject, arms },
+            span: self.previous().span,
+        }
+    }
+
+    fn pattern(&mut self) -> Pattern {
+        let token = self.peek().clone();
+        match &token.kind {
+            TokenKind::Identifier(name) => {
+                self.advance();
+                if name == "_" {
+                    Pattern::Wildcard
+                } else {
+                    Pattern::Binding(name.clone())
+                }
+            }
+            TokenKind::Number(_) | TokenKind::String(_) | TokenKind::Boolean(_) => {
+                Pattern::Literal(self.primary())
+            }
+            TokenKind::Minus
+                if matches!(
+                    self.peek_next(),
+                    Some(Token {
+                        kind: TokenKind::Number(_),
+                        ..
+                    })
+                ) =>
+            {
+                Pattern::Literal(self.unary())
+            }
+            _ => {
+                self.report_error(ParserError::new(
+                    token.span,
+                    error::ParserErrorKind::UnexpectedToken(Box::new(token.clone())),
+                    Message::ExpectPattern.text().into(),
+                ));
+                self.advance();
+                Pattern::Wildcard
+            }
+        }
     }
 
     fn if_expression(&mut self) -> Expr {
         let condition = Box::new(self.expression());
         let then_branch = Box::new(self.expression());
 
+        if self.check_after_newlines(&TokenKind::Else) {
+            self.delete_empty_lines();
+        }
         let else_branch = if self.match_token(&TokenKind::Else) {
-            Some(Box::new(self.expression()))
+            if self.match_token(&TokenKind::If) {
+                Some(Box::new(self.if_expression()))
+            } else {
+                Some(Box::new(self.expression()))
+            }
         } else {
             None
         };
@@ -634,31 +1192,148 @@ impl Parser {
     }
 
     fn function_statement(&mut self) -> Stmt {
+        let fn_span = self.previous().span;
+        let doc = self
+            .docs
+            .iter()
+            .find(|(span, _)| *span == fn_span)
+            .map(|(_, doc)| doc.clone());
         let name = self.consume_identifier();
-        self.consume(&TokenKind::LeftParen, "Expect '(' after function name");
+        self.consume(&TokenKind::LeftParen, Message::ExpectParenAfterFunctionName);
+        let (params, rest) = self.parameters();
+        let body = self.expression();
 
+        Stmt {
+            kind: StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                doc,
+            },
+            span: self.previous().span,
+        }
+    }
+
+    fn pattern_names(&mut self, close: &TokenKind, message: Message) -> Vec<(String, Span)> {
+        let mut names = Vec::new();
+        loop {
+            self.delete_empty_lines();
+            if self.check(close) || self.is_at_end() {
+                break;
+            }
+            let span = self.peek().span;
+            names.push((self.consume_identifier(), span));
+            self.delete_empty_lines();
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.consume(close, message);
+        names
+    }
+
+    fn parameters(&mut self) -> (Vec<String>, Option<String>) {
         let mut params = Vec::new();
+        let mut rest = None;
         if !self.match_token(&TokenKind::RightParen) {
             loop {
+                if self.match_token(&TokenKind::Ellipsis) {
+                    rest = Some(self.consume_identifier());
+                    if self.check(&TokenKind::Comma) {
+                        self.report_error(ParserError::new(
+                            self.peek().span,
+                            error::ParserErrorKind::UnexpectedToken(Box::new(self.peek().clone())),
+                            Message::RestParameterNotLast.text().into(),
+                        ));
+                        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                            self.advance();
+                        }
+                    }
+                    break;
+                }
                 params.push(self.consume_identifier());
-                if !self.match_token(&TokenKind::Comma) {
+                if !self.match_token(&TokenKind::Comma) || self.check(&TokenKind::RightParen) {
                     break;
                 }
             }
-            self.consume(&TokenKind::RightParen, "Expect ')' after parameters");
+            self.consume(&TokenKind::RightParen, Message::ExpectParenAfterParameters);
         }
+        (params, rest)
+    }
 
-        let body = self.expression();
+    fn import_statement(&mut self) -> Stmt {
+        let alias = if let TokenKind::Identifier(name) = &self.peek().kind {
+            let name = name.clone();
+            self.advance();
+            // `from` is only special here, so it stays usable as a variable name.
+            if matches!(&self.peek().kind, TokenKind::Identifier(word) if word == "from") {
+                self.advance();
+            } else {
+                let token = self.peek().clone();
+                self.report_error(ParserError::new(
+                    token.span,
+                    error::ParserErrorKind::UnexpectedToken(Box::new(token)),
+                    Message::ExpectFromAfterImport.text().into(),
+                ));
+            }
+            Some(name)
+        } else {
+            None
+        };
+
+        let path = if let TokenKind::String(path) = &self.peek().kind {
+            let path = path.clone();
+            self.advance();
+            path
+        } else {
+            let token = self.peek().clone();
+            self.report_error(ParserError::new(
+                token.span,
+                error::ParserErrorKind::UnexpectedToken(Box::new(token)),
+                Message::ExpectModulePath.text().into(),
+            ));
+            String::new()
+        };
 
         Stmt {
-            kind: StmtKind::Function { name, params, body },
+            kind: StmtKind::Import { path, alias },
             span: self.previous().span,
         }
     }
 
+    fn export_statement(&mut self) -> Stmt {
+        let span = self.previous().span;
+        let declaration = if self.match_token(&TokenKind::Let) {
+            self.let_statement()
+        } else if self.match_token(&TokenKind::Const) {
+            self.const_statement()
+        } else if self.match_token(&TokenKind::Fn) {
+            self.function_statement()
+        } else if self.match_token(&TokenKind::Struct) {
+            self.struct_statement()
+        } else {
+            let token = self.peek().clone();
+            self.report_error(ParserError::new(
+                token.span,
+                error::ParserErrorKind::UnexpectedToken(Box::new(token)),
+                Message::ExpectDeclarationAfterExport.text().into(),
+            ));
+            let expr = self.expression();
+            Stmt {
+                kind: StmtKind::Expr(expr),
+                span: self.previous().span,
+            }
+        };
+        Stmt {
+            kind: StmtKind::Export(Box::new(declaration)),
+            span,
+        }
+    }
+
     fn struct_statement(&mut self) -> Stmt {
         let name = self.consume_identifier();
-        self.consume(&TokenKind::LeftBrace, "Expect '{' after struct name");
+        self.consume(&TokenKind::LeftBrace, Message::ExpectBraceAfterStructName);
 
         let mut fields = Vec::new();
         let mut previous_current = self.current;
@@ -685,7 +1360,7 @@ impl Parser {
             self.delete_empty_lines();
         }
 
-        self.consume(&TokenKind::RightBrace, "Expect '}' after struct fields");
+        self.consume(&TokenKind::RightBrace, Message::ExpectBraceAfterFields);
 
         Stmt {
             kind: StmtKind::Struct { name, fields },
@@ -699,14 +1374,50 @@ impl Parser {
         } else {
             self.report_error(ParserError::new(
                 self.peek().span,
-                error::ParserErrorKind::UnexpectedToken(self.peek().clone()),
-                "Expect identifier".into(),
+                error::ParserErrorKind::UnexpectedToken(Box::new(self.peek().clone())),
+                Message::ExpectIdentifier.text().into(),
             ));
             "".to_owned()
         }
     }
 }
 
+/// How tightly each binary operator binds; higher binds tighter. Adding an
+/// operator takes a row here, plus a case in `Parser::binary` if it builds
+/// something other than a `BinaryOp`.
+pub(crate) fn binding_power(kind: &TokenKind) -> Option<u8> {
+    let power = match kind {
+        TokenKind::NilCoalesce => 1,
+        TokenKind::LogicalOr => 2,
+        TokenKind::LogicalAnd => 3,
+        TokenKind::Equal | TokenKind::NotEqual => 4,
+        TokenKind::GreaterThan
+        | TokenKind::GreaterThanOrEqual
+        | TokenKind::LessThan
+        | TokenKind::LessThanOrEqual
+        | TokenKind::Is => 5,
+        TokenKind::DotDot => 6,
+        TokenKind::BitOr => 7,
+        TokenKind::BitXor => 8,
+        TokenKind::BitAnd => 9,
+        TokenKind::ShiftLeft | TokenKind::ShiftRight => 10,
+        TokenKind::Plus | TokenKind::Minus => 11,
+        TokenKind::Multiply | TokenKind::Divide | TokenKind::FloorDivide | TokenKind::Modulo => 12,
+        // `x as Float / 2` converts `x` before dividing.
+        TokenKind::As => 13,
+        _ => return None,
+    };
+    Some(power)
+}
+
+fn is_assignment_target(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Variable(_) | ExprKind::Index { .. } | ExprKind::GetProperty { .. } => true,
+        ExprKind::Array(items) => !items.is_empty() && items.iter().all(is_assignment_target),
+        _ => false,
+    }
+}
+
 pub fn parse(tokens: Vec<Token>) -> Vec<Stmt> {
     let mut parser = Parser::new(tokens);
     parser.parse()
@@ -717,3 +1428,33 @@ pub fn parse_with_errors(tokens: Vec<Token>) -> (Vec<Stmt>, Vec<ParserError>) {
     let stmts = parser.parse();
     (stmts, parser.get_errors().to_vec())
 }
+
+/// Parses a single expression, e.g. for evaluating `:type expr` in a REPL.
+/// Surrounding line breaks are allowed; anything else after the expression is an error.
+pub fn parse_expression(tokens: Vec<Token>) -> Result<Expr, ParserError> {
+    let mut parser = Parser::new(tokens);
+    parser.strip_comments();
+    parser.delete_empty_lines();
+    let expr = parser.expression();
+    parser.delete_empty_statements();
+    if !parser.is_at_end() {
+        let token = parser.peek().clone();
+        parser.report_error(ParserError::new(
+            token.span,
+            error::ParserErrorKind::UnexpectedToken(Box::new(token)),
+            Message::ExpectEndOfInput.text().into(),
+        ));
+    }
+    match parser.errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(expr),
+    }
+}
+
+/// Like `parse_with_errors`, also returning the comments so they can be
+/// written back out.
+pub fn parse_with_comments(tokens: Vec<Token>) -> (Vec<Stmt>, Comments, Vec<ParserError>) {
+    let mut parser = Parser::new(tokens);
+    let stmts = parser.parse();
+    (stmts, parser.comments, parser.errors)
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::lexer::Span;
+
+/// A `//` or `/* */` comment the parser skipped over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The comment exactly as written, delimiters included.
+    pub text: String,
+    pub span: Span,
+}
+
+/// The comments that belong to one statement.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trivia {
+    /// Comments between the previous statement and this one.
+    pub leading: Vec<Comment>,
+    /// A comment after the statement on its last line.
+    pub trailing: Option<Comment>,
+}
+
+/// Comments gathered while parsing, kept beside the tree so tools such as the
+/// formatter can put them back. Statements are addressed by the span of their
+/// enclosing block expression (`None` for the top level) and their index in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Comments {
+    statements: HashMap<(Option<Span>, usize), Trivia>,
+    dangling: HashMap<Option<Span>, Vec<Comment>>,
+}
+
+impl Comments {
+    pub fn statement(&self, block: Option<Span>, index: usize) -> Option<&Trivia> {
+        self.statements.get(&(block, index))
+    }
+
+    /// Comments after the last statement of a block, or of the file.
+    pub fn dangling(&self, block: Option<Span>) -> &[Comment] {
+        self.dangling.get(&block).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty() && self.dangling.is_empty()
+    }
+
+    pub(crate) fn insert_statement(&mut self, block: Option<Span>, index: usize, trivia: Trivia) {
+        if trivia != Trivia::default() {
+            self.statements.insert((block, index), trivia);
+        }
+    }
+
+    pub(crate) fn insert_dangling(&mut self, block: Option<Span>, comments: Vec<Comment>) {
+        if !comments.is_empty() {
+            self.dangling.insert(block, comments);
+        }
+    }
+}
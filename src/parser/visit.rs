@@ -0,0 +1,214 @@
+//! Generic traversal over the AST.
+//!
+//! Implement [`Visitor`] (or [`VisitorMut`] to rewrite nodes in place) and
+//! override only the hooks you care about; the default methods call the
+//! matching `walk_*` function, which visits every child. An override that
+//! still wants to descend calls the `walk_*` function itself.
+
+use super::ast::{Expr, ExprKind, InterpolationPart, ObjectKey, Pattern, Stmt, StmtKind};
+
+pub trait Visitor<'ast> {
+    fn visit_stmt(&mut self, stmt: &'ast StmtKind) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_stmt_mut(&mut self, stmt: &mut StmtKind) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+}
+
+/// Visits every top-level statement of a program.
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmts: &'ast [Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(&stmt.kind);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmts: &mut [Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt_mut(&mut stmt.kind);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast StmtKind) {
+    match stmt {
+        StmtKind::Expr(expr)
+        | StmtKind::Result(expr)
+        | StmtKind::LetPattern { value: expr, .. }
+        | StmtKind::Function { body: expr, .. }
+        | StmtKind::Let {
+            value: Some(expr), ..
+        }
+        | StmtKind::Break(Some(expr))
+        | StmtKind::Return(Some(expr)) => visitor.visit_expr(expr),
+        StmtKind::Struct { fields, .. } => {
+            for default in fields.iter().filter_map(|(_, default)| default.as_ref()) {
+                visitor.visit_expr(default);
+            }
+        }
+        StmtKind::Export(stmt) => visitor.visit_stmt(&stmt.kind),
+        StmtKind::Let { value: None, .. }
+        | StmtKind::Import { .. }
+        | StmtKind::Break(None)
+        | StmtKind::Continue
+        | StmtKind::Return(None) => {}
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut StmtKind) {
+    match stmt {
+        StmtKind::Expr(expr)
+        | StmtKind::Result(expr)
+        | StmtKind::LetPattern { value: expr, .. }
+        | StmtKind::Function { body: expr, .. }
+        | StmtKind::Let {
+            value: Some(expr), ..
+        }
+        | StmtKind::Break(Some(expr))
+        | StmtKind::Return(Some(expr)) => visitor.visit_expr_mut(expr),
+        StmtKind::Struct { fields, .. } => {
+            for default in fields
+                .iter_mut()
+                .filter_map(|(_, default)| default.as_mut())
+            {
+                visitor.visit_expr_mut(default);
+            }
+        }
+        StmtKind::Export(stmt) => visitor.visit_stmt_mut(&mut stmt.kind),
+        StmtKind::Let { value: None, .. }
+        | StmtKind::Import { .. }
+        | StmtKind::Break(None)
+        | StmtKind::Continue
+        | StmtKind::Return(None) => {}
+    }
+}
+
+/// Visits the children of `expr`, including every statement of a block.
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expr) {
+    match &expr.kind {
+        ExprKind::Block(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        _ => {
+            for child in expr.children() {
+                visitor.visit_expr(child);
+            }
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Number(_)
+        | ExprKind::Boolean(_)
+        | ExprKind::String(_)
+        | ExprKind::Variable(_) => {}
+        ExprKind::Interpolation(parts) => {
+            for part in parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    visitor.visit_expr_mut(expr);
+                }
+            }
+        }
+        ExprKind::Array(items)
+        | ExprKind::FunctionCall { args: items, .. }
+        | ExprKind::StructInstance { args: items, .. } => {
+            for item in items {
+                visitor.visit_expr_mut(item);
+            }
+        }
+        ExprKind::Object(fields) => {
+            for (key, value) in fields {
+                if let ObjectKey::Computed(key) = key {
+                    visitor.visit_expr_mut(key);
+                }
+                visitor.visit_expr_mut(value);
+            }
+        }
+        ExprKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expr_mut(else_branch);
+            }
+        }
+        ExprKind::Block(stmts) => {
+            for stmt in stmts {
+                visitor.visit_stmt_mut(stmt);
+            }
+        }
+        ExprKind::BinaryOp { left, right, .. } => {
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        ExprKind::Assign { target, value } => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        ExprKind::MethodCall { object, args, .. } => {
+            visitor.visit_expr_mut(object);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::Parenthesized(inner)
+        | ExprKind::UnaryOp { expr: inner, .. }
+        | ExprKind::Lambda { body: inner, .. }
+        | ExprKind::Loop(inner)
+        | ExprKind::Collect(inner)
+        | ExprKind::Spread(inner)
+        | ExprKind::TypeTest { expr: inner, .. }
+        | ExprKind::Cast { expr: inner, .. }
+        | ExprKind::GetProperty { object: inner, .. } => visitor.visit_expr_mut(inner),
+        ExprKind::While { condition, body } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(body);
+        }
+        ExprKind::DoWhile { body, condition } => {
+            visitor.visit_expr_mut(body);
+            visitor.visit_expr_mut(condition);
+        }
+        ExprKind::For { iterable, body, .. } => {
+            visitor.visit_expr_mut(iterable);
+            visitor.visit_expr_mut(body);
+        }
+        ExprKind::Match { subject, arms } => {
+            visitor.visit_expr_mut(subject);
+            for arm in arms {
+                if let Pattern::Literal(expr) = &mut arm.pattern {
+                    visitor.visit_expr_mut(expr);
+                }
+                visitor.visit_expr_mut(&mut arm.body);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_expr_mut(index);
+        }
+        ExprKind::Slice { object, start, end } => {
+            visitor.visit_expr_mut(object);
+            if let Some(start) = start {
+                visitor.visit_expr_mut(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_expr_mut(end);
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Span;
+use crate::messages::{Message, arity_mismatch};
+use crate::parser::{Expr, ExprKind, Pattern, Stmt, StmtKind};
+use crate::runtime::environment::Environment;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolverErrorKind {
+    UndefinedVariable(String),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        /// The function has a rest parameter, so `expected` is a minimum.
+        variadic: bool,
+    },
+    ConstReassignment(String),
+}
+
+impl ResolverErrorKind {
+    /// The same code as the runtime error this would otherwise become.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResolverErrorKind::UndefinedVariable(_) => "E0001",
+            ResolverErrorKind::ArityMismatch { .. } => "E0005",
+            ResolverErrorKind::ConstReassignment(_) => "E0003",
+        }
+    }
+}
+
+impl fmt::Display for ResolverErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ResolverErrorKind::UndefinedVariable(name) => {
+                Message::UndefinedVariable.format(&[("name", name)])
+            }
+            ResolverErrorKind::ArityMismatch {
+                name,
+                expected,
+                found,
+                variadic,
+            } => arity_mismatch(name, *expected, *found, *variadic),
+            ResolverErrorKind::ConstReassignment(name) => {
+                Message::ConstReassignment.format(&[("name", name)])
+            }
+        };
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolverError {
+    pub span: Span,
+    pub kind: ResolverErrorKind,
+}
+
+impl From<&ResolverError> for Diagnostic {
+    fn from(error: &ResolverError) -> Self {
+        Diagnostic::error(Some(error.span), error.kind.to_string()).with_code(error.kind.code())
+    }
+}
+
+impl fmt::Display for ResolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Diagnostic::from(self))
+    }
+}
+
+impl std::error::Error for ResolverError {}
+
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Variable,
+    Constant,
+    Function { params: usize, variadic: bool },
+}
+
+/// A function or lambda body, resolved once the scope that declares it is complete
+/// so it can refer to names bound later in that scope.
+struct Deferred<'a> {
+    params: &'a [String],
+    rest: Option<&'a str>,
+    body: &'a Expr,
+}
+
+/// Checks a program before it runs: every name must be bound somewhere in an
+/// enclosing scope, calls to `fn` declarations must pass the right number of
+/// arguments, and constants must not be assigned.
+pub fn resolve(stmts: &[Stmt]) -> Vec<ResolverError> {
+    let mut resolver = Resolver::new();
+    resolver.push_scope();
+    for stmt in stmts {
+        resolver.resolve_stmt(&stmt.kind);
+    }
+    resolver.pop_scope();
+    resolver.errors
+}
+
+struct Resolver<'a> {
+    scopes: Vec<HashMap<&'a str, Binding>>,
+    deferred: Vec<Vec<Deferred<'a>>>,
+    builtins: Vec<String>,
+    /// Set by `import "path"`, after which any name may come from the module.
+    glob_import: bool,
+    errors: Vec<ResolverError>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            deferred: Vec::new(),
+            builtins: Environment::new_root().local_names(),
+            glob_import: false,
+            errors: Vec::new(),
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.deferred.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let deferred = self.deferred.pop().unwrap_or_default();
+        for function in deferred {
+            self.push_scope();
+            for param in function.params {
+                self.declare(param, Binding::Variable);
+            }
+            if let Some(rest) = function.rest {
+                self.declare(rest, Binding::Variable);
+            }
+            self.resolve_expr(function.body);
+            self.pop_scope();
+        }
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &'a str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, binding);
+        }
+    }
+
+    fn defer(&mut self, params: &'a [String], rest: Option<&'a str>, body: &'a Expr) {
+        if let Some(deferred) = self.deferred.last_mut() {
+            deferred.push(Deferred { params, rest, body });
+        }
+    }
+
+    /// `Some(None)` for a builtin, `None` if the name isn't bound at all.
+    fn lookup(&self, name: &str) -> Option<Option<Binding>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .map(|binding| Some(*binding))
+            .or_else(|| self.builtins.iter().any(|b| b == name).then_some(None))
+    }
+
+    fn report(&mut self, span: Span, kind: ResolverErrorKind) {
+        self.errors.push(ResolverError { span, kind });
+    }
+
+    fn check_defined(&mut self, name: &str, span: Span) -> Option<Option<Binding>> {
+        let binding = self.lookup(name);
+        if binding.is_none() && !self.glob_import {
+            self.report(span, ResolverErrorKind::UndefinedVariable(name.to_string()));
+        }
+        binding
+    }
+
+    fn resolve_stmt(&mut self, stmt: &'a StmtKind) {
+        match stmt {
+            StmtKind::Expr(expr) | StmtKind::Result(expr) => self.resolve_expr(expr),
+            StmtKind::Let {
+                name,
+                value,
+                constant,
+                ..
+            } => {
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+                let binding = if *constant {
+                    Binding::Constant
+                } else {
+                    Binding::Variable
+                };
+                self.declare(name, binding);
+            }
+            StmtKind::LetPattern { pattern, value } => {
+                self.resolve_expr(value);
+                for (name, _) in pattern.names() {
+                    self.declare(name, Binding::Variable);
+                }
+            }
+            StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                ..
+            } => {
+                self.declare(
+                    name,
+                    Binding::Function {
+                        params: params.len(),
+                        variadic: rest.is_some(),
+                    },
+                );
+                self.defer(params, rest.as_deref(), body);
+            }
+            StmtKind::Struct { name, fields } => {
+                for (_, default) in fields {
+                    if let Some(default) = default {
+                        self.resolve_expr(default);
+                    }
+                }
+                self.declare(name, Binding::Variable);
+            }
+            StmtKind::Import { alias, .. } => match alias {
+                Some(alias) => self.declare(alias, Binding::Variable),
+                None => self.glob_import = true,
+            },
+            StmtKind::Export(declaration) => self.resolve_stmt(&declaration.kind),
+            StmtKind::Break(value) | StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+            StmtKind::Continue => {}
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &'a Expr) {
+        match &expr.kind {
+            ExprKind::Variable(name) => {
+                self.check_defined(name, expr.span);
+            }
+            ExprKind::Assign { target, value } => {
+                self.resolve_expr(value);
+                self.resolve_target(target);
+            }
+            ExprKind::FunctionCall { name, args } => {
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+                let spread = args
+                    .iter()
+                    .any(|arg| matches!(arg.kind, ExprKind::Spread(_)));
+                if let Some(Some(Binding::Function { params, variadic })) =
+                    self.check_defined(name, expr.span)
+                {
+                    let found = args.len();
+                    let mismatch = if variadic {
+                        found < params
+                    } else {
+                        found != params
+                    };
+                    if mismatch && !spread {
+                        self.report(
+                            expr.span,
+                            ResolverErrorKind::ArityMismatch {
+                                name: name.clone(),
+                                expected: params,
+                                found,
+                                variadic,
+                            },
+                        );
+                    }
+                }
+            }
+            ExprKind::StructInstance { name, args } => {
+                self.check_defined(name, expr.span);
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+            }
+            ExprKind::Lambda { params, rest, body } => {
+                self.defer(params, rest.as_deref(), body);
+            }
+            ExprKind::Block(stmts) => {
+                self.push_scope();
+                for stmt in stmts {
+                    self.resolve_stmt(stmt);
+                }
+                self.pop_scope();
+            }
+            ExprKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                self.resolve_expr(iterable);
+                self.push_scope();
+                self.declare(variable, Binding::Variable);
+                self.resolve_expr(body);
+                self.pop_scope();
+            }
+            ExprKind::Match { subject, arms } => {
+                self.resolve_expr(subject);
+                for arm in arms {
+                    self.push_scope();
+                    match &arm.pattern {
+                        Pattern::Literal(pattern) => self.resolve_expr(pattern),
+                        Pattern::Binding(name) => self.declare(name, Binding::Variable),
+                        Pattern::Wildcard => {}
+                    }
+                    self.resolve_expr(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            _ => {
+                for child in expr.children() {
+                    self.resolve_expr(child);
+                }
+            }
+        }
+    }
+
+    fn resolve_target(&mut self, target: &'a Expr) {
+        match &target.kind {
+            ExprKind::Variable(name) => {
+                if let Some(Some(Binding::Constant)) = self.check_defined(name, target.span) {
+                    self.report(
+                        target.span,
+                        ResolverErrorKind::ConstReassignment(name.clone()),
+                    );
+                }
+            }
+            ExprKind::Array(targets) => {
+                for target in targets {
+                    self.resolve_target(target);
+                }
+            }
+            _ => self.resolve_expr(target),
+        }
+    }
+}
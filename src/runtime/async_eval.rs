@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::{
+    parser::Stmt,
+    runtime::{
+        concurrency::{STACK_SIZE, SendValue},
+        environment::Environment,
+        error::InterpreterError,
+        eval::eval_with_env,
+        stack,
+    },
+};
+
+/// What the evaluating thread reports back to the future.
+enum Progress {
+    Yielded,
+    Done(Result<SendValue, String>),
+}
+
+/// The waker of the task that last polled the future, for the evaluating
+/// thread to call once it has something to report.
+type SharedWaker = Arc<Mutex<Option<Waker>>>;
+
+/// Sends `progress` to the future and wakes the task waiting on it.
+fn report(sender: &Sender<Progress>, waker: &SharedWaker, progress: Progress) {
+    let _ = sender.send(progress);
+    let waker = waker.lock().unwrap_or_else(|err| err.into_inner()).take();
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// A program being evaluated by [`eval_async`]. Polling never waits for the
+/// program: it is resumed for at most the requested number of steps, and
+/// wakes the task when it pauses or finishes. Dropping the future stops the
+/// program at its next yield.
+pub struct EvalFuture {
+    resume: Option<Sender<()>>,
+    progress: Receiver<Progress>,
+    waker: SharedWaker,
+    /// Whether the program was resumed and hasn't reported back since.
+    running: bool,
+}
+
+/// Evaluates `ast` without blocking an async executor for long. The program
+/// runs on its own thread, in a fresh environment that `setup` can prepare
+/// (limits, observers, variables). It pauses every `yield_every` steps until
+/// the future is polled again. Time spent waiting to be polled counts against
+/// a `max_duration` limit.
+///
+/// The result is copied out of the program's thread; errors come back as
+/// their message.
+pub fn eval_async(
+    ast: Vec<Stmt>,
+    yield_every: u64,
+    setup: impl FnOnce(&Rc<RefCell<Environment>>) + Send + 'static,
+) -> EvalFuture {
+    let (resume, resumed) = mpsc::channel::<()>();
+    let (sender, progress) = mpsc::channel();
+    let waker = SharedWaker::default();
+    let thread_waker = waker.clone();
+    let spawned = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            // Wait for the first poll; a future dropped before it never runs.
+            if resumed.recv().is_err() {
+                return;
+            }
+            stack::set_stack_size(STACK_SIZE);
+            let env = Rc::new(RefCell::new(Environment::new_root()));
+            setup(&env);
+            if let Some(state) = env.borrow().state() {
+                let sender = sender.clone();
+                let waker = thread_waker.clone();
+                state.borrow_mut().set_yield_point(yield_every, move || {
+                    report(&sender, &waker, Progress::Yielded);
+                    resumed.recv().map_err(|_| InterpreterError::Interrupted)
+                });
+            }
+            let result = match eval_with_env(ast, &env) {
+                Ok(value) | Err(InterpreterError::Return(value)) => {
+                    SendValue::from_value(&value).map_err(|err| err.to_string())
+                }
+                Err(err) => Err(err.to_string()),
+            };
+            report(&sender, &thread_waker, Progress::Done(result));
+        });
+    if let Err(err) = spawned {
+        let (sender, progress) = mpsc::channel();
+        let _ = sender.send(Progress::Done(Err(format!(
+            "Cannot start evaluation thread: {err}"
+        ))));
+        return EvalFuture {
+            resume: None,
+            progress,
+            waker,
+            running: false,
+        };
+    }
+    EvalFuture {
+        resume: Some(resume),
+        progress,
+        waker,
+        running: false,
+    }
+}
+
+impl Future for EvalFuture {
+    type Output = Result<SendValue, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // Stored before looking for progress, so a report sent in between
+        // still wakes this task.
+        *this.waker.lock().unwrap_or_else(|err| err.into_inner()) = Some(cx.waker().clone());
+        match this.progress.try_recv() {
+            Ok(Progress::Yielded) => this.running = false,
+            Ok(Progress::Done(result)) => {
+                this.resume = None;
+                return Poll::Ready(result);
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                return Poll::Ready(Err("Evaluation thread stopped unexpectedly".to_string()));
+            }
+        }
+        if !this.running
+            && let Some(resume) = &this.resume
+        {
+            let _ = resume.send(());
+            this.running = true;
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,64 @@
+use crate::lexer::Span;
+use crate::runtime::error::InterpreterError;
+
+/// How many user function calls may be in progress at once unless a program
+/// sets its own limit. Every level also costs Rust stack, so on a smaller
+/// thread calls stop earlier; see [`stack`](crate::runtime::stack).
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// One user function call in progress.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name the function was called by.
+    pub function: String,
+    pub call_site: Span,
+}
+
+/// The user function calls in progress, shared by every scope and module of
+/// one program.
+#[derive(Debug)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+    max_depth: usize,
+}
+
+impl Default for CallStack {
+    fn default() -> Self {
+        Self {
+            frames: Vec::new(),
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+}
+
+impl CallStack {
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The calls in progress, outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Enters a call, failing instead once `max_depth` calls are in progress.
+    pub fn push(&mut self, frame: Frame) -> Result<(), InterpreterError> {
+        if self.frames.len() >= self.max_depth {
+            return Err(InterpreterError::StackOverflow(self.max_depth));
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+}
@@ -0,0 +1,447 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    messages::Message,
+    parser::Expr,
+    runtime::{
+        call_stack::DEFAULT_MAX_CALL_DEPTH,
+        environment::{
+            BuiltinFunction, Environment, UserFunction,
+            function::{Fun, Function},
+            value::{EnvironmentValue, Number, StructDef, Value},
+        },
+        error::InterpreterError,
+        stack,
+        state::EvalLimits,
+    },
+};
+
+/// Spawned threads evaluate on a stack as large as the CLI's main thread, so
+/// they allow the same call depth.
+pub(crate) const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// A value copied so it can move to another thread or be saved in a
+/// [`Snapshot`](crate::runtime::snapshot::Snapshot). Arrays are copied deeply,
+/// and functions lose the scope they closed over.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SendValue {
+    Number(Number),
+    Boolean(bool),
+    String(String),
+    Array(Vec<SendValue>),
+    Object(HashMap<String, SendValue>),
+    StructInstance {
+        name: String,
+        fields: HashMap<String, SendValue>,
+    },
+    Range {
+        start: i128,
+        end: i128,
+    },
+    Function(SendFunction),
+    /// Channels are shared rather than copied, and can't be saved.
+    #[serde(skip)]
+    Channel(Channel),
+    Nil,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SendFunction {
+    Builtin(BuiltinFunction),
+    User {
+        name: Option<String>,
+        params: Vec<String>,
+        rest: Option<String>,
+        body: Expr,
+        doc: Option<String>,
+    },
+}
+
+impl SendValue {
+    pub fn from_value(value: &Value) -> Result<Self, InterpreterError> {
+        Self::copy(value, &mut Vec::new())
+    }
+
+    /// `arrays` holds the arrays being copied around `value`, which would
+    /// never finish copying if `value` contained one of them.
+    fn copy(
+        value: &Value,
+        arrays: &mut Vec<*const RefCell<Vec<Value>>>,
+    ) -> Result<Self, InterpreterError> {
+        Ok(match value {
+            Value::Number(n) => SendValue::Number(n.clone()),
+            Value::Boolean(b) => SendValue::Boolean(*b),
+            Value::String(s) => SendValue::String(s.clone()),
+            Value::Array(items) => {
+                if arrays.contains(&Rc::as_ptr(items)) {
+                    return Err(InterpreterError::InvalidOperation(
+                        Message::CopySelfContaining.text().to_string(),
+                    ));
+                }
+                arrays.push(Rc::as_ptr(items));
+                let copied = items
+                    .borrow()
+                    .iter()
+                    .map(|item| Self::copy(item, arrays))
+                    .collect::<Result<_, _>>()?;
+                arrays.pop();
+                SendValue::Array(copied)
+            }
+            Value::Object(fields) => SendValue::Object(copy_fields(fields, arrays)?),
+            Value::StructInstance { name, fields } => SendValue::StructInstance {
+                name: name.clone(),
+                fields: copy_fields(fields, arrays)?,
+            },
+            Value::Range { start, end } => SendValue::Range {
+                start: *start,
+                end: *end,
+            },
+            Value::Function(function) => SendValue::Function(SendFunction::from_function(function)),
+            Value::Channel(channel) => SendValue::Channel(channel.clone()),
+            Value::Thread(_) => {
+                return Err(InterpreterError::InvalidOperation(
+                    Message::CopyThread.text().to_string(),
+                ));
+            }
+            Value::Nil => SendValue::Nil,
+        })
+    }
+
+    pub fn into_value(self) -> Value {
+        match self {
+            SendValue::Number(n) => Value::Number(n),
+            SendValue::Boolean(b) => Value::Boolean(b),
+            SendValue::String(s) => Value::String(s),
+            SendValue::Array(items) => Value::Array(Rc::new(RefCell::new(
+                items.into_iter().map(SendValue::into_value).collect(),
+            ))),
+            SendValue::Object(fields) => Value::Object(restore_fields(fields)),
+            SendValue::StructInstance { name, fields } => Value::StructInstance {
+                name,
+                fields: restore_fields(fields),
+            },
+            SendValue::Range { start, end } => Value::Range { start, end },
+            SendValue::Function(function) => Value::Function(Rc::new(function.into_function())),
+            SendValue::Channel(channel) => Value::Channel(channel),
+            SendValue::Nil => Value::Nil,
+        }
+    }
+}
+
+fn copy_fields(
+    fields: &HashMap<String, Value>,
+    arrays: &mut Vec<*const RefCell<Vec<Value>>>,
+) -> Result<HashMap<String, SendValue>, InterpreterError> {
+    fields
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), SendValue::copy(value, arrays)?)))
+        .collect()
+}
+
+fn restore_fields(fields: HashMap<String, SendValue>) -> HashMap<String, Value> {
+    fields
+        .into_iter()
+        .map(|(name, value)| (name, value.into_value()))
+        .collect()
+}
+
+impl SendFunction {
+    pub fn from_function(function: &Function) -> Self {
+        match function {
+            Function::Builtin(builtin) => SendFunction::Builtin(builtin.clone()),
+            Function::User(function) => SendFunction::User {
+                name: function.name.clone(),
+                params: function.params.clone(),
+                rest: function.rest.clone(),
+                body: function.body.clone(),
+                doc: function.doc.clone(),
+            },
+        }
+    }
+
+    /// The function again, resolving names where it is called since its
+    /// closure stayed behind.
+    pub fn into_function(self) -> Function {
+        match self {
+            SendFunction::Builtin(builtin) => Function::Builtin(builtin),
+            SendFunction::User {
+                name,
+                params,
+                rest,
+                body,
+                doc,
+            } => {
+                let function = UserFunction::new(params, rest, body).with_doc(doc);
+                Function::User(match name {
+                    Some(name) => function.with_name(name),
+                    None => function,
+                })
+            }
+        }
+    }
+}
+
+/// A binding of the spawning scope, copied for the spawned thread.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum SendBinding {
+    Variable(SendValue),
+    Constant(SendValue),
+    Function(SendFunction),
+    Struct {
+        name: String,
+        fields: Vec<(String, Option<SendValue>)>,
+    },
+}
+
+impl SendBinding {
+    pub(crate) fn from_binding(binding: &EnvironmentValue) -> Result<Self, InterpreterError> {
+        Ok(match binding {
+            EnvironmentValue::Variable(value) => {
+                SendBinding::Variable(SendValue::from_value(value)?)
+            }
+            EnvironmentValue::Constant(value) => {
+                SendBinding::Constant(SendValue::from_value(value)?)
+            }
+            EnvironmentValue::Function(function) => {
+                SendBinding::Function(SendFunction::from_function(function))
+            }
+            EnvironmentValue::Struct(StructDef { name, fields }) => SendBinding::Struct {
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, default)| {
+                        let default = default.as_ref().map(SendValue::from_value).transpose()?;
+                        Ok((field.clone(), default))
+                    })
+                    .collect::<Result<_, InterpreterError>>()?,
+            },
+        })
+    }
+
+    pub(crate) fn into_binding(self) -> EnvironmentValue {
+        match self {
+            SendBinding::Variable(value) => EnvironmentValue::Variable(value.into_value()),
+            SendBinding::Constant(value) => EnvironmentValue::Constant(value.into_value()),
+            SendBinding::Function(function) => EnvironmentValue::Function(function.into_function()),
+            SendBinding::Struct { name, fields } => EnvironmentValue::Struct(StructDef {
+                name,
+                fields: fields
+                    .into_iter()
+                    .map(|(field, default)| (field, default.map(SendValue::into_value)))
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// The limits a spawned thread inherits, so spawning can't escape them. Each
+/// thread counts its steps and allocations on its own.
+#[derive(Debug)]
+struct Inherited {
+    limits: EvalLimits,
+    max_call_depth: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    /// Drawn from the spawner's generator, so a seeded program's threads are
+    /// seeded too.
+    seed: u64,
+}
+
+/// What `spawn()` returns: the running thread until `join()` takes its result.
+#[derive(Debug)]
+pub struct ThreadHandle(RefCell<Option<JoinHandle<Result<SendValue, String>>>>);
+
+/// Handles are only ever equal to themselves.
+impl PartialEq for ThreadHandle {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// `spawn(func, ...args)`: calls `func` on a new thread, in a copy of the
+/// scope it closed over, or else of the scope `spawn` was called from.
+/// Bindings that can't be copied, such as other thread handles, are left out.
+pub fn spawn(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let mut args = args.into_iter();
+    let Some(Value::Function(function)) = args.next() else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::SpawnArgument.text().to_string(),
+        ));
+    };
+    let scope = match function.as_ref() {
+        Function::User(UserFunction {
+            closure: Some(closure),
+            ..
+        }) => closure.clone(),
+        _ => env.clone(),
+    };
+    let bindings: Vec<_> = scope
+        .borrow()
+        .visible_bindings()
+        .iter()
+        .filter_map(|(name, binding)| {
+            let binding = SendBinding::from_binding(binding).ok()?;
+            Some((name.clone(), binding))
+        })
+        .collect();
+    let args = args
+        .map(|arg| SendValue::from_value(&arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let function = SendFunction::from_function(&function);
+    let inherited = match env.borrow().state() {
+        Some(state) => {
+            let mut state = state.borrow_mut();
+            Inherited {
+                limits: state.limits(),
+                max_call_depth: state.call_stack.max_depth(),
+                cancel: state.cancel_flag(),
+                seed: state.rng().random(),
+            }
+        }
+        None => Inherited {
+            limits: EvalLimits::default(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            cancel: None,
+            seed: rand::random(),
+        },
+    };
+    let handle = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run_spawned(bindings, function, args, inherited))
+        .map_err(|err| {
+            InterpreterError::InvalidOperation(
+                Message::Failed.format(&[("function", &"spawn"), ("reason", &err)]),
+            )
+        })?;
+    Ok(Value::Thread(Rc::new(ThreadHandle(RefCell::new(Some(
+        handle,
+    ))))))
+}
+
+fn run_spawned(
+    bindings: Vec<(String, SendBinding)>,
+    function: SendFunction,
+    args: Vec<SendValue>,
+    inherited: Inherited,
+) -> Result<SendValue, String> {
+    stack::set_stack_size(STACK_SIZE);
+    let root = Environment::new_root();
+    root.set_limits(inherited.limits);
+    root.set_max_call_depth(inherited.max_call_depth);
+    root.set_seed(inherited.seed);
+    if let Some(cancel) = inherited.cancel {
+        root.set_cancel_flag(cancel);
+    }
+    // A child of the root, so copied bindings may shadow builtins.
+    let scope = Rc::new(RefCell::new(Environment::new_child(Rc::new(RefCell::new(
+        root,
+    )))));
+    for (name, binding) in bindings {
+        scope
+            .borrow_mut()
+            .define_imported(name, binding.into_binding())
+            .map_err(|err| err.to_string())?;
+    }
+    let args = args.into_iter().map(SendValue::into_value).collect();
+    let result = match function.into_function().call(args, &scope) {
+        Ok(value) | Err(InterpreterError::Return(value)) => value,
+        Err(err) => return Err(err.to_string()),
+    };
+    SendValue::from_value(&result).map_err(|err| err.to_string())
+}
+
+/// `join(handle)`: waits for a spawned thread and returns its function's result.
+pub fn join(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let Some(Value::Thread(handle)) = args.first() else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::JoinThreadArgument.text().to_string(),
+        ));
+    };
+    let Some(handle) = handle.0.borrow_mut().take() else {
+        return Err(InterpreterError::InvalidOperation(
+            Message::JoinTwice.text().to_string(),
+        ));
+    };
+    match handle.join() {
+        Ok(Ok(value)) => Ok(value.into_value()),
+        Ok(Err(message)) => Err(InterpreterError::InvalidOperation(
+            Message::ThreadFailed.format(&[("message", &message)]),
+        )),
+        Err(_) => Err(InterpreterError::InvalidOperation(
+            Message::ThreadPanicked.text().to_string(),
+        )),
+    }
+}
+
+/// How long `recv()` waits at a time before checking whether the program was
+/// cancelled or ran out of time.
+const RECV_POLL: Duration = Duration::from_millis(50);
+
+/// What `channel()` returns: a queue any thread holding a copy can send to and
+/// receive from, in order.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    sender: Sender<SendValue>,
+    receiver: Arc<Mutex<Receiver<SendValue>>>,
+}
+
+/// Copies of a channel are the same channel.
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.receiver, &other.receiver)
+    }
+}
+
+/// `channel()`: a new, empty channel.
+pub fn channel() -> Result<Value, InterpreterError> {
+    let (sender, receiver) = mpsc::channel();
+    Ok(Value::Channel(Channel {
+        sender,
+        receiver: Arc::new(Mutex::new(receiver)),
+    }))
+}
+
+/// `send(ch, value)`: queues a copy of `value` without waiting for a receiver.
+pub fn send(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let [Value::Channel(channel), value] = args.as_slice() else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::SendArguments.text().to_string(),
+        ));
+    };
+    // Every channel value holds a receiver, so the queue is always open.
+    let _ = channel.sender.send(SendValue::from_value(value)?);
+    Ok(Value::Nil)
+}
+
+/// `recv(ch)`: waits for the next value sent on `ch` and returns it.
+pub fn recv(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let Some(Value::Channel(channel)) = args.first() else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::RecvArgument.text().to_string(),
+        ));
+    };
+    loop {
+        let received = match channel.receiver.lock() {
+            Ok(receiver) => receiver.recv_timeout(RECV_POLL),
+            Err(poisoned) => poisoned.into_inner().recv_timeout(RECV_POLL),
+        };
+        match received {
+            Ok(value) => return Ok(value.into_value()),
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(state) = env.borrow().state() {
+                    state.borrow_mut().step()?;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(Value::Nil),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::lexer::Span;
+use crate::runtime::call_stack::Frame;
+use crate::runtime::environment::Environment;
+use crate::runtime::error::InterpreterError;
+
+/// A line to stop at, optionally only in one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    /// Matches any file whose path ends with this one; `None` matches every file.
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+impl Breakpoint {
+    fn matches(&self, file: Option<&Path>, line: usize) -> bool {
+        if self.line != line {
+            return false;
+        }
+        match (&self.file, file) {
+            (None, _) => true,
+            (Some(wanted), Some(file)) => file.ends_with(wanted),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Parses `line` or `file:line`.
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (file, line) = match s.rsplit_once(':') {
+            Some((file, line)) => (Some(PathBuf::from(file)), line),
+            None => (None, s),
+        };
+        match line.trim().parse() {
+            Ok(line) if line > 0 => Ok(Breakpoint { file, line }),
+            _ => Err(format!(
+                "Invalid breakpoint '{s}': expected LINE or FILE:LINE"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "{}", self.line),
+        }
+    }
+}
+
+/// How to go on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Stop at the very next statement, entering function calls.
+    Step,
+    /// Stop at the next statement of this function or its callers, running
+    /// over any calls in between.
+    Next,
+    /// Stop the program with [`InterpreterError::Interrupted`].
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    Breakpoint,
+    Step,
+}
+
+/// Where the program stopped, handed to a [`DebugHook`].
+pub struct Pause<'a> {
+    pub reason: PauseReason,
+    pub file: Option<&'a Path>,
+    /// The statement about to run.
+    pub span: Span,
+    /// The user function calls in progress, outermost first.
+    pub frames: &'a [Frame],
+    /// The scope the statement runs in, for inspecting variables.
+    pub env: &'a Rc<RefCell<Environment>>,
+    /// The debugger's breakpoints, which the hook may change.
+    pub breakpoints: &'a mut Vec<Breakpoint>,
+}
+
+/// Decides what to do each time the program stops. Embedders implement this
+/// to drive a debugger UI.
+pub trait DebugHook {
+    fn pause(&mut self, pause: &mut Pause) -> DebugCommand;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Run,
+    Step,
+    /// Stepping over calls made deeper than this call depth.
+    Next(usize),
+}
+
+/// Stops the program at breakpoints and while stepping, asking its hook what
+/// to do next.
+pub struct Debugger {
+    hook: Box<dyn DebugHook>,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+}
+
+impl fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("breakpoints", &self.breakpoints)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Debugger {
+    /// A debugger that pauses before the first statement.
+    pub fn new(hook: impl DebugHook + 'static) -> Self {
+        Self {
+            hook: Box::new(hook),
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+        }
+    }
+
+    /// Runs until the first breakpoint instead of pausing right away.
+    pub fn run_to_breakpoint(mut self) -> Self {
+        self.mode = Mode::Run;
+        self
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Called before every statement; pauses if a breakpoint or the current
+    /// stepping mode says so.
+    pub(crate) fn before_statement(
+        &mut self,
+        span: Span,
+        frames: &[Frame],
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<(), InterpreterError> {
+        // Some statements inside blocks have no known position.
+        if span.line == 0 {
+            return Ok(());
+        }
+        let file = env.borrow().file();
+        let file = file.as_deref();
+        let stepping = match self.mode {
+            Mode::Run => false,
+            Mode::Step => true,
+            Mode::Next(depth) => frames.len() <= depth,
+        };
+        let reason = if stepping {
+            PauseReason::Step
+        } else if self
+            .breakpoints
+            .iter()
+            .any(|breakpoint| breakpoint.matches(file, span.line))
+        {
+            PauseReason::Breakpoint
+        } else {
+            return Ok(());
+        };
+        let mut pause = Pause {
+            reason,
+            file,
+            span,
+            frames,
+            env,
+            breakpoints: &mut self.breakpoints,
+        };
+        self.mode = match self.hook.pause(&mut pause) {
+            DebugCommand::Continue => Mode::Run,
+            DebugCommand::Step => Mode::Step,
+            DebugCommand::Next => Mode::Next(frames.len()),
+            DebugCommand::Quit => return Err(InterpreterError::Interrupted),
+        };
+        Ok(())
+    }
+}
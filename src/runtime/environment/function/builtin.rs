@@ -1,40 +1,156 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
 use std::rc::Rc;
 
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+
 use crate::{
     Environment,
+    lexer::Span,
+    messages::Message,
     runtime::{
+        concurrency::{channel, join, recv, send, spawn},
         environment::{
-            function::Fun,
+            function::{Fun, Function, UserFunction},
             value::{Number, Value},
         },
         error::InterpreterError,
+        eval::{allocate, array_size, call_callback},
     },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BuiltinFunction {
     Print,
+    Println,
+    Eprint,
+    Eprintln,
+    PrintSep,
+    PrintlnSep,
+    EprintSep,
+    EprintlnSep,
+    Write,
     Input,
     Int,
     Float,
     String,
     Random,
+    Seed,
+    Format,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Pow,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Ln,
+    Log10,
+    Log2,
+    Exp,
+    Min,
+    Max,
+    Sum,
+    Sort,
+    Reverse,
+    Map,
+    Filter,
+    Reduce,
+    Split,
+    Trim,
+    TrimStart,
+    TrimEnd,
+    Upper,
+    Lower,
+    Replace,
     Len,
     Type,
     Push,
     Pop,
     Time,
+    Chars,
+    Ord,
+    Chr,
+    Help,
+    Profile,
+    Spawn,
+    Join,
+    Channel,
+    Send,
+    Recv,
 }
 
-fn print(args: Vec<Value>) -> Result<Value, InterpreterError> {
-    for arguments in args {
-        print!("{arguments} ");
+/// Prints every value separated by `sep`; `format()` lays out anything else.
+fn print(
+    values: &[Value],
+    sep: &str,
+    stream: Stream,
+    newline: bool,
+) -> Result<Value, InterpreterError> {
+    let texts: Vec<String> = values.iter().map(Value::to_string).collect();
+    let mut text = texts.join(sep);
+    if newline {
+        text.push('\n');
     }
-    println!();
+    stream.write(&text);
     Ok(Value::Nil)
 }
 
+/// `print_sep(sep, ...)` and its variants: the separator comes first, so any
+/// value after it is printed, never mistaken for an option.
+fn print_sep(
+    name: &str,
+    args: Vec<Value>,
+    stream: Stream,
+    newline: bool,
+) -> Result<Value, InterpreterError> {
+    match args.split_first() {
+        Some((Value::String(sep), values)) => print(values, sep, stream, newline),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::SeparatorFirst.format(&[("function", &name)]),
+        )),
+    }
+}
+
+/// Writes each value as it is, with no separator or newline.
+fn write(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let text: String = args.iter().map(Value::to_string).collect();
+    Stream::Stdout.write(&text);
+    Ok(Value::Nil)
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// Flushes as well, so output without a newline shows up straight away.
+    fn write(self, text: &str) {
+        match self {
+            Stream::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                let _ = stdout
+                    .write_all(text.as_bytes())
+                    .and_then(|_| stdout.flush());
+            }
+            Stream::Stderr => {
+                let _ = std::io::stderr().write_all(text.as_bytes());
+            }
+        }
+    }
+}
+
 fn input() -> Result<Value, InterpreterError> {
     let mut input = String::new();
     std::io::stdin().read_line(&mut input).unwrap();
@@ -48,7 +164,7 @@ fn push(args: Vec<Value>) -> Result<Value, InterpreterError> {
             Ok(Value::Array(v.clone()))
         }
         _ => Err(InterpreterError::TypeMismatch(
-            "push() expects a vector and an item".to_string(),
+            Message::PushArguments.text().to_string(),
         )),
     }
 }
@@ -60,10 +176,10 @@ fn pop(args: Vec<Value>) -> Result<Value, InterpreterError> {
             Ok(popped)
         }
         Some(Value::Array(_)) => Err(InterpreterError::InvalidOperation(
-            "Cannot pop from empty vector".to_string(),
+            Message::PopEmpty.text().to_string(),
         )),
         _ => Err(InterpreterError::TypeMismatch(
-            "pop() expects a vector".to_string(),
+            Message::PopArgument.text().to_string(),
         )),
     }
 }
@@ -71,13 +187,13 @@ fn pop(args: Vec<Value>) -> Result<Value, InterpreterError> {
 fn int(args: Vec<Value>) -> Result<Value, InterpreterError> {
     match args.first() {
         Some(Value::Number(n)) => Ok(Value::Number(Number::Int(n.to_int()))),
-        Some(Value::String(s)) => {
-            Ok(Value::Number(Number::Int(s.parse().map_err(|e| {
-                InterpreterError::InvalidOperation(format!("int() failed: {e}"))
-            })?)))
-        }
+        Some(Value::String(s)) => Ok(Value::Number(Number::Int(s.parse().map_err(|e| {
+            InterpreterError::InvalidOperation(
+                Message::Failed.format(&[("function", &"int"), ("reason", &e)]),
+            )
+        })?))),
         _ => Err(InterpreterError::TypeMismatch(
-            "int() expects a number or a string".to_string(),
+            Message::ExpectsNumberOrString.format(&[("function", &"int")]),
         )),
     }
 }
@@ -85,13 +201,13 @@ fn int(args: Vec<Value>) -> Result<Value, InterpreterError> {
 fn float(args: Vec<Value>) -> Result<Value, InterpreterError> {
     match args.first() {
         Some(Value::Number(n)) => Ok(Value::Number(Number::Float(n.to_float()))),
-        Some(Value::String(s)) => {
-            Ok(Value::Number(Number::Float(s.parse().map_err(|e| {
-                InterpreterError::InvalidOperation(format!("float() failed: {e}"))
-            })?)))
-        }
+        Some(Value::String(s)) => Ok(Value::Number(Number::Float(s.parse().map_err(|e| {
+            InterpreterError::InvalidOperation(
+                Message::Failed.format(&[("function", &"float"), ("reason", &e)]),
+            )
+        })?))),
         _ => Err(InterpreterError::TypeMismatch(
-            "float() expects a number or a string".to_string(),
+            Message::ExpectsNumberOrString.format(&[("function", &"float")]),
         )),
     }
 }
@@ -103,57 +219,579 @@ fn string(args: Vec<Value>) -> Result<Value, InterpreterError> {
     }
 }
 
+fn format(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    match args.split_first() {
+        Some((Value::String(template), values)) => {
+            Ok(Value::String(format_values(template, values, env)?))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::FormatTemplate.text().to_string(),
+        )),
+    }
+}
+
+/// How a `{}` placeholder lays out its value: `{:>8.2}` right-aligns it in
+/// eight columns with two decimal places.
+#[derive(Default)]
+struct Placeholder {
+    align: Option<char>,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Placeholder {
+    /// Parses what is between the braces, or `None` if it isn't a placeholder.
+    fn parse(spec: &str) -> Option<Self> {
+        let mut placeholder = Placeholder::default();
+        if spec.is_empty() {
+            return Some(placeholder);
+        }
+        let mut rest = spec.strip_prefix(':')?;
+        if let Some(align) = rest.chars().next().filter(|c| matches!(c, '<' | '>' | '^')) {
+            placeholder.align = Some(align);
+            rest = &rest[1..];
+        }
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision)),
+            None => (rest, None),
+        };
+        if !width.is_empty() {
+            placeholder.width = width.parse().ok()?;
+        }
+        if let Some(precision) = precision {
+            placeholder.precision = Some(precision.parse().ok()?);
+        }
+        Some(placeholder)
+    }
+
+    /// Bytes the placeholder may add beyond its value's own text: padding to
+    /// the width, or a number's decimal places.
+    fn expansion(&self, value: &Value) -> usize {
+        match (value, self.precision) {
+            (Value::Number(_), Some(places)) => self.width.max(places),
+            _ => self.width,
+        }
+    }
+
+    /// Numbers take the precision as decimal places and align right by
+    /// default; other values are cut to that many characters and align left.
+    fn apply(&self, value: &Value) -> String {
+        let text = match (value, self.precision) {
+            (Value::Number(Number::Float(f)), Some(places)) => format!("{f:.places$}"),
+            (Value::Number(Number::Int(i)), Some(places)) => format!("{:.places$}", *i as f64),
+            (value, Some(chars)) => value.to_string().chars().take(chars).collect(),
+            (value, None) => value.to_string(),
+        };
+        let padding = self.width.saturating_sub(text.chars().count());
+        let default = if matches!(value, Value::Number(_)) {
+            '>'
+        } else {
+            '<'
+        };
+        let (left, right) = match self.align.unwrap_or(default) {
+            '>' => (padding, 0),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (0, padding),
+        };
+        format!("{}{text}{}", " ".repeat(left), " ".repeat(right))
+    }
+}
+
+/// Fills each `{}` in `template` with the next value. `{{` and `}}` stand for
+/// literal braces, and every value must have a placeholder. Widths and
+/// precisions are charged before they are laid out, the rest once the text is
+/// done.
+fn format_values(
+    template: &str,
+    values: &[Value],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<String, InterpreterError> {
+    let mut text = String::new();
+    let mut charged = 0usize;
+    let mut values_iter = values.iter();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(|| {
+                    InterpreterError::InvalidOperation(Message::FormatUnclosed.text().to_string())
+                })?;
+                let spec = &rest[..end];
+                let placeholder = Placeholder::parse(spec).ok_or_else(|| {
+                    InterpreterError::InvalidOperation(
+                        Message::FormatInvalidPlaceholder.format(&[("spec", &spec)]),
+                    )
+                })?;
+                let value = values_iter.next().ok_or_else(|| {
+                    InterpreterError::InvalidOperation(
+                        Message::FormatTooFewValues.format(&[("count", &values.len())]),
+                    )
+                })?;
+                let expansion = placeholder.expansion(value);
+                allocate(env, expansion)?;
+                charged = charged.saturating_add(expansion);
+                text.push_str(&placeholder.apply(value));
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                return Err(InterpreterError::InvalidOperation(
+                    Message::FormatUnopened.text().to_string(),
+                ));
+            }
+            c => text.push(c),
+        }
+    }
+    if values_iter.len() > 0 {
+        let used = values.len() - values_iter.len();
+        return Err(InterpreterError::InvalidOperation(
+            Message::FormatTooManyValues.format(&[("count", &values.len()), ("used", &used)]),
+        ));
+    }
+    allocate(env, text.len().saturating_sub(charged))?;
+    Ok(text)
+}
+
+fn number_arg(name: &str, value: Option<&Value>) -> Result<Number, InterpreterError> {
+    match value {
+        Some(Value::Number(n)) => Ok(n.clone()),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ExpectsNumber.format(&[("function", &name)]),
+        )),
+    }
+}
+
+fn sqrt(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let n = number_arg("sqrt", args.first())?.to_float();
+    if n < 0.0 {
+        return Err(InterpreterError::InvalidOperation(
+            Message::NegativeSqrt.format(&[("value", &format!("{n:?}"))]),
+        ));
+    }
+    Ok(Value::Number(Number::Float(n.sqrt())))
+}
+
+fn abs(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    match number_arg("abs", args.first())? {
+        Number::Int(i) => i
+            .checked_abs()
+            .map(|i| Value::Number(Number::Int(i)))
+            .ok_or(InterpreterError::Overflow),
+        Number::Float(f) => Ok(Value::Number(Number::Float(f.abs()))),
+    }
+}
+
+/// `floor`, `ceil` and `round` give an Int, which an Int already is.
+fn to_whole(
+    name: &str,
+    args: Vec<Value>,
+    rounding: fn(f64) -> f64,
+) -> Result<Value, InterpreterError> {
+    let f = match number_arg(name, args.first())? {
+        Number::Int(i) => return Ok(Value::Number(Number::Int(i))),
+        Number::Float(f) => rounding(f),
+    };
+    if !f.is_finite() {
+        return Err(InterpreterError::InvalidOperation(
+            Message::NoIntegerResult.format(&[("function", &name), ("value", &f)]),
+        ));
+    }
+    // i128::MAX as f64 rounds up to 2^127, which no longer fits.
+    if f < i128::MIN as f64 || f >= i128::MAX as f64 {
+        return Err(InterpreterError::Overflow);
+    }
+    Ok(Value::Number(Number::Int(f as i128)))
+}
+
+fn pow(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let base = number_arg("pow", args.first())?;
+    let exponent = number_arg("pow", args.get(1))?;
+    Ok(Value::Number(base.checked_pow(exponent)?))
+}
+
+/// Applies a float function, such as `sin`, to the argument. Arguments
+/// outside its domain, like `ln(-1)`, are an error rather than NaN.
+fn float_fn(
+    name: &str,
+    args: Vec<Value>,
+    apply: fn(f64) -> f64,
+) -> Result<Value, InterpreterError> {
+    let x = number_arg(name, args.first())?.to_float();
+    let result = apply(x);
+    if result.is_nan() && !x.is_nan() {
+        return Err(InterpreterError::InvalidOperation(
+            Message::UndefinedFor.format(&[("function", &name), ("value", &format!("{x:?}"))]),
+        ));
+    }
+    Ok(Value::Number(Number::Float(result)))
+}
+
+fn atan2(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let y = number_arg("atan2", args.first())?.to_float();
+    let x = number_arg("atan2", args.get(1))?.to_float();
+    Ok(Value::Number(Number::Float(y.atan2(x))))
+}
+
+/// Orders two numbers or two strings the way `<` does.
+fn compare_values(name: &str, a: &Value, b: &Value) -> Result<Ordering, InterpreterError> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y).ok_or_else(|| {
+            InterpreterError::InvalidOperation(
+                Message::CannotOrderNan.format(&[("function", &name)]),
+            )
+        }),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::CannotCompare.format(&[
+                ("function", &name),
+                ("left", &a.type_name()),
+                ("right", &b.type_name()),
+            ]),
+        )),
+    }
+}
+
+/// The values `min`, `max` and `sum` work on: a single array's elements, or
+/// the arguments themselves.
+fn operands(args: Vec<Value>) -> Vec<Value> {
+    match args.as_slice() {
+        [Value::Array(array)] => array.borrow().clone(),
+        _ => args,
+    }
+}
+
+/// The first of the smallest (`Less`) or largest (`Greater`) values.
+fn extreme(name: &str, args: Vec<Value>, wanted: Ordering) -> Result<Value, InterpreterError> {
+    let mut values = operands(args).into_iter();
+    let mut best = values.next().ok_or_else(|| {
+        InterpreterError::InvalidOperation(Message::ExpectsValues.format(&[("function", &name)]))
+    })?;
+    for value in values {
+        if compare_values(name, &value, &best)? == wanted {
+            best = value;
+        }
+    }
+    Ok(best)
+}
+
+/// Adds up numbers, giving an Int unless one of them is a Float. An empty
+/// array sums to 0.
+fn sum(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let mut total = Number::Int(0);
+    for value in operands(args) {
+        match value {
+            Value::Number(n) => total = total.checked_add(n)?,
+            other => {
+                return Err(InterpreterError::TypeMismatch(
+                    Message::SumNumbers.format(&[("type", &other.type_name())]),
+                ));
+            }
+        }
+    }
+    Ok(Value::Number(total))
+}
+
+/// A sorted copy of an array of numbers or strings. With a comparator,
+/// `cmp(a, b)` returns a negative number to put `a` first, a positive one to
+/// put `b` first, or 0 to keep their order.
+fn sort(
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Option<Span>,
+) -> Result<Value, InterpreterError> {
+    let (array, comparator) = match args.as_slice() {
+        [Value::Array(array)] => (array, None),
+        [Value::Array(array), Value::Function(comparator)] => (array, Some(comparator)),
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::SortArguments.text().to_string(),
+            ));
+        }
+    };
+    let items = array.borrow().clone();
+    let sorted = merge_sort(items, &mut |a, b| match comparator {
+        None => compare_values("sort", a, b),
+        Some(comparator) => {
+            match call_callback(comparator, vec![a.clone(), b.clone()], env, call_site)? {
+                Value::Number(n) => n.partial_cmp(&Number::Int(0)).ok_or_else(|| {
+                    InterpreterError::InvalidOperation(Message::ComparatorNan.text().to_string())
+                }),
+                other => Err(InterpreterError::TypeMismatch(
+                    Message::ComparatorResult.format(&[("type", &other.type_name())]),
+                )),
+            }
+        }
+    })?;
+    Ok(Value::Array(Rc::new(RefCell::new(sorted))))
+}
+
+/// A stable merge sort that stops at the first error from `compare`. Unlike
+/// `sort_by`, it never panics when a comparator isn't a consistent order; the
+/// result is then just some permutation of `items`.
+fn merge_sort(
+    mut items: Vec<Value>,
+    compare: &mut impl FnMut(&Value, &Value) -> Result<Ordering, InterpreterError>,
+) -> Result<Vec<Value>, InterpreterError> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, compare)?;
+    let right = merge_sort(right, compare)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if compare(a, b)? == Ordering::Greater {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// A reversed copy of an array, or a string with its characters reversed.
+fn reverse(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    match args.first() {
+        Some(Value::Array(array)) => {
+            let reversed = array.borrow().iter().rev().cloned().collect();
+            Ok(Value::Array(Rc::new(RefCell::new(reversed))))
+        }
+        Some(Value::String(s)) => Ok(Value::String(s.chars().rev().collect())),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ReverseArgument.text().to_string(),
+        )),
+    }
+}
+
+/// The array and function a higher-order builtin like `map(array, f)` takes,
+/// with any arguments after them.
+fn array_and_function<'a>(
+    name: &str,
+    args: &'a [Value],
+) -> Result<(Vec<Value>, &'a Function, &'a [Value]), InterpreterError> {
+    match args {
+        [Value::Array(array), Value::Function(function), rest @ ..] => {
+            Ok((array.borrow().clone(), function, rest))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ExpectsArrayAndFunction.format(&[("function", &name)]),
+        )),
+    }
+}
+
+fn map(
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Option<Span>,
+) -> Result<Value, InterpreterError> {
+    let (items, function, _) = array_and_function("map", &args)?;
+    let mapped = items
+        .into_iter()
+        .map(|item| call_callback(function, vec![item], env, call_site))
+        .collect::<Result<_, _>>()?;
+    Ok(Value::Array(Rc::new(RefCell::new(mapped))))
+}
+
+fn filter(
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Option<Span>,
+) -> Result<Value, InterpreterError> {
+    let (items, predicate, _) = array_and_function("filter", &args)?;
+    let mut kept = Vec::new();
+    for item in items {
+        match call_callback(predicate, vec![item.clone()], env, call_site)? {
+            Value::Boolean(true) => kept.push(item),
+            Value::Boolean(false) => {}
+            other => {
+                return Err(InterpreterError::TypeMismatch(
+                    Message::PredicateResult.format(&[("type", &other.type_name())]),
+                ));
+            }
+        }
+    }
+    Ok(Value::Array(Rc::new(RefCell::new(kept))))
+}
+
+/// Folds the array into one value with `f(accumulator, item)`, starting from
+/// `init`, or from the first element when there is no `init`.
+fn reduce(
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Option<Span>,
+) -> Result<Value, InterpreterError> {
+    let (items, function, rest) = array_and_function("reduce", &args)?;
+    let mut items = items.into_iter();
+    let init = match rest {
+        [init] => init.clone(),
+        [] => items.next().ok_or_else(|| {
+            InterpreterError::InvalidOperation(Message::ReduceEmpty.text().to_string())
+        })?,
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::ReduceArguments.text().to_string(),
+            ));
+        }
+    };
+    items.try_fold(init, |accumulator, item| {
+        call_callback(function, vec![accumulator, item], env, call_site)
+    })
+}
+
+/// Splits a string at each `sep`, or at runs of whitespace without one. An
+/// empty `sep` splits it into characters. Charged before the parts are copied.
+fn split(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let parts: Vec<&str> = match args.as_slice() {
+        [Value::String(s)] => s.split_whitespace().collect(),
+        [Value::String(s), Value::String(sep)] if sep.is_empty() => {
+            return chars(vec![Value::String(s.clone())], env);
+        }
+        [Value::String(s), Value::String(sep)] => s.split(sep.as_str()).collect(),
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::SplitArguments.text().to_string(),
+            ));
+        }
+    };
+    let text_size: usize = parts.iter().map(|part| part.len()).sum();
+    allocate(env, array_size(parts.len()).saturating_add(text_size))?;
+    let parts = parts
+        .into_iter()
+        .map(|part| Value::String(part.to_string()))
+        .collect();
+    Ok(Value::Array(Rc::new(RefCell::new(parts))))
+}
+
+/// `join(array, sep)` puts the elements, as `str()` shows them, between
+/// copies of `sep`; `join(handle)` waits for a thread.
+fn join_array(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    match args.as_slice() {
+        [Value::Array(array), Value::String(sep)] => {
+            let texts: Vec<String> = array.borrow().iter().map(Value::to_string).collect();
+            Ok(Value::String(texts.join(sep)))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::JoinArguments.text().to_string(),
+        )),
+    }
+}
+
+/// Applies a string function, such as `trim`, to the argument.
+fn string_fn(
+    name: &str,
+    args: Vec<Value>,
+    apply: fn(&str) -> String,
+) -> Result<Value, InterpreterError> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(Value::String(apply(s))),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ExpectsString.format(&[("function", &name)]),
+        )),
+    }
+}
+
+/// Replaces every occurrence of `from` in the string with `to`.
+/// Charged for the result before it is built.
+fn replace(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    match args.as_slice() {
+        [Value::String(_), Value::String(from), Value::String(_)] if from.is_empty() => Err(
+            InterpreterError::InvalidOperation(Message::ReplaceEmpty.text().to_string()),
+        ),
+        [Value::String(s), Value::String(from), Value::String(to)] => {
+            let count = s.matches(from.as_str()).count();
+            let size =
+                (s.len() - count * from.len()).saturating_add(count.saturating_mul(to.len()));
+            allocate(env, size)?;
+            Ok(Value::String(s.replace(from.as_str(), to)))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ReplaceArguments.text().to_string(),
+        )),
+    }
+}
+
 fn len(args: Vec<Value>) -> Result<Value, InterpreterError> {
     match args.first() {
-        Some(Value::String(s)) => Ok(Value::Number(Number::Int(s.len() as i128))),
+        Some(Value::String(s)) => Ok(Value::Number(Number::Int(s.chars().count() as i128))),
         Some(Value::Array(arr)) => Ok(Value::Number(Number::Int(arr.borrow().len() as i128))),
         Some(Value::Object(obj)) => Ok(Value::Number(Number::Int(obj.len() as i128))),
+        Some(Value::Range { start, end }) => Ok(Value::Number(Number::Int((end - start).max(0)))),
         _ => Err(InterpreterError::TypeMismatch(
-            "len() expects a string, array, or object".to_string(),
+            Message::LenArgument.text().to_string(),
         )),
     }
 }
 
 fn type_of(args: Vec<Value>) -> Result<Value, InterpreterError> {
     match args.first() {
-        Some(Value::Number(n)) => Ok(Value::String(match n {
-            Number::Int(_) => "int".to_string(),
-            Number::Float(_) => "float".to_string(),
-        })),
-        Some(Value::Boolean(_)) => Ok(Value::String("boolean".to_string())),
-        Some(Value::String(_)) => Ok(Value::String("string".to_string())),
-        Some(Value::Array(_)) => Ok(Value::String("array".to_string())),
-        Some(Value::Object(_)) => Ok(Value::String("object".to_string())),
-        Some(Value::StructInstance { name, .. }) => Ok(Value::String(name.clone())),
-        Some(Value::Nil) => Ok(Value::String("nil".to_string())),
+        Some(value) => Ok(Value::String(value.type_name())),
         None => Ok(Value::String("nil".to_string())),
     }
 }
 
-fn random(args: Vec<Value>) -> Result<Value, InterpreterError> {
-    match args.as_slice() {
-        [] => Ok(Value::Number(Number::Int(rand::random()))),
-        [Value::Number(n)] => match n {
-            Number::Int(n) => Ok(Value::Number(Number::Int(rand::random_range(0..*n)))),
-            Number::Float(n) => Ok(Value::Number(Number::Float(rand::random_range(0.0..*n)))),
-        },
-        [Value::Number(n1), Value::Number(n2)] => match (n1, n2) {
-            (Number::Int(n1), Number::Int(n2)) => {
-                Ok(Value::Number(Number::Int(rand::random_range(*n1..*n2))))
-            }
-            (Number::Float(n1), Number::Float(n2)) => {
-                Ok(Value::Number(Number::Float(rand::random_range(*n1..*n2))))
-            }
-            _ => Err(InterpreterError::TypeMismatch(
-                "random() expects two integers or two floats".to_string(),
-            )),
-        },
-        _ => Err(InterpreterError::InvalidOperation(
-            "random() expects 0, 1 or 2 arguments".to_string(),
+/// Draws from the program's generator, so `seed()` makes the results
+/// repeatable.
+fn random(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    match env.borrow().state() {
+        Some(state) => draw_random(args, state.borrow_mut().rng()),
+        None => draw_random(args, &mut rand::rng()),
+    }
+}
+
+/// Draws from `low..high`, or from `0..high` given one bound. The range must
+/// hold at least one value, and a float one must have a finite width.
+fn draw_random(args: Vec<Value>, rng: &mut impl RngExt) -> Result<Value, InterpreterError> {
+    let (low, high) = match args.as_slice() {
+        [] => return Ok(Value::Number(Number::Int(rng.random()))),
+        [Value::Number(Number::Int(high))] => (Number::Int(0), Number::Int(*high)),
+        [Value::Number(Number::Float(high))] => (Number::Float(0.0), Number::Float(*high)),
+        [Value::Number(low), Value::Number(high)] => (low.clone(), high.clone()),
+        _ => {
+            return Err(InterpreterError::InvalidOperation(
+                Message::RandomArguments.text().to_string(),
+            ));
+        }
+    };
+    match (&low, &high) {
+        (Number::Int(l), Number::Int(h)) if l < h => {
+            Ok(Value::Number(Number::Int(rng.random_range(*l..*h))))
+        }
+        (Number::Float(l), Number::Float(h)) if l < h && (h - l).is_finite() => {
+            Ok(Value::Number(Number::Float(rng.random_range(*l..*h))))
+        }
+        (Number::Int(_), Number::Int(_)) | (Number::Float(_), Number::Float(_)) => {
+            Err(InterpreterError::InvalidOperation(
+                Message::EmptyRandomRange.format(&[("low", &low), ("high", &high)]),
+            ))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::RandomBounds.text().to_string(),
         )),
     }
 }
 
+fn seed(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let [Value::Number(Number::Int(seed))] = args.as_slice() else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::ExpectsInteger.format(&[("function", &"seed")]),
+        ));
+    };
+    env.borrow().set_seed(*seed as u64);
+    Ok(Value::Nil)
+}
+
 fn time() -> Result<Value, InterpreterError> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -162,24 +800,203 @@ fn time() -> Result<Value, InterpreterError> {
     Ok(Value::Number(Number::Int(now as i128)))
 }
 
+/// Charged for the array and its one-character strings before they are built.
+fn chars(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    match args.first() {
+        Some(Value::String(s)) => {
+            allocate(env, array_size(s.chars().count()).saturating_add(s.len()))?;
+            Ok(Value::Array(Rc::new(RefCell::new(
+                s.chars().map(|c| Value::String(c.to_string())).collect(),
+            ))))
+        }
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ExpectsString.format(&[("function", &"chars")]),
+        )),
+    }
+}
+
+fn ord(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    let c = match args.first() {
+        Some(Value::String(s)) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => {
+                    return Err(InterpreterError::InvalidOperation(
+                        Message::OrdArgument.format(&[("text", s)]),
+                    ));
+                }
+            }
+        }
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::ExpectsString.format(&[("function", &"ord")]),
+            ));
+        }
+    };
+    Ok(Value::Number(Number::Int(c as i128)))
+}
+
+fn chr(args: Vec<Value>) -> Result<Value, InterpreterError> {
+    match args.first() {
+        Some(Value::Number(Number::Int(n))) => u32::try_from(*n)
+            .ok()
+            .and_then(char::from_u32)
+            .map(|c| Value::String(c.to_string()))
+            .ok_or_else(|| {
+                InterpreterError::InvalidOperation(Message::ChrCodePoint.format(&[("value", n)]))
+            }),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::ExpectsInteger.format(&[("function", &"chr")]),
+        )),
+    }
+}
+
+fn help(args: Vec<Value>, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let function = match args.first() {
+        Some(Value::Function(function)) => Some(function.as_ref().clone()),
+        Some(Value::String(name)) => env.borrow().get_function_recursive(name),
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::HelpArgument.text().to_string(),
+            ));
+        }
+    };
+    match function {
+        Some(Function::User(UserFunction { doc: Some(doc), .. })) => Ok(Value::String(doc)),
+        _ => Ok(Value::Nil),
+    }
+}
+
+/// The timings recorded so far as an array of objects, most expensive first,
+/// or nil when profiling is off.
+fn profile(env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let Some(report) = env.borrow().profile() else {
+        return Ok(Value::Nil);
+    };
+    let millis = |duration: std::time::Duration| {
+        Value::Number(Number::Float(duration.as_secs_f64() * 1000.0))
+    };
+    let rows = report
+        .into_iter()
+        .map(|function| {
+            Value::Object(HashMap::from([
+                (
+                    "calls".to_string(),
+                    Value::Number(Number::Int(function.calls.into())),
+                ),
+                ("total_ms".to_string(), millis(function.total)),
+                ("self_ms".to_string(), millis(function.self_time)),
+                ("name".to_string(), Value::String(function.name)),
+            ]))
+        })
+        .collect();
+    Ok(Value::Array(Rc::new(RefCell::new(rows))))
+}
+
 impl Fun for BuiltinFunction {
     fn call(
         &self,
         args: Vec<Value>,
-        _env: &Rc<RefCell<Environment>>,
+        env: &Rc<RefCell<Environment>>,
     ) -> Result<Value, InterpreterError> {
-        match self {
-            BuiltinFunction::Print => print(args),
+        self.call_at(args, env, None)
+    }
+}
+
+impl BuiltinFunction {
+    /// Runs the builtin for a call written at `call_site`, if there is one.
+    /// Functions it is handed, like a `sort` comparator, are called from there.
+    pub(crate) fn call_at(
+        &self,
+        args: Vec<Value>,
+        env: &Rc<RefCell<Environment>>,
+        call_site: Option<Span>,
+    ) -> Result<Value, InterpreterError> {
+        let result = match self {
+            BuiltinFunction::Print => print(&args, " ", Stream::Stdout, false),
+            BuiltinFunction::Println => print(&args, " ", Stream::Stdout, true),
+            BuiltinFunction::Eprint => print(&args, " ", Stream::Stderr, false),
+            BuiltinFunction::Eprintln => print(&args, " ", Stream::Stderr, true),
+            BuiltinFunction::PrintSep => print_sep("print_sep", args, Stream::Stdout, false),
+            BuiltinFunction::PrintlnSep => print_sep("println_sep", args, Stream::Stdout, true),
+            BuiltinFunction::EprintSep => print_sep("eprint_sep", args, Stream::Stderr, false),
+            BuiltinFunction::EprintlnSep => print_sep("eprintln_sep", args, Stream::Stderr, true),
+            BuiltinFunction::Write => write(args),
             BuiltinFunction::Input => input(),
             BuiltinFunction::Push => push(args),
             BuiltinFunction::Pop => pop(args),
             BuiltinFunction::Int => int(args),
             BuiltinFunction::Float => float(args),
             BuiltinFunction::String => string(args),
+            BuiltinFunction::Format => format(args, env),
+            BuiltinFunction::Sqrt => sqrt(args),
+            BuiltinFunction::Abs => abs(args),
+            BuiltinFunction::Floor => to_whole("floor", args, f64::floor),
+            BuiltinFunction::Ceil => to_whole("ceil", args, f64::ceil),
+            BuiltinFunction::Round => to_whole("round", args, f64::round),
+            BuiltinFunction::Pow => pow(args),
+            BuiltinFunction::Sin => float_fn("sin", args, f64::sin),
+            BuiltinFunction::Cos => float_fn("cos", args, f64::cos),
+            BuiltinFunction::Tan => float_fn("tan", args, f64::tan),
+            BuiltinFunction::Asin => float_fn("asin", args, f64::asin),
+            BuiltinFunction::Acos => float_fn("acos", args, f64::acos),
+            BuiltinFunction::Atan => float_fn("atan", args, f64::atan),
+            BuiltinFunction::Atan2 => atan2(args),
+            BuiltinFunction::Ln => float_fn("ln", args, f64::ln),
+            BuiltinFunction::Log10 => float_fn("log10", args, f64::log10),
+            BuiltinFunction::Log2 => float_fn("log2", args, f64::log2),
+            BuiltinFunction::Exp => float_fn("exp", args, f64::exp),
+            BuiltinFunction::Min => extreme("min", args, Ordering::Less),
+            BuiltinFunction::Max => extreme("max", args, Ordering::Greater),
+            BuiltinFunction::Sum => sum(args),
+            BuiltinFunction::Sort => sort(args, env, call_site),
+            BuiltinFunction::Reverse => reverse(args),
+            BuiltinFunction::Map => map(args, env, call_site),
+            BuiltinFunction::Filter => filter(args, env, call_site),
+            BuiltinFunction::Reduce => reduce(args, env, call_site),
+            BuiltinFunction::Split => split(args, env),
+            BuiltinFunction::Trim => string_fn("trim", args, |s| s.trim().to_string()),
+            BuiltinFunction::TrimStart => {
+                string_fn("trim_start", args, |s| s.trim_start().to_string())
+            }
+            BuiltinFunction::TrimEnd => string_fn("trim_end", args, |s| s.trim_end().to_string()),
+            BuiltinFunction::Upper => string_fn("upper", args, str::to_uppercase),
+            BuiltinFunction::Lower => string_fn("lower", args, str::to_lowercase),
+            BuiltinFunction::Replace => replace(args, env),
             BuiltinFunction::Len => len(args),
             BuiltinFunction::Type => type_of(args),
-            BuiltinFunction::Random => random(args),
+            BuiltinFunction::Random => random(args, env),
+            BuiltinFunction::Seed => seed(args, env),
             BuiltinFunction::Time => time(),
-        }
+            BuiltinFunction::Chars => chars(args, env),
+            BuiltinFunction::Ord => ord(args),
+            BuiltinFunction::Chr => chr(args),
+            BuiltinFunction::Help => help(args, env),
+            BuiltinFunction::Profile => profile(env),
+            BuiltinFunction::Spawn => spawn(args, env),
+            BuiltinFunction::Join => match args.first() {
+                Some(Value::Array(_)) => join_array(args),
+                _ => join(args),
+            },
+            BuiltinFunction::Channel => channel(),
+            BuiltinFunction::Send => send(args),
+            BuiltinFunction::Recv => recv(args, env),
+        }?;
+        // `push` and `pop` hand back values that already exist; the array
+        // `push` grew is charged for the one new element. Builtins whose
+        // result could be far larger than their arguments charge for it
+        // before building it.
+        let bytes = match self {
+            BuiltinFunction::Push => std::mem::size_of::<Value>(),
+            BuiltinFunction::Pop
+            | BuiltinFunction::Format
+            | BuiltinFunction::Replace
+            | BuiltinFunction::Split
+            | BuiltinFunction::Chars => 0,
+            _ => result.heap_size(),
+        };
+        allocate(env, bytes)?;
+        Ok(result)
     }
 }
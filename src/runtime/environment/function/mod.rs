@@ -19,7 +19,7 @@ pub trait Fun {
     ) -> Result<Value, InterpreterError>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Function {
     Builtin(BuiltinFunction),
     User(UserFunction),
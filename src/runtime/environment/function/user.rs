@@ -12,8 +12,30 @@ use crate::{
 
 #[derive(Debug, Clone)]
 pub struct UserFunction {
+    /// The declared name, for error messages; `None` for lambdas.
+    pub name: Option<String>,
     pub params: Vec<String>,
+    /// Name bound to an array of any arguments past `params`.
+    pub rest: Option<String>,
     pub body: Expr,
+    /// The scope a lambda was created in; named functions run in the caller's scope.
+    pub closure: Option<Rc<RefCell<Environment>>>,
+    /// The declaration's `///` comment, returned by `help()`.
+    pub doc: Option<String>,
+}
+
+impl PartialEq for UserFunction {
+    fn eq(&self, other: &Self) -> bool {
+        let same_closure = match (&self.closure, &other.closure) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        self.params == other.params
+            && self.rest == other.rest
+            && self.body == other.body
+            && same_closure
+    }
 }
 
 impl Fun for UserFunction {
@@ -22,11 +44,28 @@ impl Fun for UserFunction {
         args: Vec<Value>,
         parent: &Rc<RefCell<Environment>>,
     ) -> Result<Value, InterpreterError> {
+        let too_few = args.len() < self.params.len();
+        let too_many = self.rest.is_none() && args.len() > self.params.len();
+        if too_few || too_many {
+            return Err(InterpreterError::ArityMismatch {
+                name: self.name.clone().unwrap_or_else(|| "<lambda>".to_string()),
+                expected: self.params.len(),
+                found: args.len(),
+                variadic: self.rest.is_some(),
+            });
+        }
+
+        let parent = self.closure.as_ref().unwrap_or(parent);
         let env = Rc::new(RefCell::new(Environment::new_child(parent.clone())));
 
-        for (param, arg) in self.params.iter().zip(args) {
+        let mut args = args.into_iter();
+        for (param, arg) in self.params.iter().zip(args.by_ref()) {
             env.borrow_mut().define(param.to_string(), arg)?;
         }
+        if let Some(rest) = &self.rest {
+            let extra = Value::Array(Rc::new(RefCell::new(args.collect())));
+            env.borrow_mut().define(rest.to_string(), extra)?;
+        }
 
         match eval_expr(&self.body, &env) {
             Err(InterpreterError::Return(value)) => Ok(value),
@@ -37,7 +76,40 @@ impl Fun for UserFunction {
 }
 
 impl UserFunction {
-    pub fn new(params: Vec<String>, body: Expr) -> Self {
-        Self { params, body }
+    pub fn new(params: Vec<String>, rest: Option<String>, body: Expr) -> Self {
+        Self {
+            name: None,
+            params,
+            rest,
+            body,
+            closure: None,
+            doc: None,
+        }
+    }
+
+    pub fn with_closure(
+        params: Vec<String>,
+        rest: Option<String>,
+        body: Expr,
+        closure: Rc<RefCell<Environment>>,
+    ) -> Self {
+        Self {
+            name: None,
+            params,
+            rest,
+            body,
+            closure: Some(closure),
+            doc: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_doc(mut self, doc: Option<String>) -> Self {
+        self.doc = doc;
+        self
     }
 }
@@ -1,11 +1,22 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
 use crate::{
-    parser::Expr,
-    runtime::environment::{function::Function, value::EnvironmentValue, value::StructDef},
+    messages::Message,
+    runtime::debug::Debugger,
+    runtime::environment::{
+        function::Function,
+        value::{EnvironmentValue, Number, StructDef},
+    },
     runtime::error::InterpreterError,
+    runtime::module::ModuleLoader,
+    runtime::observer::Observer,
+    runtime::profile::{FunctionProfile, Profiler},
+    runtime::state::{EvalLimits, EvalState},
 };
 
 pub mod function;
@@ -14,21 +25,69 @@ pub mod value;
 pub use function::{BuiltinFunction, UserFunction};
 pub use value::Value;
 
+/// Constants every program starts with.
+const CONSTANTS: &[(&str, f64)] = &[("PI", std::f64::consts::PI), ("E", std::f64::consts::E)];
+
 /// The execution environment storing variables and functions
 #[derive(Debug, Clone)]
 pub struct Environment {
     parent: Option<Rc<RefCell<Environment>>>,
     locals: HashMap<String, EnvironmentValue>,
+    /// Set on root environments only; shared by every module of one program.
+    modules: Option<Rc<RefCell<ModuleLoader>>>,
+    /// Shared by every scope of a program and the modules it imports. Child
+    /// scopes copy their parent's, so finding it never walks the chain.
+    state: Option<Rc<RefCell<EvalState>>>,
+    /// Bindings marked with `export`; only top-level scopes have one.
+    exports: Option<Rc<RefCell<Environment>>>,
+    /// The file whose code runs in this scope, when known. Copied like `state`.
+    file: Option<Rc<Path>>,
 }
 
 impl Environment {
     pub fn new_root() -> Self {
+        Self::new_root_with_modules(Rc::new(RefCell::new(ModuleLoader::default())))
+    }
+
+    pub fn new_root_with_modules(modules: Rc<RefCell<ModuleLoader>>) -> Self {
         let mut locals = HashMap::new();
 
         locals.insert(
             "print".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Print)),
         );
+        locals.insert(
+            "println".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Println)),
+        );
+        locals.insert(
+            "eprint".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Eprint)),
+        );
+        locals.insert(
+            "eprintln".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Eprintln)),
+        );
+        locals.insert(
+            "print_sep".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::PrintSep)),
+        );
+        locals.insert(
+            "println_sep".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::PrintlnSep)),
+        );
+        locals.insert(
+            "eprint_sep".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::EprintSep)),
+        );
+        locals.insert(
+            "eprintln_sep".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::EprintlnSep)),
+        );
+        locals.insert(
+            "write".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Write)),
+        );
         locals.insert(
             "push".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Push)),
@@ -53,6 +112,138 @@ impl Environment {
             "str".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::String)),
         );
+        locals.insert(
+            "format".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Format)),
+        );
+        locals.insert(
+            "sqrt".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Sqrt)),
+        );
+        locals.insert(
+            "abs".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Abs)),
+        );
+        locals.insert(
+            "floor".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Floor)),
+        );
+        locals.insert(
+            "ceil".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Ceil)),
+        );
+        locals.insert(
+            "round".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Round)),
+        );
+        locals.insert(
+            "pow".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Pow)),
+        );
+        locals.insert(
+            "sin".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Sin)),
+        );
+        locals.insert(
+            "cos".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Cos)),
+        );
+        locals.insert(
+            "tan".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Tan)),
+        );
+        locals.insert(
+            "asin".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Asin)),
+        );
+        locals.insert(
+            "acos".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Acos)),
+        );
+        locals.insert(
+            "atan".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Atan)),
+        );
+        locals.insert(
+            "atan2".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Atan2)),
+        );
+        locals.insert(
+            "ln".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Ln)),
+        );
+        locals.insert(
+            "log10".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Log10)),
+        );
+        locals.insert(
+            "log2".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Log2)),
+        );
+        locals.insert(
+            "exp".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Exp)),
+        );
+        locals.insert(
+            "min".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Min)),
+        );
+        locals.insert(
+            "max".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Max)),
+        );
+        locals.insert(
+            "sum".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Sum)),
+        );
+        locals.insert(
+            "sort".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Sort)),
+        );
+        locals.insert(
+            "reverse".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Reverse)),
+        );
+        locals.insert(
+            "map".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Map)),
+        );
+        locals.insert(
+            "filter".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Filter)),
+        );
+        locals.insert(
+            "reduce".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Reduce)),
+        );
+        locals.insert(
+            "split".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Split)),
+        );
+        locals.insert(
+            "trim".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Trim)),
+        );
+        locals.insert(
+            "trim_start".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::TrimStart)),
+        );
+        locals.insert(
+            "trim_end".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::TrimEnd)),
+        );
+        locals.insert(
+            "upper".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Upper)),
+        );
+        locals.insert(
+            "lower".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Lower)),
+        );
+        locals.insert(
+            "replace".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Replace)),
+        );
         locals.insert(
             "len".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Len)),
@@ -65,34 +256,381 @@ impl Environment {
             "random".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Random)),
         );
+        locals.insert(
+            "seed".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Seed)),
+        );
         locals.insert(
             "time".to_string(),
             EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Time)),
         );
+        locals.insert(
+            "chars".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Chars)),
+        );
+        locals.insert(
+            "ord".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Ord)),
+        );
+        locals.insert(
+            "chr".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Chr)),
+        );
+        locals.insert(
+            "help".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Help)),
+        );
+        locals.insert(
+            "profile".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Profile)),
+        );
+        locals.insert(
+            "spawn".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Spawn)),
+        );
+        locals.insert(
+            "join".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Join)),
+        );
+        locals.insert(
+            "channel".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Channel)),
+        );
+        locals.insert(
+            "send".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Send)),
+        );
+        locals.insert(
+            "recv".to_string(),
+            EnvironmentValue::Function(Function::Builtin(BuiltinFunction::Recv)),
+        );
         locals.insert("nil".to_string(), EnvironmentValue::Variable(Value::Nil));
+        for (name, value) in CONSTANTS {
+            locals.insert(
+                name.to_string(),
+                EnvironmentValue::Constant(Value::Number(Number::Float(*value))),
+            );
+        }
 
         Self {
             locals,
             parent: None,
+            modules: Some(modules),
+            state: Some(Rc::new(RefCell::new(EvalState::default()))),
+            exports: Some(Rc::new(RefCell::new(Self::new_detached()))),
+            file: None,
         }
     }
 
     pub fn new_child(parent: Rc<RefCell<Environment>>) -> Self {
+        let state = parent.borrow().state.clone();
+        let file = parent.borrow().file.clone();
         Self {
             locals: HashMap::new(),
             parent: Some(parent),
+            modules: None,
+            state,
+            exports: None,
+            file,
+        }
+    }
+
+    /// The top-level scope of an imported file, which collects its own exports.
+    pub fn new_module(parent: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            exports: Some(Rc::new(RefCell::new(Self::new_detached()))),
+            ..Self::new_child(parent)
+        }
+    }
+
+    fn new_detached() -> Self {
+        Self {
+            locals: HashMap::new(),
+            parent: None,
+            modules: None,
+            state: None,
+            exports: None,
+            file: None,
+        }
+    }
+
+    /// The names bound directly in this scope.
+    pub fn local_names(&self) -> Vec<String> {
+        self.locals.keys().cloned().collect()
+    }
+
+    /// Whether this is the top-level scope of a program or module.
+    pub fn is_top_level(&self) -> bool {
+        self.exports.is_some()
+    }
+
+    /// A root environment that shares `state` with another program's instead
+    /// of having its own.
+    pub fn with_state(mut self, state: Rc<RefCell<EvalState>>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn state(&self) -> Option<Rc<RefCell<EvalState>>> {
+        self.state.clone()
+    }
+
+    /// A root environment for running the code of `file`.
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn file(&self) -> Option<Rc<Path>> {
+        self.file.clone()
+    }
+
+    /// The variables and constants visible from this scope, innermost first.
+    /// Functions and shadowed bindings are left out.
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.visible_bindings()
+            .into_iter()
+            .filter_map(|(name, binding)| match binding {
+                EnvironmentValue::Variable(value) | EnvironmentValue::Constant(value) => {
+                    Some((name, value))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every binding visible from this scope that the program made, innermost
+    /// first. Builtins and shadowed bindings are left out.
+    pub fn visible_bindings(&self) -> Vec<(String, EnvironmentValue)> {
+        let mut seen = HashSet::new();
+        let mut bindings = Vec::new();
+        self.collect_bindings(&mut seen, &mut bindings);
+        let mut parent = self.parent.clone();
+        while let Some(scope) = parent {
+            scope.borrow().collect_bindings(&mut seen, &mut bindings);
+            parent = scope.borrow().parent.clone();
+        }
+        bindings
+    }
+
+    fn collect_bindings(
+        &self,
+        seen: &mut HashSet<String>,
+        bindings: &mut Vec<(String, EnvironmentValue)>,
+    ) {
+        let mut locals: Vec<_> = self.locals.iter().collect();
+        locals.sort_by_key(|(name, _)| name.as_str());
+        for (name, binding) in locals {
+            // The root's `nil` binding is how the literal is spelled, not a variable.
+            let builtin =
+                self.is_predefined(name, binding) || (self.parent.is_none() && name == "nil");
+            if seen.insert(name.clone()) && !builtin {
+                bindings.push((name.clone(), binding.clone()));
+            }
+        }
+    }
+
+    /// Limits how deeply user functions may call each other, including recursion.
+    pub fn set_max_call_depth(&self, max_depth: usize) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().call_stack.set_max_depth(max_depth);
+        }
+    }
+
+    /// Bounds the work the program may do from now on; see [`EvalLimits`].
+    pub fn set_limits(&self, limits: EvalLimits) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().set_limits(limits);
+        }
+    }
+
+    /// Starts or stops recording how often each function is called and how
+    /// long it takes. Turning profiling on discards any earlier timings.
+    pub fn set_profiling(&self, enabled: bool) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().profiler = enabled.then(Profiler::default);
+        }
+    }
+
+    /// The timings recorded so far, or `None` when profiling is off.
+    pub fn profile(&self) -> Option<Vec<FunctionProfile>> {
+        let state = self.state.as_ref()?;
+        let state = state.borrow();
+        state.profiler.as_ref().map(Profiler::report)
+    }
+
+    /// Attaches `debugger`, which pauses the program before statements it
+    /// wants to stop at.
+    pub fn set_debugger(&self, debugger: Debugger) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().debugger = Some(debugger);
+        }
+    }
+
+    /// Reports what the program does to `observer` from now on.
+    pub fn add_observer(&self, observer: impl Observer + 'static) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().observers.push(Box::new(observer));
+        }
+    }
+
+    /// Lets another thread stop the program by setting `cancel`; see
+    /// [`EvalState::set_cancel_flag`].
+    pub fn set_cancel_flag(&self, cancel: Arc<AtomicBool>) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().set_cancel_flag(Some(cancel));
+        }
+    }
+
+    /// Makes `random()` deterministic; see [`EvalState::seed_rng`].
+    pub fn set_seed(&self, seed: u64) {
+        if let Some(state) = &self.state {
+            state.borrow_mut().seed_rng(seed);
+        }
+    }
+
+    pub fn modules(&self) -> Option<Rc<RefCell<ModuleLoader>>> {
+        match &self.modules {
+            Some(modules) => Some(modules.clone()),
+            None => self
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.borrow().modules()),
+        }
+    }
+
+    /// The bindings defined directly in `scope`. User functions are closed over
+    /// `scope` so they keep resolving names in the module that defined them.
+    pub fn own_bindings(scope: &Rc<RefCell<Environment>>) -> Vec<(String, EnvironmentValue)> {
+        scope
+            .borrow()
+            .locals
+            .iter()
+            .map(|(name, binding)| (name.clone(), Self::close_over(scope, binding)))
+            .collect()
+    }
+
+    fn close_over(
+        scope: &Rc<RefCell<Environment>>,
+        binding: &EnvironmentValue,
+    ) -> EnvironmentValue {
+        match binding {
+            EnvironmentValue::Function(Function::User(function)) if function.closure.is_none() => {
+                EnvironmentValue::Function(Function::User(UserFunction {
+                    closure: Some(scope.clone()),
+                    ..function.clone()
+                }))
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// Copies the binding of `name` in `scope` into its exports.
+    pub fn export(scope: &Rc<RefCell<Environment>>, name: &str) -> Result<(), InterpreterError> {
+        let Some(exports) = scope.borrow().exports.clone() else {
+            return Err(InterpreterError::InvalidOperation(
+                Message::ExportOutsideTopLevel.text().to_string(),
+            ));
+        };
+        let binding = match scope.borrow().locals.get(name) {
+            Some(binding) => Self::close_over(scope, binding),
+            None => return Err(InterpreterError::UndefinedVariable(name.to_string())),
+        };
+        exports
+            .borrow_mut()
+            .locals
+            .insert(name.to_string(), binding);
+        Ok(())
+    }
+
+    /// What `import` exposes from a module: its exports if it declared any,
+    /// otherwise every top-level binding.
+    pub fn public_bindings(module: &Rc<RefCell<Environment>>) -> Vec<(String, EnvironmentValue)> {
+        let exports = module.borrow().exports.clone();
+        match exports {
+            Some(exports) if !exports.borrow().locals.is_empty() => Self::own_bindings(&exports),
+            _ => Self::own_bindings(module),
+        }
+    }
+
+    /// Adds a binding brought in by `import`. Importing the same binding twice is
+    /// allowed; shadowing a different local binding is not.
+    pub fn define_imported(
+        &mut self,
+        name: String,
+        binding: EnvironmentValue,
+    ) -> Result<(), InterpreterError> {
+        if let Some(existing) = self.locals.get(&name)
+            && *existing != binding
+        {
+            return Err(InterpreterError::RedefinedVariable(name));
+        }
+        self.locals.insert(name, binding);
+        Ok(())
+    }
+
+    /// Binds `name` here, replacing whatever it was bound to.
+    pub(crate) fn bind(&mut self, name: String, binding: EnvironmentValue) {
+        self.locals.insert(name, binding);
+    }
+
+    /// Whether `binding` is one the root starts with, like a builtin function
+    /// or `PI`, which a program may define its own `name` in place of.
+    fn is_predefined(&self, name: &str, binding: &EnvironmentValue) -> bool {
+        match binding {
+            EnvironmentValue::Function(Function::Builtin(_)) => true,
+            EnvironmentValue::Constant(Value::Number(Number::Float(value))) => {
+                self.parent.is_none()
+                    && CONSTANTS
+                        .iter()
+                        .any(|(constant, predefined)| *constant == name && predefined == value)
+            }
+            _ => false,
         }
     }
 
+    /// Whether defining `name` here would clash with a binding the program made.
+    fn is_taken(&self, name: &str) -> bool {
+        self.locals
+            .get(name)
+            .is_some_and(|binding| !self.is_predefined(name, binding))
+    }
+
     pub fn define(&mut self, name: String, value: Value) -> Result<(), InterpreterError> {
-        if self.locals.contains_key(&name) {
+        if self.is_taken(&name) {
             return Err(InterpreterError::RedefinedVariable(name));
         }
         self.locals.insert(name, EnvironmentValue::Variable(value));
         Ok(())
     }
 
+    pub fn define_constant(&mut self, name: String, value: Value) -> Result<(), InterpreterError> {
+        if self.is_taken(&name) {
+            return Err(InterpreterError::RedefinedVariable(name));
+        }
+        self.locals.insert(name, EnvironmentValue::Constant(value));
+        Ok(())
+    }
+
+    /// Whether the nearest binding of `name` was declared with `const`.
+    pub fn is_constant(&self, name: &str) -> bool {
+        match self.locals.get(name) {
+            Some(EnvironmentValue::Constant(_)) => true,
+            Some(_) => false,
+            None => self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.borrow().is_constant(name)),
+        }
+    }
+
     pub fn assign(&mut self, name: &str, value: Value) -> Result<(), InterpreterError> {
+        if let Some(EnvironmentValue::Constant(_)) = self.locals.get(name) {
+            return Err(InterpreterError::ConstReassignment {
+                name: name.to_string(),
+                span: None,
+            });
+        }
         if self.locals.contains_key(name) {
             self.locals
                 .insert(name.to_string(), EnvironmentValue::Variable(value));
@@ -100,25 +638,20 @@ impl Environment {
         } else if let Some(parent) = &self.parent {
             parent.borrow_mut().assign(name, value)
         } else {
-            self.locals
-                .insert(name.to_string(), EnvironmentValue::Variable(value));
-            Ok(())
+            Err(InterpreterError::UndefinedVariable(name.to_string()))
         }
     }
 
     pub fn define_function(
         &mut self,
         name: String,
-        params: Vec<String>,
-        body: Expr,
+        function: UserFunction,
     ) -> Result<(), InterpreterError> {
-        if self.locals.contains_key(&name) {
+        if self.is_taken(&name) {
             return Err(InterpreterError::RedefinedVariable(name));
         }
-        self.locals.insert(
-            name,
-            EnvironmentValue::Function(Function::User(UserFunction { params, body })),
-        );
+        self.locals
+            .insert(name, EnvironmentValue::Function(Function::User(function)));
         Ok(())
     }
 
@@ -127,7 +660,7 @@ impl Environment {
         name: String,
         fields: Vec<(String, Option<Value>)>,
     ) -> Result<(), InterpreterError> {
-        if self.locals.contains_key(&name) {
+        if self.is_taken(&name) {
             return Err(InterpreterError::RedefinedVariable(name));
         }
         self.locals.insert(
@@ -149,7 +682,9 @@ impl Environment {
 
     pub fn get_value(&self, name: &str) -> Option<Value> {
         match self.locals.get(name) {
-            Some(EnvironmentValue::Variable(value)) => Some(value.clone()),
+            Some(EnvironmentValue::Variable(value) | EnvironmentValue::Constant(value)) => {
+                Some(value.clone())
+            }
             _ => self
                 .parent
                 .as_ref()
@@ -1,5 +1,6 @@
 use std::{
     cell::RefCell,
+    cmp::Ordering,
     collections::HashMap,
     fmt::{self, Display},
     ops::{Add, Div, Mul, Neg, Rem, Sub},
@@ -7,11 +8,17 @@ use std::{
     str::FromStr,
 };
 
+use serde::{Deserialize, Serialize};
+
+use crate::parser::format_params;
+use crate::runtime::concurrency::{Channel, ThreadHandle};
 use crate::runtime::environment::function::Function;
+use crate::runtime::error::InterpreterError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EnvironmentValue {
     Variable(Value),
+    Constant(Value),
     Function(Function),
     Struct(StructDef),
 }
@@ -38,7 +45,7 @@ impl fmt::Display for StructDef {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Number {
     Int(i128),
     Float(f64),
@@ -63,6 +70,104 @@ impl Number {
             Number::Float(f) => *f != 0.0,
         }
     }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(i) => *i == 0,
+            Number::Float(f) => *f == 0.0,
+        }
+    }
+
+    // The checked operations back the interpreter's operators: an Int result
+    // that doesn't fit is an error instead of a panic or a wrapped value, and so
+    // is a zero divisor, even for Floats.
+
+    pub fn checked_add(self, other: Self) -> Result<Self, InterpreterError> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => checked_int(a.checked_add(b)),
+            (a, b) => Ok(a + b),
+        }
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, InterpreterError> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => checked_int(a.checked_sub(b)),
+            (a, b) => Ok(a - b),
+        }
+    }
+
+    pub fn checked_mul(self, other: Self) -> Result<Self, InterpreterError> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => checked_int(a.checked_mul(b)),
+            (a, b) => Ok(a * b),
+        }
+    }
+
+    pub fn checked_div(self, other: Self) -> Result<Self, InterpreterError> {
+        if other.is_zero() {
+            return Err(InterpreterError::DivisionByZero);
+        }
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => checked_int(a.checked_div(b)),
+            (a, b) => Ok(a / b),
+        }
+    }
+
+    pub fn checked_rem(self, other: Self) -> Result<Self, InterpreterError> {
+        if other.is_zero() {
+            return Err(InterpreterError::DivisionByZero);
+        }
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => checked_int(a.checked_rem(b)),
+            (a, b) => Ok(a % b),
+        }
+    }
+
+    pub fn checked_neg(self) -> Result<Self, InterpreterError> {
+        match self {
+            Number::Int(i) => checked_int(i.checked_neg()),
+            Number::Float(f) => Ok(Number::Float(-f)),
+        }
+    }
+
+    /// `self` raised to `exponent`, an Int when both are Ints and the exponent
+    /// isn't negative.
+    pub fn checked_pow(self, exponent: Self) -> Result<Self, InterpreterError> {
+        match (self, exponent) {
+            (Number::Int(base), Number::Int(exponent)) if exponent >= 0 => {
+                match u32::try_from(exponent) {
+                    Ok(exponent) => checked_int(base.checked_pow(exponent)),
+                    // Only 0, 1 and -1 survive an exponent this large.
+                    Err(_) if base == 0 || base == 1 => Ok(Number::Int(base)),
+                    Err(_) if base == -1 => Ok(Number::Int(if exponent % 2 == 0 { 1 } else { -1 })),
+                    Err(_) => Err(InterpreterError::Overflow),
+                }
+            }
+            (base, exponent) => Ok(Number::Float(base.to_float().powf(exponent.to_float()))),
+        }
+    }
+
+    /// Division rounded toward negative infinity, always producing an Int.
+    pub fn floor_div(self, other: Self) -> Result<Self, InterpreterError> {
+        if other.is_zero() {
+            return Err(InterpreterError::DivisionByZero);
+        }
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => {
+                let quotient = a.checked_div(b).ok_or(InterpreterError::Overflow)?;
+                if a % b != 0 && (a < 0) != (b < 0) {
+                    Ok(Number::Int(quotient - 1))
+                } else {
+                    Ok(Number::Int(quotient))
+                }
+            }
+            (a, b) => Ok(Number::Int((a.to_float() / b.to_float()).floor() as i128)),
+        }
+    }
+}
+
+fn checked_int(result: Option<i128>) -> Result<Number, InterpreterError> {
+    result.map(Number::Int).ok_or(InterpreterError::Overflow)
 }
 
 impl Display for Number {
@@ -74,32 +179,34 @@ impl Display for Number {
     }
 }
 
-impl From<Number> for i128 {
-    fn from(n: Number) -> Self {
+/// Fails for a float with a fractional part, which has no exact integer value.
+impl TryFrom<Number> for i128 {
+    type Error = Number;
+
+    fn try_from(n: Number) -> Result<Self, Self::Error> {
         match n {
-            Number::Int(i) => i,
-            _ => panic!("Cannot convert non-integer number to i128"),
+            Number::Int(i) => Ok(i),
+            Number::Float(f) if f.fract() == 0.0 => Ok(f as i128),
+            other => Err(other),
         }
     }
 }
 
 impl From<Number> for f64 {
     fn from(n: Number) -> Self {
-        match n {
-            Number::Float(f) => f,
-            _ => panic!("Cannot convert non-float number to f64"),
-        }
+        n.to_float()
     }
 }
 
+// Arithmetic between an Int and a Float promotes the Int, so the result is a Float.
+
 impl Add for Number {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => Number::Int(i1 + i2),
-            (Number::Float(f1), Number::Float(f2)) => Number::Float(f1 + f2),
-            _ => panic!("Cannot add non-numeric values"),
+            (a, b) => Number::Float(a.to_float() + b.to_float()),
         }
     }
 }
@@ -109,8 +216,7 @@ impl Sub for Number {
     fn sub(self, other: Self) -> Self {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => Number::Int(i1 - i2),
-            (Number::Float(f1), Number::Float(f2)) => Number::Float(f1 - f2),
-            _ => panic!("Cannot subtract non-numeric values"),
+            (a, b) => Number::Float(a.to_float() - b.to_float()),
         }
     }
 }
@@ -120,8 +226,7 @@ impl Mul for Number {
     fn mul(self, other: Self) -> Self {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => Number::Int(i1 * i2),
-            (Number::Float(f1), Number::Float(f2)) => Number::Float(f1 * f2),
-            _ => panic!("Cannot multiply non-numeric values"),
+            (a, b) => Number::Float(a.to_float() * b.to_float()),
         }
     }
 }
@@ -131,8 +236,7 @@ impl Div for Number {
     fn div(self, other: Self) -> Self {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => Number::Int(i1 / i2),
-            (Number::Float(f1), Number::Float(f2)) => Number::Float(f1 / f2),
-            _ => panic!("Cannot divide non-numeric values"),
+            (a, b) => Number::Float(a.to_float() / b.to_float()),
         }
     }
 }
@@ -142,18 +246,17 @@ impl Rem for Number {
     fn rem(self, other: Self) -> Self {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => Number::Int(i1 % i2),
-            (Number::Float(f1), Number::Float(f2)) => Number::Float(f1 % f2),
-            _ => panic!("Cannot calculate remainder of non-numeric values"),
+            (a, b) => Number::Float(a.to_float() % b.to_float()),
         }
     }
 }
 
+/// Orders mixed Ints and Floats by value. Only NaN is unordered.
 impl PartialOrd for Number {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Number::Int(i1), Number::Int(i2)) => i1.partial_cmp(i2),
-            (Number::Float(f1), Number::Float(f2)) => f1.partial_cmp(f2),
-            _ => None,
+            (a, b) => a.to_float().partial_cmp(&b.to_float()),
         }
     }
 }
@@ -206,9 +309,96 @@ pub enum Value {
         name: String,
         fields: HashMap<String, Value>,
     },
+    Range {
+        start: i128,
+        end: i128,
+    },
+    Function(Rc<Function>),
+    /// A handle from `spawn()`.
+    Thread(Rc<ThreadHandle>),
+    Channel(Channel),
     Nil,
 }
 
+impl Value {
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Number(Number::Int(_)) => "int".to_string(),
+            Value::Number(Number::Float(_)) => "float".to_string(),
+            Value::Boolean(_) => "bool".to_string(),
+            Value::String(_) => "string".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Object(_) => "object".to_string(),
+            Value::StructInstance { name, .. } => name.clone(),
+            Value::Range { .. } => "range".to_string(),
+            Value::Function(_) => "function".to_string(),
+            Value::Thread(_) => "thread".to_string(),
+            Value::Channel(_) => "channel".to_string(),
+            Value::Nil => "nil".to_string(),
+        }
+    }
+
+    /// Equality as `==` sees it: numbers compare by value (`1 == 1.0`), arrays,
+    /// objects and struct instances compare their contents, and functions are
+    /// equal only to themselves. Values of different types are never equal.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b) == Some(Ordering::Equal),
+            (Value::Array(a), Value::Array(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b))
+            }
+            (Value::Object(a), Value::Object(b)) => fields_equal(a, b),
+            (
+                Value::StructInstance { name, fields },
+                Value::StructInstance {
+                    name: other_name,
+                    fields: other_fields,
+                },
+            ) => name == other_name && fields_equal(fields, other_fields),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (a, b) => a == b,
+        }
+    }
+
+    /// Roughly how many heap bytes the value holds itself. Values inside an
+    /// array or object aren't included; they were counted when created.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::Array(items) => items.borrow().len() * std::mem::size_of::<Value>(),
+            Value::Object(fields) | Value::StructInstance { fields, .. } => fields
+                .keys()
+                .map(|key| key.len() + std::mem::size_of::<(String, Value)>())
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    pub fn to_bool(&self) -> bool {
+        match self {
+            Value::Number(n) => n.to_bool(),
+            Value::Boolean(b) => *b,
+            Value::String(s) => !s.is_empty(),
+            Value::Array(arr) => !arr.borrow().is_empty(),
+            Value::Object(obj) => !obj.is_empty(),
+            Value::StructInstance { .. } => true,
+            Value::Range { start, end } => start < end,
+            Value::Function(_) | Value::Thread(_) | Value::Channel(_) => true,
+            Value::Nil => false,
+        }
+    }
+}
+
+fn fields_equal(a: &HashMap<String, Value>, b: &HashMap<String, Value>) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, value)| b.get(key).is_some_and(|other| value.equals(other)))
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -249,6 +439,17 @@ impl fmt::Display for Value {
                 }
                 write!(f, " }}")
             }
+            Value::Range { start, end } => write!(f, "{start}..{end}"),
+            Value::Function(function) => match function.as_ref() {
+                Function::User(function) => write!(
+                    f,
+                    "<fn({})>",
+                    format_params(&function.params, function.rest.as_deref())
+                ),
+                Function::Builtin(_) => write!(f, "<builtin fn>"),
+            },
+            Value::Thread(_) => write!(f, "<thread>"),
+            Value::Channel(_) => write!(f, "<channel>"),
             Value::Nil => write!(f, "nil"),
         }
     }
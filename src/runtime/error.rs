@@ -1,7 +1,11 @@
 use std::{error::Error, fmt};
 
+use crate::diagnostic::{Diagnostic, Severity};
 use crate::lexer::Span;
+use crate::messages::{Message, arity_mismatch};
+use crate::runtime::call_stack::Frame;
 use crate::runtime::environment::value::Value;
+use crate::runtime::state::Limit;
 
 impl Error for InterpreterError {}
 
@@ -9,39 +13,229 @@ impl Error for InterpreterError {}
 pub enum InterpreterError {
     UndefinedVariable(String),
     RedefinedVariable(String),
+    ConstReassignment {
+        name: String,
+        span: Option<Span>,
+    },
     InvalidOperation(String),
+    /// A user function called with the wrong number of arguments. `variadic`
+    /// functions take `expected` or more.
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        variadic: bool,
+    },
+    /// More nested user function calls than the call depth limit, or the Rust
+    /// stack, allows.
+    StackOverflow(usize),
+    /// The program ran past one of its [`EvalLimits`](crate::runtime::state::EvalLimits).
+    LimitExceeded(Limit),
+    /// The host set the program's cancel flag while it was running.
+    Interrupted,
+    /// Expressions and calls nested deeper than the Rust stack can follow, at
+    /// the depth reached.
+    TooDeeplyNested(usize),
+    /// `/`, `~/` or `%` with a zero divisor.
+    DivisionByZero,
+    /// An Int result that doesn't fit in 128 bits.
+    Overflow,
     TypeMismatch(String),
     UnsupportedExpression(String),
+    ImportError(String),
     Return(Value),
-    Break,
+    Break(Value),
     Continue,
     WithSpan {
         error: Box<InterpreterError>,
         span: Span,
     },
+    /// An error raised inside user function calls, with the calls that were in
+    /// progress, innermost first.
+    WithTrace {
+        error: Box<InterpreterError>,
+        trace: Vec<Frame>,
+    },
+}
+
+/// One line per call, with recursion that repeats a frame many times shown
+/// once with a count.
+fn trace_notes(trace: &[Frame]) -> Vec<String> {
+    let mut notes = Vec::new();
+    let mut frames = trace.iter().peekable();
+    while let Some(frame) = frames.next() {
+        let mut repeats = 0;
+        while frames.next_if_eq(&frame).is_some() {
+            repeats += 1;
+        }
+        let mut note =
+            Message::CalledAt.format(&[("function", &frame.function), ("span", &frame.call_site)]);
+        if repeats > 0 {
+            note.push_str(&Message::MoreTimes.format(&[("count", &repeats)]));
+        }
+        notes.push(note);
+    }
+    notes
+}
+
+impl From<&InterpreterError> for Diagnostic {
+    fn from(error: &InterpreterError) -> Self {
+        // The span is the diagnostic's own, so don't repeat it in the message.
+        let message = match error.inner() {
+            InterpreterError::ConstReassignment { name, .. } => {
+                Message::ConstReassignment.format(&[("name", name)])
+            }
+            inner => inner.to_string(),
+        };
+        Diagnostic::error(error.span(), message)
+            .with_code(error.code())
+            .with_notes(error.trace().map(trace_notes).unwrap_or_default())
+    }
 }
 
 impl fmt::Display for InterpreterError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            InterpreterError::UndefinedVariable(name) => write!(f, "Undefined variable: {name}"),
-            InterpreterError::RedefinedVariable(name) => write!(f, "Redefined variable: {name}"),
-            InterpreterError::InvalidOperation(op) => write!(f, "Invalid operation: {op}"),
-            InterpreterError::TypeMismatch(message) => write!(f, "Type mismatch: {message}"),
+        let text = match self {
+            InterpreterError::UndefinedVariable(name) => {
+                Message::UndefinedVariable.format(&[("name", name)])
+            }
+            InterpreterError::RedefinedVariable(name) => {
+                Message::RedefinedVariable.format(&[("name", name)])
+            }
+            InterpreterError::ConstReassignment { name, span } => match span {
+                Some(span) => {
+                    Message::ConstReassignmentAt.format(&[("name", name), ("span", span)])
+                }
+                None => Message::ConstReassignment.format(&[("name", name)]),
+            },
+            InterpreterError::InvalidOperation(op) => {
+                Message::InvalidOperation.format(&[("detail", op)])
+            }
+            InterpreterError::ArityMismatch {
+                name,
+                expected,
+                found,
+                variadic,
+            } => arity_mismatch(name, *expected, *found, *variadic),
+            InterpreterError::StackOverflow(limit) => {
+                Message::StackOverflow.format(&[("limit", limit)])
+            }
+            InterpreterError::LimitExceeded(limit) => {
+                Message::LimitExceeded.format(&[("limit", limit)])
+            }
+            InterpreterError::Interrupted => Message::Interrupted.text().to_string(),
+            InterpreterError::TooDeeplyNested(limit) => {
+                Message::TooDeeplyNested.format(&[("limit", limit)])
+            }
+            InterpreterError::DivisionByZero => Message::DivisionByZero.text().to_string(),
+            InterpreterError::Overflow => Message::Overflow.text().to_string(),
+            InterpreterError::TypeMismatch(message) => {
+                Message::TypeMismatch.format(&[("detail", message)])
+            }
+            InterpreterError::ImportError(message) => {
+                Message::ImportError.format(&[("detail", message)])
+            }
             InterpreterError::UnsupportedExpression(expression) => {
-                write!(f, "Unsupported expression: {expression}")
+                Message::UnsupportedExpression.format(&[("detail", expression)])
             }
-            InterpreterError::Return(value) => write!(f, "Function return value: {value}"),
-            InterpreterError::Break => write!(f, "Break statement"),
-            InterpreterError::Continue => write!(f, "Continue statement"),
-            InterpreterError::WithSpan { error, span } => {
-                write!(f, "Error at {}: {}", span, error)
+            InterpreterError::Return(value) => Message::ReturnValue.format(&[("value", value)]),
+            InterpreterError::Break(_) => Message::BreakStatement.text().to_string(),
+            InterpreterError::Continue => Message::ContinueStatement.text().to_string(),
+            InterpreterError::WithSpan { error, span } => Message::Header.format(&[
+                ("severity", &Severity::Error),
+                ("span", span),
+                ("message", error),
+            ]),
+            InterpreterError::WithTrace { error, trace } => {
+                write!(f, "{error}")?;
+                for note in trace_notes(trace) {
+                    write!(f, "\n  {note}")?;
+                }
+                return Ok(());
             }
-        }
+        };
+        write!(f, "{text}")
     }
 }
 
 impl InterpreterError {
+    /// The error itself, without the span or stack trace wrapped around it.
+    pub fn inner(&self) -> &InterpreterError {
+        match self {
+            InterpreterError::WithSpan { error, .. }
+            | InterpreterError::WithTrace { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+
+    /// Where the error happened, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            InterpreterError::WithSpan { error, span } => error.span().or(Some(*span)),
+            InterpreterError::WithTrace { error, .. } => error.span(),
+            InterpreterError::ConstReassignment { span, .. } => *span,
+            _ => None,
+        }
+    }
+
+    /// Attaches `span` unless the error already has one. `return`, `break` and
+    /// `continue` aren't errors and are left alone.
+    pub fn or_span(self, span: Span) -> Self {
+        match self {
+            InterpreterError::Return(_)
+            | InterpreterError::Break(_)
+            | InterpreterError::Continue
+            | InterpreterError::WithTrace { .. } => self,
+            _ if self.span().is_some() => self,
+            _ => self.with_span(span),
+        }
+    }
+
+    /// A stable identifier for this kind of error, for tools to match on.
+    /// Errors wrapped with a span or stack trace have the code of the error
+    /// inside.
+    pub fn code(&self) -> &'static str {
+        match self.inner() {
+            InterpreterError::UndefinedVariable(_) => "E0001",
+            InterpreterError::RedefinedVariable(_) => "E0002",
+            InterpreterError::ConstReassignment { .. } => "E0003",
+            InterpreterError::InvalidOperation(_) => "E0004",
+            InterpreterError::ArityMismatch { .. } => "E0005",
+            InterpreterError::StackOverflow(_) => "E0006",
+            InterpreterError::LimitExceeded(_) => "E0007",
+            InterpreterError::Interrupted => "E0008",
+            InterpreterError::TooDeeplyNested(_) => "E0009",
+            InterpreterError::DivisionByZero => "E0010",
+            InterpreterError::Overflow => "E0011",
+            InterpreterError::TypeMismatch(_) => "E0012",
+            InterpreterError::UnsupportedExpression(_) => "E0013",
+            InterpreterError::ImportError(_) => "E0014",
+            // `return`, `break` or `continue` with nothing to return from or
+            // loop to leave.
+            InterpreterError::Return(_)
+            | InterpreterError::Break(_)
+            | InterpreterError::Continue => "E0015",
+            InterpreterError::WithSpan { .. } | InterpreterError::WithTrace { .. } => {
+                unreachable!("inner() unwraps spans and traces")
+            }
+        }
+    }
+
+    /// The calls in progress when the error happened, innermost first.
+    pub fn trace(&self) -> Option<&[Frame]> {
+        match self {
+            InterpreterError::WithTrace { trace, .. } => Some(trace),
+            InterpreterError::WithSpan { error, .. } => error.trace(),
+            _ => None,
+        }
+    }
+
+    /// The error followed by the source line it happened on, with a caret
+    /// under the column.
+    pub fn render(&self, source: &str) -> String {
+        Diagnostic::from(self).render(source)
+    }
+
     pub fn with_span(self, span: Span) -> Self {
         InterpreterError::WithSpan {
             error: Box::new(self),
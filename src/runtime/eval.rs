@@ -1,13 +1,25 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::{
-    lexer::TokenKind,
-    parser::{Expr, ExprKind, Stmt, StmtKind},
+    lexer::{Span, TokenKind},
+    messages::Message,
+    parser::{
+        BindingPattern, Expr, ExprKind, InterpolationPart, ObjectKey, Pattern, Stmt, StmtKind,
+    },
     runtime::{
-        environment::{Environment, function::Fun, value::Value},
+        call_stack::Frame,
+        environment::{
+            Environment, UserFunction,
+            function::{Fun, Function},
+            value::{EnvironmentValue, Number, Value},
+        },
         error::InterpreterError,
+        module,
+        observer::Observer,
+        stack,
+        state::EvalState,
     },
 };
 
@@ -20,6 +32,21 @@ pub fn eval(ast: Vec<Stmt>) -> Result<Value, InterpreterError> {
 pub fn eval_with_env(
     ast: Vec<Stmt>,
     env: &Rc<RefCell<Environment>>,
+) -> Result<Value, InterpreterError> {
+    let result = eval_program(ast, env);
+    if let Err(error) = &result
+        && !matches!(error, InterpreterError::Return(_))
+    {
+        notify(env, |observer| observer.on_error(error));
+    }
+    result
+}
+
+/// Evaluates a program or module without reporting errors to observers, which
+/// hear about an imported module's error when it stops the importing program.
+pub(crate) fn eval_program(
+    ast: Vec<Stmt>,
+    env: &Rc<RefCell<Environment>>,
 ) -> Result<Value, InterpreterError> {
     let mut result = Value::Nil;
 
@@ -31,19 +58,63 @@ pub fn eval_with_env(
 }
 
 pub fn eval_stmt(stmt: &Stmt, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    step(env)?;
+    pause_for_debugger(stmt, env)?;
+    notify(env, |observer| {
+        observer.on_statement(env.borrow().file().as_deref(), stmt.span)
+    });
     match &stmt.kind {
         StmtKind::Expr(expr) => {
             eval_expr(expr, env)?;
             Ok(Value::Nil)
         }
-        StmtKind::Let { name, value, .. } => {
+        StmtKind::Let {
+            name,
+            name_span,
+            value,
+            constant,
+        } => {
+            let value = match value {
+                Some(value) => eval_expr(value, env)?,
+                None => Value::Nil,
+            };
+            let defined = if *constant {
+                env.borrow_mut().define_constant(name.clone(), value)
+            } else {
+                env.borrow_mut().define(name.clone(), value)
+            };
+            defined.map_err(|error| error.with_span(*name_span))?;
+            Ok(Value::Nil)
+        }
+        StmtKind::LetPattern { pattern, value } => {
             let value = eval_expr(value, env)?;
-            env.borrow_mut().define(name.clone(), value)?;
+            let pattern_span = stmt.kind.span_hint().unwrap_or(stmt.span);
+            let bindings =
+                destructure(pattern, value).map_err(|error| error.or_span(pattern_span))?;
+            for ((name, span), value) in pattern.names().iter().zip(bindings) {
+                env.borrow_mut()
+                    .define(name.clone(), value)
+                    .map_err(|error| error.with_span(*span))?;
+            }
             Ok(Value::Nil)
         }
-        StmtKind::Function { name, params, body } => {
-            env.borrow_mut()
-                .define_function(name.clone(), params.clone(), body.clone())?;
+        StmtKind::Function {
+            name,
+            params,
+            rest,
+            body,
+            doc,
+        } => {
+            // Nested declarations capture their scope; top-level ones resolve names
+            // where they are called, as before.
+            let function = if env.borrow().is_top_level() {
+                UserFunction::new(params.clone(), rest.clone(), body.clone())
+            } else {
+                UserFunction::with_closure(params.clone(), rest.clone(), body.clone(), env.clone())
+            }
+            .with_name(name.clone())
+            .with_doc(doc.clone());
+            env.borrow_mut().define_function(name.clone(), function)?;
             Ok(Value::Nil)
         }
         StmtKind::Struct { name, fields } => {
@@ -59,7 +130,49 @@ pub fn eval_stmt(stmt: &Stmt, env: &Rc<RefCell<Environment>>) -> Result<Value, I
                 .define_struct(name.clone(), evaluated_fields)?;
             Ok(Value::Nil)
         }
-        StmtKind::Break => Err(InterpreterError::Break),
+        StmtKind::Import { path, alias } => {
+            let Some(modules) = env.borrow().modules() else {
+                return Err(InterpreterError::ImportError(
+                    Message::ImportsUnavailable.text().to_string(),
+                ));
+            };
+            let state = env.borrow().state();
+            let module = module::load(&modules, path, state)?;
+            let bindings = Environment::public_bindings(&module);
+            match alias {
+                Some(alias) => {
+                    let mut namespace = HashMap::new();
+                    for (name, binding) in bindings {
+                        let value = match binding {
+                            EnvironmentValue::Variable(value)
+                            | EnvironmentValue::Constant(value) => value,
+                            EnvironmentValue::Function(function) => {
+                                Value::Function(Rc::new(function))
+                            }
+                            EnvironmentValue::Struct(_) => continue,
+                        };
+                        namespace.insert(name, value);
+                    }
+                    env.borrow_mut()
+                        .define(alias.clone(), Value::Object(namespace))?;
+                }
+                None => {
+                    for (name, binding) in bindings {
+                        env.borrow_mut().define_imported(name, binding)?;
+                    }
+                }
+            }
+            Ok(Value::Nil)
+        }
+        StmtKind::Export(declaration) => {
+            eval_stmt(declaration, env)?;
+            for name in declaration.kind.declared_names() {
+                Environment::export(env, name)?;
+            }
+            Ok(Value::Nil)
+        }
+        StmtKind::Break(Some(expr)) => Err(InterpreterError::Break(eval_expr(expr, env)?)),
+        StmtKind::Break(None) => Err(InterpreterError::Break(Value::Nil)),
         StmtKind::Continue => Err(InterpreterError::Continue),
         StmtKind::Result(expr) => eval_expr(expr, env),
         StmtKind::Return(Some(expr)) => Err(InterpreterError::Return(eval_expr(expr, env)?)),
@@ -67,140 +180,131 @@ pub fn eval_stmt(stmt: &Stmt, env: &Rc<RefCell<Environment>>) -> Result<Value, I
     }
 }
 
+thread_local! {
+    /// How deeply `eval_expr` is recursing, function calls included.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Evaluates `expr`. An error that doesn't know where it happened yet is
+/// tagged with the span of the innermost expression that failed. Once the
+/// Rust stack runs low it gives up instead of overflowing it.
 pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
+    let _entered = stack::enter();
+    let depth = NESTING.get();
+    if !stack::has_room_for_expression() {
+        return Err(InterpreterError::TooDeeplyNested(depth).with_span(expr.span));
+    }
+    NESTING.set(depth + 1);
+    let result = eval_expr_kind(expr, env).map_err(|error| error.or_span(expr.span));
+    NESTING.set(depth);
+    result
+}
+
+fn eval_expr_kind(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, InterpreterError> {
     match &expr.kind {
         ExprKind::Number(n) => Ok(Value::Number(n.clone())),
         ExprKind::Boolean(b) => Ok(Value::Boolean(*b)),
         ExprKind::String(s) => Ok(Value::String(s.clone())),
+        ExprKind::Interpolation(parts) => {
+            let mut result = String::new();
+            for part in parts {
+                match part {
+                    InterpolationPart::Literal(s) => result.push_str(s),
+                    InterpolationPart::Expr(expr) => {
+                        result.push_str(&eval_expr(expr, env)?.to_string())
+                    }
+                }
+            }
+            allocate(env, result.len())?;
+            Ok(Value::String(result))
+        }
         ExprKind::Parenthesized(expr) => eval_expr(expr, env),
-        ExprKind::Variable(name) => match env.borrow().get_value(name.as_str()) {
-            Some(value) => Ok(value),
-            None => Err(InterpreterError::UndefinedVariable(name.clone())),
-        },
+        ExprKind::Variable(name) => {
+            let env = env.borrow();
+            match env.get_value(name.as_str()) {
+                Some(value) => Ok(value),
+                None => match env.get_function_recursive(name.as_str()) {
+                    Some(function) => Ok(Value::Function(Rc::new(function))),
+                    None => Err(InterpreterError::UndefinedVariable(name.clone())),
+                },
+            }
+        }
+        ExprKind::Lambda { params, rest, body } => {
+            let function = UserFunction::with_closure(
+                params.clone(),
+                rest.clone(),
+                (**body).clone(),
+                env.clone(),
+            );
+            Ok(Value::Function(Rc::new(Function::User(function))))
+        }
+        ExprKind::TypeTest { expr, type_name } => {
+            let value = eval_expr(expr, env)?;
+            Ok(Value::Boolean(value_is(&value, type_name, env)?))
+        }
+        ExprKind::Cast { expr, type_name } => cast(eval_expr(expr, env)?, type_name, env),
+        ExprKind::Assign { target, value } => {
+            let value = eval_expr(value, env)?;
+            assign_to(target, value.clone(), env)?;
+            Ok(value)
+        }
         ExprKind::BinaryOp { left, op, right } => {
-            if let TokenKind::Assign = op {
-                if let ExprKind::Variable(name) = &left.as_ref().kind {
-                    let right_value = eval_expr(right, env)?;
-                    env.borrow_mut()
-                        .assign(name.as_str(), right_value.clone())?;
-                    return Ok(right_value);
-                } else if let ExprKind::Index { object, index } = &left.as_ref().kind {
-                    let obj_value = eval_expr(object, env)?;
-                    let index_value = eval_expr(index, env)?;
-                    let right_value = eval_expr(right, env)?;
-
-                    return match (obj_value, index_value) {
-                        (Value::Array(arr), Value::Number(num)) => {
-                            let idx = num.to_int() as usize;
-                            let mut arr_mut = arr.borrow_mut();
-                            if idx < arr_mut.len() {
-                                arr_mut[idx] = right_value.clone();
-                                Ok(right_value)
-                            } else {
-                                Err(InterpreterError::InvalidOperation(format!(
-                                    "Array index out of bounds: {} (length: {})",
-                                    idx,
-                                    arr_mut.len()
-                                )))
-                            }
-                        }
-                        (Value::String(s), Value::Number(num)) => {
-                            let idx = num.to_int() as isize;
-                            let len = s.len() as isize;
-                            let actual_idx = if idx < 0 { len + idx } else { idx };
-                            if actual_idx >= 0 && actual_idx < len {
-                                if let ExprKind::Variable(var_name) = &object.as_ref().kind {
-                                    let mut new_chars: Vec<char> = s.chars().collect();
-                                    let new_char = right_value.to_string();
-                                    if let Some(c) = new_char.chars().next() {
-                                        new_chars[actual_idx as usize] = c;
-                                        let new_string: String = new_chars.into_iter().collect();
-                                        let new_value = Value::String(new_string);
-                                        env.borrow_mut()
-                                            .assign(var_name.as_str(), new_value.clone())?;
-                                        Ok(right_value)
-                                    } else {
-                                        Err(InterpreterError::InvalidOperation(
-                                            "Cannot assign empty value to string index".to_string(),
-                                        ))
-                                    }
-                                } else {
-                                    Err(InterpreterError::InvalidOperation(
-                                        "Cannot assign to string index directly, use variable"
-                                            .to_string(),
-                                    ))
-                                }
-                            } else {
-                                Err(InterpreterError::InvalidOperation(format!(
-                                    "String index out of bounds: {} (length: {})",
-                                    idx, len
-                                )))
-                            }
-                        }
-                        _ => Err(InterpreterError::TypeMismatch(
-                            "Index assignment requires array or string".to_string(),
-                        )),
-                    };
-                } else {
-                    return Err(InterpreterError::InvalidOperation(
-                        "Invalid assignment target".to_string(),
-                    ));
-                }
+            if let TokenKind::LogicalAnd | TokenKind::LogicalOr = op {
+                let left_value = eval_expr(left, env)?.to_bool();
+                return match (op, left_value) {
+                    (TokenKind::LogicalAnd, false) => Ok(Value::Boolean(false)),
+                    (TokenKind::LogicalOr, true) => Ok(Value::Boolean(true)),
+                    _ => Ok(Value::Boolean(eval_expr(right, env)?.to_bool())),
+                };
             }
 
-            let left_value = eval_expr(left, env)?;
-            let right_value = eval_expr(right, env)?;
-
-            match (left_value, right_value) {
-                (Value::Number(l), Value::Number(r)) => match op {
-                    TokenKind::Plus => Ok(Value::Number(l + r)),
-                    TokenKind::Minus => Ok(Value::Number(l - r)),
-                    TokenKind::Multiply => Ok(Value::Number(l * r)),
-                    TokenKind::Divide => Ok(Value::Number(l / r)),
-                    TokenKind::Modulo => Ok(Value::Number(l % r)),
-                    TokenKind::GreaterThan => Ok(Value::Boolean(l > r)),
-                    TokenKind::GreaterThanOrEqual => Ok(Value::Boolean(l >= r)),
-                    TokenKind::LessThan => Ok(Value::Boolean(l < r)),
-                    TokenKind::LessThanOrEqual => Ok(Value::Boolean(l <= r)),
-                    TokenKind::Equal => Ok(Value::Boolean(l == r)),
-                    TokenKind::NotEqual => Ok(Value::Boolean(l != r)),
-                    TokenKind::LogicalAnd => Ok(Value::Boolean(l.to_bool() && r.to_bool())),
-                    TokenKind::LogicalOr => Ok(Value::Boolean(l.to_bool() || r.to_bool())),
-                    _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
-                },
-                (Value::Boolean(l), Value::Boolean(r)) => match op {
-                    TokenKind::Equal => Ok(Value::Boolean(l == r)),
-                    TokenKind::NotEqual => Ok(Value::Boolean(l != r)),
-                    TokenKind::LogicalAnd => Ok(Value::Boolean(l && r)),
-                    TokenKind::LogicalOr => Ok(Value::Boolean(l || r)),
-                    _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
-                },
-                (Value::String(l), Value::String(r)) => match op {
-                    TokenKind::Plus => Ok(Value::String(l + &r)),
-                    TokenKind::Equal => Ok(Value::Boolean(l == r)),
-                    TokenKind::NotEqual => Ok(Value::Boolean(l != r)),
-                    TokenKind::LogicalAnd | TokenKind::LogicalOr => {
-                        let bool_l = !l.is_empty();
-                        let bool_r = !r.is_empty();
-                        match op {
-                            TokenKind::LogicalAnd => Ok(Value::Boolean(bool_l && bool_r)),
-                            TokenKind::LogicalOr => Ok(Value::Boolean(bool_l || bool_r)),
-                            _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
-                        }
-                    }
-                    _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
-                },
-                _ => Err(InterpreterError::TypeMismatch(
-                    "Invalid operands for binary operation".to_string(),
-                )),
+            if let TokenKind::NilCoalesce = op {
+                return match eval_expr(left, env)? {
+                    Value::Nil => eval_expr(right, env),
+                    value => Ok(value),
+                };
+            }
+
+            if is_ordering(op) {
+                let holds = eval_comparison_chain(left, op, right, env)?.is_some();
+                return Ok(Value::Boolean(holds));
+            }
+
+            // A long left-leaning chain such as a generated `1 + 1 + ... + 1` is
+            // folded in a loop instead of recursing once per operator.
+            let mut chain = vec![expr];
+            let mut first = left.as_ref();
+            while let ExprKind::BinaryOp { left, op, .. } = &first.kind
+                && !is_short_circuit(op)
+                && !is_ordering(op)
+            {
+                chain.push(first);
+                first = left;
             }
+            let mut value = eval_expr(first, env)?;
+            for link in chain.into_iter().rev() {
+                let ExprKind::BinaryOp { op, right, .. } = &link.kind else {
+                    unreachable!("the chain only holds binary operations");
+                };
+                let right_value = eval_expr(right, env)?;
+                // Charged up front so `"x" * 1000000000` fails before allocating.
+                allocate(env, string_result_size(op, &value, &right_value))?;
+                value =
+                    binary_op(op, value, right_value).map_err(|error| error.or_span(link.span))?;
+            }
+            Ok(value)
         }
         ExprKind::UnaryOp { op, expr } => {
             let value = eval_expr(expr, env)?;
             match (op, value) {
-                (TokenKind::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
-                (TokenKind::Not, Value::Boolean(b)) => Ok(Value::Boolean(!b)),
-                (TokenKind::Not, Value::Nil) => Ok(Value::Boolean(true)),
+                (TokenKind::Minus, Value::Number(n)) => n.checked_neg().map(Value::Number),
+                (TokenKind::Not, value) => Ok(Value::Boolean(!value.to_bool())),
+                (TokenKind::BitNot, Value::Number(Number::Int(n))) => {
+                    Ok(Value::Number(Number::Int(!n)))
+                }
+                (TokenKind::BitNot, value) => Err(InterpreterError::TypeMismatch(
+                    Message::BitwiseOperand.format(&[("type", &value.type_name())]),
+                )),
                 _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
             }
         }
@@ -230,10 +334,7 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
             })
         }
         ExprKind::FunctionCall { name, args } => {
-            let mut args_values = Vec::new();
-            for arg in args {
-                args_values.push(eval_expr(arg, env)?);
-            }
+            let args_values = eval_elements(args, env)?;
             if let Some(struct_def) = env.borrow().get_struct(name.as_str()) {
                 let mut fields = HashMap::new();
                 for (i, (field_name, default_value)) in struct_def.fields.iter().enumerate() {
@@ -251,11 +352,26 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
                     fields,
                 });
             }
-            let fn_value = match env.borrow().get_function_recursive(name.as_str()) {
-                Some(value) => value,
-                None => return Err(InterpreterError::UndefinedVariable(name.clone())),
-            };
-            fn_value.call(args_values, env)
+            let fn_value = lookup_function(name, env)?;
+            call_function(&fn_value, name, args_values, env, expr.span)
+        }
+        ExprKind::MethodCall {
+            object,
+            method,
+            args,
+        } => {
+            let receiver = eval_expr(object, env)?;
+            // `ns.f(x)` on an imported module object calls its member without a receiver.
+            if let Value::Object(members) = &receiver
+                && let Some(Value::Function(function)) = members.get(method)
+            {
+                let args_values = eval_elements(args, env)?;
+                return call_function(function, method, args_values, env, expr.span);
+            }
+            let mut args_values = vec![receiver];
+            args_values.extend(eval_elements(args, env)?);
+            let fn_value = lookup_function(method, env)?;
+            call_function(&fn_value, method, args_values, env, expr.span)
         }
         ExprKind::If {
             condition,
@@ -273,7 +389,7 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
                 }
             } else {
                 Err(InterpreterError::TypeMismatch(
-                    "If condition must be boolean".to_string(),
+                    Message::IfCondition.text().to_string(),
                 ))
             }
         }
@@ -283,53 +399,72 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
             for stmt in statements {
                 let stmt = Stmt {
                     kind: stmt.clone(),
-                    span: crate::lexer::Span { line: 0, column: 0 },
+                    span: stmt
+                        .span_hint()
+                        .unwrap_or(crate::lexer::Span { line: 0, column: 0 }),
                 };
                 result = eval_stmt(&stmt, &block_env)?;
             }
             Ok(result)
         }
-        ExprKind::While { condition, body } => {
-            let mut result = Vec::new();
-            loop {
-                let condition_value = eval_expr(condition, env)?;
-                match condition_value {
-                    Value::Boolean(false) => break,
-                    Value::Boolean(true) => {}
-                    _ => {
-                        return Err(InterpreterError::TypeMismatch(
-                            "While condition must be boolean".to_string(),
-                        ));
+        ExprKind::Loop(body) => loop {
+            step(env)?;
+            match eval_expr(body, env) {
+                Ok(_) | Err(InterpreterError::Continue) => {}
+                Err(InterpreterError::Break(value)) => break Ok(value),
+                Err(err) => break Err(err),
+            }
+        },
+        ExprKind::While { .. } | ExprKind::DoWhile { .. } | ExprKind::For { .. } => {
+            eval_loop(expr, env, false)
+        }
+        ExprKind::Collect(body) => eval_loop(body, env, true),
+        ExprKind::Match { subject, arms } => {
+            let value = eval_expr(subject, env)?;
+            for arm in arms {
+                let arm_env = Rc::new(RefCell::new(Environment::new_child(env.clone())));
+                let matched = match &arm.pattern {
+                    Pattern::Wildcard => true,
+                    Pattern::Binding(name) => {
+                        arm_env.borrow_mut().define(name.clone(), value.clone())?;
+                        true
                     }
-                }
-                let value = match eval_expr(body, env) {
-                    Ok(value) => value,
-                    Err(InterpreterError::Break) => break,
-                    Err(InterpreterError::Continue) => continue,
-                    err @ Err(_) => return err,
+                    Pattern::Literal(literal) => eval_expr(literal, env)?.equals(&value),
                 };
-                result.push(value);
-            }
-            if result.is_empty() {
-                Ok(Value::Nil)
-            } else {
-                Ok(Value::Array(Rc::new(RefCell::new(result))))
+                if matched {
+                    return eval_expr(&arm.body, &arm_env);
+                }
             }
+            Ok(Value::Nil)
         }
         ExprKind::Array(values) => {
-            let evaluated_values = values
-                .iter()
-                .map(|value| eval_expr(value, env))
-                .collect::<Result<Vec<_>, _>>()?;
-            Ok(Value::Array(Rc::new(RefCell::new(evaluated_values))))
+            let array = Value::Array(Rc::new(RefCell::new(eval_elements(values, env)?)));
+            allocate(env, array.heap_size())?;
+            Ok(array)
         }
+        ExprKind::Spread(_) => Err(InterpreterError::UnsupportedExpression(
+            Message::MisplacedSpread.text().to_string(),
+        )),
         ExprKind::Object(vec) => {
             let mut object = HashMap::new();
             for (key, value) in vec {
+                let key = match key {
+                    ObjectKey::Name(name) => name.clone(),
+                    ObjectKey::Computed(key) => match eval_expr(key, env)? {
+                        Value::String(name) => name,
+                        other => {
+                            return Err(InterpreterError::TypeMismatch(
+                                Message::ObjectKey.format(&[("type", &other.type_name())]),
+                            ));
+                        }
+                    },
+                };
                 let value = eval_expr(value, env)?;
-                object.insert(key.clone(), value);
+                object.insert(key, value);
             }
-            Ok(Value::Object(object))
+            let object = Value::Object(object);
+            allocate(env, object.heap_size())?;
+            Ok(object)
         }
         ExprKind::Index { object, index } => {
             let obj_value = eval_expr(object, env)?;
@@ -342,50 +477,83 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
                     if idx < arr.len() {
                         Ok(arr[idx].clone())
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "Array index out of bounds: {} (length: {})",
-                            idx,
-                            arr.len()
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::ArrayIndexOutOfBounds
+                                .format(&[("index", &idx), ("length", &arr.len())]),
+                        ))
                     }
                 }
                 (Value::String(s), Value::Number(num)) => {
                     let idx = num.to_int() as isize;
-                    let len = s.len() as isize;
+                    let len = s.chars().count() as isize;
                     let actual_idx = if idx < 0 { len + idx } else { idx };
-                    if actual_idx >= 0 && actual_idx < len {
-                        let ch = s.chars().nth(actual_idx as usize).unwrap();
+                    let ch = usize::try_from(actual_idx)
+                        .ok()
+                        .and_then(|actual_idx| s.chars().nth(actual_idx));
+                    if let Some(ch) = ch {
                         Ok(Value::String(ch.to_string()))
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "String index out of bounds: {} (length: {})",
-                            idx, len
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::StringIndexOutOfBounds
+                                .format(&[("index", &idx), ("length", &len)]),
+                        ))
                     }
                 }
+                (Value::Array(arr), Value::Range { start, end }) => {
+                    let arr = arr.borrow();
+                    let (from, to) = slice_range(Some(start), Some(end), arr.len());
+                    allocate(env, array_size(to - from))?;
+                    Ok(Value::Array(Rc::new(RefCell::new(arr[from..to].to_vec()))))
+                }
+                (Value::String(s), Value::Range { start, end }) => {
+                    let chars: Vec<char> = s.chars().collect();
+                    let (from, to) = slice_range(Some(start), Some(end), chars.len());
+                    allocate(env, chars[from..to].iter().map(|c| c.len_utf8()).sum())?;
+                    Ok(Value::String(chars[from..to].iter().collect()))
+                }
                 (Value::Object(obj), Value::String(key)) => {
                     if let Some(value) = obj.get(&key) {
                         Ok(value.clone())
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "Object property not found: {}",
-                            key
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::ObjectPropertyNotFound.format(&[("name", &key)]),
+                        ))
                     }
                 }
                 (Value::StructInstance { fields, .. }, Value::String(key)) => {
                     if let Some(value) = fields.get(&key) {
                         Ok(value.clone())
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "Struct property not found: {}",
-                            key
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::StructPropertyNotFound.format(&[("name", &key)]),
+                        ))
                     }
                 }
                 _ => Err(InterpreterError::TypeMismatch(
-                    "Index access requires array/string index or object/string property"
-                        .to_string(),
+                    Message::IndexAccess.text().to_string(),
+                )),
+            }
+        }
+        ExprKind::Slice { object, start, end } => {
+            let obj_value = eval_expr(object, env)?;
+            let start = eval_slice_bound(start, env)?;
+            let end = eval_slice_bound(end, env)?;
+
+            match obj_value {
+                Value::Array(arr) => {
+                    let arr = arr.borrow();
+                    let (from, to) = slice_range(start, end, arr.len());
+                    allocate(env, array_size(to - from))?;
+                    Ok(Value::Array(Rc::new(RefCell::new(arr[from..to].to_vec()))))
+                }
+                Value::String(s) => {
+                    let chars: Vec<char> = s.chars().collect();
+                    let (from, to) = slice_range(start, end, chars.len());
+                    allocate(env, chars[from..to].iter().map(|c| c.len_utf8()).sum())?;
+                    Ok(Value::String(chars[from..to].iter().collect()))
+                }
+                _ => Err(InterpreterError::TypeMismatch(
+                    Message::SliceTarget.text().to_string(),
                 )),
             }
         }
@@ -397,26 +565,764 @@ pub fn eval_expr(expr: &Expr, env: &Rc<RefCell<Environment>>) -> Result<Value, I
                     if let Some(value) = obj.get(property.as_str()) {
                         Ok(value.clone())
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "Object property not found: {}",
-                            property
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::ObjectPropertyNotFound.format(&[("name", &property)]),
+                        ))
                     }
                 }
                 Value::StructInstance { fields, .. } => {
                     if let Some(value) = fields.get(property.as_str()) {
                         Ok(value.clone())
                     } else {
-                        Err(InterpreterError::InvalidOperation(format!(
-                            "Struct property not found: {}",
-                            property
-                        )))
+                        Err(InterpreterError::InvalidOperation(
+                            Message::StructPropertyNotFound.format(&[("name", &property)]),
+                        ))
+                    }
+                }
+                _ => Err(InterpreterError::TypeMismatch(
+                    Message::PropertyAccess.text().to_string(),
+                )),
+            }
+        }
+    }
+}
+
+/// The value for each name in a destructuring pattern, in order, failing if the shape doesn't
+/// match. A missing property is reported at its name in the pattern.
+fn destructure(pattern: &BindingPattern, value: Value) -> Result<Vec<Value>, InterpreterError> {
+    match (pattern, value) {
+        (BindingPattern::Array(names), Value::Array(arr)) => {
+            let arr = arr.borrow();
+            if arr.len() != names.len() {
+                return Err(InterpreterError::InvalidOperation(
+                    Message::DestructureLength
+                        .format(&[("length", &arr.len()), ("count", &names.len())]),
+                ));
+            }
+            Ok(arr.clone())
+        }
+        (BindingPattern::Object(names), Value::Object(fields))
+        | (BindingPattern::Object(names), Value::StructInstance { fields, .. }) => names
+            .iter()
+            .map(|(name, span)| match fields.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(InterpreterError::InvalidOperation(
+                    Message::DestructureMissing.format(&[("name", name)]),
+                )
+                .with_span(*span)),
+            })
+            .collect(),
+        (BindingPattern::Array(_), value) => Err(InterpreterError::TypeMismatch(
+            Message::DestructureArray.format(&[("type", &value.type_name())]),
+        )),
+        (BindingPattern::Object(_), value) => Err(InterpreterError::TypeMismatch(
+            Message::DestructureObject.format(&[("type", &value.type_name())]),
+        )),
+    }
+}
+
+/// `&&`, `||` and `??`, which may skip their right operand.
+fn is_short_circuit(op: &TokenKind) -> bool {
+    matches!(
+        op,
+        TokenKind::LogicalAnd | TokenKind::LogicalOr | TokenKind::NilCoalesce
+    )
+}
+
+fn is_ordering(op: &TokenKind) -> bool {
+    matches!(
+        op,
+        TokenKind::LessThan
+            | TokenKind::LessThanOrEqual
+            | TokenKind::GreaterThan
+            | TokenKind::GreaterThanOrEqual
+    )
+}
+
+/// Evaluates `a < b < c` as `a < b && b < c`, evaluating `b` only once and
+/// stopping at the first comparison that fails. Returns the rightmost operand
+/// when the whole chain holds.
+fn eval_comparison_chain(
+    left: &Expr,
+    op: &TokenKind,
+    right: &Expr,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<Value>, InterpreterError> {
+    let left_value = match &left.kind {
+        ExprKind::BinaryOp {
+            left: inner_left,
+            op: inner_op,
+            right: inner_right,
+        } if is_ordering(inner_op) => {
+            match eval_comparison_chain(inner_left, inner_op, inner_right, env)? {
+                Some(value) => value,
+                None => return Ok(None),
+            }
+        }
+        _ => eval_expr(left, env)?,
+    };
+    let right_value = eval_expr(right, env)?;
+    match binary_op(op, left_value, right_value.clone())? {
+        Value::Boolean(true) => Ok(Some(right_value)),
+        _ => Ok(None),
+    }
+}
+
+fn binary_op(op: &TokenKind, left: Value, right: Value) -> Result<Value, InterpreterError> {
+    match (left, right) {
+        (l, r) if *op == TokenKind::Equal => Ok(Value::Boolean(l.equals(&r))),
+        (l, r) if *op == TokenKind::NotEqual => Ok(Value::Boolean(!l.equals(&r))),
+        (Value::Number(Number::Int(start)), Value::Number(Number::Int(end)))
+            if *op == TokenKind::DotDot =>
+        {
+            Ok(Value::Range { start, end })
+        }
+        (Value::Number(l), Value::Number(r))
+            if matches!(
+                op,
+                TokenKind::BitAnd
+                    | TokenKind::BitOr
+                    | TokenKind::BitXor
+                    | TokenKind::ShiftLeft
+                    | TokenKind::ShiftRight
+            ) =>
+        {
+            eval_bitwise(op, l, r)
+        }
+        (Value::Number(l), Value::Number(r)) => match op {
+            TokenKind::Plus => l.checked_add(r).map(Value::Number),
+            TokenKind::Minus => l.checked_sub(r).map(Value::Number),
+            TokenKind::Multiply => l.checked_mul(r).map(Value::Number),
+            TokenKind::Divide => l.checked_div(r).map(Value::Number),
+            TokenKind::FloorDivide => l.floor_div(r).map(Value::Number),
+            TokenKind::Modulo => l.checked_rem(r).map(Value::Number),
+            TokenKind::GreaterThan => Ok(Value::Boolean(l > r)),
+            TokenKind::GreaterThanOrEqual => Ok(Value::Boolean(l >= r)),
+            TokenKind::LessThan => Ok(Value::Boolean(l < r)),
+            TokenKind::LessThanOrEqual => Ok(Value::Boolean(l <= r)),
+            _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
+        },
+        // Strings order lexicographically by Unicode code point, so `"B" < "a"`.
+        (Value::String(l), Value::String(r)) => match op {
+            TokenKind::Plus => Ok(Value::String(l + &r)),
+            TokenKind::GreaterThan => Ok(Value::Boolean(l > r)),
+            TokenKind::GreaterThanOrEqual => Ok(Value::Boolean(l >= r)),
+            TokenKind::LessThan => Ok(Value::Boolean(l < r)),
+            TokenKind::LessThanOrEqual => Ok(Value::Boolean(l <= r)),
+            _ => Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
+        },
+        (Value::String(s), Value::Number(Number::Int(n)))
+        | (Value::Number(Number::Int(n)), Value::String(s))
+            if *op == TokenKind::Multiply =>
+        {
+            if n < 0 {
+                return Err(InterpreterError::InvalidOperation(
+                    Message::NegativeRepetition.format(&[("count", &n)]),
+                ));
+            }
+            Ok(Value::String(s.repeat(n as usize)))
+        }
+        (Value::String(l), r) if *op == TokenKind::Plus => Ok(Value::String(format!("{l}{r}"))),
+        (l, Value::String(r)) if *op == TokenKind::Plus => Ok(Value::String(format!("{l}{r}"))),
+        (l, r) => Err(InterpreterError::TypeMismatch(Message::CannotApply.format(
+            &[
+                ("op", op),
+                ("left", &l.type_name()),
+                ("right", &r.type_name()),
+            ],
+        ))),
+    }
+}
+
+/// The type names `is` and `as` understand; struct names also work with `is`.
+const TYPE_NAMES: [&str; 12] = [
+    "Int", "Float", "Number", "String", "Bool", "Array", "Object", "Range", "Function", "Thread",
+    "Channel", "Nil",
+];
+
+fn value_is(
+    value: &Value,
+    type_name: &str,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<bool, InterpreterError> {
+    let matches = match type_name {
+        "Int" => matches!(value, Value::Number(Number::Int(_))),
+        "Float" => matches!(value, Value::Number(Number::Float(_))),
+        "Number" => matches!(value, Value::Number(_)),
+        "String" => matches!(value, Value::String(_)),
+        "Bool" => matches!(value, Value::Boolean(_)),
+        "Array" => matches!(value, Value::Array(_)),
+        "Object" => matches!(value, Value::Object(_)),
+        "Range" => matches!(value, Value::Range { .. }),
+        "Function" => matches!(value, Value::Function(_)),
+        "Thread" => matches!(value, Value::Thread(_)),
+        "Channel" => matches!(value, Value::Channel(_)),
+        "Nil" => matches!(value, Value::Nil),
+        _ if env.borrow().get_struct(type_name).is_some() => {
+            matches!(value, Value::StructInstance { name, .. } if name == type_name)
+        }
+        _ => {
+            return Err(InterpreterError::TypeMismatch(
+                Message::UnknownType.format(&[("type", &type_name)]),
+            ));
+        }
+    };
+    Ok(matches)
+}
+
+/// `value as type_name`. Arrays are charged against the allocation budget
+/// before they are built; a rendered string only once its length is known.
+fn cast(
+    value: Value,
+    type_name: &str,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Value, InterpreterError> {
+    if !TYPE_NAMES.contains(&type_name) {
+        return Err(InterpreterError::TypeMismatch(
+            Message::UnknownType.format(&[("type", &type_name)]),
+        ));
+    }
+    match (type_name, &value) {
+        ("Array", Value::String(s)) => allocate(env, array_size(s.chars().count()))?,
+        ("Array", Value::Range { start, end }) => {
+            let len = usize::try_from(end.saturating_sub(*start).max(0)).unwrap_or(usize::MAX);
+            allocate(env, array_size(len))?;
+        }
+        _ => {}
+    }
+    let int = |n| Value::Number(Number::Int(n));
+    let float = |n| Value::Number(Number::Float(n));
+    let converted = match (type_name, &value) {
+        // Converting a value to its own type is a no-op.
+        (_, value) if cast_type_name(value) == type_name => Some(value.clone()),
+        ("Number", Value::Number(_)) => Some(value.clone()),
+        ("Int", Value::Number(n)) => Some(int(n.to_int())),
+        ("Int", Value::String(s)) => s.trim().parse().ok().map(int),
+        ("Int", Value::Boolean(b)) => Some(int(*b as i128)),
+        ("Float", Value::Number(n)) => Some(float(n.to_float())),
+        ("Float", Value::String(s)) => s.trim().parse().ok().map(float),
+        ("Float", Value::Boolean(b)) => Some(float(if *b { 1.0 } else { 0.0 })),
+        ("Number", Value::String(s)) => {
+            let s = s.trim();
+            s.parse().map(int).or_else(|_| s.parse().map(float)).ok()
+        }
+        ("String", value) => {
+            let text = value.to_string();
+            allocate(env, text.len())?;
+            Some(Value::String(text))
+        }
+        ("Bool", Value::Number(n)) => Some(Value::Boolean(n.to_bool())),
+        ("Bool", Value::String(s)) => s.parse().ok().map(Value::Boolean),
+        ("Array", Value::String(s)) => Some(Value::Array(Rc::new(RefCell::new(
+            s.chars().map(|c| Value::String(c.to_string())).collect(),
+        )))),
+        ("Array", Value::Range { start, end }) => Some(Value::Array(Rc::new(RefCell::new(
+            (*start..*end).map(int).collect(),
+        )))),
+        _ => None,
+    };
+    converted.ok_or_else(|| {
+        InterpreterError::TypeMismatch(
+            Message::CannotConvert.format(&[("from", &value.type_name()), ("type", &type_name)]),
+        )
+    })
+}
+
+/// The `is`/`as` name for a value's own type; struct instances have none.
+fn cast_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(Number::Int(_)) => "Int",
+        Value::Number(Number::Float(_)) => "Float",
+        Value::String(_) => "String",
+        Value::Boolean(_) => "Bool",
+        Value::Array(_) => "Array",
+        Value::Object(_) => "Object",
+        Value::Range { .. } => "Range",
+        Value::Function(_) => "Function",
+        Value::Thread(_) => "Thread",
+        Value::Channel(_) => "Channel",
+        Value::Nil => "Nil",
+        Value::StructInstance { .. } => "",
+    }
+}
+
+fn eval_bitwise(op: &TokenKind, l: Number, r: Number) -> Result<Value, InterpreterError> {
+    let (Number::Int(l), Number::Int(r)) = (l, r) else {
+        return Err(InterpreterError::TypeMismatch(
+            Message::BitwiseIntegers.format(&[("op", op)]),
+        ));
+    };
+    let shift = || {
+        u32::try_from(r)
+            .ok()
+            .filter(|&bits| bits < i128::BITS)
+            .ok_or_else(|| {
+                InterpreterError::InvalidOperation(
+                    Message::ShiftOutOfRange.format(&[("amount", &r)]),
+                )
+            })
+    };
+    let result = match op {
+        TokenKind::BitAnd => l & r,
+        TokenKind::BitOr => l | r,
+        TokenKind::BitXor => l ^ r,
+        TokenKind::ShiftLeft => l << shift()?,
+        TokenKind::ShiftRight => l >> shift()?,
+        _ => return Err(InterpreterError::InvalidOperation(format!("{op:?}"))),
+    };
+    Ok(Value::Number(Number::Int(result)))
+}
+
+/// Evaluates call arguments or array elements, expanding `...array` in place.
+fn eval_elements(
+    exprs: &[Expr],
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Vec<Value>, InterpreterError> {
+    let mut values = Vec::with_capacity(exprs.len());
+    for expr in exprs {
+        match &expr.kind {
+            ExprKind::Spread(inner) => match eval_expr(inner, env)? {
+                Value::Array(arr) => values.extend(arr.borrow().iter().cloned()),
+                other => {
+                    return Err(InterpreterError::TypeMismatch(
+                        Message::SpreadNonArray.format(&[("type", &other.type_name())]),
+                    ));
+                }
+            },
+            _ => values.push(eval_expr(expr, env)?),
+        }
+    }
+    Ok(values)
+}
+
+/// Runs a `while`, `do` or `for` loop. Loops evaluate to nil unless `collect`
+/// asks for an array of each iteration's value.
+fn eval_loop(
+    expr: &Expr,
+    env: &Rc<RefCell<Environment>>,
+    collect: bool,
+) -> Result<Value, InterpreterError> {
+    let mut values = Vec::new();
+    match &expr.kind {
+        ExprKind::While { condition, body } => {
+            while loop_condition(condition, env)? {
+                match eval_expr(body, env) {
+                    Ok(value) if collect => {
+                        allocate(env, std::mem::size_of::<Value>())?;
+                        values.push(value)
+                    }
+                    Ok(_) | Err(InterpreterError::Continue) => {}
+                    Err(InterpreterError::Break(_)) => break,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        ExprKind::DoWhile { body, condition } => loop {
+            match eval_expr(body, env) {
+                Ok(value) if collect => {
+                    allocate(env, std::mem::size_of::<Value>())?;
+                    values.push(value)
+                }
+                Ok(_) | Err(InterpreterError::Continue) => {}
+                Err(InterpreterError::Break(_)) => break,
+                Err(err) => return Err(err),
+            }
+            if !loop_condition(condition, env)? {
+                break;
+            }
+        },
+        ExprKind::For {
+            variable,
+            iterable,
+            body,
+        } => {
+            let iterable_value = eval_expr(iterable, env)?;
+            let items: Box<dyn Iterator<Item = Value>> = match iterable_value {
+                Value::Range { start, end } => {
+                    Box::new((start..end).map(|i| Value::Number(Number::Int(i))))
+                }
+                Value::Array(arr) => Box::new(arr.borrow().clone().into_iter()),
+                Value::String(s) => Box::new(
+                    s.chars()
+                        .map(|c| Value::String(c.to_string()))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ),
+                other => {
+                    return Err(InterpreterError::TypeMismatch(
+                        Message::NotIterable.format(&[("type", &other.type_name())]),
+                    ));
+                }
+            };
+
+            for item in items {
+                step(env)?;
+                let loop_env = Rc::new(RefCell::new(Environment::new_child(env.clone())));
+                loop_env.borrow_mut().define(variable.clone(), item)?;
+                match eval_expr(body, &loop_env) {
+                    Ok(value) if collect => {
+                        allocate(env, std::mem::size_of::<Value>())?;
+                        values.push(value)
+                    }
+                    Ok(_) | Err(InterpreterError::Continue) => {}
+                    Err(InterpreterError::Break(_)) => break,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        _ => {
+            return Err(InterpreterError::UnsupportedExpression(
+                Message::CollectNeedsLoop.text().to_string(),
+            ));
+        }
+    }
+    if collect {
+        Ok(Value::Array(Rc::new(RefCell::new(values))))
+    } else {
+        Ok(Value::Nil)
+    }
+}
+
+/// The bytes of the string `left op right` creates, if it creates one.
+fn string_result_size(op: &TokenKind, left: &Value, right: &Value) -> usize {
+    match (op, left, right) {
+        (TokenKind::Plus, Value::String(_), _) | (TokenKind::Plus, _, Value::String(_)) => {
+            left.heap_size() + right.heap_size()
+        }
+        (TokenKind::Multiply, Value::String(s), Value::Number(Number::Int(n)))
+        | (TokenKind::Multiply, Value::Number(Number::Int(n)), Value::String(s)) => {
+            s.len().saturating_mul(usize::try_from(*n).unwrap_or(0))
+        }
+        _ => 0,
+    }
+}
+
+/// What an array of `len` values is charged.
+pub(crate) fn array_size(len: usize) -> usize {
+    len.saturating_mul(std::mem::size_of::<Value>())
+}
+
+/// Counts `bytes` of new strings, arrays or objects against the program's
+/// allocation budget.
+pub(crate) fn allocate(
+    env: &Rc<RefCell<Environment>>,
+    bytes: usize,
+) -> Result<(), InterpreterError> {
+    match env.borrow().state() {
+        Some(state) => state.borrow_mut().allocate(bytes),
+        None => Ok(()),
+    }
+}
+
+/// Gives an attached debugger the chance to stop before `stmt`. It is taken
+/// out of the state while paused, so anything the hook evaluates runs freely.
+/// Function and struct declarations only bind a name, and their span is the
+/// closing brace, so they are never stopped at.
+fn pause_for_debugger(stmt: &Stmt, env: &Rc<RefCell<Environment>>) -> Result<(), InterpreterError> {
+    if matches!(
+        stmt.kind,
+        StmtKind::Function { .. } | StmtKind::Struct { .. }
+    ) {
+        return Ok(());
+    }
+    let Some(state) = env.borrow().state() else {
+        return Ok(());
+    };
+    let Some(mut debugger) = state.borrow_mut().debugger.take() else {
+        return Ok(());
+    };
+    let frames = state.borrow().call_stack.frames().to_vec();
+    let result = debugger.before_statement(stmt.span, &frames, env);
+    state.borrow_mut().debugger = Some(debugger);
+    result
+}
+
+/// Passes an event to every observer attached to the program.
+fn notify(env: &Rc<RefCell<Environment>>, mut event: impl FnMut(&mut dyn Observer)) {
+    let Some(state) = env.borrow().state() else {
+        return;
+    };
+    for observer in state.borrow_mut().observers.iter_mut() {
+        event(observer.as_mut());
+    }
+}
+
+/// Counts a statement, loop iteration or call against the program's limits.
+fn step(env: &Rc<RefCell<Environment>>) -> Result<(), InterpreterError> {
+    match env.borrow().state() {
+        Some(state) => state.borrow_mut().step(),
+        None => Ok(()),
+    }
+}
+
+/// Checks a `while` or `do ... while` condition, which starts another iteration.
+fn loop_condition(
+    condition: &Expr,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<bool, InterpreterError> {
+    step(env)?;
+    match eval_expr(condition, env)? {
+        Value::Boolean(value) => Ok(value),
+        _ => Err(InterpreterError::TypeMismatch(
+            Message::WhileCondition.text().to_string(),
+        )),
+    }
+}
+
+/// Calls `function` by `name`, timing it when profiling is on.
+fn call_function(
+    function: &Function,
+    name: &str,
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Span,
+) -> Result<Value, InterpreterError> {
+    let Some(state) = env.borrow().state() else {
+        return function.call(args, env);
+    };
+    notify(env, |observer| observer.on_call(name, &args));
+    if let Some(profiler) = &mut state.borrow_mut().profiler {
+        profiler.enter(name);
+    }
+    let result = match function {
+        Function::User(_) => call_user_function(function, name, args, env, call_site, &state),
+        Function::Builtin(builtin) => builtin.call_at(args, env, Some(call_site)),
+    };
+    if let Some(profiler) = &mut state.borrow_mut().profiler {
+        profiler.exit();
+    }
+    notify(env, |observer| {
+        observer.on_return(name, result.as_ref().ok())
+    });
+    result
+}
+
+/// Calls a function a builtin was handed, such as a `map` callback, as if
+/// the call were written where the builtin was called: it counts toward the
+/// depth limit and shows up in stack traces, profiles and observers.
+pub(crate) fn call_callback(
+    function: &Function,
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Option<Span>,
+) -> Result<Value, InterpreterError> {
+    let Some(call_site) = call_site else {
+        return function.call(args, env);
+    };
+    let name = match function {
+        Function::User(user) => user.name.as_deref().unwrap_or("<lambda>"),
+        Function::Builtin(_) => "<builtin>",
+    };
+    call_function(function, name, args, env, call_site)
+}
+
+/// User function calls are recorded on the call stack, which enforces the
+/// depth limit and gives errors their stack trace. A call also fails, at the
+/// depth reached, once the Rust stack runs low.
+fn call_user_function(
+    function: &Function,
+    name: &str,
+    args: Vec<Value>,
+    env: &Rc<RefCell<Environment>>,
+    call_site: Span,
+    state: &Rc<RefCell<EvalState>>,
+) -> Result<Value, InterpreterError> {
+    step(env)?;
+    if !stack::has_room_for_call() {
+        let depth = state.borrow().call_stack.depth();
+        return Err(InterpreterError::StackOverflow(depth));
+    }
+    state.borrow_mut().call_stack.push(Frame {
+        function: name.to_string(),
+        call_site,
+    })?;
+    let result = function.call(args, env).map_err(|error| match error {
+        InterpreterError::WithTrace { .. }
+        | InterpreterError::Break(_)
+        | InterpreterError::Continue => error,
+        error => InterpreterError::WithTrace {
+            error: Box::new(error.or_span(call_site)),
+            trace: state
+                .borrow()
+                .call_stack
+                .frames()
+                .iter()
+                .rev()
+                .cloned()
+                .collect(),
+        },
+    });
+    state.borrow_mut().call_stack.pop();
+    result
+}
+
+/// Resolves a call target: a declared function, or a variable holding a function value.
+fn lookup_function(
+    name: &str,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Function, InterpreterError> {
+    let env = env.borrow();
+    if let Some(function) = env.get_function_recursive(name) {
+        return Ok(function);
+    }
+    match env.get_value(name) {
+        Some(Value::Function(function)) => Ok(function.as_ref().clone()),
+        Some(value) => Err(InterpreterError::TypeMismatch(
+            Message::NotAFunction.format(&[("name", &name), ("type", &value.type_name())]),
+        )),
+        None => Err(InterpreterError::UndefinedVariable(name.to_string())),
+    }
+}
+
+fn eval_slice_bound(
+    bound: &Option<Box<Expr>>,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Option<i128>, InterpreterError> {
+    match bound {
+        None => Ok(None),
+        Some(expr) => match eval_expr(expr, env)? {
+            Value::Number(Number::Int(i)) => Ok(Some(i)),
+            _ => Err(InterpreterError::TypeMismatch(
+                Message::SliceBounds.text().to_string(),
+            )),
+        },
+    }
+}
+
+/// Resolves optional, possibly negative slice bounds into a clamped `from..to` range.
+fn slice_range(start: Option<i128>, end: Option<i128>, len: usize) -> (usize, usize) {
+    let resolve = |bound: i128| {
+        let idx = if bound < 0 {
+            len as i128 + bound
+        } else {
+            bound
+        };
+        idx.clamp(0, len as i128) as usize
+    };
+    let from = start.map_or(0, resolve);
+    let to = end.map_or(len, resolve);
+    (from, to.max(from))
+}
+
+fn assign_to(
+    target: &Expr,
+    value: Value,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<(), InterpreterError> {
+    match &target.kind {
+        ExprKind::Variable(name) => {
+            if env.borrow().is_constant(name) {
+                return Err(InterpreterError::ConstReassignment {
+                    name: name.clone(),
+                    span: Some(target.span),
+                });
+            }
+            env.borrow_mut().assign(name.as_str(), value)
+        }
+        ExprKind::Array(targets) => {
+            let Value::Array(values) = value else {
+                return Err(InterpreterError::TypeMismatch(
+                    Message::AssignToTargets
+                        .format(&[("type", &value.type_name()), ("count", &targets.len())]),
+                ));
+            };
+            let values = values.borrow().clone();
+            if values.len() != targets.len() {
+                return Err(InterpreterError::InvalidOperation(
+                    Message::AssignCount
+                        .format(&[("values", &values.len()), ("count", &targets.len())]),
+                ));
+            }
+            for (target, value) in targets.iter().zip(values) {
+                assign_to(target, value, env)?;
+            }
+            Ok(())
+        }
+        ExprKind::Index { object, index } => {
+            let obj_value = eval_expr(object, env)?;
+            let index_value = eval_expr(index, env)?;
+
+            match (obj_value, index_value) {
+                (Value::Array(arr), Value::Number(num)) => {
+                    let idx = num.to_int();
+                    let mut arr_mut = arr.borrow_mut();
+                    if idx >= 0 && (idx as usize) < arr_mut.len() {
+                        arr_mut[idx as usize] = value;
+                        Ok(())
+                    } else {
+                        Err(InterpreterError::InvalidOperation(
+                            Message::ArrayIndexOutOfBounds
+                                .format(&[("index", &idx), ("length", &arr_mut.len())]),
+                        ))
+                    }
+                }
+                (Value::String(s), Value::Number(num)) => {
+                    let mut chars: Vec<char> = s.chars().collect();
+                    let idx = num.to_int() as isize;
+                    let len = chars.len() as isize;
+                    let actual_idx = if idx < 0 { len + idx } else { idx };
+                    if actual_idx < 0 || actual_idx >= len {
+                        return Err(InterpreterError::InvalidOperation(
+                            Message::StringIndexOutOfBounds
+                                .format(&[("index", &idx), ("length", &len)]),
+                        ));
+                    }
+                    let new_char = value.to_string();
+                    let mut new_chars = new_char.chars();
+                    match (new_chars.next(), new_chars.next()) {
+                        (Some(c), None) => chars[actual_idx as usize] = c,
+                        _ => {
+                            return Err(InterpreterError::InvalidOperation(
+                                Message::StringIndexAssignment.text().to_string(),
+                            ));
+                        }
                     }
+                    assign_to(object, Value::String(chars.into_iter().collect()), env)
+                }
+                (Value::Object(mut obj), Value::String(key)) => {
+                    obj.insert(key, value);
+                    assign_to(object, Value::Object(obj), env)
+                }
+                (Value::StructInstance { name, mut fields }, Value::String(key)) => {
+                    set_struct_field(&mut fields, key, value)?;
+                    assign_to(object, Value::StructInstance { name, fields }, env)
                 }
                 _ => Err(InterpreterError::TypeMismatch(
-                    "Property access requires an object".to_string(),
+                    Message::IndexAssignment.text().to_string(),
                 )),
             }
         }
+        ExprKind::GetProperty { object, property } => match eval_expr(object, env)? {
+            Value::Object(mut obj) => {
+                obj.insert(property.clone(), value);
+                assign_to(object, Value::Object(obj), env)
+            }
+            Value::StructInstance { name, mut fields } => {
+                set_struct_field(&mut fields, property.clone(), value)?;
+                assign_to(object, Value::StructInstance { name, fields }, env)
+            }
+            _ => Err(InterpreterError::TypeMismatch(
+                Message::PropertyAssignment.text().to_string(),
+            )),
+        },
+        _ => Err(InterpreterError::InvalidOperation(
+            Message::InvalidTarget.text().to_string(),
+        )),
+    }
+}
+
+fn set_struct_field(
+    fields: &mut HashMap<String, Value>,
+    key: String,
+    value: Value,
+) -> Result<(), InterpreterError> {
+    match fields.get_mut(&key) {
+        Some(field) => {
+            *field = value;
+            Ok(())
+        }
+        None => Err(InterpreterError::InvalidOperation(
+            Message::StructPropertyNotFound.format(&[("name", &key)]),
+        )),
     }
 }
@@ -1,5 +1,15 @@
+pub mod async_eval;
+pub mod call_stack;
+pub mod concurrency;
+pub mod debug;
 pub mod environment;
 pub mod error;
 pub mod eval;
+pub mod module;
+pub mod observer;
+pub mod profile;
+pub mod snapshot;
+pub mod stack;
+pub mod state;
 pub use environment::Environment;
 pub use eval::eval;
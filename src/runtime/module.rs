@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    lexer,
+    messages::Message,
+    parser,
+    runtime::{
+        environment::Environment, error::InterpreterError, eval::eval_program, state::EvalState,
+    },
+};
+
+/// Resolves, evaluates and caches the files loaded by `import`.
+#[derive(Debug, Default)]
+pub struct ModuleLoader {
+    /// Directories of the files being evaluated; relative paths resolve against the last one.
+    dirs: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Rc<RefCell<Environment>>>,
+    loading: HashSet<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn with_base_dir(dir: PathBuf) -> Self {
+        Self {
+            dirs: vec![dir],
+            ..Self::default()
+        }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        match self.dirs.last() {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+}
+
+/// Returns the top-level scope of the module at `path`, evaluating the file
+/// only the first time it is imported.
+pub fn load(
+    loader: &Rc<RefCell<ModuleLoader>>,
+    path: &str,
+    state: Option<Rc<RefCell<EvalState>>>,
+) -> Result<Rc<RefCell<Environment>>, InterpreterError> {
+    let resolved = loader.borrow().resolve(path);
+    let key = resolved.canonicalize().map_err(|err| {
+        InterpreterError::ImportError(
+            Message::CannotImport.format(&[("path", &path), ("reason", &err)]),
+        )
+    })?;
+    if let Some(module) = loader.borrow().cache.get(&key) {
+        return Ok(module.clone());
+    }
+    if !loader.borrow_mut().loading.insert(key.clone()) {
+        return Err(InterpreterError::ImportError(
+            Message::CircularImport.format(&[("path", &path)]),
+        ));
+    }
+
+    let result = evaluate(loader, &key, state);
+    let mut loader = loader.borrow_mut();
+    loader.loading.remove(&key);
+    let module = result.map_err(|err| match err {
+        InterpreterError::ImportError(_) => err,
+        err => InterpreterError::ImportError(
+            Message::ModuleFailed.format(&[("path", &path), ("error", &err)]),
+        ),
+    })?;
+    loader.cache.insert(key, module.clone());
+    Ok(module)
+}
+
+fn evaluate(
+    loader: &Rc<RefCell<ModuleLoader>>,
+    path: &Path,
+    state: Option<Rc<RefCell<EvalState>>>,
+) -> Result<Rc<RefCell<Environment>>, InterpreterError> {
+    let source = fs::read_to_string(path).map_err(|err| {
+        InterpreterError::ImportError(
+            Message::CannotRead.format(&[("path", &path.display()), ("reason", &err)]),
+        )
+    })?;
+    let (tokens, lexer_errors) = lexer::tokenize_with_errors(&source);
+    if !lexer_errors.is_empty() {
+        let messages: Vec<String> = lexer_errors.iter().map(|e| e.to_string()).collect();
+        return Err(InterpreterError::ImportError(messages.join("\n")));
+    }
+    let (stmts, errors) = parser::parse_with_errors(tokens);
+    if !errors.is_empty() {
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        return Err(InterpreterError::ImportError(messages.join("\n")));
+    }
+
+    // Module bindings live in a child of a fresh root so builtins aren't exported.
+    let mut root = Environment::new_root_with_modules(loader.clone()).with_file(path);
+    if let Some(state) = state {
+        root = root.with_state(state);
+    }
+    let module = Rc::new(RefCell::new(Environment::new_module(Rc::new(
+        RefCell::new(root),
+    ))));
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    loader.borrow_mut().dirs.push(dir);
+    let result = eval_program(stmts, &module);
+    loader.borrow_mut().dirs.pop();
+    match result {
+        Ok(_) | Err(InterpreterError::Return(_)) => Ok(module),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::lexer::Span;
+use crate::runtime::environment::Value;
+use crate::runtime::error::InterpreterError;
+
+/// Callbacks the evaluator makes as a program runs, for tools such as
+/// coverage reports, watchdogs or progress bars. Every method does nothing by
+/// default, so implement only the ones you need.
+pub trait Observer {
+    /// Before each statement, with the file it belongs to when known.
+    fn on_statement(&mut self, _file: Option<&Path>, _span: Span) {}
+
+    /// Before each function call, builtins included.
+    fn on_call(&mut self, _function: &str, _args: &[Value]) {}
+
+    /// After each call reported to [`on_call`](Observer::on_call), with its
+    /// result, or `None` if it failed.
+    fn on_return(&mut self, _function: &str, _value: Option<&Value>) {}
+
+    /// When a runtime error stops the program.
+    fn on_error(&mut self, _error: &InterpreterError) {}
+}
+
+impl fmt::Debug for dyn Observer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Timings for every call of one function.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    /// Time from entering to leaving the function, including the functions it
+    /// called. Recursive calls are only counted once, at the outermost call.
+    pub total: Duration,
+    /// Time spent in the function itself, excluding the functions it called.
+    pub self_time: Duration,
+}
+
+/// A call that hasn't returned yet.
+#[derive(Debug)]
+struct ActiveCall {
+    name: String,
+    started: Instant,
+    /// Time spent in the calls this one made so far.
+    children: Duration,
+}
+
+/// Records call counts and timings per function while a program runs.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    functions: HashMap<String, FunctionProfile>,
+    active: Vec<ActiveCall>,
+}
+
+impl Profiler {
+    pub fn enter(&mut self, name: &str) {
+        self.active.push(ActiveCall {
+            name: name.to_string(),
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    /// Leaves the innermost call, whether it returned or failed.
+    pub fn exit(&mut self) {
+        let Some(call) = self.active.pop() else {
+            return;
+        };
+        let elapsed = call.started.elapsed();
+        if let Some(caller) = self.active.last_mut() {
+            caller.children += elapsed;
+        }
+        let recursive = self.active.iter().any(|active| active.name == call.name);
+        let profile = self
+            .functions
+            .entry(call.name.clone())
+            .or_insert_with(|| FunctionProfile {
+                name: call.name,
+                ..FunctionProfile::default()
+            });
+        profile.calls += 1;
+        profile.self_time += elapsed.saturating_sub(call.children);
+        if !recursive {
+            profile.total += elapsed;
+        }
+    }
+
+    /// Every function called so far, the most expensive first.
+    pub fn report(&self) -> Vec<FunctionProfile> {
+        let mut report: Vec<FunctionProfile> = self.functions.values().cloned().collect();
+        report.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        report
+    }
+}
+
+/// Lays a report out as a table, one function per row.
+pub fn format_report(report: &[FunctionProfile]) -> String {
+    let width = report
+        .iter()
+        .map(|profile| profile.name.chars().count())
+        .chain(["function".len()])
+        .max()
+        .unwrap_or_default();
+    let mut table = format!(
+        "{:<width$}  {:>8}  {:>12}  {:>12}\n",
+        "function", "calls", "total ms", "self ms"
+    );
+    for profile in report {
+        let _ = writeln!(
+            table,
+            "{:<width$}  {:>8}  {:>12.3}  {:>12.3}",
+            profile.name,
+            profile.calls,
+            profile.total.as_secs_f64() * 1000.0,
+            profile.self_time.as_secs_f64() * 1000.0,
+        );
+    }
+    table
+}
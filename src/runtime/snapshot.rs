@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Message;
+use crate::runtime::concurrency::{SendBinding, SendValue};
+use crate::runtime::environment::Environment;
+use crate::runtime::error::InterpreterError;
+
+/// The variables, constants, functions and structs a scope can see, copied so
+/// a session can be written to disk and picked up again later. Like values
+/// sent to a thread, arrays are copied deeply, so two variables holding the
+/// same array hold separate copies once restored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    bindings: Vec<(String, SendBinding)>,
+}
+
+impl Snapshot {
+    /// Copies everything visible from `env` except the builtins. Fails if a
+    /// binding holds a thread handle, a channel or an array that contains
+    /// itself.
+    pub fn capture(env: &Environment) -> Result<Self, InterpreterError> {
+        let bindings = env
+            .visible_bindings()
+            .into_iter()
+            .map(|(name, binding)| {
+                let cannot_save = |reason: String| {
+                    InterpreterError::InvalidOperation(
+                        Message::CannotSave.format(&[("name", &name), ("reason", &reason)]),
+                    )
+                };
+                let binding = SendBinding::from_binding(&binding).map_err(|err| match err {
+                    InterpreterError::InvalidOperation(reason) => cannot_save(reason),
+                    err => err,
+                })?;
+                if binding_has_channel(&binding) {
+                    return Err(cannot_save(Message::ChannelNotSaved.text().to_string()));
+                }
+                Ok((name, binding))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Snapshot { bindings })
+    }
+
+    /// Defines every saved binding in `env`, replacing any of the same name.
+    pub fn restore(self, env: &mut Environment) {
+        for (name, binding) in self.bindings {
+            env.bind(name, binding.into_binding());
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+fn binding_has_channel(binding: &SendBinding) -> bool {
+    match binding {
+        SendBinding::Variable(value) | SendBinding::Constant(value) => has_channel(value),
+        SendBinding::Function(_) => false,
+        SendBinding::Struct { fields, .. } => fields
+            .iter()
+            .any(|(_, default)| default.as_ref().is_some_and(has_channel)),
+    }
+}
+
+fn has_channel(value: &SendValue) -> bool {
+    match value {
+        SendValue::Channel(_) => true,
+        SendValue::Array(items) => items.iter().any(has_channel),
+        SendValue::Object(fields) | SendValue::StructInstance { fields, .. } => {
+            fields.values().any(has_channel)
+        }
+        SendValue::Function(_)
+        | SendValue::Number(_)
+        | SendValue::Boolean(_)
+        | SendValue::String(_)
+        | SendValue::Range { .. }
+        | SendValue::Nil => false,
+    }
+}
@@ -0,0 +1,82 @@
+//! How much Rust stack evaluation has left. Parsing and evaluating recurse on
+//! the Rust stack for every nested expression and MP call, and a level costs
+//! many times more in a debug build than in a release one, so instead of
+//! trusting a fixed count they measure the stack used since they started on
+//! this thread.
+
+use std::cell::Cell;
+
+/// The stack a thread is assumed to have unless its host says otherwise: what
+/// Rust gives spawned threads, test threads included. Main threads usually
+/// have more.
+pub const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+/// Stack an expression leaves unused when it gives up, for the frames up to
+/// the next check and for builtins that recurse on their own.
+const EXPRESSION_RESERVE: usize = 128 * 1024;
+
+/// Calls give up with more stack to spare than expressions, more than one
+/// level of recursion takes, so runaway recursion is reported as a stack
+/// overflow rather than as deep nesting.
+const CALL_RESERVE: usize = 512 * 1024;
+
+thread_local! {
+    static SIZE: Cell<usize> = const { Cell::new(DEFAULT_STACK_SIZE) };
+    /// Where the outermost evaluation on this thread started, if one is running.
+    static BASE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Tells the evaluator that the current thread has `bytes` of stack, e.g. when
+/// the host spawned it with [`std::thread::Builder::stack_size`].
+pub fn set_stack_size(bytes: usize) {
+    SIZE.set(bytes);
+}
+
+pub fn stack_size() -> usize {
+    SIZE.get()
+}
+
+/// Marks the start of parsing or evaluating; stack use is measured from the
+/// outermost one still running on this thread.
+pub(crate) fn enter() -> Entered {
+    let outermost = BASE.get().is_none();
+    if outermost {
+        BASE.set(Some(position()));
+    }
+    Entered { outermost }
+}
+
+/// Ends the evaluation started by [`enter`] when dropped.
+pub(crate) struct Entered {
+    outermost: bool,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        if self.outermost {
+            BASE.set(None);
+        }
+    }
+}
+
+/// Whether there is still room to parse or evaluate a nested expression.
+pub(crate) fn has_room_for_expression() -> bool {
+    remaining() >= EXPRESSION_RESERVE
+}
+
+/// Whether there is still room to enter another call.
+pub(crate) fn has_room_for_call() -> bool {
+    remaining() >= CALL_RESERVE
+}
+
+fn remaining() -> usize {
+    let used = BASE.get().map_or(0, |base| base.abs_diff(position()));
+    SIZE.get().saturating_sub(used)
+}
+
+/// Roughly where the stack pointer is.
+#[inline(never)]
+fn position() -> usize {
+    let marker = 0u8;
+    std::ptr::from_ref(std::hint::black_box(&marker)).addr()
+}
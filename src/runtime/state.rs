@@ -0,0 +1,191 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::messages::Message;
+use crate::runtime::call_stack::CallStack;
+use crate::runtime::debug::Debugger;
+use crate::runtime::error::InterpreterError;
+use crate::runtime::observer::Observer;
+use crate::runtime::profile::Profiler;
+
+/// Bounds on how much work a program may do, for running untrusted scripts.
+/// `None` leaves that resource unbounded, which is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalLimits {
+    /// Statements, loop iterations and function calls each count as a step.
+    pub max_steps: Option<u64>,
+    pub max_duration: Option<Duration>,
+    /// Approximate bytes of strings, arrays and objects created over the whole
+    /// run. Values freed along the way aren't credited back, so this is an
+    /// allocation budget rather than a bound on what is live at once.
+    pub max_allocated: Option<usize>,
+}
+
+/// Which of the [`EvalLimits`] a program ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    Duration(Duration),
+    Allocated(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Limit::Steps(steps) => Message::StepLimit.format(&[("steps", steps)]),
+            Limit::Duration(duration) => {
+                Message::DurationLimit.format(&[("duration", &format!("{duration:?}"))])
+            }
+            Limit::Allocated(bytes) => Message::AllocationLimit.format(&[("bytes", bytes)]),
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// A callback made every `every` steps, e.g. to hand control back to an
+/// async executor. An error from it stops the program.
+pub struct YieldPoint {
+    every: u64,
+    callback: Box<dyn FnMut() -> Result<(), InterpreterError>>,
+}
+
+impl fmt::Debug for YieldPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("YieldPoint")
+            .field("every", &self.every)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Evaluation state shared by every scope and module of one program.
+#[derive(Debug)]
+pub struct EvalState {
+    pub call_stack: CallStack,
+    /// Per-function timings, recorded only while profiling is on.
+    pub profiler: Option<Profiler>,
+    pub debugger: Option<Debugger>,
+    pub observers: Vec<Box<dyn Observer>>,
+    yield_point: Option<YieldPoint>,
+    limits: EvalLimits,
+    steps: u64,
+    started: Instant,
+    allocated: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    /// The source of every random number the program draws.
+    rng: StdRng,
+}
+
+impl Default for EvalState {
+    fn default() -> Self {
+        Self {
+            call_stack: CallStack::default(),
+            profiler: None,
+            debugger: None,
+            observers: Vec::new(),
+            yield_point: None,
+            limits: EvalLimits::default(),
+            steps: 0,
+            started: Instant::now(),
+            allocated: 0,
+            cancel: None,
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
+}
+
+impl EvalState {
+    pub fn limits(&self) -> EvalLimits {
+        self.limits
+    }
+
+    /// Applies `limits` from now on, with a fresh budget.
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.limits = limits;
+        self.restart();
+    }
+
+    /// Starts a fresh budget under the same limits, e.g. for the next REPL line.
+    pub fn restart(&mut self) {
+        self.steps = 0;
+        self.started = Instant::now();
+        self.allocated = 0;
+    }
+
+    /// Calls `callback` every `every` steps from now on.
+    pub fn set_yield_point(
+        &mut self,
+        every: u64,
+        callback: impl FnMut() -> Result<(), InterpreterError> + 'static,
+    ) {
+        self.yield_point = Some(YieldPoint {
+            every: every.max(1),
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Restarts the random number generator from `seed`, so the program draws
+    /// the same numbers on every run.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn cancel_flag(&self) -> Option<Arc<AtomicBool>> {
+        self.cancel.clone()
+    }
+
+    /// Watches `cancel` from now on: once another thread sets it, the program
+    /// stops at its next step with [`InterpreterError::Interrupted`].
+    pub fn set_cancel_flag(&mut self, cancel: Option<Arc<AtomicBool>>) {
+        self.cancel = cancel;
+    }
+
+    /// Counts one step, failing once the program has used up its budget or
+    /// been cancelled.
+    pub fn step(&mut self) -> Result<(), InterpreterError> {
+        if let Some(cancel) = &self.cancel
+            && cancel.load(Ordering::Relaxed)
+        {
+            return Err(InterpreterError::Interrupted);
+        }
+        self.steps += 1;
+        if let Some(max_steps) = self.limits.max_steps
+            && self.steps > max_steps
+        {
+            return Err(InterpreterError::LimitExceeded(Limit::Steps(max_steps)));
+        }
+        if let Some(max_duration) = self.limits.max_duration
+            && self.started.elapsed() > max_duration
+        {
+            return Err(InterpreterError::LimitExceeded(Limit::Duration(
+                max_duration,
+            )));
+        }
+        if let Some(yield_point) = &mut self.yield_point
+            && self.steps.is_multiple_of(yield_point.every)
+        {
+            (yield_point.callback)()?;
+        }
+        Ok(())
+    }
+
+    /// Counts `bytes` of newly created values, failing once the program has
+    /// allocated more than its budget.
+    pub fn allocate(&mut self, bytes: usize) -> Result<(), InterpreterError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        match self.limits.max_allocated {
+            Some(max_allocated) if self.allocated > max_allocated => Err(
+                InterpreterError::LimitExceeded(Limit::Allocated(max_allocated)),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lexer::{Span, TokenKind};
+use crate::messages::Message;
+use crate::parser::{Expr, ExprKind, Pattern, Stmt, StmtKind};
+
+/// The static type of an expression. `Unknown` is the gradual escape hatch:
+/// it is compatible with everything and never produces a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    String,
+    Bool,
+    Array,
+    Object,
+    Nil,
+    Range,
+    Function,
+    Unknown,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Bool => "boolean",
+            Type::Array => "array",
+            Type::Object => "object",
+            Type::Nil => "nil",
+            Type::Range => "range",
+            Type::Function => "function",
+            Type::Unknown => "unknown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeWarning {
+    pub span: Span,
+    pub message: String,
+}
+
+impl From<&TypeWarning> for Diagnostic {
+    fn from(warning: &TypeWarning) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            span: Some(warning.span),
+            code: Some("W0101"),
+            message: warning.message.clone(),
+            notes: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for TypeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Diagnostic::from(self))
+    }
+}
+
+/// Infers types across a program and reports operations that would fail at
+/// runtime with a type mismatch. Anything it can't infer is left unchecked.
+pub fn check(stmts: &[Stmt]) -> Vec<TypeWarning> {
+    let mut checker = TypeChecker {
+        scopes: vec![HashMap::new()],
+        warnings: Vec::new(),
+    };
+    for stmt in stmts {
+        checker.check_stmt(&stmt.kind);
+    }
+    checker.warnings
+}
+
+/// The argument types a builtin accepts for its first parameter, and its result.
+fn builtin_signature(name: &str) -> Option<(&'static [Type], Type)> {
+    let signature: (&'static [Type], Type) = match name {
+        "print" | "println" | "eprint" | "eprintln" | "write" => (&[], Type::Nil),
+        "print_sep" | "println_sep" | "eprint_sep" | "eprintln_sep" => (&[Type::String], Type::Nil),
+        "input" | "str" | "type" => (&[], Type::String),
+        "format" => (&[Type::String], Type::String),
+        "int" | "float" => (&[Type::Number, Type::String], Type::Number),
+        "sqrt" | "abs" | "floor" | "ceil" | "round" | "pow" => (&[Type::Number], Type::Number),
+        "min" | "max" => (&[Type::Number, Type::String, Type::Array], Type::Unknown),
+        "sum" => (&[Type::Number, Type::Array], Type::Number),
+        "sort" | "map" | "filter" => (&[Type::Array], Type::Array),
+        "reduce" => (&[Type::Array], Type::Unknown),
+        "split" => (&[Type::String], Type::Array),
+        "trim" | "trim_start" | "trim_end" | "upper" | "lower" | "replace" => {
+            (&[Type::String], Type::String)
+        }
+        "reverse" => (&[Type::Array, Type::String], Type::Unknown),
+        "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "ln" | "log10" | "log2"
+        | "exp" => (&[Type::Number], Type::Number),
+        "len" => (
+            &[Type::String, Type::Array, Type::Object, Type::Range],
+            Type::Number,
+        ),
+        "random" => (&[Type::Number], Type::Number),
+        "seed" => (&[Type::Number], Type::Nil),
+        "time" => (&[], Type::Number),
+        "chars" => (&[Type::String], Type::Array),
+        "ord" => (&[Type::String], Type::Number),
+        "chr" => (&[Type::Number], Type::String),
+        "help" => (&[Type::Function, Type::String], Type::Unknown),
+        "profile" => (&[], Type::Unknown),
+        "spawn" => (&[Type::Function], Type::Unknown),
+        "join" => (&[], Type::Unknown),
+        "channel" => (&[], Type::Unknown),
+        "send" => (&[], Type::Nil),
+        "recv" => (&[], Type::Unknown),
+        "push" => (&[Type::Array], Type::Array),
+        "pop" => (&[Type::Array], Type::Unknown),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+struct TypeChecker {
+    scopes: Vec<HashMap<String, Type>>,
+    warnings: Vec<TypeWarning>,
+}
+
+impl TypeChecker {
+    fn warn(&mut self, span: Span, message: String) {
+        self.warnings.push(TypeWarning { span, message });
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Returns the type of the statement's value: the expression for a result
+    /// statement, nil for everything else.
+    fn check_stmt(&mut self, stmt: &StmtKind) -> Type {
+        match stmt {
+            StmtKind::Result(expr) => return self.check_expr(expr),
+            StmtKind::Expr(expr) => {
+                self.check_expr(expr);
+            }
+            StmtKind::Let { name, value, .. } => {
+                let ty = match value {
+                    Some(value) => self.check_expr(value),
+                    None => Type::Nil,
+                };
+                self.declare(name, ty);
+            }
+            StmtKind::LetPattern { pattern, value } => {
+                self.check_expr(value);
+                for (name, _) in pattern.names() {
+                    self.declare(name, Type::Unknown);
+                }
+            }
+            StmtKind::Function {
+                name,
+                params,
+                rest,
+                body,
+                ..
+            } => {
+                self.declare(name, Type::Function);
+                self.in_scope(|checker| {
+                    for param in params {
+                        checker.declare(param, Type::Unknown);
+                    }
+                    if let Some(rest) = rest {
+                        checker.declare(rest, Type::Array);
+                    }
+                    checker.check_expr(body);
+                });
+            }
+            StmtKind::Struct { name, fields } => {
+                for (_, default) in fields {
+                    if let Some(default) = default {
+                        self.check_expr(default);
+                    }
+                }
+                self.declare(name, Type::Function);
+            }
+            StmtKind::Import { alias, .. } => {
+                if let Some(alias) = alias {
+                    self.declare(alias, Type::Object);
+                }
+            }
+            StmtKind::Export(declaration) => {
+                self.check_stmt(&declaration.kind);
+            }
+            StmtKind::Break(value) | StmtKind::Return(value) => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+            }
+            StmtKind::Continue => {}
+        }
+        Type::Nil
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Number(_) => Type::Number,
+            ExprKind::Boolean(_) => Type::Bool,
+            ExprKind::String(_) => Type::String,
+            ExprKind::Interpolation(_) => {
+                self.check_children(expr);
+                Type::String
+            }
+            ExprKind::Array(_) => {
+                self.check_children(expr);
+                Type::Array
+            }
+            ExprKind::Object(_) => {
+                self.check_children(expr);
+                Type::Object
+            }
+            ExprKind::Variable(name) => self.lookup(name).unwrap_or(Type::Unknown),
+            ExprKind::Parenthesized(inner) => self.check_expr(inner),
+            ExprKind::TypeTest { expr: inner, .. } => {
+                self.check_expr(inner);
+                Type::Bool
+            }
+            ExprKind::Cast {
+                expr: inner,
+                type_name,
+            } => {
+                self.check_expr(inner);
+                match type_name.as_str() {
+                    "Int" | "Float" | "Number" => Type::Number,
+                    "String" => Type::String,
+                    "Bool" => Type::Bool,
+                    "Array" => Type::Array,
+                    "Object" => Type::Object,
+                    "Range" => Type::Range,
+                    "Function" => Type::Function,
+                    "Nil" => Type::Nil,
+                    _ => Type::Unknown,
+                }
+            }
+            ExprKind::Assign { target, value } => {
+                let ty = self.check_expr(value);
+                match &target.kind {
+                    ExprKind::Variable(name) => {
+                        let declared = self.lookup(name).unwrap_or(Type::Unknown);
+                        if declared != Type::Nil && declared != ty && known(declared) && known(ty) {
+                            self.warn(
+                                target.span,
+                                Message::AssignedType.format(&[
+                                    ("name", name),
+                                    ("declared", &declared),
+                                    ("found", &ty),
+                                ]),
+                            );
+                        }
+                    }
+                    _ => {
+                        self.check_expr(target);
+                    }
+                }
+                ty
+            }
+            ExprKind::BinaryOp { left, op, right } => {
+                let left_ty = self.check_expr(left);
+                let right_ty = self.check_expr(right);
+                // In `a < b < c` the left operand is the comparison `a < b`; the
+                // chain compares `b` with `c`, which isn't worth re-inferring here.
+                if is_ordering(op)
+                    && matches!(&left.kind, ExprKind::BinaryOp { op, .. } if is_ordering(op))
+                {
+                    return Type::Bool;
+                }
+                self.binary_type(expr.span, op, left_ty, right_ty)
+            }
+            ExprKind::UnaryOp { op, expr: operand } => {
+                let ty = self.check_expr(operand);
+                match op {
+                    TokenKind::Not => Type::Bool,
+                    _ => {
+                        self.expect(operand.span, ty, &[Type::Number], &format!("'{op}'"));
+                        Type::Number
+                    }
+                }
+            }
+            ExprKind::FunctionCall { name, args } => {
+                let arg_types: Vec<Type> = args.iter().map(|arg| self.check_expr(arg)).collect();
+                if self.lookup(name).is_some() {
+                    return Type::Unknown;
+                }
+                match builtin_signature(name) {
+                    Some((accepted, result)) => {
+                        if let (Some(arg), Some(ty)) = (args.first(), arg_types.first())
+                            && !accepted.is_empty()
+                        {
+                            self.expect(arg.span, *ty, accepted, &format!("{name}()"));
+                        }
+                        result
+                    }
+                    None => Type::Unknown,
+                }
+            }
+            ExprKind::Lambda { params, rest, body } => {
+                self.in_scope(|checker| {
+                    for param in params {
+                        checker.declare(param, Type::Unknown);
+                    }
+                    if let Some(rest) = rest {
+                        checker.declare(rest, Type::Array);
+                    }
+                    checker.check_expr(body);
+                });
+                Type::Function
+            }
+            ExprKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_condition(condition, "If");
+                let then_ty = self.check_expr(then_branch);
+                let else_ty = match else_branch {
+                    Some(else_branch) => self.check_expr(else_branch),
+                    None => Type::Nil,
+                };
+                if then_ty == else_ty {
+                    then_ty
+                } else {
+                    Type::Unknown
+                }
+            }
+            ExprKind::While { condition, body } => {
+                self.check_condition(condition, "While");
+                self.check_expr(body);
+                Type::Nil
+            }
+            ExprKind::DoWhile { body, condition } => {
+                self.check_expr(body);
+                self.check_condition(condition, "While");
+                Type::Nil
+            }
+            ExprKind::Collect(body) => {
+                self.check_expr(body);
+                Type::Array
+            }
+            ExprKind::Block(stmts) => self.in_scope(|checker| {
+                let mut ty = Type::Nil;
+                for stmt in stmts {
+                    ty = checker.check_stmt(stmt);
+                }
+                ty
+            }),
+            ExprKind::For {
+                variable,
+                iterable,
+                body,
+            } => {
+                let iterable_ty = self.check_expr(iterable);
+                self.expect(
+                    iterable.span,
+                    iterable_ty,
+                    &[Type::Array, Type::Range],
+                    "a for loop",
+                );
+                let item_ty = if iterable_ty == Type::Range {
+                    Type::Number
+                } else {
+                    Type::Unknown
+                };
+                self.in_scope(|checker| {
+                    checker.declare(variable, item_ty);
+                    checker.check_expr(body);
+                });
+                Type::Nil
+            }
+            ExprKind::Match { subject, arms } => {
+                self.check_expr(subject);
+                for arm in arms {
+                    self.in_scope(|checker| {
+                        match &arm.pattern {
+                            Pattern::Literal(pattern) => {
+                                checker.check_expr(pattern);
+                            }
+                            Pattern::Binding(name) => checker.declare(name, Type::Unknown),
+                            Pattern::Wildcard => {}
+                        }
+                        checker.check_expr(&arm.body);
+                    });
+                }
+                Type::Unknown
+            }
+            ExprKind::Index { object, index } => {
+                let object_ty = self.check_expr(object);
+                let index_ty = self.check_expr(index);
+                match object_ty {
+                    Type::Array | Type::String => {
+                        self.expect(
+                            index.span,
+                            index_ty,
+                            &[Type::Number, Type::Range],
+                            &Message::IndexContext.format(&[("type", &object_ty)]),
+                        );
+                    }
+                    Type::Object => {
+                        self.expect(
+                            index.span,
+                            index_ty,
+                            &[Type::String],
+                            Message::ObjectKeyContext.text(),
+                        );
+                    }
+                    _ => {}
+                }
+                if object_ty == Type::String {
+                    Type::String
+                } else {
+                    Type::Unknown
+                }
+            }
+            _ => {
+                self.check_children(expr);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_children(&mut self, expr: &Expr) {
+        for child in expr.children() {
+            self.check_expr(child);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expr, keyword: &str) {
+        let ty = self.check_expr(condition);
+        self.expect(
+            condition.span,
+            ty,
+            &[Type::Bool],
+            &Message::ConditionContext.format(&[("keyword", &keyword)]),
+        );
+    }
+
+    /// Warns unless `ty` is unknown or one of `accepted`.
+    fn expect(&mut self, span: Span, ty: Type, accepted: &[Type], context: &str) {
+        if known(ty) && !accepted.contains(&ty) {
+            let expected: Vec<String> = accepted.iter().map(Type::to_string).collect();
+            self.warn(
+                span,
+                Message::ExpectsType.format(&[
+                    ("context", &context),
+                    ("expected", &expected.join(Message::Or.text())),
+                    ("found", &ty),
+                ]),
+            );
+        }
+    }
+
+    fn binary_type(&mut self, span: Span, op: &TokenKind, left: Type, right: Type) -> Type {
+        use Type::*;
+        let result = match op {
+            TokenKind::Equal
+            | TokenKind::NotEqual
+            | TokenKind::LogicalAnd
+            | TokenKind::LogicalOr => return Bool,
+            TokenKind::NilCoalesce => return if left == Nil { right } else { Unknown },
+            _ if !known(left) || !known(right) => return Unknown,
+            TokenKind::Plus => match (left, right) {
+                (Number, Number) => Some(Number),
+                (String, _) | (_, String) => Some(String),
+                _ => None,
+            },
+            TokenKind::Multiply => match (left, right) {
+                (Number, Number) => Some(Number),
+                (String, Number) | (Number, String) => Some(String),
+                _ => None,
+            },
+            TokenKind::Minus
+            | TokenKind::Divide
+            | TokenKind::FloorDivide
+            | TokenKind::Modulo
+            | TokenKind::BitAnd
+            | TokenKind::BitOr
+            | TokenKind::BitXor
+            | TokenKind::ShiftLeft
+            | TokenKind::ShiftRight => (left == Number && right == Number).then_some(Number),
+            _ if is_ordering(op) => {
+                matches!((left, right), (Number, Number) | (String, String)).then_some(Bool)
+            }
+            TokenKind::DotDot => (left == Number && right == Number).then_some(Range),
+            _ => Some(Unknown),
+        };
+        result.unwrap_or_else(|| {
+            self.warn(
+                span,
+                Message::CannotApply.format(&[("op", op), ("left", &left), ("right", &right)]),
+            );
+            Unknown
+        })
+    }
+}
+
+fn is_ordering(op: &TokenKind) -> bool {
+    matches!(
+        op,
+        TokenKind::GreaterThan
+            | TokenKind::GreaterThanOrEqual
+            | TokenKind::LessThan
+            | TokenKind::LessThanOrEqual
+    )
+}
+
+fn known(ty: Type) -> bool {
+    ty != Type::Unknown
+}
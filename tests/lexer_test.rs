@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
     use mp_lang::{
-        lexer::{Span, TokenKind, tokenize, tokenize_with_errors},
+        lexer::{
+            Lexer, LexerErrorKind, Span, TemplatePart, TokenKind, tokenize, tokenize_with_errors,
+        },
         runtime::environment::value::Number,
     };
 
@@ -211,4 +213,515 @@ mod tests {
         assert_eq!(tokens[14].kind, TokenKind::RightBrace);
         assert_eq!(tokens[14].span, Span { line: 4, column: 1 });
     }
+
+    #[test]
+    fn test_not_operator() {
+        let tokens = tokenize("!x != !y");
+        assert_eq!(tokens[0].kind, TokenKind::Not);
+        assert_eq!(tokens[0].span, Span { line: 1, column: 1 });
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("x".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::NotEqual);
+        assert_eq!(tokens[2].span, Span { line: 1, column: 4 });
+        assert_eq!(tokens[3].kind, TokenKind::Not);
+        assert_eq!(tokens[3].span, Span { line: 1, column: 7 });
+        assert_eq!(tokens[4].kind, TokenKind::Identifier("y".to_string()));
+        assert_eq!(tokens[5].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn test_template_string() {
+        let (tokens, errors) = tokenize_with_errors("`x is ${x + 1}\\``");
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].span, Span { line: 1, column: 1 });
+        let TokenKind::Template(parts) = &tokens[0].kind else {
+            panic!("Expected Template token");
+        };
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], TemplatePart::Literal("x is ".to_string()));
+        let TemplatePart::Code(code) = &parts[1] else {
+            panic!("Expected Code part");
+        };
+        assert_eq!(code[0].kind, TokenKind::Identifier("x".to_string()));
+        assert_eq!(code[0].span, Span { line: 1, column: 9 });
+        assert_eq!(code[1].kind, TokenKind::Plus);
+        assert_eq!(code[3].kind, TokenKind::Eof);
+        assert_eq!(parts[2], TemplatePart::Literal("`".to_string()));
+        assert_eq!(tokens[1].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn test_unclosed_template_string() {
+        let (_, errors) = tokenize_with_errors("`abc ${1 + ");
+        assert_eq!(errors.len(), 1);
+        let (_, errors) = tokenize_with_errors("`abc");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_range_dots() {
+        let tokens = tokenize("1..3 1.5");
+        assert_eq!(tokens[0].kind, TokenKind::Number(Number::Int(1)));
+        assert_eq!(tokens[1].kind, TokenKind::DotDot);
+        assert_eq!(tokens[1].span, Span { line: 1, column: 2 });
+        assert_eq!(tokens[2].kind, TokenKind::Number(Number::Int(3)));
+        assert_eq!(tokens[3].kind, TokenKind::Number(Number::Float(1.5)));
+        assert_eq!(tokens[4].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn test_match_tokens() {
+        let tokens = tokenize("match x { _ => 1 }");
+        assert_eq!(tokens[0].kind, TokenKind::Match);
+        assert_eq!(tokens[4].kind, TokenKind::FatArrow);
+        assert_eq!(
+            tokens[4].span,
+            Span {
+                line: 1,
+                column: 13
+            }
+        );
+    }
+
+    #[test]
+    fn test_for_in_keywords() {
+        let tokens = tokenize("for i in 0..3");
+        assert_eq!(tokens[0].kind, TokenKind::For);
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("i".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::In);
+        assert_eq!(tokens[4].kind, TokenKind::DotDot);
+    }
+
+    #[test]
+    fn test_ellipsis() {
+        let tokens = tokenize("...args ..");
+        assert_eq!(tokens[0].kind, TokenKind::Ellipsis);
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("args".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::DotDot);
+    }
+
+    #[test]
+    fn test_const_keyword() {
+        let tokens = tokenize("const limit = 10");
+        assert_eq!(tokens[0].kind, TokenKind::Const);
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("limit".to_string()));
+    }
+
+    #[test]
+    fn test_loop_keyword() {
+        let tokens = tokenize("loop { break 1 }");
+        assert_eq!(tokens[0].kind, TokenKind::Loop);
+        assert_eq!(tokens[2].kind, TokenKind::Break);
+    }
+
+    #[test]
+    fn test_nil_coalesce() {
+        let (tokens, errors) = tokenize_with_errors("a ?? b ? c");
+        assert_eq!(tokens[1].kind, TokenKind::NilCoalesce);
+        assert_eq!(tokens[2].kind, TokenKind::Identifier("b".to_string()));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let tokens = tokenize("a & b | c ^ ~d << 1 >> 2 && e || f");
+        let kinds: Vec<&TokenKind> = tokens
+            .iter()
+            .map(|t| &t.kind)
+            .filter(|k| !matches!(k, TokenKind::Identifier(_) | TokenKind::Number(_)))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                &TokenKind::BitAnd,
+                &TokenKind::BitOr,
+                &TokenKind::BitXor,
+                &TokenKind::BitNot,
+                &TokenKind::ShiftLeft,
+                &TokenKind::ShiftRight,
+                &TokenKind::LogicalAnd,
+                &TokenKind::LogicalOr,
+                &TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_floor_divide() {
+        let tokens = tokenize("7 ~/ 2 // comment\n~x");
+        assert_eq!(tokens[1].kind, TokenKind::FloorDivide);
+        assert_eq!(tokens[3].kind, TokenKind::Comment(" comment".to_string()));
+        assert_eq!(tokens[5].kind, TokenKind::BitNot);
+    }
+
+    #[test]
+    fn test_code_point_escapes() {
+        let (tokens, errors) = tokenize_with_errors(r#""\u{1F600} \x41\0 \u{e9}""#);
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].kind, TokenKind::String("😀 A\0 é".to_string()));
+
+        for (source, sequence) in [
+            (r#""\u{110000}""#, r"\u{110000}"),
+            (r#""\u{}""#, r"\u{}"),
+            (r#""\u41""#, r"\u41"),
+            (r#""\x4""#, r"\x4"),
+            (r#""\xFF""#, r"\xFF"),
+            (r#""\q""#, r"\q"),
+            (r#"'\d'"#, r"\d"),
+            ("`a\\{b`", r"\{"),
+        ] {
+            let (_, errors) = tokenize_with_errors(source);
+            assert_eq!(errors.len(), 1, "{source}");
+            assert!(
+                matches!(&errors[0].kind, LexerErrorKind::InvalidEscape(s) if s == sequence),
+                "{source}: {:?}",
+                errors[0].kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiline_strings() {
+        let source = "let s = \"\"\"\nfirst\n  \\\"second\\\"\n\"\"\";\nlet y = 1;";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[3].kind,
+            TokenKind::String("first\n  \"second\"\n".to_string())
+        );
+        assert_eq!((tokens[3].span.line, tokens[3].span.column), (1, 9));
+
+        let y = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Identifier("y".to_string()))
+            .unwrap();
+        assert_eq!((y.span.line, y.span.column), (5, 5));
+
+        let (_, errors) = tokenize_with_errors("\"\"\"never closed\n");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, LexerErrorKind::UnclosedString));
+
+        // A lone newline still ends an ordinary string.
+        let (_, errors) = tokenize_with_errors("\"abc\ndef\"");
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_radix_literals() {
+        let (tokens, errors) = tokenize_with_errors("0xFF 0o755 0b1010 0XaB");
+        assert!(errors.is_empty());
+        let values: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            &values[..4],
+            &[
+                TokenKind::Number(Number::Int(255)),
+                TokenKind::Number(Number::Int(0o755)),
+                TokenKind::Number(Number::Int(10)),
+                TokenKind::Number(Number::Int(0xab)),
+            ]
+        );
+
+        for (source, literal) in [
+            ("0x", "0x"),
+            ("0b102", "0b102"),
+            ("0o8", "0o8"),
+            ("0xZZ", "0xZZ"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert_eq!(errors.len(), 1, "{source}");
+            assert!(
+                matches!(&errors[0].kind, LexerErrorKind::InvalidNumber(s) if s == literal),
+                "{source}: {:?}",
+                errors[0].kind
+            );
+            assert_eq!(tokens[0].kind, TokenKind::Number(Number::Int(0)));
+        }
+    }
+
+    #[test]
+    fn test_separators_and_exponents() {
+        let (tokens, errors) =
+            tokenize_with_errors("1_000_000 1.5e-3 2E10 6.02e+23 1_0.2_5 0xFF_FF");
+        assert!(errors.is_empty());
+        let values: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            &values[..6],
+            &[
+                TokenKind::Number(Number::Int(1_000_000)),
+                TokenKind::Number(Number::Float(1.5e-3)),
+                TokenKind::Number(Number::Float(2e10)),
+                TokenKind::Number(Number::Float(6.02e23)),
+                TokenKind::Number(Number::Float(10.25)),
+                TokenKind::Number(Number::Int(0xffff)),
+            ]
+        );
+
+        // Stray separators and empty exponents are malformed, not a number
+        // followed by an identifier.
+        for (source, literal) in [
+            ("1_", "1_"),
+            ("1__0", "1__0"),
+            ("1_.5", "1_"),
+            ("3e", "3e"),
+            ("3e+", "3e+"),
+            ("2.5ex", "2.5ex"),
+            ("1e5_", "1e5_"),
+            ("0xFF_", "0xFF_"),
+            ("0b1__0", "0b1__0"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert_eq!(errors.len(), 1, "{source}");
+            assert!(
+                matches!(&errors[0].kind, LexerErrorKind::InvalidNumber(s) if s == literal),
+                "{source}: {:?}",
+                errors[0].kind
+            );
+            assert_eq!(tokens[0].kind, TokenKind::Number(Number::Int(0)));
+        }
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let (tokens, errors) = tokenize_with_errors(r"'a' '\n' '\'' 'é' `${'}'}`");
+        assert!(errors.is_empty(), "{errors:?}");
+        let values: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            &values[..4],
+            &[
+                TokenKind::String("a".to_string()),
+                TokenKind::String("\n".to_string()),
+                TokenKind::String("'".to_string()),
+                TokenKind::String("é".to_string()),
+            ]
+        );
+
+        for source in ["''", "'ab'"] {
+            let (_, errors) = tokenize_with_errors(source);
+            assert_eq!(errors.len(), 1, "{source}");
+            assert!(matches!(errors[0].kind, LexerErrorKind::InvalidChar(_)));
+        }
+        let (_, errors) = tokenize_with_errors("'a");
+        assert!(matches!(errors[0].kind, LexerErrorKind::UnclosedString));
+    }
+
+    #[test]
+    fn test_newlines_inside_brackets() {
+        let count = |source: &str| {
+            tokenize(source)
+                .iter()
+                .filter(|t| t.kind == TokenKind::Newline)
+                .count()
+        };
+        assert_eq!(count("f(\n1,\n2\n)\n"), 1);
+        assert_eq!(count("[\n1\n]"), 0);
+        // A block keeps its line breaks even when it sits inside parentheses.
+        assert_eq!(count("f(fn() {\na\nb\n})"), 3);
+    }
+
+    #[test]
+    fn test_line_continuation_after_operators() {
+        let kinds = |source: &str| -> Vec<TokenKind> {
+            tokenize(source).into_iter().map(|t| t.kind).collect()
+        };
+        assert_eq!(kinds("a +\nb"), kinds("a + b"));
+        assert!(!kinds("a && // why\nb").contains(&TokenKind::Newline));
+        assert_eq!(kinds("x, y =\n1, 2"), kinds("x, y = 1, 2"));
+        assert!(kinds("a\n+b").contains(&TokenKind::Newline));
+    }
+
+    #[test]
+    fn test_shebang_line() {
+        let (tokens, errors) = tokenize_with_errors("#!/usr/bin/env mp\nlet x = 1");
+        assert!(errors.is_empty());
+        assert_eq!(tokens[0].kind, TokenKind::Newline);
+        assert_eq!(tokens[1].kind, TokenKind::Let);
+        assert_eq!(tokens[1].span, Span { line: 2, column: 1 });
+
+        // Only the first line can be a shebang.
+        let (_, errors) = tokenize_with_errors("let x = 1\n#!/usr/bin/env mp");
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let tokens = tokenize("/// Adds.\n// plain\n//// banner\nfn");
+        assert_eq!(tokens[0].kind, TokenKind::DocComment(" Adds.".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::Comment(" plain".to_string()));
+        assert_eq!(tokens[4].kind, TokenKind::Comment("// banner".to_string()));
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let (tokens, errors) = tokenize_with_errors("1 /* a /* b */ c */ 2");
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[1].kind,
+            TokenKind::Comment(" a /* b */ c ".to_string())
+        );
+        assert_eq!(tokens[2].kind, TokenKind::Number(Number::Int(2)));
+
+        let (tokens, errors) = tokenize_with_errors("/* /* /* deep */ */ */x");
+        assert!(errors.is_empty());
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("x".to_string()));
+
+        let (_, errors) = tokenize_with_errors("/* outer /* inner */ still open");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, LexerErrorKind::UnclosedComment));
+    }
+
+    #[test]
+    fn test_identifiers_with_digits() {
+        let (tokens, errors) = tokenize_with_errors("item2 v2_final _9 x1y2 letter if2 fn_1");
+        assert!(errors.is_empty());
+        let names: Vec<_> = tokens
+            .iter()
+            .take_while(|t| t.kind != TokenKind::Eof)
+            .map(|t| t.kind.clone())
+            .collect();
+        let expected: Vec<_> = ["item2", "v2_final", "_9", "x1y2", "letter", "if2", "fn_1"]
+            .iter()
+            .map(|name| TokenKind::Identifier(name.to_string()))
+            .collect();
+        assert_eq!(names, expected);
+
+        // A leading digit still starts a number.
+        let tokens = tokenize("2x");
+        assert_eq!(tokens[0].kind, TokenKind::Number(Number::Int(2)));
+        assert_eq!(tokens[1].kind, TokenKind::Identifier("x".to_string()));
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let (tokens, errors) = tokenize_with_errors("let 总数 = café_2 + Δx + 値");
+        assert!(errors.is_empty());
+        let names: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Identifier(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["总数", "café_2", "Δx", "値"]);
+
+        // Symbols outside XID_Start can't begin a name.
+        let (_, errors) = tokenize_with_errors("let ★ = 1");
+        assert!(matches!(
+            errors[0].kind,
+            LexerErrorKind::UnexpectedCharacter('★')
+        ));
+    }
+
+    #[test]
+    fn test_reports_every_error_and_keeps_going() {
+        let (tokens, errors) =
+            tokenize_with_errors("let a = 1 @ 2\nlet b = 0b2 # \"\\q\\u{}\nlet c = 3");
+        let kinds: Vec<String> = errors.iter().map(|e| e.kind.to_string()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "Unexpected character: '@'",
+                "Invalid number: '0b2'",
+                "Unexpected character: '#'",
+                "Invalid escape sequence: '\\q'",
+                "Invalid escape sequence: '\\u{}'",
+                "Unclosed string",
+            ]
+        );
+        assert_eq!(
+            errors[2].span,
+            Span {
+                line: 2,
+                column: 13
+            }
+        );
+        assert!(errors[0].to_string().starts_with("Error at 1:11: "));
+
+        // Tokens after each problem are still produced.
+        let last_let = tokens
+            .iter()
+            .rposition(|t| t.kind == TokenKind::Let)
+            .unwrap();
+        assert_eq!(tokens[last_let].span.line, 3);
+        assert_eq!(tokens[last_let + 3].kind, TokenKind::Number(Number::Int(3)));
+    }
+
+    #[test]
+    fn test_streaming_lexer() {
+        let mut lexer = Lexer::new("let x = @1");
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Let);
+        assert_eq!(
+            lexer.next().unwrap().unwrap().kind,
+            TokenKind::Identifier("x".to_string())
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Assign);
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            LexerErrorKind::UnexpectedCharacter('@')
+        ));
+        assert_eq!(
+            lexer.next().unwrap().unwrap().kind,
+            TokenKind::Number(Number::Int(1))
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Eof);
+        assert!(lexer.next().is_none());
+
+        // Collecting the stream gives the same tokens as `tokenize`.
+        let source = "fn f(a,\n b) { a +\n b }\n/* c */ f(1, 2)";
+        let streamed: Vec<_> = Lexer::new(source).map(|item| item.unwrap().kind).collect();
+        let collected: Vec<_> = tokenize(source).into_iter().map(|t| t.kind).collect();
+        assert_eq!(streamed, collected);
+    }
+
+    #[test]
+    fn test_tokens_keep_their_lexeme() {
+        let source = "let n = 0xFF_FF + 1.5e3 // note\nprint(\"a\\u{41}\", 'c', `x${n}`)";
+        let lexemes: Vec<_> = tokenize(source).into_iter().map(|t| t.lexeme).collect();
+        assert_eq!(
+            lexemes,
+            [
+                "let",
+                "n",
+                "=",
+                "0xFF_FF",
+                "+",
+                "1.5e3",
+                "// note",
+                "\n",
+                "print",
+                "(",
+                "\"a\\u{41}\"",
+                ",",
+                "'c'",
+                ",",
+                "`x${n}`",
+                ")",
+                ""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crlf_and_bom() {
+        let source = "\u{FEFF}let a = 1 // one\r\nlet s = \"\"\"\r\nx\r\ny\"\"\"\r\n  b";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(tokens[0].kind, TokenKind::Let);
+        assert_eq!(tokens[0].span, Span { line: 1, column: 1 });
+        assert_eq!(tokens[4].kind, TokenKind::Comment(" one".to_string()));
+        assert_eq!(tokens[5].kind, TokenKind::Newline);
+        assert_eq!(tokens[5].lexeme, "\r\n");
+        assert_eq!(tokens[6].span, Span { line: 2, column: 1 });
+        assert_eq!(tokens[9].kind, TokenKind::String("x\ny".to_string()));
+        let b = tokens.iter().rev().nth(1).unwrap();
+        assert_eq!(b.kind, TokenKind::Identifier("b".to_string()));
+        assert_eq!(b.span, Span { line: 5, column: 3 });
+
+        let (tokens, _) = tokenize_with_errors("#!/usr/bin/env mp\r\nx");
+        assert_eq!(tokens[1].span, Span { line: 2, column: 1 });
+
+        // A newline still ends an ordinary string, without the `\r`.
+        let (tokens, errors) = tokenize_with_errors("\"ab\r\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(tokens[0].kind, TokenKind::String("ab".to_string()));
+    }
 }
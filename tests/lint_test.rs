@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use mp_lang::{
+        lexer::tokenize_with_errors,
+        lint::{LintKind, Severity, lint},
+        parser::parse,
+    };
+
+    fn lints(source: &str) -> Vec<String> {
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        lint(&parse(tokens))
+            .into_iter()
+            .map(|lint| lint.kind.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_programs() {
+        for source in [
+            "let x = 1; print(x)",
+            "fn f(a) { a + 1 }; f(2)",
+            "let _ignored = 1",
+            "export let shared = 1",
+            "let xs = [1]; xs[0] = 2",
+            "fn later() { value }; let value = 1; later()",
+            "let [a, b] = [1, 2]; a + b",
+            "for i in 0..3 { print(i) }",
+            "fn f(n) { if n > 0 { return 1 }; 0 }; f(1)",
+        ] {
+            assert_eq!(lints(source), Vec::<String>::new(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_unused_bindings() {
+        assert_eq!(lints("let x = 1"), ["Unused variable 'x'"]);
+        assert_eq!(lints("let x; x = 5"), ["Unused variable 'x'"]);
+        assert_eq!(
+            lints("let f = fn(a, b) a; f(1, 2)"),
+            ["Unused parameter 'b'"]
+        );
+        assert_eq!(
+            lints("fn g(x, ...rest) { 1 }; g(1)"),
+            ["Unused parameter 'x'", "Unused parameter 'rest'"]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        assert_eq!(
+            lints("fn f() { return 1; print(2) }; f()"),
+            ["Unreachable code"]
+        );
+        assert_eq!(
+            lints("while true { break; print(1) }"),
+            ["Unreachable code"]
+        );
+    }
+
+    #[test]
+    fn test_shadowing_is_a_hint() {
+        let (tokens, _) = tokenize_with_errors("let x = 1;\n{ let x = 2; print(x) }\nprint(x)");
+        let lints = lint(&parse(tokens));
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].kind, LintKind::Shadowing("x".into()));
+        assert_eq!(lints[0].severity, Severity::Hint);
+        assert_eq!(
+            lints[0].to_string(),
+            "Hint at 2:7: 'x' shadows a variable from an outer scope"
+        );
+    }
+}
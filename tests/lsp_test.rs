@@ -32,7 +32,7 @@ mod tests {
         let result = diagnostics.analyze(content);
 
         assert!(
-            result.0.len() > 0,
+            !result.0.is_empty(),
             "Should have lexer error for invalid number"
         );
         assert_eq!(
@@ -50,7 +50,7 @@ mod tests {
         let result = diagnostics.analyze(content);
 
         assert!(
-            result.0.len() > 0,
+            !result.0.is_empty(),
             "Should have lexer error for unexpected character"
         );
         assert_eq!(
@@ -68,7 +68,7 @@ mod tests {
         let result = diagnostics.analyze(content);
 
         assert!(
-            result.0.len() > 0,
+            !result.0.is_empty(),
             "Should have lexer error for unclosed string"
         );
         assert_eq!(
@@ -86,7 +86,7 @@ mod tests {
         let result = diagnostics.analyze(content);
 
         assert!(
-            result.0.len() > 0,
+            !result.0.is_empty(),
             "Should have lexer error for unclosed comment"
         );
         assert_eq!(
@@ -116,7 +116,7 @@ mod tests {
         let content = "let x = ";
         let result = diagnostics.analyze(content);
 
-        assert!(result.0.len() > 0, "Should have parser error");
+        assert!(!result.0.is_empty(), "Should have parser error");
         assert_eq!(
             result.0[0].code,
             Some(tower_lsp_server::ls_types::NumberOrString::String(
@@ -131,7 +131,7 @@ mod tests {
         let content = "let x = @\nlet y = 10";
         let result = diagnostics.analyze(content);
 
-        assert!(result.0.len() > 0, "Should have lexer error");
+        assert!(!result.0.is_empty(), "Should have lexer error");
         assert_eq!(
             result.0[0].code,
             Some(tower_lsp_server::ls_types::NumberOrString::String(
@@ -146,7 +146,7 @@ mod tests {
         let content = "let x = @";
         let result = diagnostics.analyze(content);
 
-        assert!(result.0.len() > 0, "Should have diagnostic");
+        assert!(!result.0.is_empty(), "Should have diagnostic");
         let range = &result.0[0].range;
         assert!(range.start.line == 0, "Should have valid line at 0");
         assert!(
@@ -304,4 +304,51 @@ mod tests {
             "Should have hover information for strings"
         );
     }
+
+    #[test]
+    fn test_diagnostics_match_binding_scoped() {
+        let diagnostics = MpDiagnostics::new();
+        let content = "let x = 1\nlet y = match x { n => n + 1 }\nprint(n)";
+        let result = diagnostics.analyze(content);
+
+        assert_eq!(
+            result.0.len(),
+            1,
+            "Only the use outside the arm is undefined"
+        );
+        assert!(result.0[0].message.contains("'n'"));
+    }
+
+    #[test]
+    fn test_diagnostics_lambda_params_and_calls() {
+        let diagnostics = MpDiagnostics::new();
+        let content = "let double = fn(x) { x * 2 }\nprint(double(2))";
+        let result = diagnostics.analyze(content);
+
+        assert!(
+            result.0.is_empty(),
+            "Lambda params and calls should resolve"
+        );
+    }
+
+    #[test]
+    fn test_diagnostics_variadic_arity() {
+        let diagnostics = MpDiagnostics::new();
+        let content = "fn f(a, ...rest) { rest }\nf(1, 2, 3)\nf()";
+        let result = diagnostics.analyze(content);
+
+        assert_eq!(result.0.len(), 1, "Only the call missing 'a' is reported");
+        assert!(result.0[0].message.contains("at least 1"));
+    }
+
+    #[test]
+    fn test_diagnostics_imports() {
+        let diagnostics = MpDiagnostics::new();
+        let result = diagnostics.analyze("import m from \"m.mp\"\nprint(m)\nprint(other)");
+        assert_eq!(result.0.len(), 1, "Only the unknown name is reported");
+        assert!(result.0[0].message.contains("'other'"));
+
+        let result = diagnostics.analyze("import \"m.mp\"\nprint(helper(1))");
+        assert!(result.0.is_empty(), "Names may come from a merged import");
+    }
 }
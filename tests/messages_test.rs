@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use mp_lang::{
+        lexer::tokenize_with_errors,
+        messages::{Language, Message, is_placeholder, set_language},
+        parser::parse,
+        runtime::eval::eval,
+    };
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .filter(|name| is_placeholder(name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_every_message_has_the_same_placeholders_in_each_language() {
+        for message in Message::ALL {
+            let english = message.template(Language::English);
+            let chinese = message.template(Language::Chinese);
+            let mut expected = placeholders(english);
+            // Chinese has no plural endings.
+            expected.retain(|name| *name != "s");
+            assert_eq!(expected, placeholders(chinese), "{message:?}");
+        }
+    }
+
+    // The language is process-wide, so this is the only test that changes it.
+    #[test]
+    fn test_errors_follow_the_chosen_language() {
+        let (tokens, _) = tokenize_with_errors("fn f(a, b) { a / b }\nf(1)");
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at 2:4: Function 'f' expects 2 arguments, got 1\n  in f called at 2:4"
+        );
+
+        set_language(Language::Chinese);
+        let translated = error.to_string();
+        let (tokens, _) = tokenize_with_errors("1 / 0");
+        let division = eval(parse(tokens)).unwrap_err().to_string();
+        let (tokens, _) = tokenize_with_errors("if 1 { 2 }");
+        let condition = eval(parse(tokens)).unwrap_err().to_string();
+        let (tokens, _) = tokenize_with_errors("[1] - \"a\"");
+        let operands = eval(parse(tokens)).unwrap_err().to_string();
+        let (_, errors) = tokenize_with_errors("\"abc");
+        let unclosed = errors[0].to_string();
+        set_language(Language::English);
+
+        assert_eq!(
+            translated,
+            "错误（2:4）：函数 'f' 需要 2 个参数，实际传入 1 个\n  在 f 中，调用于 2:4"
+        );
+        assert_eq!(division, "错误（1:5）：除以零");
+        assert_eq!(condition, "错误（1:10）：类型不匹配：if 条件必须是布尔值");
+        assert_eq!(
+            operands,
+            "错误（1:7）：类型不匹配：不能对 array 和 string 使用 '-'"
+        );
+        assert_eq!(unclosed, "错误（1:1）：未闭合的字符串：未闭合的字符串");
+        assert_eq!("zh".parse::<Language>(), Ok(Language::Chinese));
+    }
+}
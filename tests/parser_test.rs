@@ -1,8 +1,11 @@
 #[cfg(test)]
 mod tests {
     use mp_lang::{
-        lexer::{TokenKind, tokenize_with_errors},
-        parser::{ExprKind, StmtKind, parse},
+        lexer::{Span, TokenKind, tokenize_with_errors},
+        parser::{
+            BindingPattern, Expr, ExprKind, InterpolationPart, ObjectKey, Pattern, StmtKind, parse,
+            parse_with_errors,
+        },
         runtime::environment::value::Number,
     };
 
@@ -254,4 +257,921 @@ mod tests {
             _ => panic!("Expected Result statement"),
         }
     }
+
+    #[test]
+    fn test_logical_operator_precedence() {
+        let (tokens, errors) = tokenize_with_errors("x > 0 && x < 10 || y == 1");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert_eq!(ast.len(), 1);
+        match &ast[0].kind {
+            StmtKind::Result(expr) => {
+                if let ExprKind::BinaryOp { left, op, right } = &expr.kind {
+                    assert!(matches!(op, TokenKind::LogicalOr));
+                    assert!(matches!(
+                        &left.kind,
+                        ExprKind::BinaryOp {
+                            op: TokenKind::LogicalAnd,
+                            ..
+                        }
+                    ));
+                    assert!(matches!(
+                        &right.kind,
+                        ExprKind::BinaryOp {
+                            op: TokenKind::Equal,
+                            ..
+                        }
+                    ));
+                } else {
+                    panic!("Expected BinaryOp");
+                }
+            }
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_template_string_expr() {
+        let (tokens, errors) = tokenize_with_errors("`a${1}b${\"}\"}`");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => {
+                if let ExprKind::Interpolation(parts) = &expr.kind {
+                    assert_eq!(parts.len(), 4);
+                    assert!(matches!(&parts[0], InterpolationPart::Literal(s) if s == "a"));
+                    assert!(matches!(&parts[3], InterpolationPart::Expr(e)
+                        if matches!(&e.kind, ExprKind::String(s) if s == "}")));
+                } else {
+                    panic!("Expected Interpolation");
+                }
+            }
+            _ => panic!("Expected Result statement"),
+        }
+
+        let (tokens, _) = tokenize_with_errors("`${1 2}`");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_slice_expression() {
+        for (source, has_start, has_end) in [
+            ("arr[1..3]", true, true),
+            ("arr[..3]", false, true),
+            ("arr[2..]", true, false),
+            ("arr[..]", false, false),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let (ast, errors) = parse_with_errors(tokens);
+            assert!(errors.is_empty(), "{source}");
+            match &ast[0].kind {
+                StmtKind::Result(expr) => match &expr.kind {
+                    ExprKind::Slice { start, end, .. } => {
+                        assert_eq!(start.is_some(), has_start, "{source}");
+                        assert_eq!(end.is_some(), has_end, "{source}");
+                    }
+                    _ => panic!("Expected Slice"),
+                },
+                _ => panic!("Expected Result statement"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_method_call_expression() {
+        let (tokens, errors) = tokenize_with_errors("arr.push(1, 2).len()");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::MethodCall {
+                    object,
+                    method,
+                    args,
+                } => {
+                    assert_eq!(method, "len");
+                    assert!(args.is_empty());
+                    match &object.kind {
+                        ExprKind::MethodCall { method, args, .. } => {
+                            assert_eq!(method, "push");
+                            assert_eq!(args.len(), 2);
+                        }
+                        _ => panic!("Expected inner MethodCall"),
+                    }
+                }
+                _ => panic!("Expected MethodCall"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        let source = "if a { 1 } else if b { 2 }\nelse if c { 3 }\nelse { 4 }";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(ast.len(), 1);
+
+        let mut arms = 0;
+        let mut current = match &ast[0].kind {
+            StmtKind::Result(expr) => expr,
+            _ => panic!("Expected Result statement"),
+        };
+        while let ExprKind::If { else_branch, .. } = &current.kind {
+            arms += 1;
+            current = else_branch.as_ref().expect("Expected else branch");
+        }
+        assert!(matches!(current.kind, ExprKind::Block(_)));
+        assert_eq!(arms, 3);
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let source = "match x {\n    1 => \"one\",\n    -2 => \"minus two\",\n    _ => \"other\",\n    n => n\n}";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Match { arms, .. } => {
+                    assert_eq!(arms.len(), 4);
+                    assert!(matches!(arms[0].pattern, Pattern::Literal(_)));
+                    assert!(matches!(arms[1].pattern, Pattern::Literal(_)));
+                    assert_eq!(arms[2].pattern, Pattern::Wildcard);
+                    assert_eq!(arms[3].pattern, Pattern::Binding("n".to_string()));
+                }
+                _ => panic!("Expected Match"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_range_and_for_expression() {
+        let (tokens, errors) = tokenize_with_errors("for i in 0..n + 1 { i }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::For {
+                    variable, iterable, ..
+                } => {
+                    assert_eq!(variable, "i");
+                    match &iterable.kind {
+                        ExprKind::BinaryOp { op, right, .. } => {
+                            assert_eq!(*op, TokenKind::DotDot);
+                            assert!(matches!(
+                                right.kind,
+                                ExprKind::BinaryOp {
+                                    op: TokenKind::Plus,
+                                    ..
+                                }
+                            ));
+                        }
+                        _ => panic!("Expected range BinaryOp"),
+                    }
+                }
+                _ => panic!("Expected For"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_lambda_expression() {
+        let (tokens, errors) = tokenize_with_errors("let f = fn(a, b) { a + b }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Let {
+                value: Some(value), ..
+            } => match &value.kind {
+                ExprKind::Lambda { params, .. } => assert_eq!(params, &["a", "b"]),
+                _ => panic!("Expected Lambda"),
+            },
+            _ => panic!("Expected Let statement"),
+        }
+
+        let (tokens, errors) = tokenize_with_errors("fn() { 1 }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert!(matches!(
+            &ast[0].kind,
+            StmtKind::Result(Expr {
+                kind: ExprKind::Lambda { .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_variadic_function_decl() {
+        let (tokens, errors) = tokenize_with_errors("fn log(level, ...parts) { parts }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Function { params, rest, .. } => {
+                assert_eq!(params, &["level"]);
+                assert_eq!(rest.as_deref(), Some("parts"));
+            }
+            _ => panic!("Expected Function statement"),
+        }
+
+        let (tokens, errors) = tokenize_with_errors("fn bad(...a, b) { a }");
+        assert!(errors.is_empty());
+        let (_, errors) = parse_with_errors(tokens);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let (tokens, errors) = tokenize_with_errors("let [a, b] = pair\nlet {x, y} = point");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(ast.len(), 2);
+        match &ast[0].kind {
+            StmtKind::LetPattern {
+                pattern: BindingPattern::Array(names),
+                ..
+            } => {
+                let names: Vec<&str> = names.iter().map(|(n, _)| n.as_str()).collect();
+                assert_eq!(names, ["a", "b"]);
+            }
+            _ => panic!("Expected array LetPattern"),
+        }
+        match &ast[1].kind {
+            StmtKind::LetPattern {
+                pattern: BindingPattern::Object(names),
+                ..
+            } => {
+                let names: Vec<&str> = names.iter().map(|(n, _)| n.as_str()).collect();
+                assert_eq!(names, ["x", "y"]);
+            }
+            _ => panic!("Expected object LetPattern"),
+        }
+    }
+
+    #[test]
+    fn test_multiple_assignment() {
+        let (tokens, errors) = tokenize_with_errors("let a, b = 1, 2\na, b = b, a");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::LetPattern {
+                pattern: BindingPattern::Array(names),
+                value,
+            } => {
+                assert_eq!(names.len(), 2);
+                assert!(matches!(&value.kind, ExprKind::Array(items) if items.len() == 2));
+            }
+            _ => panic!("Expected LetPattern"),
+        }
+        match &ast[1].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Assign { target, .. } => {
+                    assert!(matches!(&target.kind, ExprKind::Array(targets) if targets.len() == 2))
+                }
+                _ => panic!("Expected assignment"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+
+        let (tokens, errors) = tokenize_with_errors("a, 1 = 2, 3");
+        assert!(errors.is_empty());
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_const_declaration() {
+        let (tokens, errors) = tokenize_with_errors("const a = 1\nlet b = 2");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert!(matches!(
+            &ast[0].kind,
+            StmtKind::Let { name, constant: true, .. } if name == "a"
+        ));
+        assert!(matches!(
+            &ast[1].kind,
+            StmtKind::Let { name, constant: false, .. } if name == "b"
+        ));
+    }
+
+    #[test]
+    fn test_loop_with_break_value() {
+        let (tokens, errors) = tokenize_with_errors("loop { break x + 1 }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Loop(body) => match &body.kind {
+                    ExprKind::Block(stmts) => {
+                        assert!(matches!(&stmts[0], StmtKind::Break(Some(_))));
+                    }
+                    _ => panic!("Expected block body"),
+                },
+                _ => panic!("Expected loop expression"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+
+        let (tokens, _) = tokenize_with_errors("loop { break }");
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Loop(body) => {
+                    assert!(
+                        matches!(&body.kind, ExprKind::Block(stmts) if stmts[0] == StmtKind::Break(None))
+                    );
+                }
+                _ => panic!("Expected loop expression"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_do_while_expression() {
+        let (tokens, errors) = tokenize_with_errors("do {\n  x = x + 1\n}\nwhile x < 3");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(ast.len(), 1);
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::DoWhile { body, condition } => {
+                    assert!(matches!(body.kind, ExprKind::Block(_)));
+                    assert!(matches!(
+                        condition.kind,
+                        ExprKind::BinaryOp {
+                            op: TokenKind::LessThan,
+                            ..
+                        }
+                    ));
+                }
+                _ => panic!("Expected do-while expression"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+
+        let (tokens, _) = tokenize_with_errors("do { 1 } 2");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_nil_coalesce_precedence() {
+        let (tokens, errors) = tokenize_with_errors("a ?? b || c");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::BinaryOp { left, op, right } => {
+                    assert_eq!(*op, TokenKind::NilCoalesce);
+                    assert!(matches!(left.kind, ExprKind::Variable(_)));
+                    assert!(matches!(
+                        right.kind,
+                        ExprKind::BinaryOp {
+                            op: TokenKind::LogicalOr,
+                            ..
+                        }
+                    ));
+                }
+                _ => panic!("Expected binary operation"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_spread_elements() {
+        let (tokens, errors) = tokenize_with_errors("f(1, ...args)\n[0, ...rest, 5]");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::FunctionCall { args, .. } => {
+                    assert!(matches!(args[0].kind, ExprKind::Number(_)));
+                    assert!(
+                        matches!(&args[1].kind, ExprKind::Spread(inner) if matches!(inner.kind, ExprKind::Variable(_)))
+                    );
+                }
+                _ => panic!("Expected function call"),
+            },
+            _ => panic!("Expected Expr statement"),
+        }
+        match &ast[1].kind {
+            StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Array(items) => {
+                    assert_eq!(items.len(), 3);
+                    assert!(matches!(items[1].kind, ExprKind::Spread(_)));
+                }
+                _ => panic!("Expected array literal"),
+            },
+            _ => panic!("Expected Result statement"),
+        }
+    }
+
+    #[test]
+    fn test_import_statement() {
+        let (tokens, errors) =
+            tokenize_with_errors("import \"lib/util.mp\"\nimport util from \"util.mp\"");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(
+            ast[0].kind,
+            StmtKind::Import {
+                path: "lib/util.mp".to_string(),
+                alias: None
+            }
+        );
+        assert_eq!(
+            ast[1].kind,
+            StmtKind::Import {
+                path: "util.mp".to_string(),
+                alias: Some("util".to_string())
+            }
+        );
+
+        for source in ["import util \"util.mp\"", "import 5"] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let (_, errors) = parse_with_errors(tokens);
+            assert!(!errors.is_empty(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_export_statement() {
+        let (tokens, errors) = tokenize_with_errors("export fn f(x) { x }\nexport const LIMIT = 3");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(ast.len(), 2);
+        match &ast[0].kind {
+            StmtKind::Export(declaration) => {
+                assert!(
+                    matches!(&declaration.kind, StmtKind::Function { name, .. } if name == "f")
+                );
+            }
+            _ => panic!("Expected export"),
+        }
+        assert_eq!(ast[1].kind.declared_names(), ["LIMIT"]);
+
+        let (tokens, _) = tokenize_with_errors("export 5");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        // `|` binds looser than `^`, which binds looser than `&`, then shifts, then `+`.
+        let (tokens, errors) = tokenize_with_errors("a | b ^ c & d << 1 + 2");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        let StmtKind::Result(expr) = &ast[0].kind else {
+            panic!("Expected Result statement");
+        };
+        let mut ops = Vec::new();
+        let mut current = expr;
+        while let ExprKind::BinaryOp { op, right, .. } = &current.kind {
+            ops.push(op.clone());
+            current = right;
+        }
+        assert_eq!(
+            ops,
+            [
+                TokenKind::BitOr,
+                TokenKind::BitXor,
+                TokenKind::BitAnd,
+                TokenKind::ShiftLeft,
+                TokenKind::Plus
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assignment_and_equality_nodes() {
+        let (tokens, errors) = tokenize_with_errors("x = 5\nx == 5");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty());
+        match &ast[0].kind {
+            StmtKind::Expr(expr) | StmtKind::Result(expr) => match &expr.kind {
+                ExprKind::Assign { target, value } => {
+                    assert!(matches!(&target.kind, ExprKind::Variable(name) if name == "x"));
+                    assert!(matches!(&value.kind, ExprKind::Number(Number::Int(5))));
+                }
+                other => panic!("Expected Assign, got {other:?}"),
+            },
+            other => panic!("Expected expression statement, got {other:?}"),
+        }
+        match &ast[1].kind {
+            StmtKind::Result(expr) => assert!(matches!(
+                &expr.kind,
+                ExprKind::BinaryOp {
+                    op: TokenKind::Equal,
+                    ..
+                }
+            )),
+            other => panic!("Expected Result statement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_let_without_initializer() {
+        let (tokens, errors) = tokenize_with_errors("let x;\nlet y\n{ let z }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(matches!(
+            &ast[0].kind,
+            StmtKind::Let { name, value: None, .. } if name == "x"
+        ));
+        assert!(matches!(
+            &ast[1].kind,
+            StmtKind::Let { name, value: None, .. } if name == "y"
+        ));
+
+        let (tokens, _) = tokenize_with_errors("const c;");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty(), "Constants still need a value");
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let (tokens, errors) =
+            tokenize_with_errors("f(1, 2,)\n[1, 2,]\n{ \"a\": 1, }\nfn g(a, b,) { a }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty(), "{errors:?}");
+        let exprs: Vec<&ExprKind> = ast
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Expr(expr) | StmtKind::Result(expr) => Some(&expr.kind),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(exprs[0], ExprKind::FunctionCall { args, .. } if args.len() == 2));
+        assert!(matches!(exprs[1], ExprKind::Array(items) if items.len() == 2));
+        assert!(matches!(exprs[2], ExprKind::Object(props) if props.len() == 1));
+        assert!(matches!(&ast[3].kind, StmtKind::Function { params, .. } if params.len() == 2));
+
+        let (tokens, _) = tokenize_with_errors("f(1,,)");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_object_keys() {
+        let (tokens, errors) = tokenize_with_errors("{a: 1, \"b\": 2, [k]: 3}\n{ [1, 2] }");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty(), "{errors:?}");
+        let StmtKind::Expr(expr) = &ast[0].kind else {
+            panic!("Expected expression statement");
+        };
+        let ExprKind::Object(fields) = &expr.kind else {
+            panic!("Expected Object");
+        };
+        assert!(matches!(&fields[0].0, ObjectKey::Name(name) if name == "a"));
+        assert!(matches!(&fields[1].0, ObjectKey::Name(name) if name == "b"));
+        assert!(
+            matches!(&fields[2].0, ObjectKey::Computed(key) if matches!(&key.kind, ExprKind::Variable(k) if k == "k"))
+        );
+        // A block that starts with an array literal is still a block.
+        assert!(matches!(
+            &ast[1].kind,
+            StmtKind::Result(Expr {
+                kind: ExprKind::Block(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_type_operators() {
+        let (tokens, errors) = tokenize_with_errors("x as Float / 2\nx is Int == true");
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        assert!(errors.is_empty(), "{errors:?}");
+        let StmtKind::Expr(expr) = &ast[0].kind else {
+            panic!("Expected expression statement");
+        };
+        match &expr.kind {
+            ExprKind::BinaryOp {
+                left,
+                op: TokenKind::Divide,
+                ..
+            } => assert!(matches!(
+                &left.kind,
+                ExprKind::Cast { type_name, .. } if type_name == "Float"
+            )),
+            other => panic!("Expected division, got {other:?}"),
+        }
+        let StmtKind::Result(expr) = &ast[1].kind else {
+            panic!("Expected Result statement");
+        };
+        match &expr.kind {
+            ExprKind::BinaryOp {
+                left,
+                op: TokenKind::Equal,
+                ..
+            } => assert!(matches!(
+                &left.kind,
+                ExprKind::TypeTest { type_name, .. } if type_name == "Int"
+            )),
+            other => panic!("Expected equality, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_doc_comment_attaches_to_function() {
+        let source = "/// Adds two numbers.\n///\n/// Both must be numbers.\nfn add(a, b) { a + b }\n/// Orphaned.\nlet x = 1\n/// Exported.\nexport fn id(v) { v }\nfn bare() { 0 }";
+        let (tokens, _) = tokenize_with_errors(source);
+        let stmts = parse(tokens);
+        let docs: Vec<Option<&str>> = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Function { doc, .. } => Some(doc.as_deref()),
+                StmtKind::Export(declaration) => match &declaration.kind {
+                    StmtKind::Function { doc, .. } => Some(doc.as_deref()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            [
+                Some("Adds two numbers.\n\nBoth must be numbers."),
+                Some("Exported."),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_input_reports_errors_instead_of_panicking() {
+        for source in [
+            "...[1]",
+            "...",
+            ")",
+            "]",
+            "}",
+            "`${}`",
+            "else",
+            "=>",
+            ")(",
+            "{ ) }",
+            "[1, )]",
+            "f(])",
+            "match x { 1 => ) }",
+            "let [a, )] = 1",
+            "fn f( ) ) {}",
+            "{[)]: 1}",
+            "x as )",
+            "let",
+            "fn",
+            "struct S {",
+            "for x in",
+            "x[1..",
+        ] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let (_, errors) = parse_with_errors(tokens);
+            assert!(!errors.is_empty(), "{source}");
+        }
+
+        // Token streams that don't end in `Eof` are fine too.
+        assert!(parse(Vec::new()).is_empty());
+        let (mut tokens, _) = tokenize_with_errors("1 + 2");
+        tokens.pop();
+        assert_eq!(parse(tokens).len(), 1);
+    }
+
+    #[test]
+    fn test_parser_error_messages_name_expected_and_found_tokens() {
+        let (tokens, _) = tokenize_with_errors("let x = 1\nlet y = f(1,\n  2}");
+        let (_, errors) = parse_with_errors(tokens);
+        let error = &errors[0];
+        assert_eq!(error.expected(), [TokenKind::RightParen]);
+        assert_eq!(error.span, Span { line: 3, column: 4 });
+        assert_eq!(
+            error.to_string(),
+            "Error at 3:4: Expect ')' after arguments (expected ')' but found '}')"
+        );
+
+        let (tokens, _) = tokenize_with_errors("let x = ");
+        let (_, errors) = parse_with_errors(tokens);
+        assert!(
+            errors[0].to_string().contains("end of file"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_operator_precedence_and_associativity() {
+        let render = |source: &str| {
+            let (tokens, _) = tokenize_with_errors(source);
+            let (stmts, errors) = parse_with_errors(tokens);
+            assert!(errors.is_empty(), "{source}: {errors:?}");
+            fn show(expr: &Expr) -> String {
+                match &expr.kind {
+                    ExprKind::BinaryOp { left, op, right } => {
+                        format!("({} {op} {})", show(left), show(right))
+                    }
+                    ExprKind::TypeTest { expr, type_name } => {
+                        format!("({} is {type_name})", show(expr))
+                    }
+                    ExprKind::Cast { expr, type_name } => {
+                        format!("({} as {type_name})", show(expr))
+                    }
+                    ExprKind::Variable(name) => name.clone(),
+                    ExprKind::Number(n) => n.to_string(),
+                    other => format!("{other:?}"),
+                }
+            }
+            match &stmts[0].kind {
+                StmtKind::Expr(expr) | StmtKind::Result(expr) => show(expr),
+                other => panic!("{other:?}"),
+            }
+        };
+        assert_eq!(render("a - b - c"), "((a - b) - c)");
+        assert_eq!(render("a + b * c % d"), "(a + ((b * c) % d))");
+        assert_eq!(render("a ?? b || c && d"), "(a ?? (b || (c && d)))");
+        assert_eq!(render("a | b ^ c & d << 1"), "(a | (b ^ (c & (d << 1))))");
+        assert_eq!(render("a == b < c"), "(a == (b < c))");
+        assert_eq!(render("1 + 2 is Number"), "((1 + 2) is Number)");
+        assert_eq!(render("x as Float / 2"), "((x as Float) / 2)");
+        assert_eq!(render("0..n + 1"), "(0 .. (n + 1))");
+    }
+
+    #[test]
+    fn test_visitor_walks_nested_statements() {
+        use mp_lang::parser::visit::{
+            Visitor, VisitorMut, walk_expr, walk_program, walk_program_mut,
+        };
+
+        struct Variables(Vec<String>);
+        impl<'ast> Visitor<'ast> for Variables {
+            fn visit_expr(&mut self, expr: &'ast Expr) {
+                if let ExprKind::Variable(name) = &expr.kind {
+                    self.0.push(name.clone());
+                }
+                walk_expr(self, expr);
+            }
+        }
+
+        struct Rename;
+        impl VisitorMut for Rename {
+            fn visit_expr_mut(&mut self, expr: &mut Expr) {
+                if let ExprKind::Variable(name) = &mut expr.kind {
+                    name.make_ascii_uppercase();
+                }
+                mp_lang::parser::visit::walk_expr_mut(self, expr);
+            }
+        }
+
+        let source = "fn f(a) { let b = a + c; if b { return [d, e.f] } }\nf(g)";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let mut ast = parse(tokens);
+
+        let mut variables = Variables(Vec::new());
+        walk_program(&mut variables, &ast);
+        assert_eq!(variables.0, ["a", "c", "b", "d", "e", "g"]);
+
+        walk_program_mut(&mut Rename, &mut ast);
+        let mut renamed = Variables(Vec::new());
+        walk_program(&mut renamed, &ast);
+        assert_eq!(renamed.0, ["A", "C", "B", "D", "E", "G"]);
+    }
+
+    #[test]
+    fn test_to_source_round_trips() {
+        use mp_lang::parser::{VisitorMut, to_source, visit::walk_expr_mut};
+
+        for entry in std::fs::read_dir("examples").unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let (tokens, _) = tokenize_with_errors(&source);
+            let printed = to_source(&parse(tokens));
+            let (tokens, lexer_errors) = tokenize_with_errors(&printed);
+            let (reparsed, parser_errors) = parse_with_errors(tokens);
+            assert!(
+                lexer_errors.is_empty(),
+                "{}: {lexer_errors:?}",
+                path.display()
+            );
+            assert!(
+                parser_errors.is_empty(),
+                "{}: {parser_errors:?}",
+                path.display()
+            );
+            assert_eq!(to_source(&reparsed), printed, "{}", path.display());
+        }
+
+        // A tree built without `Parenthesized` nodes still prints its grouping.
+        struct Unparenthesize;
+        impl VisitorMut for Unparenthesize {
+            fn visit_expr_mut(&mut self, expr: &mut Expr) {
+                while let ExprKind::Parenthesized(inner) = &expr.kind {
+                    *expr = (**inner).clone();
+                }
+                walk_expr_mut(self, expr);
+            }
+        }
+        let source = "let x = (a + b) * -(c - (d - e)) + (f ?? g).h;\n{ x; }";
+        let (tokens, _) = tokenize_with_errors(source);
+        let mut ast = parse(tokens);
+        mp_lang::parser::visit::walk_program_mut(&mut Unparenthesize, &mut ast);
+        assert_eq!(
+            to_source(&ast),
+            "let x = (a + b) * -(c - (d - e)) + (f ?? g).h;\n{\n    x;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_ast_serializes_to_json_and_back() {
+        use mp_lang::parser::Stmt;
+
+        let source = "/// Doubles.\nfn double(x) { x * 2.5 }\nlet s = `n=${double(1)}`;\nmatch s { \"a\" => 1, _ => nil }";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let json = serde_json::to_string(&ast).unwrap();
+        assert!(json.contains("\"Function\""));
+        let decoded: Vec<Stmt> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, ast);
+    }
+
+    #[test]
+    fn test_comments_are_kept_beside_the_tree() {
+        use mp_lang::parser::parse_with_comments;
+
+        let source = "// header\nlet x = 1; // one\nfn f() {\n    // inside\n    x\n    // last\n}\n// end\n";
+        let (tokens, _) = tokenize_with_errors(source);
+        let (stmts, comments, errors) = parse_with_comments(tokens);
+        assert!(errors.is_empty());
+        assert_eq!(stmts.len(), 2);
+
+        let first = comments.statement(None, 0).unwrap();
+        assert_eq!(first.leading[0].text, "// header");
+        assert_eq!(first.trailing.as_ref().unwrap().text, "// one");
+
+        let StmtKind::Function { body, .. } = &stmts[1].kind else {
+            panic!("Expected function");
+        };
+        let block = Some(body.span);
+        assert_eq!(
+            comments.statement(block, 0).unwrap().leading[0].text,
+            "// inside"
+        );
+        assert_eq!(comments.dangling(block)[0].text, "// last");
+        assert_eq!(comments.dangling(None)[0].text, "// end");
+
+        assert_eq!(mp_lang::format_code(source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        use mp_lang::parser::parse_expression;
+
+        let (tokens, _) = tokenize_with_errors("\n1 + 2 * x // note\n");
+        let expr = parse_expression(tokens).unwrap();
+        assert!(matches!(
+            expr.kind,
+            ExprKind::BinaryOp {
+                op: TokenKind::Plus,
+                ..
+            }
+        ));
+
+        let (tokens, _) = tokenize_with_errors("1 + 2; 3");
+        let error = parse_expression(tokens).unwrap_err();
+        assert_eq!(error.span, Span { line: 1, column: 8 });
+
+        let (tokens, _) = tokenize_with_errors("let x = 1");
+        assert!(parse_expression(tokens).is_err());
+    }
 }
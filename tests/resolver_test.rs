@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use mp_lang::{
+        lexer::tokenize_with_errors,
+        parser::parse,
+        resolver::{ResolverErrorKind, resolve},
+    };
+
+    fn resolve_source(source: &str) -> Vec<ResolverErrorKind> {
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        resolve(&parse(tokens))
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_programs() {
+        for source in [
+            "let x = 1; print(x + len([1]))",
+            // Function bodies may use names bound later in the enclosing scope.
+            "fn f() { x }; let x = 1; f()",
+            "fn even(n) { if n == 0 { true } else { odd(n - 1) } }; fn odd(n) { !even(n) }; even(4)",
+            "let f = fn(a, ...rest) { a + len(rest) }; f(1, 2, 3)",
+            "for i in 0..3 { print(i) }",
+            "match 1 { n => n + 1 }",
+            "let x; x = 2",
+            "import \"lib.mp\"; anything()",
+        ] {
+            assert_eq!(resolve_source(source), [], "{source}");
+        }
+    }
+
+    #[test]
+    fn test_undefined_variables() {
+        assert_eq!(
+            resolve_source("print(y)"),
+            [ResolverErrorKind::UndefinedVariable("y".to_string())]
+        );
+        assert_eq!(
+            resolve_source("{ let inner = 1 }; inner"),
+            [ResolverErrorKind::UndefinedVariable("inner".to_string())]
+        );
+        assert_eq!(
+            resolve_source("fn f() { missing() }"),
+            [ResolverErrorKind::UndefinedVariable("missing".to_string())]
+        );
+        assert_eq!(
+            resolve_source("z = 1"),
+            [ResolverErrorKind::UndefinedVariable("z".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        assert_eq!(
+            resolve_source("fn add(a, b) { a + b }; add(1)"),
+            [ResolverErrorKind::ArityMismatch {
+                name: "add".to_string(),
+                expected: 2,
+                found: 1,
+                variadic: false,
+            }]
+        );
+        assert_eq!(
+            resolve_source("fn sum(first, ...rest) { first }; sum()"),
+            [ResolverErrorKind::ArityMismatch {
+                name: "sum".to_string(),
+                expected: 1,
+                found: 0,
+                variadic: true,
+            }]
+        );
+        assert_eq!(
+            resolve_source("fn add(a, b) { a + b }; add(...[1, 2]); sum_all(1)"),
+            [ResolverErrorKind::UndefinedVariable("sum_all".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_const_reassignment() {
+        let (tokens, _) = tokenize_with_errors("const limit = 1\nlimit = 2");
+        let errors = resolve(&parse(tokens));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ResolverErrorKind::ConstReassignment("limit".to_string())
+        );
+        assert_eq!(errors[0].span.line, 2);
+    }
+}
@@ -3,11 +3,14 @@ mod tests {
     use std::{cell::RefCell, rc::Rc};
 
     use mp_lang::{
-        lexer::tokenize_with_errors,
-        parser::parse,
+        lexer::{Span, tokenize_with_errors},
+        parser::{ParserErrorKind, parse, parse_with_errors},
         runtime::{
+            Environment,
             environment::value::{Number, Value},
-            eval::eval,
+            error::InterpreterError,
+            eval::{eval, eval_with_env},
+            state::{EvalLimits, Limit},
         },
     };
 
@@ -111,6 +114,13 @@ mod tests {
         assert!(errors.is_empty());
         let ast = parse(tokens);
         let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Nil);
+
+        let (tokens, errors) =
+            tokenize_with_errors("{ let x = 0; collect while x < 3 { x = x + 1 } }");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
         assert_eq!(
             result,
             Value::Array(Rc::new(RefCell::new(vec![
@@ -131,135 +141,2428 @@ mod tests {
     }
 
     #[test]
-    fn test_nested_while_loops() {
-        let (tokens, errors) = tokenize_with_errors(
-            "{
-            let x = 0;
-            let y = 0;
-            while x < 2 {
-                x = x + 1;
-                while y < 3 {
-                    y = y + 1;
-                }
-            };
-            y
-        }",
-        );
-        assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(3)))
+    fn test_mixed_int_and_float_arithmetic() {
+        for (source, expected) in [
+            ("1 + 2.5", "3.5"),
+            ("2.5 - 1", "1.5"),
+            ("2 * 1.5", "3.0"),
+            ("3 / 2.0", "1.5"),
+            ("7 % 2.5", "2.0"),
+            ("1 < 1.5", "true"),
+            ("2.0 >= 2", "true"),
+            ("1 == 1.0", "true"),
+            ("1 != 1.5", "true"),
+            ("0 < 0.5 < 1", "true"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
     }
 
     #[test]
-    fn test_vector_operations() {
-        let (tokens, errors) = tokenize_with_errors("let v = [1, 2, 3]; push(v, 4); pop(v)");
-        assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(4)));
+    fn test_division_by_zero_and_overflow_are_errors() {
+        for (source, expected) in [
+            ("1 / 0", "Division by zero"),
+            ("1.5 / 0", "Division by zero"),
+            ("5 % 0", "Division by zero"),
+            ("7 ~/ 0.0", "Division by zero"),
+            (
+                "170141183460469231731687303715884105727 + 1",
+                "Integer overflow",
+            ),
+            (
+                "-170141183460469231731687303715884105727 - 2",
+                "Integer overflow",
+            ),
+            (
+                "85070591730234615865843651857942052864 * 2",
+                "Integer overflow",
+            ),
+            (
+                "let x = -170141183460469231731687303715884105727 - 1; -x",
+                "Integer overflow",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
     }
 
     #[test]
-    fn test_function_return() {
-        let (tokens, errors) = tokenize_with_errors("fn add(a, b) { return a + b; }; add(2, 3)");
+    fn test_string_and_array_comparisons() {
+        for (source, expected) in [
+            (r#""abc" < "abd""#, "true"),
+            (r#""abc" <= "ab""#, "false"),
+            (r#""B" < "a""#, "true"),
+            (r#""abc" == "abc""#, "true"),
+            (r#""abc" != "abd""#, "true"),
+            ("[1, [2, 3]] == [1, [2, 3]]", "true"),
+            ("[1, 2] == [1.0, 2.0]", "true"),
+            ("[1, 2] != [1, 2, 3]", "true"),
+            ("{a: 1, b: [2]} == {b: [2], a: 1}", "true"),
+            ("{a: 1} == {a: 2}", "false"),
+            (r#"1 == "1""#, "false"),
+            ("nil == nil", "true"),
+            ("[] != nil", "true"),
+            (r#"match 1.0 { 1 => "one", _ => "other" }"#, "one"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("[1] < [2]");
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(5)));
+        assert!(eval(parse(tokens)).is_err());
     }
 
     #[test]
-    fn test_early_return() {
-        let (tokens, errors) = tokenize_with_errors("fn test() { return 10; 20; }; test()");
+    fn test_arity_mismatch() {
+        for (source, expected) in [
+            (
+                "fn add(a, b) { a + b } add(1)",
+                "Function 'add' expects 2 arguments, got 1",
+            ),
+            (
+                "fn add(a, b) { a + b } add(1, 2, 3)",
+                "Function 'add' expects 2 arguments, got 3",
+            ),
+            (
+                "fn log(level, ...rest) { rest } log()",
+                "Function 'log' expects at least 1 argument, got 0",
+            ),
+            (
+                "let f = fn(x) { x }; f()",
+                "Function '<lambda>' expects 1 argument, got 0",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("fn log(level, ...rest) { rest } log(1, 2, 3)");
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(10)));
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "[2, 3]");
     }
 
     #[test]
-    fn test_array_index_access() {
-        let (tokens, errors) = tokenize_with_errors("let arr = [10, 20, 30]; arr[1]");
+    fn test_recursion_and_call_depth_limit() {
+        for source in [
+            "fn outer() { fn count(n) { if n == 0 { return 0; } return 1 + count(n - 1); } return count(5); } outer()",
+            "let count = fn(n) { if n == 0 { return 0; } return 1 + count(n - 1); }; count(5)",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert_eq!(
+                eval(parse(tokens)).unwrap(),
+                Value::Number(Number::Int(5)),
+                "{source}"
+            );
+        }
+
+        let source = "fn count(n) { if n == 0 { return 0; } return 1 + count(n - 1); }";
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_max_call_depth(10);
+        let (tokens, _) = tokenize_with_errors(source);
+        eval_with_env(parse(tokens), &env).unwrap();
+
+        let (tokens, _) = tokenize_with_errors("count(9)");
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(9))
+        );
+        let (tokens, _) = tokenize_with_errors("count(10)");
+        let error = eval_with_env(parse(tokens), &env).unwrap_err();
+        assert!(
+            matches!(error.inner(), InterpreterError::StackOverflow(10)),
+            "{error}"
+        );
+        // The depth unwinds with the error, so later calls work again.
+        let (tokens, _) = tokenize_with_errors("count(3)");
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(3))
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion_on_an_ordinary_thread() {
+        // No extra stack here: the test thread has Rust's default size.
+        let source = "fn f(n) { if n == 0 { 0 } else { 1 + f(n - 1) } }; f(100000)";
+        let (tokens, errors) = tokenize_with_errors(source);
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(20)));
+        let error = eval(parse(tokens)).unwrap_err();
+        assert!(
+            matches!(error.inner(), InterpreterError::StackOverflow(_)),
+            "{error}"
+        );
     }
 
     #[test]
-    fn test_object_property_access() {
-        let (tokens, errors) =
-            tokenize_with_errors("let obj = {\"name\": \"John\", \"age\": 30}; obj:age");
+    fn test_sort_calls_its_comparator_like_any_call() {
+        let source = "let cmp = fn(a, b) { sort([a, b], cmp) }; sort([1, 2], cmp)";
+        let (tokens, errors) = tokenize_with_errors(source);
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(30)));
+        let error = eval(parse(tokens)).unwrap_err();
+        assert!(
+            matches!(error.inner(), InterpreterError::StackOverflow(_)),
+            "{error}"
+        );
+
+        let (tokens, _) = tokenize_with_errors("sort([1, 2], fn(a, b) { missing })");
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at 1:25: Undefined variable: missing\n  in <lambda> called at 1:34"
+        );
     }
 
     #[test]
-    fn test_builtin_len() {
-        let (tokens, errors) = tokenize_with_errors("len(\"hello\")");
+    fn test_map_filter_and_reduce_call_their_function_like_any_call() {
+        for source in [
+            "let g = fn(x) { map([x], g) }; g(1)",
+            "let g = fn(x) { filter([x], g) }; g(1)",
+            "let g = fn(a, x) { reduce([x], g, a) }; g(0, 1)",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(
+                matches!(error.inner(), InterpreterError::StackOverflow(_)),
+                "{source}: {error}"
+            );
+        }
+
+        let (tokens, _) = tokenize_with_errors("fn twice(x) { missing }\nmap([1], twice)");
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at 1:15: Undefined variable: missing\n  in twice called at 2:15"
+        );
+    }
+
+    #[test]
+    fn test_errors_carry_a_stack_trace() {
+        let source = "fn inner() { return missing; }\nfn outer() { return inner(); }\nouter()";
+        let (tokens, errors) = tokenize_with_errors(source);
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::Number(Number::Int(5)));
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at 1:21: Undefined variable: missing\n  in inner called at 2:27\n  in outer called at 3:7"
+        );
+
+        let source = "fn down(n) { if n == 0 { return missing; } return down(n - 1); }\ndown(3)";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error at 1:33: Undefined variable: missing\n  in down called at 1:61 (2 more times)\n  in down called at 2:7"
+        );
     }
 
     #[test]
-    fn test_builtin_type() {
-        let (tokens, errors) = tokenize_with_errors("type(123)");
+    fn test_errors_point_at_the_source() {
+        let source = "let a = 1;\nlet b = a + missing;";
+        let (tokens, errors) = tokenize_with_errors(source);
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::String("int".to_string()));
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            error.span(),
+            Some(Span {
+                line: 2,
+                column: 13
+            })
+        );
+        assert_eq!(
+            error.render(source),
+            "Error at 2:13: Undefined variable: missing\n  |\n2 | let b = a + missing;\n  |             ^"
+        );
     }
 
     #[test]
-    fn test_builtin_str() {
-        let (tokens, errors) = tokenize_with_errors("str(42)");
+    fn test_every_phase_reports_a_diagnostic() {
+        use mp_lang::{Diagnostic, Severity, parser::parse_with_errors};
+
+        let source = "let s = \"open";
+        let (_, errors) = tokenize_with_errors(source);
+        let diagnostic = Diagnostic::from(&errors[0]);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.to_string(), errors[0].to_string());
+
+        let source = "let = 1";
+        let (tokens, _) = tokenize_with_errors(source);
+        let (_, errors) = parse_with_errors(tokens);
+        assert_eq!(
+            Diagnostic::from(&errors[0]).to_string(),
+            errors[0].to_string()
+        );
+
+        // Runtime errors carry their stack trace as notes, after the snippet.
+        let source = "fn inner() {\n  return missing;\n}\ninner()";
+        let (tokens, _) = tokenize_with_errors(source);
+        let error = eval(parse(tokens)).unwrap_err();
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(diagnostic.notes, ["in inner called at 4:7"]);
+        assert_eq!(
+            diagnostic.render(source),
+            "Error at 2:10: Undefined variable: missing\n  |\n2 |   return missing;\n  |          ^\n  in inner called at 4:7"
+        );
+    }
+
+    #[test]
+    fn test_errors_have_stable_codes() {
+        use mp_lang::Diagnostic;
+
+        let code = |source: &str| {
+            let (tokens, _) = tokenize_with_errors(source);
+            eval(parse(tokens)).unwrap_err().code()
+        };
+        assert_eq!(code("missing"), "E0001");
+        assert_eq!(code("const a = 1; a = 2"), "E0003");
+        assert_eq!(code("fn f() { 1 / 0 }; f()"), "E0010");
+        assert_eq!(code("1 - \"a\""), "E0012");
+
+        let (tokens, _) = tokenize_with_errors("fn f() { 1 / 0 }\nf()");
+        let error = eval(parse(tokens)).unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&error).to_json(),
+            r#"{"severity":"error","span":{"line":1,"column":14},"code":"E0010","message":"Division by zero","notes":["in f called at 2:3"]}"#
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_expressions() {
+        // No extra stack here: the test thread has Rust's default size. Long
+        // operator chains are folded in a loop, though dropping the tree still
+        // recurses.
+        let sum = vec!["1"; 10_000].join(" + ");
+        let (tokens, errors) = tokenize_with_errors(&sum);
         assert!(errors.is_empty());
-        let ast = parse(tokens);
-        let result = eval(ast).unwrap();
-        assert_eq!(result, Value::String("42".to_string()));
+        assert_eq!(
+            eval(parse(tokens)).unwrap(),
+            Value::Number(Number::Int(10_000))
+        );
+
+        let parenthesized = format!("{}1{}", "(".repeat(4_900), ")".repeat(4_900));
+        let array = format!("{}1{}", "[".repeat(4_900), "]".repeat(4_900));
+        for source in [parenthesized, array] {
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let (_, errors) = parse_with_errors(tokens);
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(errors[0].kind, ParserErrorKind::TooDeeplyNested));
+        }
+
+        // Parsed in a loop, but evaluated recursively.
+        let chain = format!("let o = nil; o{}", ".a".repeat(4_900));
+        let (tokens, errors) = tokenize_with_errors(&chain);
+        assert!(errors.is_empty());
+        let error = eval(parse(tokens)).unwrap_err();
+        assert!(
+            matches!(error.inner(), InterpreterError::TooDeeplyNested(_)),
+            "{error}"
+        );
+
+        // Whether the parser or the evaluator runs out first depends on the build.
+        let negated = format!("{}1", "-".repeat(4_900));
+        let (tokens, errors) = tokenize_with_errors(&negated);
+        assert!(errors.is_empty());
+        let (ast, errors) = parse_with_errors(tokens);
+        if let [error] = &errors[..] {
+            assert!(matches!(error.kind, ParserErrorKind::TooDeeplyNested));
+        } else {
+            assert!(errors.is_empty());
+            let error = eval(ast).unwrap_err();
+            assert!(
+                matches!(error.inner(), InterpreterError::TooDeeplyNested(_)),
+                "{error}"
+            );
+        }
     }
 
     #[test]
-    fn test_examples() {
-        use std::fs;
-        use std::path::Path;
+    fn test_eval_limits() {
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_limits(EvalLimits {
+            max_steps: Some(1_000),
+            ..EvalLimits::default()
+        });
+        let (tokens, _) = tokenize_with_errors("let n = 0; while n < 10 { n = n + 1 }; n");
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(10))
+        );
+        let (tokens, _) = tokenize_with_errors("while true {}");
+        let error = eval_with_env(parse(tokens), &env).unwrap_err();
+        assert!(
+            matches!(
+                error.inner(),
+                InterpreterError::LimitExceeded(Limit::Steps(1_000))
+            ),
+            "{error}"
+        );
 
-        let examples_dir = Path::new("examples");
-        let mut example_files: Vec<_> = fs::read_dir(examples_dir)
-            .unwrap()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                let path = entry.path();
-                path.extension().map_or(false, |ext| ext == "mp")
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_limits(EvalLimits {
+            max_duration: Some(std::time::Duration::from_millis(20)),
+            ..EvalLimits::default()
+        });
+        let (tokens, _) = tokenize_with_errors("fn spin() { loop {} } spin()");
+        let error = eval_with_env(parse(tokens), &env).unwrap_err();
+        assert!(
+            matches!(
+                error.inner(),
+                InterpreterError::LimitExceeded(Limit::Duration(_))
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_allocation_limit() {
+        let limited = || {
+            let env = Rc::new(RefCell::new(Environment::new_root()));
+            env.borrow().set_limits(EvalLimits {
+                max_allocated: Some(64 * 1024),
+                ..EvalLimits::default()
+            });
+            env
+        };
+        let (tokens, _) =
+            tokenize_with_errors("let a = []; for i in 0..100 { push(a, \"item {i}\") }; len(a)");
+        assert_eq!(
+            eval_with_env(parse(tokens), &limited()).unwrap(),
+            Value::Number(Number::Int(100))
+        );
+
+        for source in [
+            "\"x\" * 1000000000000",
+            "let a = []; while true { push(a, 1) }",
+            "let s = \"x\"; while true { s = s + s }",
+            "collect for i in 0..1000000 { i }",
+            // Each string is dropped straight away, but nothing is credited back.
+            "for i in 0..100000 { let s = \"item\" + \"!\" }",
+            // Charged before the result is built.
+            "(0..1000000000000) as Array",
+            "let s = \"x\" * 10000; s as Array",
+            "format(\"{:99999999999}\", 1)",
+            "format(\"{:.99999999999}\", 1.5)",
+            "replace(\"x\" * 1000, \"x\", \"y\" * 1000)",
+            "chars(\"x\" * 10000)",
+            // Copies, however small, add up.
+            "let a = [1, 2, 3, 4]; let b = nil; while true { b = a[1..] }",
+            "let a = [1, 2, 3, 4]; let r = 0..4; let b = nil; while true { b = a[r] }",
+            "let s = \"abcd\"; let t = nil; while true { t = s[1..] }",
+            "let s = \"abcd\"; let r = 0..4; let t = nil; while true { t = s[r] }",
+            "let t = nil; while true { t = [1, 2] as String }",
+        ] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let error = eval_with_env(parse(tokens), &limited()).unwrap_err();
+            assert!(
+                matches!(
+                    error.inner(),
+                    InterpreterError::LimitExceeded(Limit::Allocated(_))
+                ),
+                "{source}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_cancel_flag_interrupts_evaluation() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        };
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_cancel_flag(cancel.clone());
+        let (tokens, _) = tokenize_with_errors("1 + 1");
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(2))
+        );
+
+        let canceller = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                cancel.store(true, Ordering::Relaxed);
             })
+        };
+        let (tokens, _) = tokenize_with_errors("fn spin() { loop {} } spin()");
+        let error = eval_with_env(parse(tokens), &env).unwrap_err();
+        canceller.join().unwrap();
+        assert!(
+            matches!(error.inner(), InterpreterError::Interrupted),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_profiling() {
+        let (tokens, _) = tokenize_with_errors("profile()");
+        assert_eq!(eval(parse(tokens)).unwrap(), Value::Nil);
+
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_profiling(true);
+        let (tokens, _) = tokenize_with_errors(
+            "fn fact(n) { if n <= 1 { return 1; }; return n * fact(n - 1); }
+            fn twice(x) { fact(x) + fact(x) }
+            twice(5)",
+        );
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(240))
+        );
+        let report = env.borrow().profile().unwrap();
+        let calls: Vec<(&str, u64)> = report
+            .iter()
+            .map(|function| (function.name.as_str(), function.calls))
             .collect();
+        assert_eq!(calls, [("twice", 1), ("fact", 10)]);
+        assert!(report[0].total >= report[1].total);
+        assert!(report[0].self_time <= report[0].total);
 
-        example_files.sort_by_key(|entry| entry.path());
+        let (tokens, _) = tokenize_with_errors("len(profile())");
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap(),
+            Value::Number(Number::Int(2))
+        );
+    }
 
-        for entry in example_files {
-            let path = entry.path();
-            let file_name = path.file_name().unwrap().to_str().unwrap();
-            println!("Testing: {}", file_name);
+    #[test]
+    fn test_debugger_pauses_at_breakpoints_and_steps() {
+        use mp_lang::runtime::debug::{DebugCommand, DebugHook, Debugger, Pause, PauseReason};
 
-            let content = fs::read_to_string(&path).unwrap();
-            let (tokens, errors) = tokenize_with_errors(&content);
-            assert!(errors.is_empty());
-            let ast = parse(tokens);
-            let result = eval(ast);
+        /// Why the program stopped, the line and call depth, and `sum` there.
+        type Stop = (PauseReason, usize, usize, Option<Value>);
 
-            match result {
-                Ok(_) | Err(mp_lang::InterpreterError::Return(_)) => {
-                    println!("  ✓ {} passed", file_name);
-                }
-                Err(e) => {
-                    panic!("  ✗ {} failed: {:?}", file_name, e);
-                }
+        /// Answers each pause with the next scripted command.
+        struct Scripted {
+            commands: Vec<DebugCommand>,
+            pauses: Rc<RefCell<Vec<Stop>>>,
+        }
+
+        impl DebugHook for Scripted {
+            fn pause(&mut self, pause: &mut Pause) -> DebugCommand {
+                let sum = pause.env.borrow().get_value("sum");
+                self.pauses.borrow_mut().push((
+                    pause.reason,
+                    pause.span.line,
+                    pause.frames.len(),
+                    sum,
+                ));
+                self.commands.pop().unwrap_or(DebugCommand::Continue)
+            }
+        }
+
+        let source = "fn add(a, b) {
+            let sum = a + b
+            return sum
+        }
+        let x = add(1, 2)
+        let y = add(x, 3)
+        print(y)";
+        let run = |commands: Vec<DebugCommand>, breakpoint: &str| {
+            let pauses = Rc::new(RefCell::new(Vec::new()));
+            let mut debugger = Debugger::new(Scripted {
+                commands: commands.into_iter().rev().collect(),
+                pauses: pauses.clone(),
+            })
+            .run_to_breakpoint();
+            debugger.add_breakpoint(breakpoint.parse().unwrap());
+            let env = Rc::new(RefCell::new(Environment::new_root()));
+            env.borrow().set_debugger(debugger);
+            let (tokens, _) = tokenize_with_errors(source);
+            let result = eval_with_env(parse(tokens), &env);
+            (result, pauses.take())
+        };
+
+        let (result, pauses) = run(vec![DebugCommand::Continue; 2], "3");
+        assert!(result.is_ok());
+        let three = Some(Value::Number(Number::Int(3)));
+        let six = Some(Value::Number(Number::Int(6)));
+        assert_eq!(
+            pauses,
+            [
+                (PauseReason::Breakpoint, 3, 1, three),
+                (PauseReason::Breakpoint, 3, 1, six),
+            ]
+        );
+
+        let (_, pauses) = run(vec![DebugCommand::Step, DebugCommand::Next], "5");
+        let lines: Vec<(usize, usize)> = pauses
+            .iter()
+            .map(|(_, line, depth, _)| (*line, *depth))
+            .collect();
+        assert_eq!(lines, [(5, 0), (2, 1), (3, 1)]);
+
+        let (_, pauses) = run(vec![DebugCommand::Next, DebugCommand::Next], "5");
+        let lines: Vec<usize> = pauses.iter().map(|(_, line, _, _)| *line).collect();
+        assert_eq!(lines, [5, 6, 7]);
+
+        let (result, _) = run(vec![DebugCommand::Quit], "6");
+        assert!(matches!(
+            result.unwrap_err().inner(),
+            InterpreterError::Interrupted
+        ));
+    }
+
+    #[test]
+    fn test_observers_see_statements_calls_and_errors() {
+        use mp_lang::runtime::observer::Observer;
+        use std::path::Path;
+
+        #[derive(Default)]
+        struct Log(Rc<RefCell<Vec<String>>>);
+
+        impl Observer for Log {
+            fn on_statement(&mut self, _file: Option<&Path>, span: Span) {
+                self.0.borrow_mut().push(format!("line {}", span.line));
+            }
+
+            fn on_call(&mut self, function: &str, args: &[Value]) {
+                self.0
+                    .borrow_mut()
+                    .push(format!("call {function}/{}", args.len()));
+            }
+
+            fn on_return(&mut self, function: &str, value: Option<&Value>) {
+                let value = value.map_or("failed".to_string(), Value::to_string);
+                self.0
+                    .borrow_mut()
+                    .push(format!("return {function} {value}"));
+            }
+
+            fn on_error(&mut self, error: &InterpreterError) {
+                self.0.borrow_mut().push(format!("error {}", error.inner()));
             }
         }
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().add_observer(Log(log.clone()));
+        let (tokens, _) = tokenize_with_errors(
+            "fn share(n) { 4 / n }
+            let x = share(2)
+            share(x - 2)",
+        );
+        assert!(eval_with_env(parse(tokens), &env).is_err());
+        assert_eq!(
+            *log.borrow(),
+            [
+                "line 1",
+                "line 2",
+                "call share/1",
+                "line 1",
+                "return share 2",
+                "line 3",
+                "call share/1",
+                "line 1",
+                "return share failed",
+                "error Division by zero",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spawn_and_join() {
+        let (tokens, _) = tokenize_with_errors(
+            "fn fib(n) { if n < 2 { return n; }; return fib(n - 1) + fib(n - 2); }
+            let handles = collect for n in 10..13 { spawn(fib, n) }
+            collect for h in handles { join(h) }",
+        );
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "[55, 89, 144]");
+
+        // The thread changes its own copy of `data`.
+        let (tokens, _) = tokenize_with_errors(
+            "let data = [1, 2]
+            let copy = join(spawn(fn() { push(data, 3); data }))
+            [copy, data, spawn(fn() { 0 }) is Thread]",
+        );
+        assert_eq!(
+            eval(parse(tokens)).unwrap().to_string(),
+            "[[1, 2, 3], [1, 2], true]"
+        );
+
+        for (source, message) in [
+            ("join(spawn(fn() { 1 / 0 }))", "Division by zero"),
+            (
+                "let h = spawn(fn() { 1 }); join(h); join(h)",
+                "called twice",
+            ),
+            ("spawn(1)", "expects a function"),
+        ] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let error = eval(parse(tokens)).unwrap_err().to_string();
+            assert!(error.contains(message), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_channels() {
+        let (tokens, _) = tokenize_with_errors(
+            "let ch = channel()
+            let replies = channel()
+            let worker = spawn(fn() {
+                loop {
+                    let n = recv(ch)
+                    if n == nil { break }
+                    send(replies, n * n)
+                }
+                \"done\"
+            })
+            for n in 1..4 { send(ch, n) }
+            send(ch, nil)
+            [recv(replies), recv(replies), recv(replies), join(worker), ch is Channel]",
+        );
+        assert_eq!(
+            eval(parse(tokens)).unwrap().to_string(),
+            "[1, 4, 9, done, true]"
+        );
+
+        // A receive that nothing will answer still stops when cancelled.
+        use std::sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_cancel_flag(cancel.clone());
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            cancel.store(true, Ordering::Relaxed);
+        });
+        let (tokens, _) = tokenize_with_errors("recv(channel())");
+        let error = eval_with_env(parse(tokens), &env).unwrap_err();
+        canceller.join().unwrap();
+        assert!(
+            matches!(error.inner(), InterpreterError::Interrupted),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_eval_async_yields_to_the_executor() {
+        use mp_lang::eval_async;
+        use std::time::Duration;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (tokens, _) =
+                tokenize_with_errors("let total = 0; for i in 1..101 { total = total + i }; total");
+            let value = eval_async(parse(tokens), 10, |_| {}).await.unwrap();
+            assert_eq!(value.into_value().to_string(), "5050");
+
+            // An endless loop still lets the timer fire, and dropping the
+            // future stops it.
+            let (tokens, _) = tokenize_with_errors("loop {}");
+            let endless = eval_async(parse(tokens), 100, |_| {});
+            let timed_out = tokio::time::timeout(Duration::from_millis(50), endless).await;
+            assert!(timed_out.is_err());
+
+            let (tokens, _) = tokenize_with_errors("missing");
+            let error = eval_async(parse(tokens), 10, |_| {}).await.unwrap_err();
+            assert!(error.contains("missing"), "{error}");
+
+            // Polling doesn't wait for the program to reach a yield, so the
+            // timer fires long before the program hits its time limit.
+            let (tokens, _) = tokenize_with_errors("loop {}");
+            let unyielding = eval_async(parse(tokens), u64::MAX, |env| {
+                env.borrow().set_limits(EvalLimits {
+                    max_duration: Some(Duration::from_millis(500)),
+                    ..EvalLimits::default()
+                });
+            });
+            let timed_out = tokio::time::timeout(Duration::from_millis(20), unyielding).await;
+            assert!(timed_out.is_err());
+        });
+    }
+
+    #[test]
+    fn test_random_rejects_empty_ranges() {
+        for (source, message) in [
+            ("random(0)", "random() can't draw from 0..0"),
+            ("random(-3)", "random() can't draw from 0..-3"),
+            ("random(5, 5)", "random() can't draw from 5..5"),
+            ("random(0.0)", "random() can't draw from 0.0..0.0"),
+            ("random(2.0, 1.0)", "random() can't draw from 2.0..1.0"),
+            (
+                "random(-1e308, 1e308)",
+                "random() can't draw from -1e308..1e308",
+            ),
+        ] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let error = eval(parse(tokens)).unwrap_err();
+            assert_eq!(
+                error.inner().to_string(),
+                format!("Invalid operation: {message}"),
+                "{source}"
+            );
+        }
+        let (tokens, _) = tokenize_with_errors("random(5, 6)");
+        assert_eq!(eval(parse(tokens)).unwrap(), Value::Number(Number::Int(5)));
+    }
+
+    #[test]
+    fn test_seed_makes_random_repeatable() {
+        let draw = "[random(), random(100), random(1.0, 2.0)]";
+        let run = |source: &str| {
+            let (tokens, _) = tokenize_with_errors(source);
+            eval(parse(tokens)).unwrap().to_string()
+        };
+        let first = run(&format!("seed(7); {draw}"));
+        assert_eq!(first, run(&format!("seed(7); {draw}")));
+        assert_ne!(first, run(&format!("seed(8); {draw}")));
+
+        // Embedders can seed the environment before running anything.
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        env.borrow().set_seed(7);
+        let (tokens, _) = tokenize_with_errors(draw);
+        assert_eq!(
+            eval_with_env(parse(tokens), &env).unwrap().to_string(),
+            first
+        );
+
+        // Spawned threads are seeded from their parent.
+        let threaded = format!("seed(3); join(spawn(fn() {{ {draw} }}))");
+        assert_eq!(run(&threaded), run(&threaded));
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_json() {
+        use mp_lang::Snapshot;
+
+        let env = Rc::new(RefCell::new(Environment::new_root()));
+        let (tokens, _) = tokenize_with_errors(
+            "let xs = [1, 2.5, \"three\", nil]
+            const LIMIT = 10
+            struct Point { x, y }
+            let p = Point(3, 4)
+            fn double(n) { n * 2 }
+            let settings = { depth: 1..4, on: true }",
+        );
+        eval_with_env(parse(tokens), &env).unwrap();
+        let json = Snapshot::capture(&env.borrow()).unwrap().to_json().unwrap();
+
+        let restored = Rc::new(RefCell::new(Environment::new_root()));
+        Snapshot::from_json(&json)
+            .unwrap()
+            .restore(&mut restored.borrow_mut());
+        let (tokens, _) = tokenize_with_errors(
+            "[xs, LIMIT, p:x, Point(0, 1):y, double(21), settings[\"depth\"], settings[\"on\"]]",
+        );
+        assert_eq!(
+            eval_with_env(parse(tokens), &restored).unwrap().to_string(),
+            "[[1, 2.5, three, nil], 10, 3, 1, 42, 1..4, true]"
+        );
+
+        // Channels and thread handles can't outlive the program.
+        let (tokens, _) = tokenize_with_errors("let ch = channel()");
+        eval_with_env(parse(tokens), &restored).unwrap();
+        let error = Snapshot::capture(&restored.borrow()).unwrap_err();
+        assert!(error.to_string().contains("Cannot save 'ch'"), "{error}");
+    }
+
+    #[test]
+    fn test_file_errors_are_grouped_by_phase() {
+        use mp_lang::{MpError, lint_file, run_file};
+
+        let dir = std::env::temp_dir().join(format!("mp_lang_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lex = dir.join("lex.mp");
+        std::fs::write(&lex, "let s = \"open").unwrap();
+        let runtime = dir.join("runtime.mp");
+        std::fs::write(&runtime, "let x = 1;\nprint(x / 0);").unwrap();
+
+        let err = lint_file(lex.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, MpError::Lex { .. }));
+        assert_eq!(err.diagnostics()[0].code, Some("E0103"));
+
+        let err = run_file(runtime.to_str().unwrap()).unwrap_err();
+        let MpError::Runtime { error, .. } = &err else {
+            panic!("expected a runtime error, got {err:?}");
+        };
+        assert!(matches!(error.inner(), InterpreterError::DivisionByZero));
+        assert!(err.to_string().contains("print(x / 0);"));
+
+        let err = run_file(dir.join("missing.mp").to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, MpError::Io(_)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_fills_placeholders() {
+        for (source, expected) in [
+            ("format(\"x={} y={}\", 1, \"a\")", "x=1 y=a"),
+            ("format(\"{:.2}\", 3.14159)", "3.14"),
+            ("format(\"{:.1}\", 2)", "2.0"),
+            ("format(\"[{:5}]\", 42)", "[   42]"),
+            ("format(\"[{:5}]\", \"ab\")", "[ab   ]"),
+            (
+                "format(\"[{:^6}|{:<4.1}]\", \"mid\", 1.25)",
+                "[ mid  |1.2 ]",
+            ),
+            ("format(\"{{}} {}\", [1, 2])", "{} [1, 2]"),
+            ("format(\"plain\")", "plain"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+
+        for source in [
+            "format(\"{} {}\", 1)",
+            "format(\"{}\", 1, 2)",
+            "format(\"{:x}\", 1)",
+            "format(\"{\", 1)",
+            "format(1)",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert!(eval(parse(tokens)).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_print_variants_write_where_asked() {
+        let dir = std::env::temp_dir().join(format!("mp_lang_print_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("print.mp");
+        std::fs::write(
+            &file,
+            "print(1, 2); println(); println(\"a\", [3]); print(\"x {}\", \"y\");
+            write(\"|\", 4, \"|\"); println(format(\"{:.1}\", 2)); eprint(\"e\"); eprintln(5, 6);
+            print_sep(\", \", 1, {\"sep\": 2}); println_sep(\"\", 3, 4); println_sep(\"-\");
+            eprint_sep(\"+\", 7, 8); eprintln_sep(\"\", 9)",
+        )
+        .unwrap();
+
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_mp"))
+            .arg(&file)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "1 2\na [3]\nx {} y|4|2.0\n1, {sep: 2}34\n\n"
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "e5 6\n7+89\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_print_sep_needs_a_separator() {
+        for source in ["print_sep(1, 2)", "println_sep()", "eprintln_sep(nil, 1)"] {
+            let (tokens, _) = tokenize_with_errors(source);
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(
+                matches!(error.inner(), InterpreterError::TypeMismatch(_)),
+                "{source}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_math_builtins() {
+        for (source, expected) in [
+            ("sqrt(16)", Number::Float(4.0)),
+            ("sqrt(2.25)", Number::Float(1.5)),
+            ("abs(-3)", Number::Int(3)),
+            ("abs(-2.5)", Number::Float(2.5)),
+            ("floor(2.7)", Number::Int(2)),
+            ("floor(-2.5)", Number::Int(-3)),
+            ("ceil(2.1)", Number::Int(3)),
+            ("ceil(7)", Number::Int(7)),
+            ("round(2.5)", Number::Int(3)),
+            ("round(-2.5)", Number::Int(-3)),
+            ("pow(2, 10)", Number::Int(1024)),
+            ("pow(2, -1)", Number::Float(0.5)),
+            ("pow(4, 0.5)", Number::Float(2.0)),
+            ("pow(-1, 100000000001)", Number::Int(-1)),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::Number(expected), "{source}");
+        }
+
+        for (source, expected) in [
+            ("pow(10, 40)", "Integer overflow"),
+            ("sqrt(-1)", "sqrt() of a negative number: -1.0"),
+            ("floor(1e300)", "Integer overflow"),
+            ("abs(\"1\")", "Type mismatch: abs() expects a number"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_trig_and_log_builtins() {
+        for (source, expected) in [
+            ("sin(PI / 2)", 1.0),
+            ("cos(0)", 1.0),
+            ("tan(PI / 4)", 1.0),
+            ("asin(1) * 2", std::f64::consts::PI),
+            ("acos(1)", 0.0),
+            ("atan(1) * 4", std::f64::consts::PI),
+            ("atan2(1, -1)", 3.0 * std::f64::consts::FRAC_PI_4),
+            ("ln(E)", 1.0),
+            ("log10(1000)", 3.0),
+            ("log2(8)", 3.0),
+            ("exp(0)", 1.0),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let Value::Number(Number::Float(result)) = eval(parse(tokens)).unwrap() else {
+                panic!("{source} is not a float");
+            };
+            assert!((result - expected).abs() < 1e-12, "{source} = {result}");
+        }
+
+        for (source, expected) in [
+            ("ln(-1)", "ln() is undefined for -1.0"),
+            ("asin(2)", "asin() is undefined for 2.0"),
+            ("PI = 3", "Cannot reassign constant 'PI'"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_programs_may_reuse_predefined_names() {
+        let (tokens, errors) = tokenize_with_errors(
+            "let E = 2; const PI = 3; fn abs(x) { 0 - x }; let print = 1; [E, PI, abs(1), print]",
+        );
+        assert!(errors.is_empty());
+        let result = eval(parse(tokens)).unwrap();
+        assert_eq!(result.to_string(), "[2, 3, -1, 1]");
+
+        for source in [
+            "let x = 1; let x = 2",
+            "const PI = 3; const PI = 4",
+            "let nil = 1",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(
+                matches!(error.inner(), InterpreterError::RedefinedVariable(_)),
+                "{source}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_max_and_sum() {
+        for (source, expected) in [
+            ("min(3, 1, 2)", "1"),
+            ("max([3, 1.5, 2])", "3"),
+            ("max(1, 2.5)", "2.5"),
+            ("min([\"pear\", \"apple\"])", "apple"),
+            ("min([7])", "7"),
+            ("sum([1, 2, 3])", "6"),
+            ("sum(1, 2.5)", "3.5"),
+            ("sum([])", "0"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for (source, expected) in [
+            (
+                "min([])",
+                "min() expects an array with at least one element, or values",
+            ),
+            ("max(1, \"a\")", "max() can't compare string with int"),
+            ("sum([1, \"a\"])", "sum() expects numbers, found string"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_sort_returns_a_sorted_copy() {
+        for (source, expected) in [
+            (
+                "let a = [3, 1.5, 2]; [sort(a), a]",
+                "[[1.5, 2, 3], [3, 1.5, 2]]",
+            ),
+            ("sort([\"pear\", \"Apple\", \"fig\"])", "[Apple, fig, pear]"),
+            ("sort([3, 1, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+            (
+                "sort([\"ccc\", \"a\", \"bb\", \"d\"], fn(a, b) { len(a) - len(b) })",
+                "[a, d, bb, ccc]",
+            ),
+            ("sort([])", "[]"),
+            ("len(sort([5, 3, 8, 1, 9, 2, 7], fn(a, b) { 1 }))", "7"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for (source, expected) in [
+            ("sort([1, \"a\"])", "can't compare"),
+            (
+                "sort([1, 2], fn(a, b) { a < b })",
+                "comparator must return a number, got bool",
+            ),
+            ("sort([1, 2], fn(a) { a })", "expects 1 argument, got 2"),
+            (
+                "sort([3, 1, 2], fn(a, b) { nil })",
+                "must return a number, got nil",
+            ),
+            (
+                "sort([3, 1, 2], fn(a, b) { float(\"NaN\") })",
+                "comparator returned NaN",
+            ),
+            ("sort([3, 1, 2], fn(a, b) { a[0] })", "Index access"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_reverse_copies_arrays_and_strings() {
+        for (source, expected) in [
+            (
+                "let a = [1, 2, 3]; [reverse(a), a]",
+                "[[3, 2, 1], [1, 2, 3]]",
+            ),
+            ("reverse(\"héllo\")", "olléh"),
+            ("reverse([])", "[]"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, _) = tokenize_with_errors("reverse(1)");
+        assert!(eval(parse(tokens)).is_err());
+    }
+
+    #[test]
+    fn test_map_filter_and_reduce() {
+        for (source, expected) in [
+            ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+            ("map([\"ab\", \"c\"], len)", "[2, 1]"),
+            ("filter([1, 2, 3, 4], fn(x) { x % 2 == 0 })", "[2, 4]"),
+            ("reduce([1, 2, 3], fn(acc, x) { acc + x }, 10)", "16"),
+            (
+                "reduce([\"a\", \"b\", \"c\"], fn(acc, x) { x + acc })",
+                "cba",
+            ),
+            ("reduce([], fn(acc, x) { acc + x }, 0)", "0"),
+            (
+                "let k = 3; map(filter([1, 5, 2, 7], fn(x) { x > k }), str)",
+                "[5, 7]",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for (source, expected) in [
+            (
+                "map(1, fn(x) { x })",
+                "map() expects an array and a function",
+            ),
+            (
+                "filter([1], fn(x) { x })",
+                "predicate must return a boolean, got int",
+            ),
+            ("reduce([], fn(acc, x) { acc })", "needs an initial value"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        for (source, expected) in [
+            ("split(\"a,b,,c\", \",\")", "[a, b, , c]"),
+            ("split(\"  one two\\tthree \")", "[one, two, three]"),
+            ("split(\"héy\", \"\")", "[h, é, y]"),
+            ("join([\"a\", \"b\", \"c\"], \"-\")", "a-b-c"),
+            ("join([1, 2.5, nil], \", \")", "1, 2.5, nil"),
+            ("join(split(\"x y\"), \"\")", "xy"),
+            ("join(spawn(fn() { 7 }))", "7"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for (source, expected) in [
+            ("split(1, \",\")", "split() expects a string"),
+            (
+                "join([1, 2])",
+                "join() expects an array and a separator string",
+            ),
+            ("join(1)", "join() expects a thread handle"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_string_case_trim_and_replace() {
+        for (source, expected) in [
+            ("trim(\"  hi \\n\")", "hi"),
+            ("trim_start(\"  hi \") + \"|\"", "hi |"),
+            ("trim_end(\"  hi \") + \"|\"", "  hi|"),
+            ("upper(\"straße\")", "STRASSE"),
+            ("lower(\"ÀB\")", "àb"),
+            ("replace(\"a-b-c\", \"-\", \"+\")", "a+b+c"),
+            ("replace(\"aaa\", \"aa\", \"b\")", "ba"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+
+        for (source, expected) in [
+            ("upper(1)", "upper() expects a string"),
+            ("replace(\"a\", \"a\")", "replace() expects three strings"),
+            (
+                "replace(\"a\", \"\", \"b\")",
+                "can't replace an empty string",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(error.to_string().contains(expected), "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_string_indexing_counts_characters() {
+        for (source, expected) in [
+            ("let s = \"héllo\"; s[1]", "é"),
+            ("let s = \"héllo\"; s[4]", "o"),
+            ("let s = \"héllo\"; s[-4]", "é"),
+            ("let s = \"héllo\"; s[1] = \"e\"; s", "hello"),
+            ("\"日本語\"[1..]", "本語"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+
+        for source in ["let s = \"héllo\"; s[5]", "let s = \"héllo\"; s[-6]"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(
+                error.to_string().contains("String index out of bounds"),
+                "{source}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_collect_needs_a_loop() {
+        let (tokens, errors) =
+            tokenize_with_errors("let x = 0; collect do { x = x + 1 } while x < 2");
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "[1, 2]");
+
+        let (tokens, errors) = tokenize_with_errors("collect [1, 2]");
+        assert!(errors.is_empty());
+        let (_, parser_errors) = mp_lang::parser::parse_with_errors(tokens);
+        assert_eq!(
+            parser_errors[0].to_string(),
+            "Error at 1:9: Expect a loop after 'collect' (expected 'for' or 'while' or 'do' but found '[')"
+        );
+    }
+
+    #[test]
+    fn test_while_with_empty_body() {
+        let (tokens, errors) =
+            tokenize_with_errors("let n = 0; while (n = n + 1) < 3 {}; do {} while false; n");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(3)));
+    }
+
+    #[test]
+    fn test_nested_while_loops() {
+        let (tokens, errors) = tokenize_with_errors(
+            "{
+            let x = 0;
+            let y = 0;
+            while x < 2 {
+                x = x + 1;
+                while y < 3 {
+                    y = y + 1;
+                }
+            };
+            y
+        }",
+        );
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(3)))
+    }
+
+    #[test]
+    fn test_vector_operations() {
+        let (tokens, errors) = tokenize_with_errors("let v = [1, 2, 3]; push(v, 4); pop(v)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(4)));
+    }
+
+    #[test]
+    fn test_function_return() {
+        let (tokens, errors) = tokenize_with_errors("fn add(a, b) { return a + b; }; add(2, 3)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(5)));
+    }
+
+    #[test]
+    fn test_early_return() {
+        let (tokens, errors) = tokenize_with_errors("fn test() { return 10; 20; }; test()");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(10)));
+    }
+
+    #[test]
+    fn test_array_index_access() {
+        let (tokens, errors) = tokenize_with_errors("let arr = [10, 20, 30]; arr[1]");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(20)));
+    }
+
+    #[test]
+    fn test_object_property_access() {
+        let (tokens, errors) =
+            tokenize_with_errors("let obj = {\"name\": \"John\", \"age\": 30}; obj:age");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(30)));
+    }
+
+    #[test]
+    fn test_builtin_len() {
+        let (tokens, errors) = tokenize_with_errors("len(\"hello\")");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(5)));
+    }
+
+    #[test]
+    fn test_len_counts_characters_and_keys() {
+        for (source, expected) in [
+            ("len(\"héllo, 世界\")", 9),
+            ("len([1, [2, 3], 4])", 3),
+            ("len({\"a\": 1, \"b\": 2})", 2),
+            ("len(\"\")", 0),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::Number(Number::Int(expected)), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_builtin_type() {
+        let (tokens, errors) = tokenize_with_errors("type(123)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("int".to_string()));
+    }
+
+    #[test]
+    fn test_type_names() {
+        for (source, expected) in [
+            ("type(1)", "int"),
+            ("type(1.5)", "float"),
+            ("type(\"s\")", "string"),
+            ("type(true)", "bool"),
+            ("type([1])", "array"),
+            ("type({\"a\": 1})", "object"),
+            ("type(nil)", "nil"),
+            ("type(print)", "function"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_builtin_str() {
+        let (tokens, errors) = tokenize_with_errors("str(42)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("42".to_string()));
+    }
+
+    #[test]
+    fn test_str_matches_printed_text() {
+        for (source, expected) in [
+            ("str(\"hi\")", "hi"),
+            ("str(1.0)", "1.0"),
+            ("str(true)", "true"),
+            ("str(nil)", "nil"),
+            ("str([1, \"a\", nil])", "[1, a, nil]"),
+            ("str(1..3)", "1..3"),
+            ("\"n = \" + str(3)", "n = 3"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_examples() {
+        use std::fs;
+        use std::path::Path;
+
+        let examples_dir = Path::new("examples");
+        let mut example_files: Vec<_> = fs::read_dir(examples_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let path = entry.path();
+                path.extension().is_some_and(|ext| ext == "mp")
+            })
+            .collect();
+
+        example_files.sort_by_key(|entry| entry.path());
+
+        for entry in example_files {
+            let path = entry.path();
+            let file_name = path.file_name().unwrap().to_str().unwrap();
+            println!("Testing: {}", file_name);
+
+            let content = fs::read_to_string(&path).unwrap();
+            let (tokens, errors) = tokenize_with_errors(&content);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast);
+
+            match result {
+                Ok(_) | Err(mp_lang::InterpreterError::Return(_)) => {
+                    println!("  ✓ {} passed", file_name);
+                }
+                Err(e) => {
+                    panic!("  ✗ {} failed: {:?}", file_name, e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_logical_and_or() {
+        let (tokens, errors) = tokenize_with_errors("let x = 5; x > 0 && x < 10");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+
+        let (tokens, errors) = tokenize_with_errors("let x = 15; x < 0 || x < 10");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_logical_short_circuit() {
+        let (tokens, errors) = tokenize_with_errors("let arr = []; len(arr) > 0 && arr[0] == 1");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(false));
+
+        let (tokens, errors) = tokenize_with_errors("true || undefined_name");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_logical_not() {
+        let (tokens, errors) = tokenize_with_errors("let done = false; !done && !(1 > 2)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(true));
+
+        let (tokens, errors) = tokenize_with_errors("!!nil");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Boolean(false));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let (tokens, errors) = tokenize_with_errors("let n = 3; \"n = \" + n + \", ok: \" + true");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("n = 3, ok: true".to_string()));
+
+        let (tokens, errors) = tokenize_with_errors("1.5 + \"x\"");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("1.5x".to_string()));
+    }
+
+    #[test]
+    fn test_string_repetition() {
+        let (tokens, errors) = tokenize_with_errors("\"ab\" * 3");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("ababab".to_string()));
+
+        let (tokens, errors) = tokenize_with_errors("2 * \"-\"");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("--".to_string()));
+
+        for source in ["\"ab\" * 1.5", "\"ab\" * -1", "\"ab\" - \"b\"", "[1] * 2"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source} should fail");
+        }
+    }
+
+    #[test]
+    fn test_template_string() {
+        let (tokens, errors) =
+            tokenize_with_errors("let x = 2; let name = \"mp\"; `${name}: x + 1 = ${x + 1}`");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("mp: x + 1 = 3".to_string()));
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let (tokens, errors) = tokenize_with_errors(
+            "let grid = [[1, 2], [3, 4]]; grid[1][0] = 30; grid[0] = grid[0][1] + grid[1][0]; grid",
+        );
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result.to_string(), "[32, [30, 4]]");
+
+        let (tokens, errors) =
+            tokenize_with_errors("let s = \"cat\"; s[0] = \"b\"; s[-1] = \"d\"; s");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::String("bad".to_string()));
+
+        let (tokens, errors) = tokenize_with_errors("let arr = [1]; arr[5] = 0");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(eval(ast).is_err());
+    }
+
+    #[test]
+    fn test_object_index_assignment() {
+        let (tokens, errors) = tokenize_with_errors(
+            "let obj = {\"inner\": {\"n\": 1}}; obj[\"inner\"][\"n\"] = 2; obj[\"k\"] = 3; obj:inner:n + obj:k",
+        );
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(5)));
+
+        let (tokens, errors) = tokenize_with_errors(
+            "struct P { x, y }; let p = P(1, 2); p:x = 10; p[\"y\"] = 20; p:x + p:y",
+        );
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(30)));
+
+        let (tokens, errors) = tokenize_with_errors("struct P { x }; let p = P(1); p:z = 1");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(eval(ast).is_err());
+    }
+
+    #[test]
+    fn test_slicing() {
+        for (source, expected) in [
+            ("[1, 2, 3, 4, 5][1..3]", "[2, 3]"),
+            ("[1, 2, 3, 4, 5][..2]", "[1, 2]"),
+            ("[1, 2, 3, 4, 5][3..]", "[4, 5]"),
+            ("[1, 2, 3, 4, 5][-2..]", "[4, 5]"),
+            ("[1, 2, 3][2..10]", "[3]"),
+            ("[1, 2, 3][2..1]", "[]"),
+            ("\"hello\"[1..4]", "ell"),
+            ("\"hello\"[..-1]", "hell"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, errors) =
+            tokenize_with_errors("let a = [1, 2]; let b = a[..]; push(b, 3); len(a)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(2)));
+    }
+
+    #[test]
+    fn test_method_call() {
+        let (tokens, errors) = tokenize_with_errors("let a = [1, 2]; a.push(3); a.len()");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(3)));
+
+        let (tokens, errors) =
+            tokenize_with_errors("fn add(x, y) { return x + y; }; let n = 2; n.add(3).add(4)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        let result = eval(ast).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(9)));
+
+        let (tokens, errors) = tokenize_with_errors("let a = [1]; a.missing()");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(eval(ast).is_err());
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        for (x, expected) in [(1, "small"), (4, "medium"), (7, "large"), (10, "huge")] {
+            let source = format!(
+                "let x = {x};\nif x < 3 {{ \"small\" }}\nelse if x < 6 {{ \"medium\" }}\nelse if x < 9 {{ \"large\" }}\nelse {{ \"huge\" }}"
+            );
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_match_expression() {
+        for (subject, expected) in [
+            ("0", "zero"),
+            ("-1", "minus one"),
+            ("\"hi\"", "greeting"),
+            ("true", "yes"),
+            ("42", "other 42"),
+        ] {
+            let source = format!(
+                "match {subject} {{ 0 => \"zero\", -1 => \"minus one\", \"hi\" => \"greeting\", true => \"yes\", n => `other ${{n}}` }}"
+            );
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result, Value::String(expected.to_string()), "{source}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("match 5 { 1 => 1 }");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert_eq!(eval(ast).unwrap(), Value::Nil);
+
+        let (tokens, errors) = tokenize_with_errors("let n = 1; match 2 { n => n }; n");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert_eq!(eval(ast).unwrap(), Value::Number(Number::Int(1)));
+    }
+
+    #[test]
+    fn test_range_value() {
+        for (source, expected) in [
+            ("1..5", "1..5"),
+            ("len(2..6)", "4"),
+            ("type(0..1)", "range"),
+            ("let r = 1..3; [10, 20, 30, 40][r]", "[20, 30]"),
+            ("\"hello\"[(0..2)]", "he"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("1.5..3");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(eval(ast).is_err());
+    }
+
+    #[test]
+    fn test_for_in_loop() {
+        for (source, expected) in [
+            ("let t = 0; for i in 0..5 { t = t + i; }; t", "10"),
+            ("let t = 0; for x in [1, 2, 3] { t = t + x; }; t", "6"),
+            ("let s = \"\"; for c in \"abc\" { s = c + s; }; s", "cba"),
+            ("for i in 0..4 { i * i }", "nil"),
+            ("collect for i in 0..4 { i * i }", "[0, 1, 4, 9]"),
+            (
+                "let t = 0; for i in 0..10 { if i == 2 { continue; }; if i == 5 { break; }; t = t + i; }; t",
+                "8",
+            ),
+            ("for i in 3..3 { i }", "nil"),
+            ("collect for i in 3..3 { i }", "[]"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("for i in 5 { i }");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(eval(ast).is_err());
+    }
+
+    #[test]
+    fn test_lambda() {
+        for (source, expected) in [
+            ("let double = fn(x) { x * 2 }; double(21)", "42"),
+            ("type(fn() { 1 })", "function"),
+            (
+                "fn make_adder(n) { return fn(x) { x + n }; }; let add5 = make_adder(5); add5(10)",
+                "15",
+            ),
+            (
+                "fn apply(f, v) { return f(v); }; apply(fn(s) { s + \"!\" }, \"hi\")",
+                "hi!",
+            ),
+            ("fn sq(x) { x * x }; let g = sq; g(7)", "49"),
+            (
+                "let n = 0; let inc = fn() { n = n + 1 }; inc(); inc(); n",
+                "2",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, errors) = tokenize_with_errors("let x = 1; x(2)");
+        assert!(errors.is_empty());
+        let ast = parse(tokens);
+        assert!(matches!(
+            eval(ast).as_ref().map_err(InterpreterError::inner),
+            Err(InterpreterError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_variadic_parameters() {
+        for (source, expected) in [
+            (
+                "fn sum(...args) { let t = 0; for a in args { t = t + a; }; return t; }; [sum(), sum(1), sum(1, 2, 3)]",
+                "[0, 1, 6]",
+            ),
+            ("fn tail(head, ...rest) { rest }; tail(1)", "[]"),
+            ("fn tail(head, ...rest) { rest }; tail(1, 2, 3)", "[2, 3]"),
+            ("let count = fn(...xs) { len(xs) }; count(4, 5)", "2"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        for (source, expected) in [
+            ("let [a, b] = [1, 2]; a * 10 + b", "12"),
+            ("let {x, y} = {\"x\": 3, \"y\": 4}; x * y", "12"),
+            ("struct P { px, py }; let {py} = P(1, 2); py", "2"),
+            (
+                "fn pair() { return [5, 6]; }; let [p, q] = pair(); q - p",
+                "1",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in [
+            "let [a] = [1, 2]",
+            "let [a, b] = 5",
+            "let {z} = {\"x\": 1}",
+            "let {x} = [1]",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+
+        // Shape errors point at the pattern, a missing property at its name.
+        for (source, column) in [
+            ("1\nlet [a] = [1, 2]", 6),
+            ("1\nlet [a, b] = 5", 6),
+            ("1\nlet {x, z} = {\"x\": 1}", 9),
+            ("let a = 1\nlet [a] = [2]", 6),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let span = eval(parse(tokens)).unwrap_err().span().expect(source);
+            assert_eq!((span.line, span.column), (2, column), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_multiple_assignment() {
+        for (source, expected) in [
+            (
+                "fn divmod(a, b) { return [a / b, a % b]; }; let q, r = divmod(17, 5); [q, r]",
+                "[3, 2]",
+            ),
+            ("let x, y = 1, 2; x, y = y, x; [x, y]", "[2, 1]"),
+            ("let a = [1, 2, 3]; a[0], a[2] = a[2], a[0]; a", "[3, 2, 1]"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in [
+            "let x, y = 1, 2; x, y = 1, 2, 3",
+            "let x, y = 1, 2; x, y = 5",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_const_declaration() {
+        for (source, expected) in [
+            ("const n = 5; n * 2", "10"),
+            ("const a = [1, 2]; a[0] = 9; a", "[9, 2]"),
+            (
+                "const n = 1; fn f() { let n = 2; n = 3; return n; }; f() + n",
+                "4",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in [
+            "const n = 1; n = 2",
+            "const n = 1; if true { n = 2 }",
+            "const x = 1; let y = 2; x, y = y, x",
+            "const n = 1; let n = 2",
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+
+        let (tokens, _) = tokenize_with_errors("const n = 1;\nn = 2");
+        match eval(parse(tokens)) {
+            Err(InterpreterError::ConstReassignment { name, span }) => {
+                assert_eq!(name, "n");
+                assert_eq!(span.map(|s| s.line), Some(2));
+            }
+            other => panic!("Expected ConstReassignment, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_loop_break_value() {
+        for (source, expected) in [
+            (
+                "let i = 0; loop { i = i + 1; if i == 5 { break i * 10 } }",
+                "50",
+            ),
+            (
+                "let i = 0; loop { i = i + 1; if i < 3 { continue }; break }; i",
+                "3",
+            ),
+            ("let n = loop { break \"done\" }; n", "done"),
+            (
+                "let i = 0; while true { i = i + 1; if i == 2 { break 99 } }; i",
+                "2",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_do_while_loop() {
+        for (source, expected) in [
+            ("let i = 10; do { i = i + 1 } while i < 5; i", "11"),
+            ("let i = 0; do { i = i + 1 } while i < 5; i", "5"),
+            (
+                "let i = 0; do { i = i + 1; if i == 2 { break } } while true; i",
+                "2",
+            ),
+            (
+                "let i = 0; let s = 0; do { i = i + 1; if i == 2 { continue }; s = s + i } while i < 4; s",
+                "8",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, _) = tokenize_with_errors("do { 1 } while 1");
+        assert!(eval(parse(tokens)).is_err());
+    }
+
+    #[test]
+    fn test_nil_coalesce() {
+        for (source, expected) in [
+            ("let a = match 1 { 2 => 0 }; a ?? 5", "5"),
+            ("0 ?? 5", "0"),
+            ("false ?? true", "false"),
+            ("let o = {\"x\": 1}; o.x ?? 2", "1"),
+            ("let n = 0; 1 ?? (n = 9); n", "0"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_spread() {
+        for (source, expected) in [
+            ("let rest = [2, 3]; [1, ...rest, 4]", "[1, 2, 3, 4]"),
+            ("[...[], ...[1]]", "[1]"),
+            (
+                "fn add(a, b, c) { return a + b + c; }; let xs = [1, 2, 3]; add(...xs)",
+                "6",
+            ),
+            ("fn count(...xs) { len(xs) }; count(0, ...[1, 2], 3)", "4"),
+            ("fn add(a, b) { a + b }; let one = 1; one.add(...[2])", "3"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in ["[...5]", "print(...\"ab\")"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_import_modules() {
+        let dir = std::env::temp_dir().join(format!("mp_lang_import_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(
+            dir.join("lib/util.mp"),
+            "let items = []\nfn helper(x) { x * 2 }\nfn twice_plus(x) { helper(x) + 1 }",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("entry.mp"),
+            "import \"lib/util.mp\"\nlet entry = helper(5)",
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.mp"), "import \"b.mp\"").unwrap();
+        std::fs::write(dir.join("b.mp"), "import \"a.mp\"").unwrap();
+        let path = |name: &str| dir.join(name).display().to_string().replace('\\', "/");
+
+        for (source, expected) in [
+            (
+                format!("import \"{}\"; twice_plus(3)", path("lib/util.mp")),
+                "7",
+            ),
+            (
+                format!("import u from \"{}\"; u.twice_plus(4)", path("lib/util.mp")),
+                "9",
+            ),
+            // A module is evaluated once, so both imports share its state.
+            (
+                format!(
+                    "import a from \"{0}\"; import b from \"{0}\"; a.items.push(1); len(b.items)",
+                    path("lib/util.mp")
+                ),
+                "1",
+            ),
+            // Relative imports resolve against the importing file.
+            (format!("import \"{}\"; entry", path("entry.mp")), "10"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in [
+            format!("import \"{}\"", path("missing.mp")),
+            format!("import \"{}\"", path("a.mp")),
+            format!("let helper = 1; import \"{}\"", path("lib/util.mp")),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_declarations() {
+        let dir = std::env::temp_dir().join(format!("mp_lang_export_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("shapes.mp"),
+            "fn square(x) { x * x }\nexport fn area(w) { square(w) }\nexport const SIDES = 4",
+        )
+        .unwrap();
+        std::fs::write(dir.join("nested.mp"), "fn f() { export let y = 1 }\nf()").unwrap();
+        let path = |name: &str| dir.join(name).display().to_string().replace('\\', "/");
+
+        for (source, expected) in [
+            (
+                format!("import s from \"{}\"; s.area(3)", path("shapes.mp")),
+                "9",
+            ),
+            (
+                format!("import s from \"{}\"; len(s)", path("shapes.mp")),
+                "2",
+            ),
+            (
+                format!("import \"{}\"; area(2) + SIDES", path("shapes.mp")),
+                "8",
+            ),
+            ("export let x = 5; x".to_string(), "5"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in [
+            format!("import \"{}\"; square(2)", path("shapes.mp")),
+            format!("import \"{}\"; SIDES = 5", path("shapes.mp")),
+            format!("import \"{}\"", path("nested.mp")),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(&source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        for (source, expected) in [
+            ("12 & 10", "8"),
+            ("12 | 3", "15"),
+            ("6 ^ 3", "5"),
+            ("~5", "-6"),
+            ("1 << 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("1 | 2 == 3", "true"),
+            ("collect for i in 0..1 << 2 { i }", "[0, 1, 2, 3]"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in ["1.5 & 1", "~2.0", "1 << -1", "1 << 200", "true | 1"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_floor_division() {
+        for (source, expected) in [
+            ("7 ~/ 2", "3"),
+            ("-7 ~/ 2", "-4"),
+            ("7 ~/ -2", "-4"),
+            ("6 ~/ 3", "2"),
+            ("7.5 ~/ 2.0", "3"),
+            ("7.0 ~/ 2.0 == 3", "true"),
+            ("1 + 7 ~/ 2 * 2", "7"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        for source in ["1 ~/ 0", "1.0 ~/ 0.0"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            assert!(eval(ast).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        for (source, expected) in [
+            ("let x = 5; 1 < x < 10", "true"),
+            ("let x = 15; 1 < x < 10", "false"),
+            ("1 <= 1 < 2 <= 2", "true"),
+            ("3 > 2 > 1", "true"),
+            ("1 < 3 > 2", "true"),
+            // The middle operand is evaluated once; the chain stops at the first failure.
+            (
+                "let n = 0; fn next() { n = n + 1; return n; }; 0 < next() < 2; n",
+                "1",
+            ),
+            (
+                "let n = 0; fn next() { n = n + 1; return n; }; 5 < 1 < next(); n",
+                "0",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let ast = parse(tokens);
+            let result = eval(ast).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, _) = tokenize_with_errors("(1 < 2) < 3");
+        assert!(
+            eval(parse(tokens)).is_err(),
+            "Parentheses opt out of chaining"
+        );
+    }
+
+    #[test]
+    fn test_equality_does_not_assign() {
+        let (tokens, errors) = tokenize_with_errors("let x = 1; x == 5; x");
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "1");
+
+        let (tokens, errors) = tokenize_with_errors("let x = 1; let y = x = 5; x + y");
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "10");
+    }
+
+    #[test]
+    fn test_scope_chain() {
+        for (source, expected) in [
+            // Writes inside blocks, loops and calls reach the enclosing binding.
+            ("let x = 1; { x = 2 }; x", "2"),
+            ("let x = 0; while x < 3 { { x = x + 1 } }; x", "3"),
+            ("let x = 0; fn bump() { x = x + 1 }; bump(); bump(); x", "2"),
+            // Bindings declared in an inner scope shadow, then disappear.
+            ("let x = 1; { let x = 2; x = 3 }; x", "1"),
+            ("let x = 1; fn f(x) { x = 5 }; f(0); x", "1"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let result = eval(parse(tokens)).unwrap();
+            assert_eq!(result.to_string(), expected, "{source}");
+        }
+
+        let (tokens, _) = tokenize_with_errors("{ let y = 1 }; y");
+        assert!(matches!(
+            eval(parse(tokens)).as_ref().map_err(InterpreterError::inner),
+            Err(InterpreterError::UndefinedVariable(name)) if name == "y"
+        ));
+    }
+
+    #[test]
+    fn test_assign_to_undefined_variable() {
+        for source in ["y = 5", "fn f() { y = 5 }; f()", "let x = 1; x, y = 2, 3"] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            let error = eval(parse(tokens)).unwrap_err();
+            assert!(
+                matches!(error.inner(), InterpreterError::UndefinedVariable(name) if name == "y"),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_let_without_initializer() {
+        for (source, expected) in [
+            ("let x; x", "nil"),
+            ("let x; if true { x = 1 } else { x = 2 }; x", "1"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert_eq!(
+                eval(parse(tokens)).unwrap().to_string(),
+                expected,
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_object_literal_keys() {
+        let (tokens, errors) =
+            tokenize_with_errors("let k = \"b\"; let o = {a: 1, [k + \"c\"]: 2}; o.a + o[\"bc\"]");
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap().to_string(), "3");
+
+        let (tokens, _) = tokenize_with_errors("{[1]: 2}");
+        assert!(matches!(
+            eval(parse(tokens))
+                .as_ref()
+                .map_err(InterpreterError::inner),
+            Err(InterpreterError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_nested_function_declarations() {
+        for (source, expected) in [
+            (
+                "fn outer(n) { fn double(x) { x * 2 }; double(n) + 1 }; outer(3)",
+                "7",
+            ),
+            // Nested functions see the enclosing locals even after it returns.
+            (
+                "fn adder(n) { fn add(x) { x + n }; return add }; let f = adder(2); f(5)",
+                "7",
+            ),
+            (
+                "fn f() { fn fact(n) { if n < 2 { 1 } else { n * fact(n - 1) } }; fact(5) }; f()",
+                "120",
+            ),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert_eq!(
+                eval(parse(tokens)).unwrap().to_string(),
+                expected,
+                "{source}"
+            );
+        }
+
+        let (tokens, _) = tokenize_with_errors("{ fn local() { 1 } }; local()");
+        assert!(eval(parse(tokens)).is_err(), "Nested functions stay local");
+    }
+
+    #[test]
+    fn test_type_operators() {
+        for (source, expected) in [
+            ("1 is Int", "true"),
+            ("1 is Float", "false"),
+            ("1.5 is Number", "true"),
+            ("\"a\" is String", "true"),
+            ("nil is Nil", "true"),
+            ("struct P { x }; P(1) is P", "true"),
+            ("7 as Float / 2.0", "3.5"),
+            ("3.9 as Int", "3"),
+            ("\" 42 \" as Int + 1", "43"),
+            ("\"2.5\" as Number", "2.5"),
+            ("12 as String + \"!\"", "12!"),
+            ("\"true\" as Bool", "true"),
+            ("len(\"abc\" as Array)", "3"),
+            ("len([1, 2] as Array)", "2"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert_eq!(
+                eval(parse(tokens)).unwrap().to_string(),
+                expected,
+                "{source}"
+            );
+        }
+
+        for source in ["\"abc\" as Int", "[1] as Float", "1 is Widget"] {
+            let (tokens, _) = tokenize_with_errors(source);
+            assert!(
+                matches!(
+                    eval(parse(tokens))
+                        .as_ref()
+                        .map_err(InterpreterError::inner),
+                    Err(InterpreterError::TypeMismatch(_))
+                ),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_char_builtins() {
+        for (source, expected) in [
+            ("'a' == \"a\"", "true"),
+            ("len(chars(\"héllo\"))", "5"),
+            ("chars(\"héllo\")[1]", "é"),
+            ("ord('A')", "65"),
+            ("chr(ord('a') + 2)", "c"),
+        ] {
+            let (tokens, errors) = tokenize_with_errors(source);
+            assert!(errors.is_empty());
+            assert_eq!(
+                eval(parse(tokens)).unwrap().to_string(),
+                expected,
+                "{source}"
+            );
+        }
+
+        for source in ["ord(\"ab\")", "chr(-1)", "chr(1114112)"] {
+            let (tokens, _) = tokenize_with_errors(source);
+            assert!(
+                matches!(
+                    eval(parse(tokens))
+                        .as_ref()
+                        .map_err(InterpreterError::inner),
+                    Err(InterpreterError::InvalidOperation(_))
+                ),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiline_parenthesized_expressions() {
+        let source =
+            "fn add(a, b) { a + b }\nlet total = add(\n    1,\n    (2\n        * 3)\n)\ntotal";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        let result = eval(parse(tokens)).unwrap();
+        assert_eq!(result, Value::Number(Number::Int(7)));
+    }
+
+    #[test]
+    fn test_expression_wraps_after_operator() {
+        let source = "let total = 1 +\n    2 *\n    3\nlet ok = total > 5 &&\n    total < 10\nok";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_help_returns_doc_comment() {
+        let source = "/// Doubles n.\nfn double(n) { n * 2 }\nfn plain() { 0 }\n[help(double), help(\"double\"), help(plain), help(len)]";
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        assert_eq!(
+            eval(parse(tokens)).unwrap().to_string(),
+            "[Doubles n., Doubles n., nil, nil]"
+        );
+    }
+
+    #[test]
+    fn test_unicode_variable_names() {
+        let (tokens, errors) = tokenize_with_errors("let 价格 = 3; let count = 4; 价格 * count");
+        assert!(errors.is_empty());
+        assert_eq!(eval(parse(tokens)).unwrap(), Value::Number(Number::Int(12)));
     }
 }
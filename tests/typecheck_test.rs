@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use mp_lang::{lexer::tokenize_with_errors, parser::parse, typecheck::check};
+
+    fn warnings(source: &str) -> Vec<String> {
+        let (tokens, errors) = tokenize_with_errors(source);
+        assert!(errors.is_empty());
+        check(&parse(tokens))
+            .into_iter()
+            .map(|warning| warning.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_well_typed_programs() {
+        for source in [
+            "let x = 1 + 2 * 3; let s = \"n = \" + x; print(s)",
+            "let xs = [1, 2]; push(xs, 3); len(xs) > 2",
+            "fn f(a) { a - 1 }; f(\"anything\")",
+            "let x; x = 5; x = \"five\"",
+            "let n = 5; 1 < n < 10",
+            "for i in 0..3 { i * 2 }",
+        ] {
+            assert_eq!(warnings(source), Vec::<String>::new(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_operator_mismatches() {
+        assert_eq!(
+            warnings("let flag = true; flag - 1"),
+            ["Cannot apply '-' to boolean and number"]
+        );
+        assert_eq!(
+            warnings("let s = \"a\"; s < 1"),
+            ["Cannot apply '<' to string and number"]
+        );
+        assert_eq!(warnings("-\"text\""), ["'-' expects number, found string"]);
+    }
+
+    #[test]
+    fn test_conditions_calls_and_assignments() {
+        assert_eq!(
+            warnings("if 1 { 2 }"),
+            ["If condition expects boolean, found number"]
+        );
+        assert_eq!(
+            warnings("len(42)"),
+            ["len() expects string or array or object or range, found number"]
+        );
+        assert_eq!(
+            warnings("let count = 0; count = \"zero\""),
+            ["'count' holds a number but is assigned a string"]
+        );
+        // Types flow through variables and builtin results.
+        assert_eq!(
+            warnings("let n = len(\"abc\"); n + true"),
+            ["Cannot apply '+' to number and boolean"]
+        );
+    }
+}